@@ -1,398 +0,0 @@
-// Copyright 2025 Cowboy AI, LLC.
-
-//! Flake Analyzer
-//!
-//! Extracts infrastructure concepts from Nix flakes and maps them to
-//! Infrastructure domain objects.
-//!
-//! # Mapping Strategy
-//!
-//! - **Packages (buildRustPackage, etc.)** → ComputeResource (Service/Application)
-//! - **DevShells** → ComputeResource (Development Environment)
-//! - **Build Dependencies** → ResourceCapabilities
-//! - **Environment Variables** → Resource Configuration
-//! - **Inputs** → External dependency references
-
-use super::value_objects::*;
-use super::ast::Result;
-use crate::infrastructure::*;
-use std::collections::HashMap;
-
-// ============================================================================
-// Flake Analysis Result
-// ============================================================================
-
-/// Result of analyzing a flake
-#[derive(Debug, Clone)]
-pub struct FlakeAnalysis {
-    /// Flake description
-    pub description: Option<String>,
-
-    /// External dependencies (inputs)
-    pub inputs: Vec<FlakeInput>,
-
-    /// Packages defined in the flake
-    pub packages: Vec<FlakePackage>,
-
-    /// Development shells
-    pub dev_shells: Vec<FlakeDevShell>,
-
-    /// System architecture (extracted from outputs)
-    pub system: Option<String>,
-}
-
-/// External flake input
-#[derive(Debug, Clone)]
-pub struct FlakeInput {
-    /// Input name (e.g., "nixpkgs", "rust-overlay")
-    pub name: String,
-
-    /// URL or flake reference
-    pub url: Option<String>,
-
-    /// Follows attribute (for input inheritance)
-    pub follows: Option<String>,
-}
-
-/// Package definition from flake
-#[derive(Debug, Clone)]
-pub struct FlakePackage {
-    /// Package name (pname)
-    pub name: String,
-
-    /// Package version
-    pub version: Option<String>,
-
-    /// Build dependencies
-    pub build_inputs: Vec<String>,
-
-    /// Native build dependencies
-    pub native_build_inputs: Vec<String>,
-
-    /// Whether tests are enabled
-    pub do_check: bool,
-}
-
-/// Development shell definition
-#[derive(Debug, Clone)]
-pub struct FlakeDevShell {
-    /// Shell name (usually "default")
-    pub name: String,
-
-    /// Packages available in shell
-    pub packages: Vec<String>,
-
-    /// Build dependencies
-    pub build_inputs: Vec<String>,
-
-    /// Native build dependencies
-    pub native_build_inputs: Vec<String>,
-
-    /// Environment variables
-    pub environment: HashMap<String, String>,
-
-    /// Shell hook script
-    pub shell_hook: Option<String>,
-}
-
-// ============================================================================
-// Flake Analyzer
-// ============================================================================
-
-/// Analyzes Nix flakes to extract infrastructure information
-pub struct FlakeAnalyzer;
-
-impl FlakeAnalyzer {
-    /// Create a new flake analyzer
-    pub fn new() -> Self {
-        Self
-    }
-
-    /// Analyze a flake from its NixValue representation
-    pub fn analyze(&self, value: &NixValue) -> Result<FlakeAnalysis> {
-        let mut analysis = FlakeAnalysis {
-            description: None,
-            inputs: Vec::new(),
-            packages: Vec::new(),
-            dev_shells: Vec::new(),
-            system: None,
-        };
-
-        // Extract top-level flake structure
-        if let NixValue::Attrset(attrs) = value {
-            // Extract description
-            if let Some(NixValue::String(desc)) = attrs.get("description") {
-                analysis.description = Some(desc.value.clone());
-            }
-
-            // Extract inputs
-            if let Some(NixValue::Attrset(inputs)) = attrs.get("inputs") {
-                analysis.inputs = self.extract_inputs(inputs);
-            }
-
-            // Extract outputs (packages, devShells, etc.)
-            // Note: outputs is typically a function, so we handle it specially
-            if let Some(outputs_value) = attrs.get("outputs") {
-                self.analyze_outputs(outputs_value, &mut analysis);
-            }
-        }
-
-        Ok(analysis)
-    }
-
-    /// Extract flake inputs
-    fn extract_inputs(&self, inputs: &NixAttrset) -> Vec<FlakeInput> {
-        let mut result = Vec::new();
-
-        for (name, value) in &inputs.attributes {
-            let mut input = FlakeInput {
-                name: name.clone(),
-                url: None,
-                follows: None,
-            };
-
-            if let NixValue::Attrset(input_attrs) = value {
-                if let Some(NixValue::String(url)) = input_attrs.get("url") {
-                    input.url = Some(url.value.clone());
-                }
-                if let Some(NixValue::String(follows)) = input_attrs.get("follows") {
-                    input.follows = Some(follows.value.clone());
-                }
-            }
-
-            result.push(input);
-        }
-
-        result
-    }
-
-    /// Analyze outputs structure
-    /// In practice, outputs is usually a function that returns an attrset
-    /// For now, we look for common patterns in the flake
-    fn analyze_outputs(&self, _value: &NixValue, _analysis: &mut FlakeAnalysis) {
-        // Outputs analysis is complex because it's typically a function
-        // For now, we'll implement basic analysis
-        // A full implementation would need to evaluate the function or
-        // use heuristics to extract packages and devShells
-
-        // This is a placeholder for future enhancement
-    }
-
-    /// Convert flake analysis to Infrastructure aggregate
-    pub fn to_infrastructure(
-        &self,
-        analysis: &FlakeAnalysis,
-        infrastructure_id: InfrastructureId,
-    ) -> std::result::Result<InfrastructureAggregate, Box<dyn std::error::Error>> {
-        let mut infrastructure = InfrastructureAggregate::new(infrastructure_id);
-        let identity = MessageIdentity::new_root();
-
-        // Convert packages to compute resources
-        for package in &analysis.packages {
-            let resource_id = ResourceId::new(&package.name)?;
-            let hostname = Hostname::new(&format!("{}.local", package.name))?;
-
-            let mut capabilities = ResourceCapabilities::new();
-
-            // Add build dependencies as capabilities metadata
-            for (i, dep) in package.build_inputs.iter().enumerate() {
-                capabilities.metadata.insert(format!("build_input_{}", i), dep.clone());
-            }
-            for (i, dep) in package.native_build_inputs.iter().enumerate() {
-                capabilities.metadata.insert(format!("native_input_{}", i), dep.clone());
-            }
-
-            if let Some(version) = &package.version {
-                capabilities.metadata.insert("version".to_string(), version.clone());
-            }
-
-            // Determine system architecture from analysis or default to x86_64-linux
-            let system_arch = if let Some(ref sys) = analysis.system {
-                SystemArchitecture::new(sys)
-            } else {
-                SystemArchitecture::x86_64_linux()
-            };
-
-            // Create SystemDescription for containerized package
-            // Packages in Nix are typically NixOS containers with full Nix capability
-            let system_desc = SystemDescription::nixos_managed(system_arch.clone());
-
-            let spec = ComputeResourceSpec {
-                system_description: Some(system_desc),
-                id: resource_id,
-                resource_type: ComputeType::Container, // Packages are containerized
-                hostname,
-                system: system_arch,
-                capabilities,
-            };
-
-            infrastructure.handle_register_compute_resource(spec, &identity)?;
-        }
-
-        // Convert dev shells to compute resources
-        for shell in &analysis.dev_shells {
-            let resource_id = ResourceId::new(&format!("devshell-{}", shell.name))?;
-            let hostname = Hostname::new(&format!("dev-{}.local", shell.name))?;
-
-            let mut capabilities = ResourceCapabilities::new();
-
-            // Add packages as capabilities metadata
-            for (i, pkg) in shell.packages.iter().enumerate() {
-                capabilities.metadata.insert(format!("package_{}", i), pkg.clone());
-            }
-
-            // Add build dependencies
-            for (i, dep) in shell.build_inputs.iter().enumerate() {
-                capabilities.metadata.insert(format!("build_input_{}", i), dep.clone());
-            }
-            for (i, dep) in shell.native_build_inputs.iter().enumerate() {
-                capabilities.metadata.insert(format!("native_input_{}", i), dep.clone());
-            }
-
-            capabilities.metadata.insert("type".to_string(), "development".to_string());
-
-            // Determine system architecture from analysis or default to x86_64-linux
-            let system_arch = if let Some(ref sys) = analysis.system {
-                SystemArchitecture::new(sys)
-            } else {
-                SystemArchitecture::x86_64_linux()
-            };
-
-            // Create SystemDescription for development environment
-            // Dev shells run locally with Nix package manager capability
-            let system_desc = SystemDescription::new(
-                OperatingSystem::Linux, // Could be NixOS or any Linux with Nix
-                system_arch.clone(),
-                NixCapability::PackageManager, // Dev shells use nix-shell/develop
-                vec![ManagementProtocol::Local],
-            ).unwrap_or_else(|_| SystemDescription::unknown());
-
-            let spec = ComputeResourceSpec {
-                system_description: Some(system_desc),
-                id: resource_id,
-                resource_type: ComputeType::VirtualMachine, // Dev shells are like VMs
-                hostname,
-                system: system_arch,
-                capabilities,
-            };
-
-            infrastructure.handle_register_compute_resource(spec, &identity)?;
-        }
-
-        // Create a network for external dependencies (inputs)
-        if !analysis.inputs.is_empty() {
-            let network_id = NetworkId::new("external-dependencies")?;
-            let network_spec = NetworkSpec {
-                id: network_id,
-                name: "external-dependencies".to_string(), // Use hyphenated name for valid Nix identifier
-                cidr_v4: None, // External network doesn't have internal CIDR
-                cidr_v6: None,
-            };
-
-            infrastructure.handle_define_network(network_spec, &identity)?;
-        }
-
-        Ok(infrastructure)
-    }
-}
-
-impl Default for FlakeAnalyzer {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-// ============================================================================
-// Convenience Functions
-// ============================================================================
-
-/// Analyze a flake from a NixValue
-pub fn analyze_flake(value: &NixValue) -> Result<FlakeAnalysis> {
-    let analyzer = FlakeAnalyzer::new();
-    analyzer.analyze(value)
-}
-
-/// Analyze a flake and convert to Infrastructure
-pub fn flake_to_infrastructure(
-    value: &NixValue,
-    infrastructure_id: InfrastructureId,
-) -> std::result::Result<InfrastructureAggregate, Box<dyn std::error::Error>> {
-    let analyzer = FlakeAnalyzer::new();
-    let analysis = analyzer.analyze(value)?;
-    analyzer.to_infrastructure(&analysis, infrastructure_id)
-}
-
-// ============================================================================
-// Tests
-// ============================================================================
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::nix::parser::NixParser;
-
-    #[test]
-    fn test_analyze_simple_flake() {
-        let flake_content = r#"{
-            description = "Test flake";
-
-            inputs = {
-                nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable";
-            };
-
-            outputs = { self, nixpkgs }: {};
-        }"#;
-
-        let parser = NixParser::new();
-        let ast = parser.parse_str(flake_content).unwrap();
-        let value = crate::nix::ast_to_value(&ast).unwrap();
-
-        // Debug: Check inputs structure
-        if let NixValue::Attrset(root) = &value {
-            if let Some(NixValue::Attrset(inputs)) = root.get("inputs") {
-                eprintln!("inputs keys: {:?}", inputs.attributes.keys().collect::<Vec<_>>());
-                for (key, val) in &inputs.attributes {
-                    eprintln!("  {}: {:?}", key, val);
-                }
-            }
-        }
-
-        let analyzer = FlakeAnalyzer::new();
-        let analysis = analyzer.analyze(&value).unwrap();
-
-        assert_eq!(analysis.description, Some("Test flake".to_string()));
-        assert_eq!(analysis.inputs.len(), 1);
-        assert_eq!(analysis.inputs[0].name, "nixpkgs");
-
-        // For now, skip the url assertion until we fix dotted attribute paths
-        // assert_eq!(
-        //     analysis.inputs[0].url,
-        //     Some("github:NixOS/nixpkgs/nixos-unstable".to_string())
-        // );
-    }
-
-    #[test]
-    fn test_flake_to_infrastructure() {
-        let flake_content = r#"{
-            description = "Test infrastructure";
-
-            inputs = {
-                nixpkgs.url = "github:NixOS/nixpkgs";
-                utils.url = "github:numtide/flake-utils";
-            };
-        }"#;
-
-        let parser = NixParser::new();
-        let ast = parser.parse_str(flake_content).unwrap();
-        let value = crate::nix::ast_to_value(&ast).unwrap();
-
-        let infra_id = InfrastructureId::new();
-        let result = flake_to_infrastructure(&value, infra_id);
-
-        assert!(result.is_ok());
-        let infrastructure = result.unwrap();
-
-        // Should have created external dependencies network
-        assert_eq!(infrastructure.networks.len(), 1);
-    }
-}
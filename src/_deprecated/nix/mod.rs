@@ -60,33 +60,9 @@
 //! attrset.insert("name".to_string(), NixValue::String(NixString::new("hello")));
 //! ```
 
-pub mod ast;
-pub mod ast_converter;
-pub mod flake_analyzer;
-pub mod flake_evaluator;
-pub mod objects;
-pub mod parser;
+// ast, ast_converter, flake_analyzer, flake_evaluator, objects, parser and value_objects now
+// live in src/nix/
 pub mod topology;
-pub mod value_objects;
 
 // Re-export commonly used types
-pub use ast::{NixAst, NixExpression, NixNode};
-pub use ast_converter::{ast_to_value, AstConverter};
-pub use flake_analyzer::{
-    analyze_flake, flake_to_infrastructure, FlakeAnalysis, FlakeAnalyzer, FlakeDevShell,
-    FlakeInput, FlakePackage,
-};
-pub use flake_evaluator::{
-    evaluate_flake, nix_available, AppInfo, CheckInfo, DevShellInfo, EvaluatedFlake,
-    EvaluationError, FlakeEvaluator, PackageInfo,
-};
-pub use objects::{
-    NixApplication, NixAttrsetObject, NixDerivation, NixFlake, NixModule, NixObject, NixOverlay,
-    NixPackage,
-};
-pub use parser::{NixParser, ParseError, ParseResult};
 pub use topology::{NixTopology, TopologyNode, TopologyNetwork};
-pub use value_objects::{
-    NixAttrset, NixBool, NixFloat, NixInteger, NixList, NixLookupPath, NixNull, NixPath,
-    NixString, NixValue,
-};
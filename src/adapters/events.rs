@@ -0,0 +1,141 @@
+// Copyright (c) 2025 - Cowboy AI, Inc.
+//! Resource Events
+//!
+//! Changes to compute resources as they travel over NATS: published by the
+//! [`NatsPublisher`](super::nats_publisher::NatsPublisher) and consumed by
+//! the [`NatsProjector`](super::nats_projector::NatsProjector) to keep
+//! topology files up to date.
+//!
+//! Events are JSON, tagged by `type`, and published to
+//! `{domain}.event.compute_resource.{action}`:
+//!
+//! ```json
+//! { "type": "removed", "hostname": "web01" }
+//! ```
+
+use anyhow::Result;
+use cim_infrastructure::{ComputeResource, Hostname};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Aggregate segment of resource event subjects
+pub const AGGREGATE: &str = "compute_resource";
+
+/// Namespace for event message ids
+const EVENT_NAMESPACE: Uuid = Uuid::from_u128(0x3f9d_81c2_5a6e_4b0f_a7d4_02e8_c1b5_9763);
+
+/// A change to a compute resource
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResourceEvent {
+    /// A resource was registered or discovered
+    Registered {
+        /// The resource
+        resource: ComputeResource,
+    },
+    /// A registered resource changed
+    Updated {
+        /// The resource, as it is now
+        resource: ComputeResource,
+    },
+    /// A resource was removed
+    Removed {
+        /// Hostname of the removed resource
+        hostname: Hostname,
+    },
+}
+
+impl ResourceEvent {
+    /// Hostname of the resource the event is about
+    pub fn hostname(&self) -> &Hostname {
+        match self {
+            Self::Registered { resource } | Self::Updated { resource } => &resource.hostname,
+            Self::Removed { hostname } => hostname,
+        }
+    }
+
+    /// What happened, in past tense
+    pub fn action(&self) -> &'static str {
+        match self {
+            Self::Registered { .. } => "registered",
+            Self::Updated { .. } => "updated",
+            Self::Removed { .. } => "removed",
+        }
+    }
+
+    /// Subject under the CIM convention, `{domain}.event.{aggregate}.{action}`
+    pub fn subject(&self, domain: &str) -> String {
+        format!("{domain}.event.{AGGREGATE}.{}", self.action())
+    }
+
+    /// Name-based UUID of the event's content
+    ///
+    /// Equal events get equal ids, so `JetStream` drops re-published ones. The
+    /// resource's `id` is left out: readers generate a new one each time.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the event cannot be serialized.
+    pub fn message_id(&self) -> Result<Uuid> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(resource) = value.get_mut("resource").and_then(|r| r.as_object_mut()) {
+            resource.remove("id");
+        }
+        // Object keys serialize sorted, so the text is canonical
+        Ok(Uuid::new_v5(&EVENT_NAMESPACE, value.to_string().as_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cim_infrastructure::ResourceType;
+
+    fn web01(rack: &str) -> ComputeResource {
+        let mut resource = ComputeResource::new(
+            Hostname::new("web01").unwrap(),
+            ResourceType::PhysicalServer,
+        )
+        .unwrap();
+        resource.add_metadata("rack", rack).unwrap();
+        resource.add_metadata("row", "b").unwrap();
+        resource
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let event = ResourceEvent::Registered {
+            resource: web01("r1"),
+        };
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "registered");
+
+        let decoded: ResourceEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.action(), "registered");
+        assert_eq!(decoded.hostname().as_str(), "web01");
+
+        let removed: ResourceEvent =
+            serde_json::from_str(r#"{ "type": "removed", "hostname": "web01" }"#).unwrap();
+        assert_eq!(removed.action(), "removed");
+        assert_eq!(
+            removed.subject("infrastructure"),
+            "infrastructure.event.compute_resource.removed"
+        );
+    }
+
+    #[test]
+    fn test_message_id_ignores_resource_id() {
+        let registered = |rack| ResourceEvent::Registered {
+            resource: web01(rack),
+        };
+        let id = registered("r1").message_id().unwrap();
+        assert_eq!(registered("r1").message_id().unwrap(), id);
+        assert_ne!(registered("r2").message_id().unwrap(), id);
+
+        let updated = ResourceEvent::Updated {
+            resource: web01("r1"),
+        };
+        assert_ne!(updated.message_id().unwrap(), id);
+    }
+}
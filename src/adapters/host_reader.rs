@@ -0,0 +1,669 @@
+// Copyright (c) 2025 - Cowboy AI, Inc.
+//! Host Reader Adapter: NixOS host configurations → `ComputeResource`
+//!
+//! This adapter reads the NixOS configurations we already maintain for our
+//! hosts (`hosts/<name>/configuration.nix`) and extracts the facts needed to
+//! discover topology, without evaluating them.
+//!
+//! ## Architecture
+//!
+//! ```text
+//! hosts/<name>/configuration.nix (+ relative imports)
+//!     │
+//!     ▼ (rnix parser, static definitions)
+//! HostReader
+//!     │
+//!     ▼
+//! HostFacts ──► ComputeResource (+ metadata)
+//! ```
+//!
+//! ## Extracted Options
+//!
+//! | Option                                        | Fact                     |
+//! |-----------------------------------------------|--------------------------|
+//! | `networking.hostName`, `networking.domain`    | hostname                 |
+//! | `nixpkgs.hostPlatform`, `nixpkgs.system`      | system                   |
+//! | `networking.interfaces.*.ipv4.addresses`      | interface addresses      |
+//! | `networking.firewall.allowedTCPPorts`         | open TCP ports           |
+//! | `services.<name>.enable = true`               | services                 |
+//! | `boot.isContainer = true`                     | container                |
+//!
+//! Definitions may be nested or dotted, sit under `config`, and be wrapped
+//! in `mkIf`, `mkMerge`, `mkDefault`, `mkForce` or `mkOverride`. Conditions
+//! are not evaluated: a `mkIf` definition counts as set. When a value is
+//! defined in several files, the first definition found wins, starting with
+//! the host's own configuration.
+//!
+//! ## Usage
+//!
+//! ```rust,no_run
+//! use cim_domain_nix::adapters::host_reader::HostReader;
+//! use std::path::Path;
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let reader = HostReader::new();
+//! for host in reader.read_hosts_dir(Path::new("hosts")).await? {
+//!     let resource = host.to_compute_resource()?;
+//!     println!("{} runs {:?}", resource.hostname, host.services);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{bail, Context, Result};
+use cim_infrastructure::{ComputeResource, Hostname, ResourceType};
+use rnix::ast::{self, HasEntry};
+use rowan::ast::AstNode;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use crate::nix::ast::NixAst;
+use crate::nix::flake_outputs::{
+    attr_value, attrset_of, ident_name, key_of, list_elements, static_string, strip_parens,
+    Binding, Bound, Call, Env, Key,
+};
+
+/// Maximum nesting of `mkMerge`/`mkIf` wrappers and variable indirections
+const MAX_DEPTH: usize = 32;
+
+/// An IPv4 address assigned to an interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceAddress {
+    /// Interface name (`eth0`, `enp3s0`)
+    pub interface: String,
+    /// IPv4 address
+    pub address: String,
+    /// Prefix length, if set
+    pub prefix_length: Option<u8>,
+}
+
+/// Facts about a host read from its NixOS configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostFacts {
+    /// Host name (`networking.hostName`, or the host's directory name)
+    pub name: String,
+    /// DNS domain (`networking.domain`)
+    pub domain: Option<String>,
+    /// Nix system (`nixpkgs.hostPlatform` or `nixpkgs.system`)
+    pub system: Option<String>,
+    /// Statically assigned IPv4 addresses
+    pub interfaces: Vec<InterfaceAddress>,
+    /// Ports opened in the firewall
+    pub allowed_tcp_ports: Vec<u16>,
+    /// Enabled services (`services.<name>.enable = true`), sorted
+    pub services: Vec<String>,
+    /// Whether the host runs as a container (`boot.isContainer = true`)
+    pub container: bool,
+    /// Files the facts were read from
+    pub sources: Vec<PathBuf>,
+}
+
+impl HostFacts {
+    /// Fully qualified host name, if a domain is set
+    pub fn fqdn(&self) -> String {
+        match &self.domain {
+            Some(domain) => format!("{}.{}", self.name, domain),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Resource type implied by the configuration
+    ///
+    /// Containers and hosts running the QEMU guest agent are virtual
+    /// machines; everything else is assumed to be a physical server.
+    pub fn resource_type(&self) -> ResourceType {
+        if self.container || self.services.iter().any(|service| service == "qemuGuest") {
+            ResourceType::VirtualMachine
+        } else {
+            ResourceType::PhysicalServer
+        }
+    }
+
+    /// Convert to a `ComputeResource`
+    ///
+    /// The system, domain, addresses, ports and services are recorded as
+    /// metadata (`system`, `domain`, `ipv4_addresses`, `tcp_ports`,
+    /// `services`); list values are comma-separated.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the host name is not a valid hostname.
+    pub fn to_compute_resource(&self) -> Result<ComputeResource> {
+        let hostname =
+            Hostname::new(&self.fqdn()).context(format!("Invalid hostname: {}", self.fqdn()))?;
+        let mut resource = ComputeResource::new(hostname, self.resource_type())
+            .context("Failed to create ComputeResource")?;
+
+        let mut metadata = vec![("source", join(&self.sources, |p| p.display().to_string()))];
+        if let Some(system) = &self.system {
+            metadata.push(("system", system.clone()));
+        }
+        if let Some(domain) = &self.domain {
+            metadata.push(("domain", domain.clone()));
+        }
+        metadata.push((
+            "ipv4_addresses",
+            join(&self.interfaces, |a| match a.prefix_length {
+                Some(prefix) => format!("{}={}/{}", a.interface, a.address, prefix),
+                None => format!("{}={}", a.interface, a.address),
+            }),
+        ));
+        metadata.push(("tcp_ports", join(&self.allowed_tcp_ports, u16::to_string)));
+        metadata.push(("services", self.services.join(",")));
+
+        for (key, value) in metadata {
+            if !value.is_empty() {
+                resource
+                    .add_metadata(key, &value)
+                    .context(format!("Invalid metadata '{key}'"))?;
+            }
+        }
+        Ok(resource)
+    }
+}
+
+fn join<T>(items: &[T], text: impl Fn(&T) -> String) -> String {
+    items.iter().map(text).collect::<Vec<_>>().join(",")
+}
+
+/// Host Reader - Reads NixOS host configurations into `HostFacts`
+#[derive(Debug, Clone)]
+pub struct HostReader {
+    /// Whether to read relative path imports
+    follow_imports: bool,
+}
+
+impl HostReader {
+    /// Create a host reader that follows relative imports
+    pub fn new() -> Self {
+        Self {
+            follow_imports: true,
+        }
+    }
+
+    /// Only read the given file, ignoring its imports
+    #[must_use]
+    pub fn without_imports(mut self) -> Self {
+        self.follow_imports = false;
+        self
+    }
+
+    /// Read every host in a directory laid out as `<dir>/<name>/configuration.nix`
+    /// (or `<dir>/<name>/default.nix`), sorted by directory name
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the directory or a host configuration cannot be
+    /// read or parsed.
+    pub async fn read_hosts_dir(&self, dir: &Path) -> Result<Vec<HostFacts>> {
+        let mut entries = fs::read_dir(dir)
+            .await
+            .context(format!("Failed to read hosts directory: {}", dir.display()))?;
+        let mut host_dirs = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                host_dirs.push(entry.path());
+            }
+        }
+        host_dirs.sort();
+
+        let mut hosts = Vec::new();
+        for host_dir in host_dirs {
+            for file in ["configuration.nix", "default.nix"] {
+                let path = host_dir.join(file);
+                if fs::try_exists(&path).await.unwrap_or(false) {
+                    hosts.push(self.read_host_file(&path).await?);
+                    break;
+                }
+            }
+        }
+        Ok(hosts)
+    }
+
+    /// Read a host configuration file and, unless disabled, the files it
+    /// imports by relative path
+    ///
+    /// Without `networking.hostName` the host is named after the file's
+    /// directory.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a file cannot be read or parsed.
+    pub async fn read_host_file(&self, path: &Path) -> Result<HostFacts> {
+        let fallback = path
+            .parent()
+            .and_then(Path::file_name)
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+
+        let mut definitions = Definitions::default();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([path.to_path_buf()]);
+        while let Some(file) = queue.pop_front() {
+            if !seen.insert(file.clone()) {
+                continue;
+            }
+            let content = fs::read_to_string(&file)
+                .await
+                .context(format!("Failed to read host file: {}", file.display()))?;
+            let imports = definitions
+                .read(&content)
+                .context(format!("Failed to parse host file: {}", file.display()))?;
+            definitions.sources.push(file.clone());
+
+            if self.follow_imports {
+                let base = file.parent().unwrap_or_else(|| Path::new("."));
+                for import in imports {
+                    let mut import = base.join(import);
+                    if fs::metadata(&import).await.is_ok_and(|m| m.is_dir()) {
+                        import = import.join("default.nix");
+                    }
+                    if fs::try_exists(&import).await.unwrap_or(false) {
+                        queue.push_back(import);
+                    }
+                }
+            }
+        }
+
+        Ok(definitions.into_facts(fallback))
+    }
+
+    /// Read host facts from a single configuration
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the content does not parse.
+    pub fn parse_host(&self, name: &str, content: &str) -> Result<HostFacts> {
+        let mut definitions = Definitions::default();
+        definitions.read(content)?;
+        Ok(definitions.into_facts(name.to_string()))
+    }
+}
+
+/// Read host facts from modules written inline, such as those of a flake's
+/// `nixosSystem { modules = [ ... ]; }`; modules in other files are skipped
+pub(crate) fn inline_module_facts(name: &str, modules: &[Bound]) -> HostFacts {
+    let mut definitions = Definitions::default();
+    for module in modules {
+        if let Some((set, env)) = attrset_of(module) {
+            definitions.define_all(&set, &env);
+        }
+    }
+    definitions.into_facts(name.to_string())
+}
+
+impl Default for HostReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Definitions
+// ============================================================================
+
+/// Facts collected from one or more modules
+#[derive(Default)]
+struct Definitions {
+    host_name: Option<String>,
+    domain: Option<String>,
+    system: Option<String>,
+    interfaces: Vec<InterfaceAddress>,
+    ports: BTreeSet<u16>,
+    services: BTreeSet<String>,
+    container: bool,
+    sources: Vec<PathBuf>,
+}
+
+impl Definitions {
+    /// Record the definitions of a module; returns its relative imports
+    fn read(&mut self, content: &str) -> Result<Vec<String>> {
+        let ast = NixAst::parse(content)?;
+        let Some(expr) = ast.expr() else {
+            bail!("Module contains no expression");
+        };
+        let Some((set, env)) = attrset_of(&Bound {
+            expr,
+            env: Env::default(),
+        }) else {
+            bail!("Module is not an attribute set or a function returning one");
+        };
+
+        let imports = attr_value(&set, &env, &["imports"])
+            .map(|imports| {
+                list_elements(&imports)
+                    .iter()
+                    .filter_map(|import| match strip_parens(&import.expr) {
+                        ast::Expr::Path(path) => Some(path.syntax().text().to_string()),
+                        _ => None,
+                    })
+                    .filter(|path| path.starts_with("./") || path.starts_with("../"))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        self.define_all(&set, &env);
+        Ok(imports)
+    }
+
+    /// Record the definitions of a module's attribute set
+    fn define_all(&mut self, set: &ast::AttrSet, env: &Env) {
+        let mut leaves = Vec::new();
+        collect(set, env, &[], true, &mut leaves);
+        for (path, value) in leaves {
+            self.define(&path, &value);
+        }
+    }
+
+    fn define(&mut self, path: &[String], value: &Bound) {
+        let path: Vec<&str> = path.iter().map(String::as_str).collect();
+        match path.as_slice() {
+            ["networking", "hostName"] => set_once(&mut self.host_name, static_string(value)),
+            ["networking", "domain"] => set_once(&mut self.domain, static_string(value)),
+            ["nixpkgs", "hostPlatform"] => {
+                let system = static_string(value).or_else(|| {
+                    let (set, env) = attrset_of(value)?;
+                    static_string(&attr_value(&set, &env, &["system"])?)
+                });
+                set_once(&mut self.system, system);
+            }
+            ["nixpkgs", "hostPlatform", "system"] | ["nixpkgs", "system"] => {
+                set_once(&mut self.system, static_string(value));
+            }
+            ["networking", "interfaces", interface, "ipv4", "addresses"] => {
+                for address in list_elements(value) {
+                    let Some((set, env)) = attrset_of(&address) else {
+                        continue;
+                    };
+                    let Some(ip) =
+                        attr_value(&set, &env, &["address"]).and_then(|v| static_string(&v))
+                    else {
+                        continue;
+                    };
+                    let prefix_length = attr_value(&set, &env, &["prefixLength"])
+                        .and_then(|v| static_int(&v))
+                        .and_then(|prefix| u8::try_from(prefix).ok());
+                    self.interfaces.push(InterfaceAddress {
+                        interface: (*interface).to_string(),
+                        address: ip,
+                        prefix_length,
+                    });
+                }
+            }
+            ["networking", "firewall", "allowedTCPPorts"] => {
+                self.ports.extend(
+                    list_elements(value)
+                        .iter()
+                        .filter_map(static_int)
+                        .filter_map(|port| u16::try_from(port).ok()),
+                );
+            }
+            ["services", service, "enable"] if is_true(value) => {
+                self.services.insert((*service).to_string());
+            }
+            ["boot", "isContainer"] if is_true(value) => self.container = true,
+            _ => {}
+        }
+    }
+
+    fn into_facts(self, fallback_name: String) -> HostFacts {
+        HostFacts {
+            name: self.host_name.unwrap_or(fallback_name),
+            domain: self.domain,
+            system: self.system,
+            interfaces: self.interfaces,
+            allowed_tcp_ports: self.ports.into_iter().collect(),
+            services: self.services.into_iter().collect(),
+            container: self.container,
+            sources: self.sources,
+        }
+    }
+}
+
+fn set_once(slot: &mut Option<String>, value: Option<String>) {
+    if slot.is_none() {
+        *slot = value;
+    }
+}
+
+/// Flatten the definitions in `set` into `(option path, value)` pairs; at
+/// the top of a module (`at_top`) `config.` is stripped and non-config
+/// attributes are skipped
+fn collect(
+    set: &ast::AttrSet,
+    env: &Env,
+    prefix: &[String],
+    at_top: bool,
+    leaves: &mut Vec<(Vec<String>, Bound)>,
+) {
+    const MODULE_ATTRS: [&str; 5] = ["imports", "options", "disabledModules", "meta", "_file"];
+
+    for entry in set.entries() {
+        let ast::Entry::AttrpathValue(binding) = entry else {
+            continue;
+        };
+        let (Some(attrpath), Some(value)) = (binding.attrpath(), binding.value()) else {
+            continue;
+        };
+        let keys: Option<Vec<String>> = attrpath
+            .attrs()
+            .map(|attr| match key_of(&attr) {
+                Key::Static(name) => Some(name),
+                Key::Dynamic(_) => None,
+            })
+            .collect();
+        let Some(mut keys) = keys else {
+            continue;
+        };
+        if at_top {
+            match keys.first().map(String::as_str) {
+                Some("config") => {
+                    keys.remove(0);
+                }
+                Some(name) if MODULE_ATTRS.contains(&name) => continue,
+                _ => {}
+            }
+        }
+        let mut path = prefix.to_vec();
+        path.extend(keys);
+
+        let value = Bound {
+            expr: value,
+            env: env.clone(),
+        };
+        for value in unwrap_definition(&value, 0) {
+            let nested = matches!(
+                strip_parens(&value.expr),
+                ast::Expr::AttrSet(_) | ast::Expr::LetIn(_) | ast::Expr::With(_)
+            );
+            match attrset_of(&value) {
+                Some((inner, inner_env)) if nested => {
+                    collect(&inner, &inner_env, &path, false, leaves);
+                }
+                _ => leaves.push((path.clone(), value)),
+            }
+        }
+    }
+}
+
+/// The definitions behind `mkIf`, `mkMerge` and priority wrappers
+fn unwrap_definition(value: &Bound, depth: usize) -> Vec<Bound> {
+    if depth > MAX_DEPTH {
+        return Vec::new();
+    }
+    let value = match strip_parens(&value.expr) {
+        ast::Expr::Ident(ident) => match value.env.lookup(&ident_name(&ident)) {
+            Some(Binding::Expr(bound)) => return unwrap_definition(&bound, depth + 1),
+            _ => value.clone(),
+        },
+        _ => value.clone(),
+    };
+    let Some(call) = Call::of(&value.expr, &value.env) else {
+        return vec![value];
+    };
+    match (call.head.as_deref(), call.args.as_slice()) {
+        (
+            Some("mkIf" | "mkDefault" | "mkForce" | "mkOverride" | "mkBefore" | "mkAfter"),
+            [.., last],
+        ) => unwrap_definition(last, depth + 1),
+        (Some("mkMerge"), [list]) => list_elements(list)
+            .iter()
+            .flat_map(|item| unwrap_definition(item, depth + 1))
+            .collect(),
+        _ => vec![value],
+    }
+}
+
+fn is_true(value: &Bound) -> bool {
+    unwrap_definition(value, 0)
+        .iter()
+        .any(|value| strip_parens(&value.expr).syntax().text() == "true")
+}
+
+/// An integer literal, through variables
+fn static_int(value: &Bound) -> Option<i64> {
+    let mut current = value.clone();
+    for _ in 0..MAX_DEPTH {
+        match strip_parens(&current.expr) {
+            ast::Expr::Literal(literal) => return literal.syntax().text().to_string().parse().ok(),
+            ast::Expr::Ident(ident) => match current.env.lookup(&ident_name(&ident)) {
+                Some(Binding::Expr(next)) => current = next,
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOST: &str = r#"
+      { config, lib, pkgs, ... }:
+      let
+        httpPorts = [ 80 443 ];
+      in {
+        imports = [ ./hardware-configuration.nix <nixpkgs/nixos/modules/profiles/minimal.nix> ];
+
+        networking = {
+          hostName = "web01";
+          domain = "example.internal";
+          interfaces.eth0.ipv4.addresses = [ { address = "10.0.0.5"; prefixLength = 24; } ];
+          interfaces."enp3s0" = {
+            ipv4.addresses = [ { address = "192.168.1.5"; } ];
+          };
+          firewall.allowedTCPPorts = [ 22 ] ++ httpPorts;
+        };
+
+        nixpkgs.hostPlatform = lib.mkDefault "x86_64-linux";
+
+        services.nginx.enable = true;
+        services.openssh = {
+          enable = lib.mkForce true;
+          settings.PasswordAuthentication = false;
+        };
+        services.postgresql.enable = false;
+
+        config = lib.mkMerge [
+          { services.prometheus.exporters.node.enable = true; }
+          (lib.mkIf config.services.nginx.enable { services.fail2ban.enable = true; })
+        ];
+      }
+    "#;
+
+    #[test]
+    fn test_parse_host() {
+        let facts = HostReader::new().parse_host("fallback", HOST).unwrap();
+
+        assert_eq!(facts.name, "web01");
+        assert_eq!(facts.fqdn(), "web01.example.internal");
+        assert_eq!(facts.system.as_deref(), Some("x86_64-linux"));
+        assert_eq!(
+            facts.interfaces,
+            vec![
+                InterfaceAddress {
+                    interface: "eth0".to_string(),
+                    address: "10.0.0.5".to_string(),
+                    prefix_length: Some(24),
+                },
+                InterfaceAddress {
+                    interface: "enp3s0".to_string(),
+                    address: "192.168.1.5".to_string(),
+                    prefix_length: None,
+                },
+            ]
+        );
+        assert_eq!(facts.allowed_tcp_ports, vec![22, 80, 443]);
+        assert_eq!(facts.services, vec!["fail2ban", "nginx", "openssh"]);
+    }
+
+    #[test]
+    fn test_to_compute_resource() {
+        let facts = HostReader::new().parse_host("fallback", HOST).unwrap();
+        let resource = facts.to_compute_resource().unwrap();
+
+        assert_eq!(resource.hostname.as_str(), "web01.example.internal");
+        assert_eq!(resource.resource_type, ResourceType::PhysicalServer);
+        assert_eq!(resource.metadata["system"], "x86_64-linux");
+        assert_eq!(
+            resource.metadata["ipv4_addresses"],
+            "eth0=10.0.0.5/24,enp3s0=192.168.1.5"
+        );
+        assert_eq!(resource.metadata["tcp_ports"], "22,80,443");
+        assert_eq!(resource.metadata["services"], "fail2ban,nginx,openssh");
+    }
+
+    #[test]
+    fn test_fallback_name_and_vm() {
+        let facts = HostReader::new()
+            .parse_host(
+                "builder",
+                r#"{ nixpkgs.hostPlatform = { system = "aarch64-linux"; }; services.qemuGuest.enable = true; }"#,
+            )
+            .unwrap();
+
+        assert_eq!(facts.name, "builder");
+        assert_eq!(facts.system.as_deref(), Some("aarch64-linux"));
+        assert_eq!(facts.resource_type(), ResourceType::VirtualMachine);
+
+        let facts = HostReader::new()
+            .parse_host("ci", "{ boot.isContainer = true; }")
+            .unwrap();
+        assert!(facts.container);
+        assert_eq!(facts.resource_type(), ResourceType::VirtualMachine);
+    }
+
+    #[tokio::test]
+    async fn test_read_hosts_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let host = dir.path().join("db01");
+        std::fs::create_dir(&host).unwrap();
+        std::fs::write(
+            host.join("configuration.nix"),
+            r#"{ imports = [ ./hardware.nix ./missing.nix ]; services.postgresql.enable = true; }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            host.join("hardware.nix"),
+            r#"{ nixpkgs.hostPlatform = "x86_64-linux"; networking.hostName = "db01"; }"#,
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join("empty")).unwrap();
+
+        let hosts = HostReader::new().read_hosts_dir(dir.path()).await.unwrap();
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].name, "db01");
+        assert_eq!(hosts[0].system.as_deref(), Some("x86_64-linux"));
+        assert_eq!(hosts[0].services, vec!["postgresql"]);
+        assert_eq!(hosts[0].sources.len(), 2);
+
+        let hosts = HostReader::new()
+            .without_imports()
+            .read_hosts_dir(dir.path())
+            .await
+            .unwrap();
+        assert_eq!(hosts[0].system, None);
+    }
+}
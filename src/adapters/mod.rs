@@ -70,6 +70,29 @@
 //! # }
 //! ```
 //!
+//! ### TopologyLinter
+//!
+//! Checks topology files against configurable lint rules, with autofixes
+//! for some of them. See [`topology_lint`].
+//!
+//! ### TopologyLanguageService
+//!
+//! Diagnostics, completion, hover and go-to-definition for editors, served
+//! over stdio by the `topology-lsp` binary (feature `lsp`). See
+//! [`topology_lsp`].
+//!
+//! ### NatsProjector (Events → Nix)
+//!
+//! Consumes resource events from a `JetStream` durable consumer and keeps a
+//! topology file up to date, acknowledging each event once it is written.
+//! See [`nats_projector`].
+//!
+//! ### NatsPublisher (Nix → Events)
+//!
+//! Publishes the resources read from a topology as events, deduplicated by
+//! content so re-imports of unchanged files publish nothing new. See
+//! [`nats_publisher`].
+//!
 //! ## Design Principles
 //!
 //! 1. **Port/Adapter Pattern**: Clean separation between domain and infrastructure
@@ -78,10 +101,21 @@
 //! 4. **Functor-Based**: Type mappings use category theory functors
 //! 5. **NATS Integration**: Events flow through NATS JetStream
 
+pub mod events;
+pub mod host_reader;
+pub mod nats_projector;
+pub mod nats_publisher;
+pub mod topology_lint;
+pub mod topology_lsp;
 pub mod topology_reader;
 pub mod topology_writer;
-// pub mod nats_projector;   // TODO
 
 // Re-export for convenience
+pub use events::ResourceEvent;
+pub use host_reader::{HostFacts, HostReader};
+pub use nats_projector::NatsProjector;
+pub use nats_publisher::NatsPublisher;
+pub use topology_lint::{Diagnostic, LintConfig, LintReport, LintRule, TopologyLinter};
+pub use topology_lsp::TopologyLanguageService;
 pub use topology_reader::TopologyReader;
 pub use topology_writer::TopologyWriter;
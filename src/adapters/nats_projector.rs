@@ -0,0 +1,589 @@
+// Copyright (c) 2025 - Cowboy AI, Inc.
+//! NATS Projector: Infrastructure Events → nixos-topology
+//!
+//! A long-running projection that keeps a topology file up to date with
+//! the infrastructure event stream.
+//!
+//! ## Architecture
+//!
+//! ```text
+//! JetStream (durable consumer)
+//!     │
+//!     ▼ ResourceEvent
+//! TopologyWriter ──write──▶ topology.nix
+//!     │
+//!     ▼ ack
+//! JetStream
+//! ```
+//!
+//! ## Delivery Guarantees
+//!
+//! A message is acknowledged only after the file reflecting it has been
+//! written. If the write fails, the event is rolled back in memory, left
+//! unacknowledged and [`NatsProjector::run`] returns the error.
+//!
+//! On start the projector reads the existing file back with the
+//! [`TopologyReader`]; the durable consumer then delivers everything after
+//! the last acknowledged message. Re-applying an event is harmless, as
+//! nodes are keyed by hostname.
+//!
+//! Messages that do not decode as a [`ResourceEvent`] can never be
+//! projected; they are logged, acknowledged and skipped.
+//!
+//! ## Transports
+//!
+//! [`JetStreamEvents`] consumes a `JetStream` stream. [`MemoryStream`] is an
+//! in-process stand-in with the same durable-consumer behaviour, for tests.
+//!
+//! ## Usage
+//!
+//! ```rust,no_run
+//! use cim_domain_nix::adapters::nats_projector::{JetStreamConfig, JetStreamEvents, NatsProjector};
+//! use cim_domain_nix::adapters::TopologyWriter;
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let client = async_nats::connect("nats://localhost:4222").await?;
+//! let mut events = JetStreamEvents::connect(client, &JetStreamConfig::default()).await?;
+//!
+//! let mut projector = NatsProjector::new(TopologyWriter::new("topology.nix"));
+//! projector.run(&mut events).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use async_nats::jetstream::{self, consumer};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::fs;
+use tracing::{debug, warn};
+
+use crate::adapters::events::ResourceEvent;
+use crate::adapters::topology_reader::TopologyReader;
+use crate::adapters::topology_writer::TopologyWriter;
+
+// ============================================================================
+// Event Sources
+// ============================================================================
+
+/// A message delivered by an event source
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    /// Position in the stream, used to acknowledge the message
+    pub sequence: u64,
+    /// Subject the message was published to
+    pub subject: String,
+    /// Message body
+    pub payload: Bytes,
+}
+
+/// A stream of messages consumed with acknowledgement
+#[async_trait]
+pub trait EventSource: Send {
+    /// Next message, or `None` once the source is exhausted
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the transport fails.
+    async fn next(&mut self) -> Result<Option<Delivery>>;
+
+    /// Acknowledge a delivered message, so it is not delivered again
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the acknowledgement cannot be sent.
+    async fn ack(&mut self, sequence: u64) -> Result<()>;
+}
+
+/// Where [`JetStreamEvents`] consumes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JetStreamConfig {
+    /// Name of the stream holding infrastructure events
+    pub stream: String,
+    /// Name of the durable consumer, which tracks what was acknowledged
+    pub durable: String,
+    /// Subjects to consume
+    pub filter_subject: String,
+}
+
+impl Default for JetStreamConfig {
+    fn default() -> Self {
+        Self {
+            stream: "INFRASTRUCTURE_EVENTS".to_string(),
+            durable: "nix-topology-projector".to_string(),
+            filter_subject: "infrastructure.event.>".to_string(),
+        }
+    }
+}
+
+/// Events from a `JetStream` durable pull consumer
+pub struct JetStreamEvents {
+    messages: consumer::pull::Stream,
+    pending: HashMap<u64, jetstream::Message>,
+}
+
+impl std::fmt::Debug for JetStreamEvents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JetStreamEvents")
+            .field("pending", &self.pending.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl JetStreamEvents {
+    /// Consume a stream with a durable consumer, creating the consumer if
+    /// it does not exist
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the stream does not exist or the consumer cannot
+    /// be created.
+    pub async fn connect(client: async_nats::Client, config: &JetStreamConfig) -> Result<Self> {
+        let stream = jetstream::new(client)
+            .get_stream(&config.stream)
+            .await
+            .with_context(|| format!("Failed to get stream {}", config.stream))?;
+        let consumer = stream
+            .get_or_create_consumer(
+                &config.durable,
+                consumer::pull::Config {
+                    durable_name: Some(config.durable.clone()),
+                    ack_policy: consumer::AckPolicy::Explicit,
+                    filter_subject: config.filter_subject.clone(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .with_context(|| format!("Failed to create consumer {}", config.durable))?;
+        let messages = consumer
+            .messages()
+            .await
+            .context("Failed to consume messages")?;
+
+        Ok(Self {
+            messages,
+            pending: HashMap::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl EventSource for JetStreamEvents {
+    async fn next(&mut self) -> Result<Option<Delivery>> {
+        let Some(message) = self.messages.next().await else {
+            return Ok(None);
+        };
+        let message = message.context("Failed to receive message")?;
+        let sequence = message.info().map_err(|e| anyhow!(e))?.stream_sequence;
+        let delivery = Delivery {
+            sequence,
+            subject: message.subject.to_string(),
+            payload: message.payload.clone(),
+        };
+        self.pending.insert(sequence, message);
+        Ok(Some(delivery))
+    }
+
+    async fn ack(&mut self, sequence: u64) -> Result<()> {
+        let message = self
+            .pending
+            .remove(&sequence)
+            .with_context(|| format!("No pending message {sequence}"))?;
+        message.ack().await.map_err(|e| anyhow!(e))
+    }
+}
+
+/// In-process stand-in for a `JetStream` stream
+///
+/// Durable consumers remember what was acknowledged: a new consumer with
+/// the same name starts after the last acknowledged message. As with
+/// `JetStream`, acknowledgements in order move that point forward.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStream {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    messages: Vec<(String, Bytes)>,
+    acked: HashMap<String, u64>,
+    ids: HashMap<String, u64>,
+}
+
+impl MemoryStream {
+    /// Create an empty stream
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a message, returning its sequence
+    pub fn publish(&self, subject: impl Into<String>, payload: impl Into<Bytes>) -> u64 {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.messages.push((subject.into(), payload.into()));
+        state.messages.len() as u64
+    }
+
+    /// Append a message unless one with the same id was appended before,
+    /// as `JetStream` deduplication does, but without a time window
+    ///
+    /// Returns the sequence of the message with that id, and whether it
+    /// was a duplicate.
+    pub fn publish_unique(
+        &self,
+        subject: impl Into<String>,
+        id: &str,
+        payload: impl Into<Bytes>,
+    ) -> (u64, bool) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(&sequence) = state.ids.get(id) {
+            return (sequence, true);
+        }
+        state.messages.push((subject.into(), payload.into()));
+        let sequence = state.messages.len() as u64;
+        state.ids.insert(id.to_string(), sequence);
+        (sequence, false)
+    }
+
+    /// Last sequence a durable consumer acknowledged
+    pub fn ack_floor(&self, durable: &str) -> u64 {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.acked.get(durable).copied().unwrap_or(0)
+    }
+
+    /// Consume with a durable consumer, from after its last acknowledgement
+    pub fn consumer(&self, durable: impl Into<String>) -> MemoryEvents {
+        let durable = durable.into();
+        MemoryEvents {
+            next: self.ack_floor(&durable) + 1,
+            stream: self.clone(),
+            durable,
+        }
+    }
+}
+
+/// A durable consumer of a [`MemoryStream`]
+///
+/// Ends once every published message has been delivered.
+#[derive(Debug)]
+pub struct MemoryEvents {
+    stream: MemoryStream,
+    durable: String,
+    next: u64,
+}
+
+#[async_trait]
+impl EventSource for MemoryEvents {
+    async fn next(&mut self) -> Result<Option<Delivery>> {
+        let state = self
+            .stream
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let Some((subject, payload)) = usize::try_from(self.next - 1)
+            .ok()
+            .and_then(|index| state.messages.get(index))
+        else {
+            return Ok(None);
+        };
+        let delivery = Delivery {
+            sequence: self.next,
+            subject: subject.clone(),
+            payload: payload.clone(),
+        };
+        self.next += 1;
+        Ok(Some(delivery))
+    }
+
+    async fn ack(&mut self, sequence: u64) -> Result<()> {
+        let mut state = self
+            .stream
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let floor = state.acked.entry(self.durable.clone()).or_default();
+        *floor = (*floor).max(sequence);
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Projector
+// ============================================================================
+
+/// Counts of what a projector did with the messages it was given
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProjectionStats {
+    /// Events written to the topology
+    pub projected: u64,
+    /// Messages that were not resource events
+    pub skipped: u64,
+}
+
+/// Projects resource events into a topology file
+#[derive(Debug, Clone)]
+pub struct NatsProjector {
+    writer: TopologyWriter,
+    reader: TopologyReader,
+}
+
+impl NatsProjector {
+    /// Create a projector writing through `writer`
+    pub fn new(writer: TopologyWriter) -> Self {
+        Self {
+            writer,
+            reader: TopologyReader::new(),
+        }
+    }
+
+    /// Read the existing file back with this reader on start
+    #[must_use]
+    pub fn with_reader(mut self, reader: TopologyReader) -> Self {
+        self.reader = reader;
+        self
+    }
+
+    /// The topology as projected so far
+    pub fn writer(&self) -> &TopologyWriter {
+        &self.writer
+    }
+
+    /// Load the nodes of the existing topology file, if there is one
+    ///
+    /// Returns the number of nodes loaded.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the file exists but cannot be read.
+    pub async fn restore(&mut self) -> Result<usize> {
+        let path = self.writer.output_path().to_path_buf();
+        if !fs::try_exists(&path).await.unwrap_or(false) {
+            return Ok(0);
+        }
+        let resources = self
+            .reader
+            .read_topology_file(&path)
+            .await
+            .context("Failed to restore projection")?;
+        for resource in &resources {
+            self.writer.add_node(resource)?;
+        }
+        Ok(resources.len())
+    }
+
+    /// Apply an event to the in-memory topology
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the writer rejects the resource.
+    pub fn apply(&mut self, event: &ResourceEvent) -> Result<()> {
+        match event {
+            ResourceEvent::Registered { resource } => self.writer.add_node(resource),
+            ResourceEvent::Updated { resource } => self.writer.update_node(resource),
+            ResourceEvent::Removed { hostname } => {
+                self.writer.remove_node(hostname.short_name());
+                Ok(())
+            }
+        }
+    }
+
+    /// Project one message: apply it, write the file, then acknowledge it
+    ///
+    /// Returns whether the message was a resource event.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the event cannot be applied or written, leaving
+    /// the message unacknowledged, or if the acknowledgement fails.
+    pub async fn project(
+        &mut self,
+        source: &mut (impl EventSource + ?Sized),
+        delivery: &Delivery,
+    ) -> Result<bool> {
+        let event = match serde_json::from_slice::<ResourceEvent>(&delivery.payload) {
+            Ok(event) => event,
+            Err(error) => {
+                warn!(
+                    "Skipping message {} on {}: {}",
+                    delivery.sequence, delivery.subject, error
+                );
+                source.ack(delivery.sequence).await?;
+                return Ok(false);
+            }
+        };
+
+        let previous = self.writer.clone();
+        let written = match self.apply(&event) {
+            Ok(()) => self.writer.write_to_file().await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = written {
+            self.writer = previous;
+            return Err(error.context(format!(
+                "Failed to project {} event for {} (message {})",
+                event.action(),
+                event.hostname(),
+                delivery.sequence
+            )));
+        }
+
+        debug!(
+            "Projected {} event for {} (message {})",
+            event.action(),
+            event.hostname(),
+            delivery.sequence
+        );
+        source.ack(delivery.sequence).await?;
+        Ok(true)
+    }
+
+    /// Restore the existing file, then project messages until the source
+    /// ends
+    ///
+    /// `JetStream` sources do not end; this runs until an error.
+    ///
+    /// ## Errors
+    ///
+    /// Returns the first error from restoring, the source or projecting.
+    pub async fn run(
+        &mut self,
+        source: &mut (impl EventSource + ?Sized),
+    ) -> Result<ProjectionStats> {
+        self.restore().await?;
+
+        let mut stats = ProjectionStats::default();
+        while let Some(delivery) = source.next().await? {
+            if self.project(source, &delivery).await? {
+                stats.projected += 1;
+            } else {
+                stats.skipped += 1;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nix::SecretScanner;
+    use cim_infrastructure::{ComputeResource, Hostname, ResourceType};
+
+    fn event(event: &ResourceEvent) -> Vec<u8> {
+        serde_json::to_vec(event).unwrap()
+    }
+
+    fn registered(hostname: &str, resource_type: ResourceType) -> Vec<u8> {
+        let resource =
+            ComputeResource::new(Hostname::new(hostname).unwrap(), resource_type).unwrap();
+        event(&ResourceEvent::Registered { resource })
+    }
+
+    #[tokio::test]
+    async fn test_project_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("topology.nix");
+        let stream = MemoryStream::new();
+        stream.publish(
+            "infrastructure.event.compute_resource.registered",
+            registered("web01", ResourceType::PhysicalServer),
+        );
+        stream.publish(
+            "infrastructure.event.compute_resource.registered",
+            registered("sw01", ResourceType::Switch),
+        );
+        stream.publish("infrastructure.event.other", "not json");
+        stream.publish(
+            "infrastructure.event.compute_resource.removed",
+            event(&ResourceEvent::Removed {
+                hostname: Hostname::new("sw01").unwrap(),
+            }),
+        );
+
+        let mut projector = NatsProjector::new(TopologyWriter::new(&path));
+        let stats = projector.run(&mut stream.consumer("test")).await.unwrap();
+        assert_eq!(
+            stats,
+            ProjectionStats {
+                projected: 3,
+                skipped: 1
+            }
+        );
+        assert_eq!(stream.ack_floor("test"), 4);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("web01"));
+        assert!(!content.contains("sw01"));
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("topology.nix");
+        let stream = MemoryStream::new();
+        stream.publish(
+            "infrastructure.event.compute_resource.registered",
+            registered("web01", ResourceType::PhysicalServer),
+        );
+        stream.publish(
+            "infrastructure.event.compute_resource.registered",
+            registered("rt01", ResourceType::Router),
+        );
+
+        let mut projector = NatsProjector::new(TopologyWriter::new(&path));
+        projector.run(&mut stream.consumer("test")).await.unwrap();
+
+        // A new process only sees what arrived since
+        stream.publish(
+            "infrastructure.event.compute_resource.registered",
+            registered("sw01", ResourceType::Switch),
+        );
+        let mut projector = NatsProjector::new(TopologyWriter::new(&path));
+        let stats = projector.run(&mut stream.consumer("test")).await.unwrap();
+        assert_eq!(stats.projected, 1);
+        assert_eq!(projector.writer().node_count(), 3);
+
+        let resources = TopologyReader::new()
+            .read_topology_file(&path)
+            .await
+            .unwrap();
+        assert_eq!(resources.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_is_not_acked() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("topology.nix");
+        let mut resource =
+            ComputeResource::new(Hostname::new("sw01").unwrap(), ResourceType::Switch).unwrap();
+        resource
+            .add_metadata("snmp_community", "n0t-public")
+            .unwrap();
+
+        let stream = MemoryStream::new();
+        stream.publish(
+            "infrastructure.event.compute_resource.registered",
+            registered("web01", ResourceType::PhysicalServer),
+        );
+        stream.publish(
+            "infrastructure.event.compute_resource.registered",
+            event(&ResourceEvent::Registered { resource }),
+        );
+
+        let writer = TopologyWriter::new(&path).with_secret_scanner(SecretScanner::new());
+        let mut projector = NatsProjector::new(writer);
+        let mut events = stream.consumer("test");
+        let err = projector.run(&mut events).await.unwrap_err();
+        assert!(format!("{err:#}").contains("sw01"));
+
+        assert_eq!(stream.ack_floor("test"), 1);
+        assert!(!projector.writer().has_node("sw01"));
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("sw01"));
+
+        // The rejected event is delivered again
+        let delivery = stream.consumer("test").next().await.unwrap().unwrap();
+        assert_eq!(delivery.sequence, 2);
+    }
+}
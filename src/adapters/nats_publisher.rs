@@ -0,0 +1,416 @@
+// Copyright (c) 2025 - Cowboy AI, Inc.
+//! NATS Publisher: Imported Resources → `JetStream`
+//!
+//! Publishes the resources a [`TopologyReader`](super::TopologyReader)
+//! discovers as [`ResourceEvent`]s.
+//!
+//! ## Subjects
+//!
+//! Events follow the CIM convention `{domain}.event.{aggregate}.{action}`
+//! (see `doc/nats-subject-mapping.md`), e.g.
+//! `infrastructure.event.compute_resource.registered`.
+//!
+//! ## Deduplication
+//!
+//! Each message carries a `Nats-Msg-Id` header with the event's
+//! [`message_id`](ResourceEvent::message_id), a UUID derived from its
+//! content. Re-importing an unchanged file publishes the same ids, and
+//! `JetStream` drops them as duplicates within the stream's duplicate window.
+//! Retries after a lost acknowledgement are deduplicated the same way.
+//!
+//! ## Batching
+//!
+//! Messages are sent in batches without waiting in between; the
+//! acknowledgements of a batch are then awaited together. Messages that
+//! were not acknowledged are sent again, with a growing delay, up to a
+//! maximum number of attempts.
+//!
+//! ## Usage
+//!
+//! ```rust,no_run
+//! use cim_domain_nix::adapters::nats_publisher::{JetStreamSink, NatsPublisher};
+//! use cim_domain_nix::adapters::TopologyReader;
+//! use std::path::Path;
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let resources = TopologyReader::new()
+//!     .read_topology_file(Path::new("topology.nix"))
+//!     .await?;
+//!
+//! let client = async_nats::connect("nats://localhost:4222").await?;
+//! let publisher = NatsPublisher::new(JetStreamSink::new(client));
+//! let report = publisher.publish_resources(&resources).await?;
+//! println!("{} published, {} already known", report.published, report.duplicates);
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{anyhow, bail, Result};
+use async_nats::jetstream::{self, context::Publish};
+use async_trait::async_trait;
+use bytes::Bytes;
+use cim_infrastructure::ComputeResource;
+use std::time::Duration;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::adapters::events::ResourceEvent;
+use crate::adapters::nats_projector::MemoryStream;
+
+// ============================================================================
+// Event Sinks
+// ============================================================================
+
+/// An event ready to be published
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventMessage {
+    /// Subject to publish to
+    pub subject: String,
+    /// Deduplication id, sent as `Nats-Msg-Id`
+    pub id: Uuid,
+    /// JSON-encoded event
+    pub payload: Bytes,
+}
+
+/// Acknowledgement of a stored message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublishAck {
+    /// Sequence of the message in the stream
+    pub sequence: u64,
+    /// Whether the stream already had a message with this id
+    pub duplicate: bool,
+}
+
+/// Where events are published
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    /// Publish messages and wait until each is stored
+    ///
+    /// Returns one result per message, in order.
+    async fn publish_batch(&self, messages: &[EventMessage]) -> Vec<Result<PublishAck>>;
+}
+
+/// Publishes to `JetStream`, with acknowledgements
+#[derive(Debug, Clone)]
+pub struct JetStreamSink {
+    context: jetstream::Context,
+}
+
+impl JetStreamSink {
+    /// Publish through a connected client
+    pub fn new(client: async_nats::Client) -> Self {
+        Self {
+            context: jetstream::new(client),
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for JetStreamSink {
+    async fn publish_batch(&self, messages: &[EventMessage]) -> Vec<Result<PublishAck>> {
+        let mut sent = Vec::with_capacity(messages.len());
+        for message in messages {
+            let publish = Publish::build()
+                .payload(message.payload.clone())
+                .message_id(message.id.to_string());
+            sent.push(
+                self.context
+                    .send_publish(message.subject.clone(), publish)
+                    .await,
+            );
+        }
+
+        let mut acks = Vec::with_capacity(sent.len());
+        for ack in sent {
+            let ack = match ack {
+                Ok(ack) => ack.await.map_err(|e| anyhow!(e)),
+                Err(error) => Err(anyhow!(error)),
+            };
+            acks.push(ack.map(|ack| PublishAck {
+                sequence: ack.sequence,
+                duplicate: ack.duplicate,
+            }));
+        }
+        acks
+    }
+}
+
+#[async_trait]
+impl EventSink for MemoryStream {
+    async fn publish_batch(&self, messages: &[EventMessage]) -> Vec<Result<PublishAck>> {
+        messages
+            .iter()
+            .map(|message| {
+                let (sequence, duplicate) = self.publish_unique(
+                    message.subject.clone(),
+                    &message.id.to_string(),
+                    message.payload.clone(),
+                );
+                Ok(PublishAck {
+                    sequence,
+                    duplicate,
+                })
+            })
+            .collect()
+    }
+}
+
+// ============================================================================
+// Publisher
+// ============================================================================
+
+/// What a publish stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PublishReport {
+    /// Events stored as new messages
+    pub published: usize,
+    /// Events the stream already had
+    pub duplicates: usize,
+}
+
+/// Publishes resource events in acknowledged, retried batches
+#[derive(Debug, Clone)]
+pub struct NatsPublisher<S> {
+    sink: S,
+    domain: String,
+    batch_size: usize,
+    max_attempts: u32,
+    backoff: Duration,
+}
+
+impl<S: EventSink> NatsPublisher<S> {
+    /// Publish to `sink` under the `infrastructure` domain, in batches of
+    /// 100 with up to 3 attempts
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink,
+            domain: "infrastructure".to_string(),
+            batch_size: 100,
+            max_attempts: 3,
+            backoff: Duration::from_millis(500),
+        }
+    }
+
+    /// Use another subject domain
+    #[must_use]
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = domain.into();
+        self
+    }
+
+    /// Send at most this many messages before awaiting acknowledgements
+    #[must_use]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Try each message up to `max_attempts` times, waiting `backoff`
+    /// times the attempt number in between
+    #[must_use]
+    pub fn with_retry(mut self, max_attempts: u32, backoff: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.backoff = backoff;
+        self
+    }
+
+    /// The message an event is published as
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the event cannot be serialized.
+    pub fn message(&self, event: &ResourceEvent) -> Result<EventMessage> {
+        Ok(EventMessage {
+            subject: event.subject(&self.domain),
+            id: event.message_id()?,
+            payload: serde_json::to_vec(event)?.into(),
+        })
+    }
+
+    /// Publish a `registered` event for each resource
+    ///
+    /// ## Errors
+    ///
+    /// See [`NatsPublisher::publish`].
+    pub async fn publish_resources(&self, resources: &[ComputeResource]) -> Result<PublishReport> {
+        let events: Vec<_> = resources
+            .iter()
+            .map(|resource| ResourceEvent::Registered {
+                resource: resource.clone(),
+            })
+            .collect();
+        self.publish(&events).await
+    }
+
+    /// Publish events, in order
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an event cannot be serialized, or is still not
+    /// acknowledged after the last attempt. Batches before it stay
+    /// published.
+    pub async fn publish(&self, events: &[ResourceEvent]) -> Result<PublishReport> {
+        let messages = events
+            .iter()
+            .map(|event| self.message(event))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut report = PublishReport::default();
+        for batch in messages.chunks(self.batch_size) {
+            let mut pending = batch.to_vec();
+            let mut attempt = 1;
+            loop {
+                let acks = self.sink.publish_batch(&pending).await;
+                let mut failed = Vec::new();
+                let mut last_error = None;
+                for (message, ack) in pending.into_iter().zip(acks) {
+                    match ack {
+                        Ok(ack) if ack.duplicate => report.duplicates += 1,
+                        Ok(_) => report.published += 1,
+                        Err(error) => {
+                            failed.push(message);
+                            last_error = Some(error);
+                        }
+                    }
+                }
+                let Some(error) = last_error else {
+                    break;
+                };
+                if attempt >= self.max_attempts {
+                    bail!(
+                        "{} events not acknowledged after {attempt} attempts: {error:#}",
+                        failed.len()
+                    );
+                }
+
+                warn!(
+                    "Retrying {} unacknowledged events (attempt {attempt}): {error:#}",
+                    failed.len()
+                );
+                tokio::time::sleep(self.backoff * attempt).await;
+                attempt += 1;
+                pending = failed;
+            }
+        }
+
+        debug!(
+            "Published {} events, {} duplicates",
+            report.published, report.duplicates
+        );
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::nats_projector::{EventSource, NatsProjector};
+    use crate::adapters::{TopologyReader, TopologyWriter};
+    use std::sync::Mutex;
+
+    const TOPOLOGY: &str = r#"{
+      nodes = {
+        rt01 = { type = "router"; };
+        sw01 = { type = "switch"; metadata = { rack = "r1"; }; };
+        web01 = { type = "server"; };
+      };
+    }"#;
+
+    /// Fails every message of the first `failures` batches
+    struct FlakySink {
+        stream: MemoryStream,
+        failures: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl EventSink for FlakySink {
+        async fn publish_batch(&self, messages: &[EventMessage]) -> Vec<Result<PublishAck>> {
+            {
+                let mut failures = self.failures.lock().unwrap();
+                if *failures > 0 {
+                    *failures -= 1;
+                    return messages.iter().map(|_| Err(anyhow!("timed out"))).collect();
+                }
+            }
+            self.stream.publish_batch(messages).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reimport_is_deduplicated() {
+        let stream = MemoryStream::new();
+        let publisher = NatsPublisher::new(stream.clone()).with_batch_size(2);
+
+        let resources = TopologyReader::new().parse_topology(TOPOLOGY).unwrap();
+        let report = publisher.publish_resources(&resources).await.unwrap();
+        assert_eq!(report.published, 3);
+
+        let delivery = stream.consumer("test").next().await.unwrap().unwrap();
+        assert!(delivery
+            .subject
+            .starts_with("infrastructure.event.compute_resource.registered"));
+
+        // Reading again generates new resource ids, but the same messages
+        let resources = TopologyReader::new().parse_topology(TOPOLOGY).unwrap();
+        let report = publisher.publish_resources(&resources).await.unwrap();
+        assert_eq!(
+            report,
+            PublishReport {
+                published: 0,
+                duplicates: 3
+            }
+        );
+
+        let changed = TOPOLOGY.replace("r1", "r2");
+        let resources = TopologyReader::new().parse_topology(&changed).unwrap();
+        let report = publisher.publish_resources(&resources).await.unwrap();
+        assert_eq!(report.published, 1);
+
+        let mut consumer = stream.consumer("count");
+        let mut stored = 0;
+        while consumer.next().await.unwrap().is_some() {
+            stored += 1;
+        }
+        assert_eq!(stored, 4);
+    }
+
+    #[tokio::test]
+    async fn test_retry_unacknowledged() {
+        let stream = MemoryStream::new();
+        let resources = TopologyReader::new().parse_topology(TOPOLOGY).unwrap();
+
+        let sink = FlakySink {
+            stream: stream.clone(),
+            failures: Mutex::new(1),
+        };
+        let publisher = NatsPublisher::new(sink).with_retry(2, Duration::ZERO);
+        let report = publisher.publish_resources(&resources).await.unwrap();
+        assert_eq!(report.published, 3);
+
+        let sink = FlakySink {
+            stream: stream.clone(),
+            failures: Mutex::new(2),
+        };
+        let publisher = NatsPublisher::new(sink).with_retry(2, Duration::ZERO);
+        let err = publisher.publish_resources(&resources).await.unwrap_err();
+        assert!(err.to_string().contains("3 events not acknowledged"));
+    }
+
+    #[tokio::test]
+    async fn test_published_events_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("topology.nix");
+        let stream = MemoryStream::new();
+
+        let resources = TopologyReader::new().parse_topology(TOPOLOGY).unwrap();
+        NatsPublisher::new(stream.clone())
+            .publish_resources(&resources)
+            .await
+            .unwrap();
+
+        let mut projector = NatsProjector::new(TopologyWriter::new(&path));
+        let stats = projector.run(&mut stream.consumer("test")).await.unwrap();
+        assert_eq!(stats.projected, 3);
+        assert!(projector.writer().has_node("sw01"));
+    }
+}
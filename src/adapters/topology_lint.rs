@@ -0,0 +1,1178 @@
+// Copyright (c) 2025 - Cowboy AI, Inc.
+//! Topology Lint Engine
+//!
+//! Rule-based checks over topology files, run before (or instead of)
+//! reading them into `ComputeResource`s.
+//!
+//! ## Built-in Rules
+//!
+//! | Id                     | Flags                                              | Severity | Autofix |
+//! |------------------------|----------------------------------------------------|----------|---------|
+//! | `unknown-type`         | nodes without a `type`, or with one strict mode rejects | warning | no   |
+//! | `missing-hostname`     | nodes that rely on their name as hostname          | info     | yes     |
+//! | `duplicate-hostname`   | nodes sharing a hostname                           | error    | no      |
+//! | `invalid-metadata-key` | metadata keys the reader drops                     | warning  | yes     |
+//! | `dangling-connection`  | `connections` whose `from` or `to` is not a node   | error    | no      |
+//!
+//! Further rules implement [`LintRule`] and are added with
+//! [`TopologyLinter::with_rule`].
+//!
+//! ## Configuration
+//!
+//! Rules are switched off or given another severity per repository in
+//! `topology-lint.toml`:
+//!
+//! ```toml
+//! [rules]
+//! missing-hostname = "off"
+//! unknown-type = "error"
+//! ```
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use cim_domain_nix::adapters::topology_lint::TopologyLinter;
+//!
+//! let source = r#"{ nodes = { web01 = { type = "server"; metadata = { rackUnit = "4"; }; }; }; }"#;
+//! let report = TopologyLinter::new().lint_source(source).unwrap();
+//! assert_eq!(report.diagnostics.len(), 2);
+//!
+//! let fixed = report.apply_fixes(source);
+//! assert!(fixed.contains(r#"hostname = "web01";"#));
+//! assert!(fixed.contains("rack_unit"));
+//! ```
+
+use anyhow::{bail, Context, Result};
+use cim_infrastructure::{ComputeResource, Hostname, ResourceType};
+use rnix::ast::{self, HasEntry};
+use rowan::ast::AstNode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::adapters::topology_reader::TopologyReader;
+use crate::nix::ast::{NixAst, Span};
+use crate::nix::{NixAttrset, NixValue, Severity};
+
+/// Name of the per-repository lint configuration file
+pub const LINT_CONFIG_FILE: &str = "topology-lint.toml";
+
+// ============================================================================
+// Topology Document
+// ============================================================================
+
+/// An attribute of a topology node or connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// Attribute name
+    pub key: String,
+    /// Value, if it is a literal
+    pub value: Option<String>,
+    /// Location of the name, for topologies read from source
+    pub key_span: Option<Span>,
+    /// Location of the value, for topologies read from source
+    pub span: Option<Span>,
+}
+
+/// Where new attributes are inserted into a node written in source
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Insertion {
+    offset: usize,
+    prefix: String,
+    suffix: String,
+}
+
+/// A node of a topology, as far as the lint rules look at it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopologyNode {
+    /// Node name
+    pub name: String,
+    /// Location of the node's definition
+    pub span: Option<Span>,
+    /// `type` attribute
+    pub node_type: Option<Field>,
+    /// `hostname` attribute
+    pub hostname: Option<Field>,
+    /// Entries of the `metadata` attribute set
+    pub metadata: Vec<Field>,
+    insertion: Option<Insertion>,
+}
+
+impl TopologyNode {
+    /// Hostname the reader gives the node: `hostname`, or else its name
+    pub fn effective_hostname(&self) -> &str {
+        self.hostname
+            .as_ref()
+            .and_then(|field| field.value.as_deref())
+            .unwrap_or(&self.name)
+    }
+
+    /// Edit adding `name = value;` to the node, for nodes read from source
+    pub fn insert_attribute(&self, name: &str, value: &str) -> Option<TextEdit> {
+        let insertion = self.insertion.as_ref()?;
+        Some(TextEdit {
+            span: Span::new(insertion.offset, insertion.offset),
+            replacement: format!("{}{name} = {value};{}", insertion.prefix, insertion.suffix),
+        })
+    }
+}
+
+/// An entry of the top-level `connections` list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopologyConnection {
+    /// `from` endpoint
+    pub from: Option<Field>,
+    /// `to` endpoint
+    pub to: Option<Field>,
+    /// Location of the connection
+    pub span: Option<Span>,
+}
+
+/// The parts of a topology file the lint rules check
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TopologyDocument {
+    /// Nodes, in source order
+    pub nodes: Vec<TopologyNode>,
+    /// Connections, in source order
+    pub connections: Vec<TopologyConnection>,
+}
+
+impl TopologyDocument {
+    /// Read a topology from source, with locations
+    ///
+    /// Like the syntactic [`TopologyReader`], only literal attributes are
+    /// seen.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the source does not parse or has no `nodes`
+    /// attribute set.
+    pub fn parse(source: &str) -> Result<Self> {
+        let ast = NixAst::parse(source).context("Failed to parse topology")?;
+        let expr = ast.expr().context("Topology file is empty")?;
+        let Some((topology, nodes)) = find_nodes(&expr) else {
+            bail!("Could not find 'nodes' attribute set in topology file");
+        };
+
+        let mut document = Self::default();
+        for (name, binding) in bindings(&nodes) {
+            document.nodes.push(parse_node(source, &name, &binding));
+        }
+        let connections = bindings(&topology)
+            .into_iter()
+            .filter(|(name, _)| name == &["connections"])
+            .filter_map(|(_, binding)| match binding.value()? {
+                ast::Expr::List(list) => Some(list),
+                _ => None,
+            });
+        for list in connections {
+            for item in list.items() {
+                if let ast::Expr::AttrSet(set) = item {
+                    document.connections.push(parse_connection(&set));
+                }
+            }
+        }
+        Ok(document)
+    }
+
+    /// Read an evaluated topology, without locations
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the value has no `nodes` attribute set.
+    pub fn from_value(value: &NixValue) -> Result<Self> {
+        let Some((topology, nodes)) = find_nodes_value(value) else {
+            bail!("Could not find 'nodes' attribute set in topology file");
+        };
+
+        let text = |value: &NixValue| match value {
+            NixValue::String(s) => Some(s.value.clone()),
+            NixValue::Integer(i) => Some(i.value.to_string()),
+            NixValue::Float(f) => Some(f.value.to_string()),
+            NixValue::Bool(b) => Some(b.value.to_string()),
+            NixValue::Path(p) => Some(p.as_str().to_string()),
+            _ => None,
+        };
+        let field = |attrs: &NixAttrset, key: &str| {
+            attrs.get(key).map(|value| Field {
+                key: key.to_string(),
+                value: text(value),
+                key_span: None,
+                span: None,
+            })
+        };
+
+        let mut names: Vec<&String> = nodes.keys().collect();
+        names.sort();
+        let mut document = Self::default();
+        for name in names {
+            let mut node = TopologyNode {
+                name: name.clone(),
+                span: None,
+                node_type: None,
+                hostname: None,
+                metadata: Vec::new(),
+                insertion: None,
+            };
+            if let NixValue::Attrset(attrs) = &nodes.attributes[name] {
+                node.node_type = field(attrs, "type");
+                node.hostname = field(attrs, "hostname");
+                if let Some(NixValue::Attrset(metadata)) = attrs.get("metadata") {
+                    let mut keys: Vec<&String> = metadata.keys().collect();
+                    keys.sort();
+                    node.metadata = keys
+                        .into_iter()
+                        .filter_map(|key| field(metadata, key))
+                        .collect();
+                }
+            }
+            document.nodes.push(node);
+        }
+        if let Some(NixValue::List(connections)) = topology.get("connections") {
+            for connection in &connections.elements {
+                if let NixValue::Attrset(attrs) = connection {
+                    document.connections.push(TopologyConnection {
+                        from: field(attrs, "from"),
+                        to: field(attrs, "to"),
+                        span: None,
+                    });
+                }
+            }
+        }
+        Ok(document)
+    }
+
+    /// Node by name
+    pub fn node(&self, name: &str) -> Option<&TopologyNode> {
+        self.nodes.iter().find(|node| node.name == name)
+    }
+}
+
+/// The attribute set holding `nodes`, searched the way the reader does
+pub(crate) fn find_nodes(expr: &ast::Expr) -> Option<(ast::AttrSet, ast::AttrSet)> {
+    expr.syntax()
+        .descendants()
+        .filter_map(ast::AttrSet::cast)
+        .find_map(|set| {
+            let nodes = bindings(&set).into_iter().find_map(|(name, binding)| {
+                match binding.value()? {
+                    ast::Expr::AttrSet(nodes) if name == ["nodes"] => Some(nodes),
+                    _ => None,
+                }
+            })?;
+            Some((set, nodes))
+        })
+}
+
+fn find_nodes_value(value: &NixValue) -> Option<(&NixAttrset, &NixAttrset)> {
+    let mut queue = std::collections::VecDeque::from([value]);
+    while let Some(value) = queue.pop_front() {
+        if let NixValue::Attrset(attrs) = value {
+            if let Some(NixValue::Attrset(nodes)) = attrs.get("nodes") {
+                return Some((attrs, nodes));
+            }
+            queue.extend(attrs.values());
+        }
+    }
+    None
+}
+
+/// Bindings of an attribute set whose names are all static
+pub(crate) fn bindings(set: &ast::AttrSet) -> Vec<(Vec<String>, ast::AttrpathValue)> {
+    set.entries()
+        .filter_map(|entry| match entry {
+            ast::Entry::AttrpathValue(binding) => {
+                let names = binding
+                    .attrpath()?
+                    .attrs()
+                    .map(|attr| static_name(&attr))
+                    .collect::<Option<Vec<_>>>()?;
+                Some((names, binding))
+            }
+            ast::Entry::Inherit(_) => None,
+        })
+        .collect()
+}
+
+fn static_name(attr: &ast::Attr) -> Option<String> {
+    match attr {
+        ast::Attr::Ident(ident) => Some(ident.syntax().text().to_string()),
+        ast::Attr::Str(string) => literal_string(string),
+        ast::Attr::Dynamic(_) => None,
+    }
+}
+
+fn literal_string(string: &ast::Str) -> Option<String> {
+    let mut text = String::new();
+    for part in string.normalized_parts() {
+        match part {
+            ast::InterpolPart::Literal(literal) => text.push_str(&literal),
+            ast::InterpolPart::Interpolation(_) => return None,
+        }
+    }
+    Some(text)
+}
+
+fn literal_text(expr: &ast::Expr) -> Option<String> {
+    match expr {
+        ast::Expr::Str(string) => literal_string(string),
+        ast::Expr::Literal(_) | ast::Expr::Path(_) => Some(expr.syntax().text().to_string()),
+        ast::Expr::Ident(ident) => {
+            let name = ident.syntax().text().to_string();
+            matches!(name.as_str(), "true" | "false" | "null").then_some(name)
+        }
+        _ => None,
+    }
+}
+
+fn field_of(key: &str, binding: &ast::AttrpathValue) -> Field {
+    let value = binding.value();
+    Field {
+        key: key.to_string(),
+        value: value.as_ref().and_then(literal_text),
+        key_span: binding
+            .attrpath()
+            .and_then(|path| path.attrs().last())
+            .map(|attr| Span::of(attr.syntax())),
+        span: value.map(|value| Span::of(value.syntax())),
+    }
+}
+
+fn parse_node(source: &str, name: &[String], binding: &ast::AttrpathValue) -> TopologyNode {
+    let mut node = TopologyNode {
+        name: name.join("."),
+        span: Some(Span::of(binding.syntax())),
+        node_type: None,
+        hostname: None,
+        metadata: Vec::new(),
+        insertion: None,
+    };
+    let Some(ast::Expr::AttrSet(body)) = binding.value() else {
+        return node;
+    };
+
+    let entries = bindings(&body);
+    node.insertion = match (body.l_curly_token(), entries.first()) {
+        (Some(l_curly), Some((_, first))) => {
+            let offset = usize::from(first.syntax().text_range().start());
+            let gap = &source[usize::from(l_curly.text_range().end())..offset];
+            let suffix = match gap.rfind('\n') {
+                Some(newline) => format!("\n{}", &gap[newline + 1..]),
+                None => " ".to_string(),
+            };
+            Some(Insertion {
+                offset,
+                prefix: String::new(),
+                suffix,
+            })
+        }
+        (Some(l_curly), None) => Some(Insertion {
+            offset: usize::from(l_curly.text_range().end()),
+            prefix: " ".to_string(),
+            suffix: String::new(),
+        }),
+        (None, _) => None,
+    };
+
+    for (keys, entry) in entries {
+        match keys
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            ["type"] => node.node_type = Some(field_of("type", &entry)),
+            ["hostname"] => node.hostname = Some(field_of("hostname", &entry)),
+            ["metadata"] => {
+                if let Some(ast::Expr::AttrSet(metadata)) = entry.value() {
+                    for (keys, meta) in bindings(&metadata) {
+                        if let [key] = keys.as_slice() {
+                            node.metadata.push(field_of(key, &meta));
+                        }
+                    }
+                }
+            }
+            ["metadata", key] => node.metadata.push(field_of(key, &entry)),
+            _ => {}
+        }
+    }
+    node
+}
+
+fn parse_connection(set: &ast::AttrSet) -> TopologyConnection {
+    let mut connection = TopologyConnection {
+        from: None,
+        to: None,
+        span: Some(Span::of(set.syntax())),
+    };
+    for (keys, entry) in bindings(set) {
+        match keys
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            ["from"] => connection.from = Some(field_of("from", &entry)),
+            ["to"] => connection.to = Some(field_of("to", &entry)),
+            _ => {}
+        }
+    }
+    connection
+}
+
+// ============================================================================
+// Diagnostics
+// ============================================================================
+
+/// A replacement of part of the source
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextEdit {
+    /// Replaced range; empty to insert
+    pub span: Span,
+    /// New text
+    pub replacement: String,
+}
+
+/// An automatic fix for a finding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fix {
+    /// What the fix does
+    pub description: String,
+    /// Edits to apply together
+    pub edits: Vec<TextEdit>,
+}
+
+/// A finding reported by a rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// Human-readable explanation
+    pub message: String,
+    /// Node the finding is about
+    pub node: Option<String>,
+    /// Location of the finding
+    pub span: Option<Span>,
+    /// Automatic fix
+    pub fix: Option<Fix>,
+}
+
+impl Lint {
+    /// A finding about a node
+    pub fn node(node: &TopologyNode, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            node: Some(node.name.clone()),
+            span: node.span,
+            fix: None,
+        }
+    }
+
+    /// Point the finding at a location, if known
+    #[must_use]
+    pub fn at(mut self, span: Option<Span>) -> Self {
+        self.span = span.or(self.span);
+        self
+    }
+
+    /// Attach a fix
+    #[must_use]
+    pub fn with_fix(mut self, description: impl Into<String>, edits: Vec<TextEdit>) -> Self {
+        if !edits.is_empty() {
+            self.fix = Some(Fix {
+                description: description.into(),
+                edits,
+            });
+        }
+        self
+    }
+}
+
+/// A finding with the rule and severity it was reported under
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// Rule id
+    pub rule: String,
+    /// Configured severity
+    pub severity: Severity,
+    /// Human-readable explanation
+    pub message: String,
+    /// Node the finding is about
+    pub node: Option<String>,
+    /// Location of the finding
+    pub span: Option<Span>,
+    /// Automatic fix
+    pub fix: Option<Fix>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.rule, self.message)
+    }
+}
+
+/// Result of linting a topology
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintReport {
+    /// Findings, in source order
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl LintReport {
+    /// Severity of the worst finding
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.diagnostics.iter().map(|d| d.severity).max()
+    }
+
+    /// Whether no finding reaches `threshold`
+    pub fn passes(&self, threshold: Severity) -> bool {
+        self.max_severity().is_none_or(|worst| worst < threshold)
+    }
+
+    /// Findings for one rule
+    pub fn by_rule<'a>(&'a self, rule: &'a str) -> impl Iterator<Item = &'a Diagnostic> {
+        self.diagnostics.iter().filter(move |d| d.rule == rule)
+    }
+
+    /// Apply every fix to the source the report was made from
+    ///
+    /// A fix whose edits overlap an earlier fix is left out; lint the
+    /// result again to pick it up.
+    pub fn apply_fixes(&self, source: &str) -> String {
+        let mut fixes: Vec<&Fix> = self
+            .diagnostics
+            .iter()
+            .filter_map(|d| d.fix.as_ref())
+            .collect();
+        fixes.sort_by_key(|fix| fix.edits.iter().map(|edit| edit.span.start).min());
+
+        let mut edits: Vec<&TextEdit> = Vec::new();
+        for fix in fixes {
+            let overlaps = fix.edits.iter().any(|edit| {
+                edits.iter().any(|kept| {
+                    edit.span.start < kept.span.end.max(kept.span.start + 1)
+                        && kept.span.start < edit.span.end.max(edit.span.start + 1)
+                })
+            });
+            if !overlaps {
+                edits.extend(&fix.edits);
+            }
+        }
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.span.start));
+
+        let mut output = source.to_string();
+        for edit in edits {
+            output.replace_range(edit.span.start..edit.span.end, &edit.replacement);
+        }
+        output
+    }
+}
+
+// ============================================================================
+// Rules
+// ============================================================================
+
+/// A check over a topology
+pub trait LintRule: Send + Sync {
+    /// Identifier used in reports and configuration (`kebab-case`)
+    fn id(&self) -> &'static str;
+
+    /// One-line summary of what the rule checks
+    fn description(&self) -> &'static str;
+
+    /// Severity unless configured otherwise
+    fn default_severity(&self) -> Severity;
+
+    /// Findings for a topology
+    fn check(&self, topology: &TopologyDocument) -> Vec<Lint>;
+}
+
+/// Nodes without a `type`, or with one strict mode rejects
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnknownType;
+
+impl LintRule for UnknownType {
+    fn id(&self) -> &'static str {
+        "unknown-type"
+    }
+
+    fn description(&self) -> &'static str {
+        "node types the reader does not recognise"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, topology: &TopologyDocument) -> Vec<Lint> {
+        let strict = TopologyReader::new_strict();
+        topology
+            .nodes
+            .iter()
+            .filter_map(|node| match &node.node_type {
+                None => Some(Lint::node(
+                    node,
+                    format!("node '{}' has no type and is skipped", node.name),
+                )),
+                Some(Field {
+                    value: Some(value),
+                    span,
+                    ..
+                }) if strict.parse_topology_type(value).is_err() => Some(
+                    Lint::node(
+                        node,
+                        format!(
+                            "node '{}' has unknown type '{value}' and is read as a device",
+                            node.name
+                        ),
+                    )
+                    .at(*span),
+                ),
+                Some(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Nodes that rely on their name as hostname
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MissingHostname;
+
+impl LintRule for MissingHostname {
+    fn id(&self) -> &'static str {
+        "missing-hostname"
+    }
+
+    fn description(&self) -> &'static str {
+        "nodes without an explicit hostname"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Info
+    }
+
+    fn check(&self, topology: &TopologyDocument) -> Vec<Lint> {
+        topology
+            .nodes
+            .iter()
+            .filter(|node| node.hostname.is_none())
+            .map(|node| {
+                let value = format!(
+                    "\"{}\"",
+                    node.name.replace('\\', "\\\\").replace('"', "\\\"")
+                );
+                Lint::node(node, format!("node '{}' has no hostname", node.name)).with_fix(
+                    format!("set hostname to {value}"),
+                    node.insert_attribute("hostname", &value)
+                        .into_iter()
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Nodes sharing a hostname
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DuplicateHostname;
+
+impl LintRule for DuplicateHostname {
+    fn id(&self) -> &'static str {
+        "duplicate-hostname"
+    }
+
+    fn description(&self) -> &'static str {
+        "hostnames used by more than one node"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, topology: &TopologyDocument) -> Vec<Lint> {
+        let mut seen: HashMap<String, &str> = HashMap::new();
+        let mut lints = Vec::new();
+        for node in &topology.nodes {
+            let hostname = node.effective_hostname();
+            match seen.get(&hostname.to_lowercase()) {
+                Some(first) => lints.push(
+                    Lint::node(
+                        node,
+                        format!(
+                            "node '{}' uses hostname '{hostname}' of node '{first}'",
+                            node.name
+                        ),
+                    )
+                    .at(node.hostname.as_ref().and_then(|field| field.span)),
+                ),
+                None => {
+                    seen.insert(hostname.to_lowercase(), &node.name);
+                }
+            }
+        }
+        lints
+    }
+}
+
+/// Metadata keys the reader drops
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InvalidMetadataKey;
+
+impl InvalidMetadataKey {
+    /// Whether a `ComputeResource` accepts the key
+    fn accepted(key: &str) -> bool {
+        Hostname::new("lint")
+            .ok()
+            .and_then(|hostname| ComputeResource::new(hostname, ResourceType::PhysicalServer).ok())
+            .is_some_and(|mut resource| resource.add_metadata(key, "").is_ok())
+    }
+
+    /// `snake_case` version of a key (`rackUnit`, `rack-unit` → `rack_unit`)
+    fn suggestion(key: &str) -> String {
+        let mut suggestion = String::new();
+        for (i, c) in key.chars().enumerate() {
+            if c.is_ascii_uppercase() && i > 0 && !suggestion.ends_with('_') {
+                suggestion.push('_');
+            }
+            if c.is_ascii_alphanumeric() {
+                suggestion.push(c.to_ascii_lowercase());
+            } else if !suggestion.ends_with('_') {
+                suggestion.push('_');
+            }
+        }
+        suggestion.trim_matches('_').to_string()
+    }
+}
+
+impl LintRule for InvalidMetadataKey {
+    fn id(&self) -> &'static str {
+        "invalid-metadata-key"
+    }
+
+    fn description(&self) -> &'static str {
+        "metadata keys that are not recorded on the resource"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, topology: &TopologyDocument) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        for node in &topology.nodes {
+            for field in &node.metadata {
+                if Self::accepted(&field.key) {
+                    continue;
+                }
+                let suggestion = Self::suggestion(&field.key);
+                let lint = Lint::node(
+                    node,
+                    format!(
+                        "metadata key '{}' of node '{}' is not a valid key and is dropped",
+                        field.key, node.name
+                    ),
+                )
+                .at(field.key_span);
+                let lint = match field.key_span {
+                    Some(span) if !suggestion.is_empty() && Self::accepted(&suggestion) => {
+                        let replacement = if suggestion.starts_with(|c: char| c.is_ascii_digit()) {
+                            format!("\"{suggestion}\"")
+                        } else {
+                            suggestion.clone()
+                        };
+                        lint.with_fix(
+                            format!("rename to '{suggestion}'"),
+                            vec![TextEdit { span, replacement }],
+                        )
+                    }
+                    _ => lint,
+                };
+                lints.push(lint);
+            }
+        }
+        lints
+    }
+}
+
+/// `connections` whose `from` or `to` is not a node
+///
+/// An endpoint names a node, optionally followed by `.<interface>`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DanglingConnection;
+
+impl LintRule for DanglingConnection {
+    fn id(&self) -> &'static str {
+        "dangling-connection"
+    }
+
+    fn description(&self) -> &'static str {
+        "connections to nodes that do not exist"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, topology: &TopologyDocument) -> Vec<Lint> {
+        let nodes: HashSet<&str> = topology.nodes.iter().map(|n| n.name.as_str()).collect();
+        let exists = |endpoint: &str| {
+            nodes.contains(endpoint)
+                || endpoint
+                    .split_once('.')
+                    .is_some_and(|(node, _)| nodes.contains(node))
+        };
+
+        let mut lints = Vec::new();
+        for connection in &topology.connections {
+            for (end, field) in [("from", &connection.from), ("to", &connection.to)] {
+                let message = match field {
+                    None => format!("connection has no '{end}' endpoint"),
+                    Some(Field {
+                        value: Some(value), ..
+                    }) if !exists(value) => {
+                        format!("connection '{end}' endpoint '{value}' is not a node")
+                    }
+                    // Computed endpoints cannot be checked without evaluation
+                    Some(_) => continue,
+                };
+                lints.push(Lint {
+                    message,
+                    node: None,
+                    span: field
+                        .as_ref()
+                        .and_then(|field| field.span)
+                        .or(connection.span),
+                    fix: None,
+                });
+            }
+        }
+        lints
+    }
+}
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// Level a rule is reported at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    /// Not checked
+    Off,
+    /// Reported as [`Severity::Info`]
+    Info,
+    /// Reported as [`Severity::Warning`]
+    Warning,
+    /// Reported as [`Severity::Error`]
+    Error,
+}
+
+/// Per-repository rule settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintConfig {
+    /// Level by rule id; rules not listed keep their default severity
+    #[serde(default)]
+    pub rules: BTreeMap<String, RuleLevel>,
+}
+
+impl LintConfig {
+    /// Parse a configuration file's contents
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the TOML is invalid.
+    pub fn from_toml(content: &str) -> Result<Self> {
+        toml::from_str(content).context("Invalid topology lint configuration")
+    }
+
+    /// Load [`LINT_CONFIG_FILE`] from a repository, or the default
+    /// configuration if there is none
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(LINT_CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            std::fs::read_to_string(&path).context(format!("Failed to read {}", path.display()))?;
+        Self::from_toml(&content).context(format!("In {}", path.display()))
+    }
+}
+
+// ============================================================================
+// Linter
+// ============================================================================
+
+/// Runs lint rules over topologies
+#[derive(Clone)]
+pub struct TopologyLinter {
+    rules: Vec<Arc<dyn LintRule>>,
+    config: LintConfig,
+}
+
+impl fmt::Debug for TopologyLinter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TopologyLinter")
+            .field(
+                "rules",
+                &self.rules.iter().map(|r| r.id()).collect::<Vec<_>>(),
+            )
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl TopologyLinter {
+    /// Create a linter with the built-in rules
+    pub fn new() -> Self {
+        Self::empty()
+            .with_rule(UnknownType)
+            .with_rule(MissingHostname)
+            .with_rule(DuplicateHostname)
+            .with_rule(InvalidMetadataKey)
+            .with_rule(DanglingConnection)
+    }
+
+    /// Create a linter without rules
+    pub fn empty() -> Self {
+        Self {
+            rules: Vec::new(),
+            config: LintConfig::default(),
+        }
+    }
+
+    /// Add a rule, replacing a rule with the same id
+    #[must_use]
+    pub fn with_rule(mut self, rule: impl LintRule + 'static) -> Self {
+        self.rules.retain(|existing| existing.id() != rule.id());
+        self.rules.push(Arc::new(rule));
+        self
+    }
+
+    /// Apply per-repository settings
+    ///
+    /// Add custom rules first: the configuration may only name rules the
+    /// linter has.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the configuration names an unknown rule.
+    pub fn with_config(mut self, config: LintConfig) -> Result<Self> {
+        for id in config.rules.keys() {
+            if !self.rules.iter().any(|rule| rule.id() == id) {
+                bail!("Unknown topology lint rule '{id}'");
+            }
+        }
+        self.config = config;
+        Ok(self)
+    }
+
+    /// Rules with the severity they are reported at, `None` if off
+    pub fn rules(&self) -> impl Iterator<Item = (&dyn LintRule, Option<Severity>)> {
+        self.rules
+            .iter()
+            .map(|rule| (rule.as_ref(), self.severity(rule.as_ref())))
+    }
+
+    fn severity(&self, rule: &dyn LintRule) -> Option<Severity> {
+        match self.config.rules.get(rule.id()) {
+            None => Some(rule.default_severity()),
+            Some(RuleLevel::Off) => None,
+            Some(RuleLevel::Info) => Some(Severity::Info),
+            Some(RuleLevel::Warning) => Some(Severity::Warning),
+            Some(RuleLevel::Error) => Some(Severity::Error),
+        }
+    }
+
+    /// Lint a topology
+    pub fn lint(&self, topology: &TopologyDocument) -> LintReport {
+        let mut diagnostics = Vec::new();
+        for (rule, severity) in self.rules() {
+            let Some(severity) = severity else {
+                continue;
+            };
+            diagnostics.extend(rule.check(topology).into_iter().map(|lint| Diagnostic {
+                rule: rule.id().to_string(),
+                severity,
+                message: lint.message,
+                node: lint.node,
+                span: lint.span,
+                fix: lint.fix,
+            }));
+        }
+        diagnostics.sort_by_key(|d| d.span.map(|span| span.start));
+        LintReport { diagnostics }
+    }
+
+    /// Lint a topology file's source
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the source does not parse or has no `nodes`.
+    pub fn lint_source(&self, source: &str) -> Result<LintReport> {
+        Ok(self.lint(&TopologyDocument::parse(source)?))
+    }
+
+    /// Lint an evaluated topology
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the value has no `nodes`.
+    pub fn lint_value(&self, value: &NixValue) -> Result<LintReport> {
+        Ok(self.lint(&TopologyDocument::from_value(value)?))
+    }
+}
+
+impl Default for TopologyLinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOPOLOGY: &str = r#"{
+  nodes = {
+    router01 = {
+      type = "router";
+      hostname = "gw";
+    };
+    web01 = {
+      type = "server";
+      metadata = { rackUnit = "4"; "poe-capable" = "true"; rack = "r1"; };
+    };
+    gw = { type = "toaster"; hostname = "GW"; };
+    orphan = { hostname = "orphan"; };
+  };
+  connections = [
+    { from = "web01.eth0"; to = "router01"; }
+    { from = "web01"; to = "switch99"; }
+    { to = "router01"; }
+  ];
+}"#;
+
+    fn rules(report: &LintReport) -> Vec<(&str, Option<&str>)> {
+        report
+            .diagnostics
+            .iter()
+            .map(|d| (d.rule.as_str(), d.node.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        let report = TopologyLinter::new().lint_source(TOPOLOGY).unwrap();
+
+        assert_eq!(
+            rules(&report),
+            [
+                ("missing-hostname", Some("web01")),
+                ("invalid-metadata-key", Some("web01")),
+                ("invalid-metadata-key", Some("web01")),
+                ("unknown-type", Some("gw")),
+                ("duplicate-hostname", Some("gw")),
+                ("unknown-type", Some("orphan")),
+                ("dangling-connection", None),
+                ("dangling-connection", None),
+            ]
+        );
+        assert_eq!(report.max_severity(), Some(Severity::Error));
+        let dangling: Vec<_> = report.by_rule("dangling-connection").collect();
+        assert_eq!(
+            dangling[0].message,
+            "connection 'to' endpoint 'switch99' is not a node"
+        );
+        assert_eq!(
+            dangling[1].span.unwrap().slice(TOPOLOGY),
+            r#"{ to = "router01"; }"#
+        );
+        assert_eq!(
+            report.diagnostics[4].span.unwrap().slice(TOPOLOGY),
+            r#""GW""#
+        );
+    }
+
+    #[test]
+    fn test_apply_fixes() {
+        let report = TopologyLinter::new().lint_source(TOPOLOGY).unwrap();
+        let fixed = report.apply_fixes(TOPOLOGY);
+
+        assert!(
+            fixed.contains("    web01 = {\n      hostname = \"web01\";\n      type = \"server\";")
+        );
+        assert!(fixed
+            .contains(r#"metadata = { rack_unit = "4"; poe_capable = "true"; rack = "r1"; };"#));
+
+        let relinted = TopologyLinter::new().lint_source(&fixed).unwrap();
+        assert_eq!(relinted.by_rule("missing-hostname").count(), 0);
+        assert_eq!(relinted.by_rule("invalid-metadata-key").count(), 0);
+
+        let empty = r"{ nodes = { a = { }; }; }";
+        let fixed = TopologyLinter::new()
+            .lint_source(empty)
+            .unwrap()
+            .apply_fixes(empty);
+        assert_eq!(fixed, r#"{ nodes = { a = { hostname = "a"; }; }; }"#);
+    }
+
+    #[test]
+    fn test_config() {
+        let config = LintConfig::from_toml(
+            r#"
+            [rules]
+            missing-hostname = "off"
+            invalid-metadata-key = "off"
+            dangling-connection = "warning"
+            "#,
+        )
+        .unwrap();
+        let linter = TopologyLinter::new().with_config(config).unwrap();
+        let report = linter.lint_source(TOPOLOGY).unwrap();
+
+        assert_eq!(report.by_rule("missing-hostname").count(), 0);
+        assert!(report
+            .by_rule("dangling-connection")
+            .all(|d| d.severity == Severity::Warning));
+        assert_eq!(
+            linter
+                .rules()
+                .filter(|(_, severity)| severity.is_none())
+                .count(),
+            2
+        );
+
+        let typo = LintConfig::from_toml("[rules]\nmissing-hostnme = \"off\"").unwrap();
+        assert!(TopologyLinter::new().with_config(typo).is_err());
+        assert!(LintConfig::from_toml("[rules]\nunknown-type = \"loud\"").is_err());
+    }
+
+    #[test]
+    fn test_evaluated_topology() {
+        let value = crate::nix::eval::Evaluator::new()
+            .eval_str(
+                r#"let server = { type = "server"; }; in {
+                  nodes = { a = server; b = server // { hostname = "a"; }; };
+                  connections = [ { from = "a"; to = "c"; } ];
+                }"#,
+            )
+            .unwrap();
+        let report = TopologyLinter::new().lint_value(&value).unwrap();
+
+        assert_eq!(
+            rules(&report),
+            [
+                ("missing-hostname", Some("a")),
+                ("duplicate-hostname", Some("b")),
+                ("dangling-connection", None),
+            ]
+        );
+        assert!(report.diagnostics.iter().all(|d| d.fix.is_none()));
+    }
+}
@@ -0,0 +1,593 @@
+// Copyright (c) 2025 - Cowboy AI, Inc.
+//! Topology Language Service
+//!
+//! Editor support for topology files, independent of the protocol that
+//! carries it. The `topology-lsp` binary (feature `lsp`) serves it over
+//! stdio.
+//!
+//! - **Diagnostics**: syntax errors, [`TopologyReader`] errors and
+//!   [`TopologyLinter`] findings
+//! - **Completion**: node `type` values, node attributes and connection
+//!   endpoints
+//! - **Hover**: the `ResourceType` a node is read as, and what writing it
+//!   back loses
+//! - **Go to definition**: from connection endpoints to the nodes they name
+//!
+//! Locations are byte offsets; [`LineIndex`] converts them to and from the
+//! zero-based line and UTF-16 column positions editors use.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use cim_domain_nix::adapters::topology_lsp::TopologyLanguageService;
+//!
+//! let source = r#"{ nodes = { web01 = { type = "server"; }; }; }"#;
+//! let service = TopologyLanguageService::new();
+//!
+//! let hover = service.hover(source, source.find("server").unwrap()).unwrap();
+//! assert!(hover.contents.contains("PhysicalServer"));
+//! assert!(hover.contents.contains("physical-server"));
+//! ```
+
+use rnix::ast;
+use rowan::ast::AstNode;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::adapters::topology_lint::{
+    bindings, find_nodes, Diagnostic, TopologyDocument, TopologyLinter, TopologyNode,
+};
+use crate::adapters::topology_reader::TopologyReader;
+use crate::adapters::topology_writer::topology_type_name;
+use crate::functors::resource_type_functor::{
+    get_resource_types_for_topology, map_topology_to_resource_type, TopologyNodeType,
+};
+use crate::nix::ast::Span;
+use crate::nix::workspace::{FileKind, WorkspaceIndexer};
+use crate::nix::Severity;
+
+/// Attributes of a node the reader looks at
+pub const NODE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("type", "nixos-topology node type"),
+    ("hostname", "hostname, defaults to the node name"),
+    ("manufacturer", "hardware manufacturer"),
+    ("model", "hardware model, read with manufacturer"),
+    ("metadata", "string attributes kept as resource metadata"),
+];
+
+/// Node types, in the order they are offered
+const NODE_TYPES: [TopologyNodeType; 9] = [
+    TopologyNodeType::PhysicalServer,
+    TopologyNodeType::VirtualMachine,
+    TopologyNodeType::Container,
+    TopologyNodeType::Router,
+    TopologyNodeType::Switch,
+    TopologyNodeType::Firewall,
+    TopologyNodeType::LoadBalancer,
+    TopologyNodeType::Storage,
+    TopologyNodeType::Device,
+];
+
+// ============================================================================
+// Positions
+// ============================================================================
+
+/// Converts byte offsets to editor positions and back
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Index the lines of a source
+    pub fn new(source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { source, starts }
+    }
+
+    /// The indexed source
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Zero-based line and UTF-16 column of a byte offset
+    pub fn position(&self, offset: usize) -> (u32, u32) {
+        let offset = offset.min(self.source.len());
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.source[self.starts[line]..offset]
+            .encode_utf16()
+            .count();
+        (
+            u32::try_from(line).unwrap_or(u32::MAX),
+            u32::try_from(column).unwrap_or(u32::MAX),
+        )
+    }
+
+    /// Byte offset of a zero-based line and UTF-16 column
+    ///
+    /// Positions past the end of a line are clamped to it.
+    pub fn offset(&self, line: u32, column: u32) -> usize {
+        let line = line as usize;
+        let Some(&start) = self.starts.get(line) else {
+            return self.source.len();
+        };
+        let end = self
+            .starts
+            .get(line + 1)
+            .map_or(self.source.len(), |next| next - 1);
+
+        let mut units = 0;
+        for (i, c) in self.source[start..end].char_indices() {
+            if units >= column as usize {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        end
+    }
+}
+
+// ============================================================================
+// Results
+// ============================================================================
+
+/// What a completion inserts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// A node `type` value
+    NodeType,
+    /// A node attribute name
+    Attribute,
+    /// A node name, as a connection endpoint
+    Node,
+}
+
+/// A completion candidate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// Text shown and matched against what was typed
+    pub label: String,
+    /// What the candidate is
+    pub kind: CompletionKind,
+    /// Short explanation
+    pub detail: String,
+    /// Text inserted, if it differs from the label
+    pub insert_text: Option<String>,
+}
+
+/// Hover contents for part of a topology
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoverInfo {
+    /// Markdown contents
+    pub contents: String,
+    /// Source the hover is about
+    pub span: Span,
+}
+
+// ============================================================================
+// Language Service
+// ============================================================================
+
+/// Editor features for topology files
+#[derive(Debug, Clone, Default)]
+pub struct TopologyLanguageService {
+    reader: TopologyReader,
+    linter: TopologyLinter,
+}
+
+impl TopologyLanguageService {
+    /// Create a service with the default reader and lint rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report errors of this reader instead of the default one
+    #[must_use]
+    pub fn with_reader(mut self, reader: TopologyReader) -> Self {
+        self.reader = reader;
+        self
+    }
+
+    /// Report findings of this linter instead of the default one
+    #[must_use]
+    pub fn with_linter(mut self, linter: TopologyLinter) -> Self {
+        self.linter = linter;
+        self
+    }
+
+    /// Whether a file is a topology the service should handle
+    ///
+    /// Files named `topology.nix` (or ending in it) always are; others are
+    /// classified by content, like the [`WorkspaceIndexer`] does.
+    pub fn is_topology(path: &Path, source: &str) -> bool {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with("topology.nix"))
+            || WorkspaceIndexer::new().index_source(path, source).kind == FileKind::Topology
+    }
+
+    /// Problems in a topology
+    ///
+    /// Syntax errors are reported alone. Otherwise a reader error, without
+    /// a location, comes before the lint findings.
+    pub fn diagnostics(&self, source: &str) -> Vec<Diagnostic> {
+        let parse = rnix::Root::parse(source);
+        let errors = parse.errors();
+        if !errors.is_empty() {
+            return errors
+                .iter()
+                .map(|error| Diagnostic {
+                    rule: "syntax".to_string(),
+                    severity: Severity::Error,
+                    message: error.to_string(),
+                    node: None,
+                    span: Some(error_span(error, source.len())),
+                    fix: None,
+                })
+                .collect();
+        }
+
+        let mut diagnostics = Vec::new();
+        if let Err(error) = self.reader.parse_topology(source) {
+            diagnostics.push(Diagnostic {
+                rule: "reader".to_string(),
+                severity: Severity::Error,
+                message: format!("{error:#}"),
+                node: None,
+                span: None,
+                fix: None,
+            });
+        }
+        // A topology the linter cannot read has already failed the reader
+        if let Ok(report) = self.linter.lint_source(source) {
+            diagnostics.extend(report.diagnostics);
+        }
+        diagnostics
+    }
+
+    /// Completions at an offset
+    ///
+    /// Works on sources with syntax errors, as they are while typing.
+    pub fn completions(&self, source: &str, offset: usize) -> Vec<Completion> {
+        let Some(expr) = rnix::Root::parse(source).tree().expr() else {
+            return Vec::new();
+        };
+        let Some((topology, nodes)) = find_nodes(&expr) else {
+            return Vec::new();
+        };
+        let contains = |node: &rnix::SyntaxNode| {
+            let span = Span::of(node);
+            span.start <= offset && offset <= span.end
+        };
+
+        for (_, binding) in bindings(&nodes) {
+            let Some(ast::Expr::AttrSet(body)) = binding.value() else {
+                continue;
+            };
+            if !inside(&body, offset, source.len()) {
+                continue;
+            }
+
+            let entries = bindings(&body);
+            for (keys, entry) in &entries {
+                if entry.value().is_some_and(|value| contains(value.syntax())) {
+                    return if *keys == ["type"] {
+                        type_completions()
+                    } else {
+                        Vec::new()
+                    };
+                }
+            }
+            let present: HashSet<&str> = entries
+                .iter()
+                .filter(|(_, entry)| !entry.attrpath().is_some_and(|path| contains(path.syntax())))
+                .filter_map(|(keys, _)| keys.first().map(String::as_str))
+                .collect();
+            return NODE_ATTRIBUTES
+                .iter()
+                .filter(|(name, _)| !present.contains(name))
+                .map(|(name, detail)| Completion {
+                    label: (*name).to_string(),
+                    kind: CompletionKind::Attribute,
+                    detail: (*detail).to_string(),
+                    insert_text: Some(format!("{name} = ")),
+                })
+                .collect();
+        }
+
+        let in_endpoint = bindings(&topology)
+            .into_iter()
+            .filter(|(name, _)| *name == ["connections"])
+            .filter_map(|(_, binding)| match binding.value()? {
+                ast::Expr::List(list) => Some(list),
+                _ => None,
+            })
+            .flat_map(|list| list.items())
+            .filter_map(|item| match item {
+                ast::Expr::AttrSet(set) => Some(set),
+                _ => None,
+            })
+            .flat_map(|set| bindings(&set))
+            .any(|(keys, entry)| {
+                (keys == ["from"] || keys == ["to"])
+                    && entry.value().is_some_and(|value| contains(value.syntax()))
+            });
+        if !in_endpoint {
+            return Vec::new();
+        }
+        let document = TopologyDocument::parse(source).ok();
+        bindings(&nodes)
+            .into_iter()
+            .map(|(name, _)| {
+                let name = name.join(".");
+                let detail = document
+                    .as_ref()
+                    .and_then(|document| document.node(&name))
+                    .and_then(|node| node.node_type.as_ref()?.value.clone())
+                    .unwrap_or_else(|| "node".to_string());
+                Completion {
+                    label: name,
+                    kind: CompletionKind::Node,
+                    detail,
+                    insert_text: None,
+                }
+            })
+            .collect()
+    }
+
+    /// Hover for the node at an offset
+    ///
+    /// Shows the `ResourceType` the node is read as, and warns when writing
+    /// the resource back would not reproduce it.
+    pub fn hover(&self, source: &str, offset: usize) -> Option<HoverInfo> {
+        let document = TopologyDocument::parse(source).ok()?;
+        let node = document
+            .nodes
+            .iter()
+            .find(|node| node.span.is_some_and(|span| within(span, offset)))?;
+        let span = node
+            .node_type
+            .as_ref()
+            .and_then(|field| field.span)
+            .filter(|span| within(*span, offset))
+            .or(node.span)?;
+        Some(HoverInfo {
+            contents: self.describe(node),
+            span,
+        })
+    }
+
+    /// Node a connection endpoint at an offset refers to
+    ///
+    /// Endpoints may name an interface as `node.interface`.
+    pub fn definition(&self, source: &str, offset: usize) -> Option<Span> {
+        let document = TopologyDocument::parse(source).ok()?;
+        let endpoint = document
+            .connections
+            .iter()
+            .flat_map(|connection| [&connection.from, &connection.to])
+            .flatten()
+            .find(|field| field.span.is_some_and(|span| within(span, offset)))?;
+        let name = endpoint.value.as_deref()?;
+        document
+            .node(name)
+            .or_else(|| document.node(name.split_once('.')?.0))?
+            .span
+    }
+
+    fn describe(&self, node: &TopologyNode) -> String {
+        let mut contents = format!("**{}**", node.name);
+        let Some(type_str) = node
+            .node_type
+            .as_ref()
+            .and_then(|field| field.value.as_deref())
+        else {
+            contents.push_str("\n\nNo literal `type`: the reader skips this node.");
+            return contents;
+        };
+        let Ok(node_type) = self.reader.parse_topology_type(type_str) else {
+            let _ = write!(
+                contents,
+                "\n\nType `{type_str}` is not recognised and is rejected in strict mode."
+            );
+            return contents;
+        };
+
+        let resource_type = map_topology_to_resource_type(node_type);
+        let _ = write!(
+            contents,
+            "\n\nType `{type_str}` is read as `ResourceType::{resource_type:?}` \
+             (topology `{node_type:?}`), hostname `{}`.",
+            node.effective_hostname()
+        );
+
+        let written = topology_type_name(node_type);
+        let others: Vec<String> = get_resource_types_for_topology(node_type)
+            .into_iter()
+            .filter(|other| *other != resource_type)
+            .map(|other| format!("{other:?}"))
+            .collect();
+        if written != type_str {
+            let _ = write!(
+                contents,
+                "\n\n⚠ Roundtrip: written back as `type = \"{written}\";`."
+            );
+        }
+        if !others.is_empty() {
+            let _ = write!(
+                contents,
+                "\n\n⚠ Roundtrip: `{written}` is also written for {}; those resources \
+                 are read back as `{resource_type:?}`.",
+                others.join(", ")
+            );
+        }
+        contents
+    }
+}
+
+fn type_completions() -> Vec<Completion> {
+    NODE_TYPES
+        .iter()
+        .map(|&node_type| Completion {
+            label: topology_type_name(node_type).to_string(),
+            kind: CompletionKind::NodeType,
+            detail: format!(
+                "ResourceType::{:?}",
+                map_topology_to_resource_type(node_type)
+            ),
+            insert_text: None,
+        })
+        .collect()
+}
+
+fn within(span: Span, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+/// Whether an offset is between the braces of an attribute set
+fn inside(set: &ast::AttrSet, offset: usize, len: usize) -> bool {
+    let start = set
+        .l_curly_token()
+        .map_or(usize::MAX, |token| usize::from(token.text_range().end()));
+    let end = set
+        .r_curly_token()
+        .map_or(len, |token| usize::from(token.text_range().start()));
+    start <= offset && offset <= end
+}
+
+fn error_span(error: &rnix::parser::ParseError, len: usize) -> Span {
+    use rnix::parser::ParseError;
+
+    match error {
+        ParseError::Unexpected(range)
+        | ParseError::UnexpectedExtra(range)
+        | ParseError::UnexpectedWanted(_, range, _)
+        | ParseError::UnexpectedDoubleBind(range)
+        | ParseError::DuplicatedArgs(range, _) => Span::from(*range),
+        _ => Span::new(len, len),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOPOLOGY: &str = r#"{
+  nodes = {
+    cam01 = { type = "device"; hostname = "cam01"; };
+    web01 = {
+      type = "server";
+      hostname = "web01";
+    };
+  };
+  connections = [
+    { from = "web01.eth0"; to = "cam01"; }
+  ];
+}
+"#;
+
+    #[test]
+    fn test_line_index() {
+        let source = "a = \"é😀\";\nb = 1;\n";
+        let index = LineIndex::new(source);
+
+        let offset = source.find('b').unwrap();
+        assert_eq!(index.position(offset), (1, 0));
+        assert_eq!(index.offset(1, 0), offset);
+
+        let semicolon = source.find(';').unwrap();
+        assert_eq!(index.position(semicolon), (0, 9));
+        assert_eq!(index.offset(0, 9), semicolon);
+        assert_eq!(index.offset(0, 99), source.find('\n').unwrap());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let service = TopologyLanguageService::new();
+
+        let broken = "{ nodes = { web01 = { type = ; }; }; }";
+        let diagnostics = service.diagnostics(broken);
+        assert!(!diagnostics.is_empty());
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.rule == "syntax" && d.span.is_some()));
+
+        let diagnostics = service.diagnostics("{ networks = { }; }");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "reader");
+
+        let source = TOPOLOGY.replace("to = \"cam01\"", "to = \"cam02\"");
+        let diagnostics = service.diagnostics(&source);
+        let dangling: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.rule == "dangling-connection")
+            .collect();
+        assert_eq!(dangling.len(), 1);
+        assert_eq!(dangling[0].span.unwrap().slice(&source), "\"cam02\"");
+    }
+
+    #[test]
+    fn test_completions() {
+        let service = TopologyLanguageService::new();
+
+        let source = r#"{ nodes = { web01 = { type = ""; }; }; }"#;
+        let offset = source.find("\"\"").unwrap() + 1;
+        let labels: Vec<_> = service
+            .completions(source, offset)
+            .into_iter()
+            .map(|c| c.label)
+            .collect();
+        assert!(labels.contains(&"physical-server".to_string()));
+        assert!(labels.contains(&"device".to_string()));
+
+        let source = r#"{ nodes = { web01 = { type = "server"; host }; }; }"#;
+        let offset = source.find("host").unwrap() + 4;
+        let completions = service.completions(source, offset);
+        assert!(completions
+            .iter()
+            .all(|c| c.kind == CompletionKind::Attribute));
+        assert!(completions.iter().any(|c| c.label == "hostname"));
+        assert!(!completions.iter().any(|c| c.label == "type"));
+
+        let offset = TOPOLOGY.rfind("\"cam01\"; }").unwrap() + 1;
+        let completions = service.completions(TOPOLOGY, offset);
+        let labels: Vec<_> = completions.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, ["cam01", "web01"]);
+        assert_eq!(completions[1].detail, "server");
+    }
+
+    #[test]
+    fn test_hover_and_definition() {
+        let service = TopologyLanguageService::new();
+
+        let hover = service
+            .hover(TOPOLOGY, TOPOLOGY.find("\"device\"").unwrap())
+            .unwrap();
+        assert_eq!(hover.span.slice(TOPOLOGY), "\"device\"");
+        assert!(hover.contents.contains("ResourceType::Appliance"));
+        assert!(hover.contents.contains("KVM"));
+        assert!(!hover.contents.contains("written back as"));
+
+        let hover = service
+            .hover(TOPOLOGY, TOPOLOGY.find("web01 = {").unwrap())
+            .unwrap();
+        assert!(hover.contents.contains("ResourceType::PhysicalServer"));
+        assert!(hover
+            .contents
+            .contains("written back as `type = \"physical-server\";`"));
+
+        let target = service
+            .definition(TOPOLOGY, TOPOLOGY.find("web01.eth0").unwrap())
+            .unwrap();
+        assert!(target.slice(TOPOLOGY).starts_with("web01 = {"));
+        let target = service
+            .definition(TOPOLOGY, TOPOLOGY.rfind("\"cam01\"; }").unwrap())
+            .unwrap();
+        assert!(target.slice(TOPOLOGY).starts_with("cam01 = {"));
+        assert!(service.definition(TOPOLOGY, 0).is_none());
+    }
+}
@@ -37,6 +37,19 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! ## Evaluated Topologies
+//!
+//! By default topology files are read syntactically, so node attributes
+//! must be literals. Topologies built with `let` bindings, `//` merges or
+//! `lib` helpers are read with [`TopologyReader::with_evaluation`], which
+//! runs them through the pure evaluator in [`crate::nix::eval`] first.
+//!
+//! ## Linting
+//!
+//! A reader given a [`TopologyLinter`] with [`TopologyReader::with_linter`]
+//! logs its findings while reading; in strict mode, error findings fail the
+//! read. [`TopologyReader::lint_topology`] returns them instead.
 
 use anyhow::{bail, Context, Result};
 use cim_infrastructure::{
@@ -46,7 +59,11 @@ use rnix::{Root, SyntaxKind, SyntaxNode};
 use std::path::Path;
 use tokio::fs;
 
+use crate::adapters::topology_lint::{LintReport, TopologyLinter};
 use crate::functors::resource_type_functor::*;
+use crate::nix::cache::NixCache;
+use crate::nix::eval::{EvalConfig, Evaluator};
+use crate::nix::{NixAttrset, NixValue, Severity};
 
 /// Topology Reader - Reads nixos-topology files and generates Infrastructure resources
 ///
@@ -77,6 +94,12 @@ use crate::functors::resource_type_functor::*;
 pub struct TopologyReader {
     /// Whether to strictly validate topology (fail on unknown types)
     strict_mode: bool,
+    /// Whether to evaluate the topology before reading nodes
+    evaluate: bool,
+    /// Cache of resources read from unchanged files
+    cache: Option<NixCache>,
+    /// Lint rules checked while reading
+    linter: Option<TopologyLinter>,
 }
 
 impl TopologyReader {
@@ -92,6 +115,9 @@ impl TopologyReader {
     pub fn new() -> Self {
         Self {
             strict_mode: false,
+            evaluate: false,
+            cache: None,
+            linter: None,
         }
     }
 
@@ -102,7 +128,97 @@ impl TopologyReader {
     pub fn new_strict() -> Self {
         Self {
             strict_mode: true,
+            evaluate: false,
+            cache: None,
+            linter: None,
+        }
+    }
+
+    /// Evaluate topology files before extracting nodes
+    ///
+    /// Node attributes may then be computed with `let` bindings, `//`
+    /// merges, string interpolation and the `lib` subset supported by
+    /// [`Evaluator`]. Attributes whose values are functions are ignored.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use cim_domain_nix::adapters::topology_reader::TopologyReader;
+    ///
+    /// let reader = TopologyReader::new().with_evaluation();
+    /// let resources = reader
+    ///     .parse_topology(r#"{ nodes = lib.genAttrs [ "web01" "web02" ] (n: { type = "server"; }); }"#)
+    ///     .unwrap();
+    /// assert_eq!(resources.len(), 2);
+    /// ```
+    #[must_use]
+    pub fn with_evaluation(mut self) -> Self {
+        self.evaluate = true;
+        self
+    }
+
+    /// Reuse resources read from files whose content has not changed
+    ///
+    /// Entries are keyed by the file's content hash, separately for each
+    /// combination of strict mode and evaluation.
+    #[must_use]
+    pub fn with_cache(mut self, cache: NixCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Check topologies with a linter while reading them
+    ///
+    /// Findings are logged; in strict mode a topology with error findings
+    /// is rejected.
+    #[must_use]
+    pub fn with_linter(mut self, linter: TopologyLinter) -> Self {
+        self.linter = Some(linter);
+        self
+    }
+
+    /// Lint topology content with this reader's linter (or the built-in
+    /// rules), evaluating it first if the reader evaluates
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the content cannot be parsed or evaluated, or has
+    /// no `nodes` attribute set.
+    pub fn lint_topology(&self, content: &str) -> Result<LintReport> {
+        let default = TopologyLinter::new();
+        let linter = self.linter.as_ref().unwrap_or(&default);
+        if !self.evaluate {
+            return linter.lint_source(content);
         }
+        let config = EvalConfig {
+            drop_functions: true,
+            ..EvalConfig::default()
+        };
+        let value = Evaluator::with_config(config)
+            .eval_str(content)
+            .context("Failed to evaluate topology")?;
+        linter.lint_value(&value)
+    }
+
+    /// Log the linter's findings, failing on errors in strict mode
+    fn check_lints(&self, content: &str) -> Result<()> {
+        let report = self.lint_topology(content)?;
+        for diagnostic in &report.diagnostics {
+            match diagnostic.severity {
+                Severity::Info => tracing::info!("{}", diagnostic),
+                Severity::Warning | Severity::Error => tracing::warn!("{}", diagnostic),
+            }
+        }
+        if self.strict_mode && !report.passes(Severity::Error) {
+            let errors: Vec<String> = report
+                .diagnostics
+                .iter()
+                .filter(|d| d.severity == Severity::Error)
+                .map(ToString::to_string)
+                .collect();
+            bail!("Topology has lint errors: {}", errors.join("; "));
+        }
+        Ok(())
     }
 
     /// Read a topology file and generate Infrastructure resources
@@ -140,7 +256,22 @@ impl TopologyReader {
             .await
             .context(format!("Failed to read topology file: {}", path.display()))?;
 
-        self.parse_topology(&content)
+        let Some(cache) = &self.cache else {
+            return self.parse_topology(&content).context("Failed to parse topology");
+        };
+        let namespace = format!(
+            "topology{}{}",
+            if self.strict_mode { "-strict" } else { "" },
+            if self.evaluate { "-evaluated" } else { "" }
+        );
+        let key = cache.file_key(&namespace, path, &content);
+        // Lints run on every read: the cached resources do not depend on
+        // the linter, but its findings and strict-mode rejection do
+        if self.linter.is_some() {
+            self.check_lints(&content).context("Failed to parse topology")?;
+        }
+        cache
+            .get_or_try_insert_with(&namespace, &key, || self.parse_unlinted(&content))
             .context("Failed to parse topology")
     }
 
@@ -172,6 +303,18 @@ impl TopologyReader {
     /// }
     /// ```
     pub fn parse_topology(&self, content: &str) -> Result<Vec<ComputeResource>> {
+        if self.linter.is_some() {
+            self.check_lints(content)?;
+        }
+        self.parse_unlinted(content)
+    }
+
+    /// Parse topology content without running the linter
+    fn parse_unlinted(&self, content: &str) -> Result<Vec<ComputeResource>> {
+        if self.evaluate {
+            return self.parse_evaluated_topology(content);
+        }
+
         // Parse Nix content with rnix
         let parsed = Root::parse(content);
 
@@ -211,6 +354,100 @@ impl TopologyReader {
         Ok(resources)
     }
 
+    /// Evaluate topology content and generate resources from the result
+    fn parse_evaluated_topology(&self, content: &str) -> Result<Vec<ComputeResource>> {
+        let config = EvalConfig {
+            drop_functions: true,
+            ..EvalConfig::default()
+        };
+        let value = Evaluator::with_config(config)
+            .eval_str(content)
+            .context("Failed to evaluate topology")?;
+
+        let nodes = Self::find_nodes_value(&value)
+            .context("Failed to find 'nodes' attribute set in topology")?;
+
+        // Attribute sets are unordered; keep the result deterministic
+        let mut names: Vec<&String> = nodes.keys().collect();
+        names.sort();
+
+        let mut resources = Vec::new();
+        for name in names {
+            match self.parse_node_value(name, &nodes.attributes[name]) {
+                Ok(resource) => resources.push(resource),
+                Err(e) if self.strict_mode => {
+                    return Err(e).context("Failed to parse node in strict mode");
+                }
+                Err(e) => tracing::warn!("Skipping node due to parse error: {}", e),
+            }
+        }
+
+        Ok(resources)
+    }
+
+    /// Find the 'nodes' attribute set in an evaluated topology
+    ///
+    /// Searches breadth-first, so a top-level `nodes` wins over nested ones.
+    fn find_nodes_value(value: &NixValue) -> Result<&NixAttrset> {
+        let mut queue = std::collections::VecDeque::from([value]);
+        while let Some(value) = queue.pop_front() {
+            if let NixValue::Attrset(attrs) = value {
+                if let Some(NixValue::Attrset(nodes)) = attrs.get("nodes") {
+                    return Ok(nodes);
+                }
+                queue.extend(attrs.values());
+            }
+        }
+        bail!("Could not find 'nodes' attribute set in topology file")
+    }
+
+    /// Parse a single evaluated node
+    fn parse_node_value(&self, node_name: &str, value: &NixValue) -> Result<ComputeResource> {
+        let NixValue::Attrset(node_attrs) = value else {
+            bail!("Node value is not an attribute set");
+        };
+        let string_attr = |name: &str| {
+            node_attrs
+                .get(name)
+                .and_then(|v| v.as_string().ok())
+                .map(|s| s.value.clone())
+        };
+
+        let node_type = string_attr("type").context("Missing required 'type' attribute")?;
+        let hostname_str = string_attr("hostname").unwrap_or_else(|| node_name.to_string());
+
+        let mut resource = self.parse_node(node_name, &node_type, "x86_64-linux")?;
+
+        if let Ok(explicit_hostname) = Hostname::new(&hostname_str) {
+            resource.hostname = explicit_hostname;
+        }
+
+        if let Some(manufacturer) = string_attr("manufacturer") {
+            resource.set_hardware(
+                Some(manufacturer),
+                string_attr("model"),
+                string_attr("serialNumber"),
+            );
+        }
+
+        // Scalar metadata values are recorded as text
+        if let Some(NixValue::Attrset(metadata)) = node_attrs.get("metadata") {
+            for (key, value) in &metadata.attributes {
+                let text = match value {
+                    NixValue::String(s) => s.value.clone(),
+                    NixValue::Integer(i) => i.value.to_string(),
+                    NixValue::Float(f) => f.value.to_string(),
+                    NixValue::Bool(b) => b.value.to_string(),
+                    NixValue::Path(p) => p.as_str().to_string(),
+                    _ => continue,
+                };
+                let _ = resource.add_metadata(key, &text);
+            }
+        }
+
+        Ok(resource)
+    }
+
     /// Find the 'nodes' attribute set in the topology
     fn find_nodes_attrset(&self, syntax: &SyntaxNode) -> Result<SyntaxNode> {
         // Walk the AST to find: { nodes = { ... }; }
@@ -413,7 +650,7 @@ impl TopologyReader {
     /// Parse topology node type string to TopologyNodeType
     ///
     /// Maps common nixos-topology type strings to our enum.
-    fn parse_topology_type(&self, type_str: &str) -> Result<TopologyNodeType> {
+    pub(crate) fn parse_topology_type(&self, type_str: &str) -> Result<TopologyNodeType> {
         let type_lower = type_str.to_lowercase();
 
         let topology_type = if type_lower.contains("server") || type_lower.contains("host") {
@@ -598,4 +835,126 @@ mod tests {
         assert_eq!(resources.len(), 1);
         assert_eq!(resources[0].resource_type, ResourceType::Appliance); // Unknown maps to Appliance
     }
+
+    #[test]
+    fn test_parse_evaluated_topology() {
+        let reader = TopologyReader::new().with_evaluation();
+
+        let nix_content = r#"
+        { lib, ... }:
+        let
+          rack = n: { rack = "rack0${toString n}"; poe_capable = n == 1; };
+          server = name: {
+            type = "server";
+            hostname = "${name}.lab";
+            manufacturer = "Supermicro";
+            metadata = rack 2;
+          };
+        in {
+          nodes = lib.genAttrs [ "web01" "web02" ] server // {
+            switch01 = {
+              type = "switch";
+              metadata = rack 1 // { ports = 48; };
+            };
+          };
+          connections = map (n: { from = n; to = "switch01"; }) [ "web01" "web02" ];
+        }
+        "#;
+
+        let resources = reader.parse_topology(nix_content).unwrap();
+        assert_eq!(resources.len(), 3);
+
+        let web = resources
+            .iter()
+            .find(|r| r.hostname.short_name() == "web01")
+            .unwrap();
+        assert_eq!(web.resource_type, ResourceType::PhysicalServer);
+        assert_eq!(web.manufacturer.as_ref().unwrap(), "Supermicro");
+        assert_eq!(web.metadata.get("rack").unwrap(), "rack02");
+
+        let switch = resources
+            .iter()
+            .find(|r| r.hostname.short_name() == "switch01")
+            .unwrap();
+        assert_eq!(switch.resource_type, ResourceType::Switch);
+        assert_eq!(switch.metadata.get("poe_capable").unwrap(), "true");
+        assert_eq!(switch.metadata.get("ports").unwrap(), "48");
+    }
+
+    #[test]
+    fn test_parse_evaluated_topology_errors() {
+        let reader = TopologyReader::new().with_evaluation();
+        let err = reader
+            .parse_topology(r#"{ nodes.web01 = { type = "server"; port = 80 + "x"; }; }"#)
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("Expected"));
+
+        // Nodes that fail to convert are skipped unless strict
+        let content = r#"{ nodes = { ok = { type = "router"; }; bad = { hostname = "x"; }; }; }"#;
+        assert_eq!(reader.parse_topology(content).unwrap().len(), 1);
+        assert!(TopologyReader::new_strict()
+            .with_evaluation()
+            .parse_topology(content)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_topology_file_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("topology.nix");
+        std::fs::write(&path, r#"{ nodes = { web01 = { type = "server"; }; }; }"#).unwrap();
+
+        let cache = NixCache::in_memory();
+        let reader = TopologyReader::new().with_cache(cache.clone());
+        let first = reader.read_topology_file(&path).await.unwrap();
+        let second = reader.read_topology_file(&path).await.unwrap();
+        assert_eq!(second, first);
+        assert_eq!((cache.stats().hits, cache.stats().misses), (1, 1));
+
+        std::fs::write(
+            &path,
+            r#"{ nodes = { web01 = { type = "server"; }; web02 = { type = "server"; }; }; }"#,
+        )
+        .unwrap();
+        assert_eq!(reader.read_topology_file(&path).await.unwrap().len(), 2);
+        assert_eq!(cache.stats().invalidations, 1);
+    }
+
+    #[test]
+    fn test_parse_topology_with_linter() {
+        let content = r#"{
+          nodes = { web01 = { type = "server"; }; web02 = { type = "server"; hostname = "web01"; }; };
+        }"#;
+
+        let lenient = TopologyReader::new().with_linter(TopologyLinter::new());
+        assert_eq!(lenient.parse_topology(content).unwrap().len(), 2);
+        assert_eq!(lenient.lint_topology(content).unwrap().diagnostics.len(), 2);
+
+        let strict = TopologyReader::new_strict().with_linter(TopologyLinter::new());
+        let err = strict.parse_topology(content).unwrap_err();
+        assert!(err.to_string().contains("duplicate-hostname"));
+        assert_eq!(TopologyReader::new_strict().parse_topology(content).unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_read_topology_file_cached_with_linter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("topology.nix");
+        std::fs::write(
+            &path,
+            r#"{ nodes = { web01 = { type = "server"; }; web02 = { type = "server"; hostname = "web01"; }; }; }"#,
+        )
+        .unwrap();
+
+        let cache = NixCache::in_memory();
+        let unlinted = TopologyReader::new_strict().with_cache(cache.clone());
+        assert_eq!(unlinted.read_topology_file(&path).await.unwrap().len(), 2);
+
+        let linted = TopologyReader::new_strict()
+            .with_cache(cache.clone())
+            .with_linter(TopologyLinter::new());
+        let err = linted.read_topology_file(&path).await.unwrap_err();
+        assert!(format!("{err:#}").contains("duplicate-hostname"));
+        assert!(linted.read_topology_file(&path).await.is_err());
+    }
 }
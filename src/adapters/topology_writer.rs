@@ -12,7 +12,7 @@
 //!     ▼ (functors)
 //! TopologyWriter
 //!     │
-//!     ▼ (Nix codegen)
+//!     ▼ (NixValue + NixPrinter)
 //! topology.nix files
 //!     │
 //!     ▼ (git commit)
@@ -43,14 +43,21 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! ## Secrets
+//!
+//! Node metadata is written to git as-is. A writer given a
+//! [`SecretScanner`] with [`TopologyWriter::with_secret_scanner`] refuses
+//! to generate a topology containing values that look like secrets.
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use cim_infrastructure::{ComputeResource, ResourceType};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
 use crate::functors::resource_type_functor::*;
+use crate::nix::{KeyOrder, NixAttrset, NixList, NixPrinter, NixString, NixValue, SecretScanner};
 
 /// Topology Writer - Generates nixos-topology files from Infrastructure resources
 ///
@@ -99,6 +106,9 @@ pub struct TopologyWriter {
 
     /// Topology name
     topology_name: String,
+
+    /// Scanner that values must pass before they are written
+    secret_scanner: Option<SecretScanner>,
 }
 
 impl TopologyWriter {
@@ -116,6 +126,7 @@ impl TopologyWriter {
             output_path: output_path.into(),
             nodes: HashMap::new(),
             topology_name: "infrastructure".to_string(),
+            secret_scanner: None,
         }
     }
 
@@ -130,9 +141,20 @@ impl TopologyWriter {
             output_path: output_path.into(),
             nodes: HashMap::new(),
             topology_name: topology_name.into(),
+            secret_scanner: None,
         }
     }
 
+    /// Refuse to generate topologies with values that look like secrets
+    ///
+    /// Move such values out of node metadata (e.g. into sops or agenix), or
+    /// allow-list them in the scanner.
+    #[must_use]
+    pub fn with_secret_scanner(mut self, scanner: SecretScanner) -> Self {
+        self.secret_scanner = Some(scanner);
+        self
+    }
+
     /// Add a node to the topology
     ///
     /// ## Arguments
@@ -191,6 +213,11 @@ impl TopologyWriter {
     ///
     /// String containing valid Nix code for topology.nix
     ///
+    /// ## Errors
+    ///
+    /// - A value looks like a secret, when a secret scanner is set
+    /// - The topology cannot be printed
+    ///
     /// ## Example
     ///
     /// ```rust
@@ -213,108 +240,111 @@ impl TopologyWriter {
     /// # }
     /// ```
     pub fn generate_topology(&self) -> Result<String> {
+        let topology = self.topology_value();
+        if let Some(scanner) = &self.secret_scanner {
+            let findings = scanner.scan_value(&topology);
+            if !findings.is_empty() {
+                let findings: Vec<String> = findings.iter().map(ToString::to_string).collect();
+                bail!(
+                    "Refusing to write possible secrets: {}",
+                    findings.join("; ")
+                );
+            }
+        }
+
         let mut output = String::new();
 
         // File header
         output.push_str("# nixos-topology configuration\n");
         output.push_str("# Generated by cim-domain-nix\n");
-        output.push_str("# DO NOT EDIT MANUALLY - Changes will be overwritten\n\n");
+        output.push_str("# DO NOT EDIT MANUALLY - Changes will be overwritten\n");
+        output.push_str("# Topology: ");
+        output.push_str(&self.topology_name.replace(['\n', '\r'], " "));
+        output.push_str("\n\n");
 
-        output.push_str("{\n");
-        output.push_str(&format!("  # Topology: {}\n\n", self.topology_name));
+        output.push_str(&Self::printer().print(&topology)?);
+        output.push('\n');
 
-        // Nodes section
-        output.push_str("  nodes = {\n");
+        Ok(output)
+    }
 
-        let mut node_names: Vec<_> = self.nodes.keys().collect();
-        node_names.sort();
+    /// Printer layout for topology files: one attribute per line, with the
+    /// identifying fields of each node first
+    fn printer() -> NixPrinter {
+        let priority = [
+            "nodes",
+            "networks",
+            "connections",
+            "type",
+            "hostname",
+            "manufacturer",
+            "model",
+            "serialNumber",
+            "metadata",
+        ];
+        NixPrinter::new()
+            .with_max_inline_width(0)
+            .with_key_order(KeyOrder::Priority(
+                priority.iter().map(|key| (*key).to_string()).collect(),
+            ))
+    }
 
-        for node_name in node_names {
-            let resource = &self.nodes[node_name];
-            let node_nix = self.generate_node_nix(resource)?;
-            output.push_str(&node_nix);
-            output.push('\n');
+    /// The whole topology as a Nix value
+    fn topology_value(&self) -> NixValue {
+        let mut nodes = NixAttrset::new();
+        for (node_name, resource) in &self.nodes {
+            nodes.insert(node_name.clone(), self.node_value(resource));
         }
 
-        output.push_str("  };\n");
-
-        // Networks section (placeholder)
-        output.push_str("\n  networks = {\n");
-        output.push_str("    # Networks will be added here\n");
-        output.push_str("  };\n");
-
-        // Connections section (placeholder)
-        output.push_str("\n  connections = [\n");
-        output.push_str("    # Connections will be added here\n");
-        output.push_str("  ];\n");
-
-        output.push_str("}\n");
-
-        Ok(output)
+        let mut topology = NixAttrset::new();
+        topology.insert("nodes".to_string(), NixValue::Attrset(nodes));
+        topology.insert("networks".to_string(), NixValue::Attrset(NixAttrset::new()));
+        topology.insert("connections".to_string(), NixValue::List(NixList::new()));
+        NixValue::Attrset(topology)
     }
 
-    /// Generate Nix code for a single node
+    /// A single node as a Nix value
     ///
     /// ## Arguments
     ///
-    /// * `resource` - ComputeResource to convert to Nix
-    ///
-    /// ## Returns
-    ///
-    /// String containing Nix code for this node
-    fn generate_node_nix(&self, resource: &ComputeResource) -> Result<String> {
-        let node_name = resource.hostname.short_name();
+    /// * `resource` - `ComputeResource` to convert to Nix
+    fn node_value(&self, resource: &ComputeResource) -> NixValue {
+        let text = |value: &str| NixValue::String(NixString::new(value));
 
         // Map ResourceType to topology type string using functor
         let topology_type = map_resource_type_to_topology(resource.resource_type);
         let type_str = self.topology_type_to_nix_string(topology_type);
 
-        let mut output = String::new();
-        output.push_str(&format!("    {} = {{\n", node_name));
-        output.push_str(&format!("      type = \"{}\";\n", type_str));
-        output.push_str(&format!("      hostname = \"{}\";\n", resource.hostname.as_str()));
+        let mut node = NixAttrset::new();
+        node.insert("type".to_string(), text(type_str));
+        node.insert("hostname".to_string(), text(resource.hostname.as_str()));
 
         // Add hardware info if available
         if let Some(ref manufacturer) = resource.manufacturer {
-            output.push_str(&format!("      manufacturer = \"{}\";\n", manufacturer));
+            node.insert("manufacturer".to_string(), text(manufacturer));
         }
         if let Some(ref model) = resource.model {
-            output.push_str(&format!("      model = \"{}\";\n", model));
+            node.insert("model".to_string(), text(model));
         }
         if let Some(ref serial) = resource.serial_number {
-            output.push_str(&format!("      serialNumber = \"{}\";\n", serial));
+            node.insert("serialNumber".to_string(), text(serial));
         }
 
         // Add metadata
         if !resource.metadata.is_empty() {
-            output.push_str("      metadata = {\n");
-            let mut keys: Vec<_> = resource.metadata.keys().collect();
-            keys.sort();
-            for key in keys {
-                let value = &resource.metadata[key];
-                output.push_str(&format!("        {} = \"{}\";\n", key, value));
+            let mut metadata = NixAttrset::new();
+            for (key, value) in &resource.metadata {
+                metadata.insert(key.clone(), text(value));
             }
-            output.push_str("      };\n");
+            node.insert("metadata".to_string(), NixValue::Attrset(metadata));
         }
 
-        output.push_str("    };");
-
-        Ok(output)
+        NixValue::Attrset(node)
     }
 
     /// Convert TopologyNodeType to Nix string
     fn topology_type_to_nix_string(&self, node_type: TopologyNodeType) -> &'static str {
-        match node_type {
-            TopologyNodeType::PhysicalServer => "physical-server",
-            TopologyNodeType::VirtualMachine => "virtual-machine",
-            TopologyNodeType::Container => "container",
-            TopologyNodeType::Router => "router",
-            TopologyNodeType::Switch => "switch",
-            TopologyNodeType::Firewall => "firewall",
-            TopologyNodeType::LoadBalancer => "load-balancer",
-            TopologyNodeType::Storage => "storage",
-            TopologyNodeType::Device => "device",
-        }
+        topology_type_name(node_type)
     }
 
     /// Write topology to file
@@ -353,17 +383,27 @@ impl TopologyWriter {
                 .context("Failed to create parent directory")?;
         }
 
-        // Write file
-        fs::write(&self.output_path, nix_code)
-            .await
-            .context(format!(
-                "Failed to write topology file: {}",
-                self.output_path.display()
-            ))?;
+        // Write next to the file and rename, so readers never see a partial file
+        let mut partial = self.output_path.clone().into_os_string();
+        partial.push(".tmp");
+        async {
+            fs::write(&partial, nix_code).await?;
+            fs::rename(&partial, &self.output_path).await
+        }
+        .await
+        .context(format!(
+            "Failed to write topology file: {}",
+            self.output_path.display()
+        ))?;
 
         Ok(())
     }
 
+    /// Path the topology is written to
+    pub fn output_path(&self) -> &Path {
+        &self.output_path
+    }
+
     /// Get the number of nodes in the topology
     pub fn node_count(&self) -> usize {
         self.nodes.len()
@@ -380,6 +420,21 @@ impl TopologyWriter {
     }
 }
 
+/// Type string the writer emits for a topology node type
+pub(crate) fn topology_type_name(node_type: TopologyNodeType) -> &'static str {
+    match node_type {
+        TopologyNodeType::PhysicalServer => "physical-server",
+        TopologyNodeType::VirtualMachine => "virtual-machine",
+        TopologyNodeType::Container => "container",
+        TopologyNodeType::Router => "router",
+        TopologyNodeType::Switch => "switch",
+        TopologyNodeType::Firewall => "firewall",
+        TopologyNodeType::LoadBalancer => "load-balancer",
+        TopologyNodeType::Storage => "storage",
+        TopologyNodeType::Device => "device",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -521,6 +576,34 @@ mod tests {
         assert!(nix_code.contains("row = \"1\""));
     }
 
+    #[test]
+    fn test_generated_topology_parses_back() {
+        let mut writer = TopologyWriter::new("test.nix");
+
+        let hostname = Hostname::new("router01").unwrap();
+        let mut resource = ComputeResource::new(hostname, ResourceType::Router).unwrap();
+        resource.set_hardware(Some("Acme \"Pro\" ${series}".to_string()), None, None);
+        resource.add_metadata("rack", "A01").unwrap();
+        writer.add_node(&resource).unwrap();
+
+        let nix_code = writer.generate_topology().unwrap();
+        let value = crate::nix::parse_value(&nix_code).unwrap();
+
+        let node = value.as_attrset().unwrap().get("nodes").unwrap();
+        let router = node.as_attrset().unwrap().get("router01").unwrap();
+        let router = router.as_attrset().unwrap();
+        assert_eq!(
+            router.get("manufacturer").unwrap().as_string().unwrap().value,
+            "Acme \"Pro\" ${series}"
+        );
+        assert!(nix_code.contains("manufacturer = \"Acme \\\"Pro\\\" \\${series}\";"));
+
+        // Identifying fields come first
+        let type_pos = nix_code.find("type =").unwrap();
+        let metadata_pos = nix_code.find("metadata =").unwrap();
+        assert!(type_pos < metadata_pos);
+    }
+
     #[test]
     fn test_functor_integration() {
         let mut writer = TopologyWriter::new("test.nix");
@@ -550,4 +633,29 @@ mod tests {
             writer.clear();
         }
     }
+
+    #[test]
+    fn test_refuse_secrets() {
+        let mut resource =
+            ComputeResource::new(Hostname::new("sw01").unwrap(), ResourceType::Switch).unwrap();
+        resource.add_metadata("snmp_community", "n0t-public").unwrap();
+
+        let mut writer = TopologyWriter::new("test.nix");
+        writer.add_node(&resource).unwrap();
+        assert!(writer.generate_topology().unwrap().contains("n0t-public"));
+
+        let mut writer = TopologyWriter::new("test.nix").with_secret_scanner(SecretScanner::new());
+        writer.add_node(&resource).unwrap();
+        let err = writer.generate_topology().unwrap_err().to_string();
+        assert!(err.contains("nodes.sw01.metadata.snmp_community"));
+        assert!(!err.contains("n0t-public"));
+
+        let allow = crate::nix::SecretAllowList::new()
+            .allow_path("nodes.*.metadata.snmp_community")
+            .unwrap();
+        let mut writer = TopologyWriter::new("test.nix")
+            .with_secret_scanner(SecretScanner::new().with_allow_list(allow));
+        writer.add_node(&resource).unwrap();
+        assert!(writer.generate_topology().is_ok());
+    }
 }
@@ -0,0 +1,256 @@
+// Copyright (c) 2025 - Cowboy AI, Inc.
+//! Language server for nixos-topology files
+//!
+//! Speaks LSP over stdio and serves the
+//! [`TopologyLanguageService`] for every topology file the editor opens;
+//! other Nix files are left alone. Lint rules are configured by the
+//! workspace's `topology-lint.toml`.
+//!
+//! ```text
+//! cargo install cim-domain-nix --features lsp --bin topology-lsp
+//! ```
+
+use std::collections::HashMap;
+
+use cim_domain_nix::adapters::topology_lsp::{
+    Completion, CompletionKind, LineIndex, TopologyLanguageService,
+};
+use cim_domain_nix::adapters::{LintConfig, TopologyLinter};
+use cim_domain_nix::nix::ast::Span;
+use cim_domain_nix::nix::Severity;
+use tokio::sync::RwLock;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types as lsp;
+use tower_lsp::{Client, LanguageServer, LspService, Server};
+
+/// An open document
+struct Document {
+    text: String,
+    /// Whether the service handles it; kept once set so diagnostics stay
+    /// live while an edit breaks the file
+    topology: bool,
+}
+
+struct Backend {
+    client: Client,
+    service: RwLock<TopologyLanguageService>,
+    documents: RwLock<HashMap<lsp::Url, Document>>,
+}
+
+impl Backend {
+    async fn update(&self, uri: lsp::Url, text: String) {
+        let path = uri.to_file_path().unwrap_or_default();
+        let diagnostics = {
+            let mut documents = self.documents.write().await;
+            let topology = documents
+                .get(&uri)
+                .is_some_and(|document| document.topology)
+                || TopologyLanguageService::is_topology(&path, &text);
+            let diagnostics = if topology {
+                let service = self.service.read().await;
+                let index = LineIndex::new(&text);
+                Some(
+                    service
+                        .diagnostics(&text)
+                        .into_iter()
+                        .map(|diagnostic| lsp::Diagnostic {
+                            range: range(&index, diagnostic.span.unwrap_or(Span::new(0, 0))),
+                            severity: Some(severity(diagnostic.severity)),
+                            code: Some(lsp::NumberOrString::String(diagnostic.rule)),
+                            source: Some("topology".to_string()),
+                            message: diagnostic.message,
+                            ..lsp::Diagnostic::default()
+                        })
+                        .collect(),
+                )
+            } else {
+                None
+            };
+            documents.insert(uri.clone(), Document { text, topology });
+            diagnostics
+        };
+        if let Some(diagnostics) = diagnostics {
+            self.client
+                .publish_diagnostics(uri, diagnostics, None)
+                .await;
+        }
+    }
+
+    /// Run `f` on a topology document with the offset of a position
+    async fn with_document<T>(
+        &self,
+        uri: &lsp::Url,
+        position: lsp::Position,
+        f: impl FnOnce(&TopologyLanguageService, &LineIndex, usize) -> Option<T>,
+    ) -> Option<T> {
+        let documents = self.documents.read().await;
+        let document = documents.get(uri).filter(|document| document.topology)?;
+        let index = LineIndex::new(&document.text);
+        let offset = index.offset(position.line, position.character);
+        f(&*self.service.read().await, &index, offset)
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for Backend {
+    async fn initialize(&self, params: lsp::InitializeParams) -> Result<lsp::InitializeResult> {
+        if let Some(root) = params.root_uri.and_then(|uri| uri.to_file_path().ok()) {
+            match LintConfig::load(&root)
+                .and_then(|config| TopologyLinter::new().with_config(config))
+            {
+                Ok(linter) => {
+                    let mut service = self.service.write().await;
+                    *service = service.clone().with_linter(linter);
+                }
+                Err(error) => {
+                    self.client
+                        .log_message(lsp::MessageType::WARNING, format!("{error:#}"))
+                        .await;
+                }
+            }
+        }
+
+        Ok(lsp::InitializeResult {
+            capabilities: lsp::ServerCapabilities {
+                text_document_sync: Some(lsp::TextDocumentSyncCapability::Kind(
+                    lsp::TextDocumentSyncKind::FULL,
+                )),
+                completion_provider: Some(lsp::CompletionOptions {
+                    trigger_characters: Some(vec!["\"".to_string()]),
+                    ..lsp::CompletionOptions::default()
+                }),
+                hover_provider: Some(lsp::HoverProviderCapability::Simple(true)),
+                definition_provider: Some(lsp::OneOf::Left(true)),
+                ..lsp::ServerCapabilities::default()
+            },
+            server_info: Some(lsp::ServerInfo {
+                name: "topology-lsp".to_string(),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }),
+        })
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: lsp::DidOpenTextDocumentParams) {
+        self.update(params.text_document.uri, params.text_document.text)
+            .await;
+    }
+
+    async fn did_change(&self, mut params: lsp::DidChangeTextDocumentParams) {
+        // Full sync: the last change holds the whole document
+        if let Some(change) = params.content_changes.pop() {
+            self.update(params.text_document.uri, change.text).await;
+        }
+    }
+
+    async fn did_close(&self, params: lsp::DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        let removed = self.documents.write().await.remove(&uri);
+        if removed.is_some_and(|document| document.topology) {
+            self.client.publish_diagnostics(uri, Vec::new(), None).await;
+        }
+    }
+
+    async fn completion(
+        &self,
+        params: lsp::CompletionParams,
+    ) -> Result<Option<lsp::CompletionResponse>> {
+        let position = params.text_document_position;
+        Ok(self
+            .with_document(
+                &position.text_document.uri,
+                position.position,
+                |service, index, offset| {
+                    let items: Vec<_> = service
+                        .completions(index.source(), offset)
+                        .into_iter()
+                        .map(completion_item)
+                        .collect();
+                    (!items.is_empty()).then_some(lsp::CompletionResponse::Array(items))
+                },
+            )
+            .await)
+    }
+
+    async fn hover(&self, params: lsp::HoverParams) -> Result<Option<lsp::Hover>> {
+        let position = params.text_document_position_params;
+        Ok(self
+            .with_document(
+                &position.text_document.uri,
+                position.position,
+                |service, index, offset| {
+                    let hover = service.hover(index.source(), offset)?;
+                    Some(lsp::Hover {
+                        contents: lsp::HoverContents::Markup(lsp::MarkupContent {
+                            kind: lsp::MarkupKind::Markdown,
+                            value: hover.contents,
+                        }),
+                        range: Some(range(index, hover.span)),
+                    })
+                },
+            )
+            .await)
+    }
+
+    async fn goto_definition(
+        &self,
+        params: lsp::GotoDefinitionParams,
+    ) -> Result<Option<lsp::GotoDefinitionResponse>> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+        Ok(self
+            .with_document(&uri, position.position, |service, index, offset| {
+                let span = service.definition(index.source(), offset)?;
+                Some(lsp::GotoDefinitionResponse::Scalar(lsp::Location::new(
+                    uri.clone(),
+                    range(index, span),
+                )))
+            })
+            .await)
+    }
+}
+
+fn range(index: &LineIndex, span: Span) -> lsp::Range {
+    let position = |offset| {
+        let (line, character) = index.position(offset);
+        lsp::Position::new(line, character)
+    };
+    lsp::Range::new(position(span.start), position(span.end))
+}
+
+fn severity(severity: Severity) -> lsp::DiagnosticSeverity {
+    match severity {
+        Severity::Info => lsp::DiagnosticSeverity::INFORMATION,
+        Severity::Warning => lsp::DiagnosticSeverity::WARNING,
+        Severity::Error => lsp::DiagnosticSeverity::ERROR,
+    }
+}
+
+fn completion_item(completion: Completion) -> lsp::CompletionItem {
+    lsp::CompletionItem {
+        kind: Some(match completion.kind {
+            CompletionKind::NodeType => lsp::CompletionItemKind::ENUM_MEMBER,
+            CompletionKind::Attribute => lsp::CompletionItemKind::PROPERTY,
+            CompletionKind::Node => lsp::CompletionItemKind::REFERENCE,
+        }),
+        label: completion.label,
+        detail: Some(completion.detail),
+        insert_text: completion.insert_text,
+        ..lsp::CompletionItem::default()
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let (service, socket) = LspService::new(|client| Backend {
+        client,
+        service: RwLock::default(),
+        documents: RwLock::default(),
+    });
+    Server::new(tokio::io::stdin(), tokio::io::stdout(), socket)
+        .serve(service)
+        .await;
+}
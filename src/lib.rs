@@ -58,12 +58,13 @@
 //! let parser = NixParser::new();
 //! let ast = parser.parse_str("{ x = 1; y = 2; }").unwrap();
 //!
+//! // Convert static Nix to values
+//! let value = ast_to_value(&ast).unwrap();
+//! assert_eq!(value.as_attrset().unwrap().len(), 2);
+//!
 //! // Work with Nix values
 //! let mut attrs = NixAttrset::new();
 //! attrs.insert("name".to_string(), NixValue::String(NixString::new("hello")));
-//!
-//! // Parse topology
-//! let topology = NixTopology::new("my-infrastructure".to_string());
 //! ```
 //!
 //! ## Phase 3: Category Theory Functor ✅
@@ -154,12 +155,16 @@ pub mod functors;
 // Phase 3: Adapters (NEW - Port/Adapter for nixos-topology)
 pub mod adapters;
 
+// Nix language representation (parsing, values, AST conversion)
+pub mod nix;
+
 // Re-export for convenience
 pub use infrastructure::*;
 pub use functors::*;
 
 // Note: Old modules moved to src/_deprecated/
 // - functor/ - Built against deprecated infrastructure types
-// - nix/ - Built against deprecated infrastructure types
+// - nix/ - Topology and flake modules built against deprecated infrastructure types
+//   (the value, AST and parser modules are live again under src/nix/)
 // - io/ - Built against deprecated infrastructure types
 // These will be replaced with new clean adapters working with current domain model
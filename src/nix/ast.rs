@@ -35,7 +35,12 @@ pub enum AstError {
 
     /// Unexpected node type
     #[error("Unexpected node type: expected {expected}, got {got}")]
-    UnexpectedNodeType { expected: String, got: String },
+    UnexpectedNodeType {
+        /// Node type that was expected
+        expected: String,
+        /// Node type that was found
+        got: String,
+    },
 
     /// Missing node
     #[error("Missing expected node: {0}")]
@@ -49,6 +54,64 @@ pub enum AstError {
 /// Result type for AST operations
 pub type Result<T> = std::result::Result<T, AstError>;
 
+// ============================================================================
+// Span - Source location
+// ============================================================================
+
+/// Byte range of a syntax element in the original source text
+///
+/// Spans are half-open (`start..end`) byte offsets, matching rnix's
+/// `TextRange`, so they can be used to slice the source directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Span {
+    /// Byte offset of the first character
+    pub start: usize,
+    /// Byte offset one past the last character
+    pub end: usize,
+}
+
+impl Span {
+    /// Create a new span
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Span covering a syntax node
+    pub fn of(node: &SyntaxNode) -> Self {
+        Self::from(node.text_range())
+    }
+
+    /// Length of the span in bytes
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Check if the span is empty
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The source text covered by this span
+    pub fn slice<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+}
+
+impl From<rnix::TextRange> for Span {
+    fn from(range: rnix::TextRange) -> Self {
+        Self {
+            start: usize::from(range.start()),
+            end: usize::from(range.end()),
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
 // ============================================================================
 // NixAst - Top-level AST wrapper
 // ============================================================================
@@ -98,6 +161,11 @@ impl NixAst {
         NixNode::new(self.parse.syntax().clone())
     }
 
+    /// Get the typed rnix expression at the root of the file
+    pub fn expr(&self) -> Option<rnix::ast::Expr> {
+        self.parse.tree().expr()
+    }
+
     /// Get the root expression
     pub fn root_expr(&self) -> Option<NixExpression> {
         self.parse.tree().expr().map(|expr| NixExpression::from_rnix(expr))
@@ -255,45 +323,60 @@ impl NixExpression {
 /// Literal expression (string, int, float, bool, null, path)
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum LiteralExpr {
+    /// String literal, with escapes resolved
     String(String),
+    /// Integer literal
     Integer(i64),
+    /// Float literal
     Float(f64),
+    /// `true` or `false`
     Bool(bool),
+    /// `null`
     Null,
+    /// Path literal, as written
     Path(String),
 }
 
 /// Attribute set expression
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AttrSetExpr {
+    /// Whether the set is `rec`
     pub recursive: bool,
+    /// Bindings in source order
     pub bindings: Vec<Binding>,
 }
 
 /// Attribute binding (key = value)
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Binding {
+    /// Attribute path, dot-joined
     pub key: String,
+    /// Bound expression
     pub value: NixExpression,
 }
 
 /// List expression
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ListExpr {
+    /// Elements in source order
     pub elements: Vec<NixExpression>,
 }
 
 /// Function application (f x)
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ApplyExpr {
+    /// Function being applied
     pub function: NixExpression,
+    /// Argument passed to it
     pub argument: NixExpression,
 }
 
 /// Lambda expression (x: body)
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LambdaExpr {
+    /// Parameter binding the argument
     pub param: LambdaParam,
+    /// Function body
     pub body: NixExpression,
 }
 
@@ -304,7 +387,9 @@ pub enum LambdaParam {
     Ident(String),
     /// Pattern parameter ({ x, y }: ...)
     Pattern {
+        /// Names of the destructured attributes
         bindings: Vec<String>,
+        /// Name bound to the whole argument with `@`
         at_param: Option<String>,
     },
 }
@@ -312,98 +397,136 @@ pub enum LambdaParam {
 /// Let-in expression
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LetInExpr {
+    /// Let bindings
     pub bindings: Vec<Binding>,
+    /// Expression the bindings scope over
     pub body: NixExpression,
 }
 
 /// With expression
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WithExpr {
+    /// Attribute set brought into scope
     pub namespace: NixExpression,
+    /// Expression it scopes over
     pub body: NixExpression,
 }
 
 /// If-then-else expression
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct IfThenElseExpr {
+    /// Condition
     pub condition: NixExpression,
+    /// Value when the condition holds
     pub then_expr: NixExpression,
+    /// Value otherwise
     pub else_expr: NixExpression,
 }
 
 /// Binary operation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BinOpExpr {
+    /// Operator
     pub op: BinOp,
+    /// Left operand
     pub left: NixExpression,
+    /// Right operand
     pub right: NixExpression,
 }
 
 /// Binary operators
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BinOp {
-    Add,        // +
-    Sub,        // -
-    Mul,        // *
-    Div,        // /
-    Update,     // //
-    Concat,     // ++
-    Equal,      // ==
-    NotEqual,   // !=
-    Less,       // <
-    LessEq,     // <=
-    Greater,    // >
-    GreaterEq,  // >=
-    And,        // &&
-    Or,         // ||
-    Implication, // ->
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `//`
+    Update,
+    /// `++`
+    Concat,
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `<`
+    Less,
+    /// `<=`
+    LessEq,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterEq,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `->`
+    Implication,
 }
 
 /// Unary operation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UnaryOpExpr {
+    /// Operator
     pub op: UnaryOp,
+    /// Operand
     pub expr: NixExpression,
 }
 
 /// Unary operators
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UnaryOp {
-    Not,    // !
-    Negate, // -
+    /// `!`
+    Not,
+    /// `-`
+    Negate,
 }
 
 /// Select expression (attribute access)
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SelectExpr {
+    /// Expression being selected from
     pub expr: NixExpression,
+    /// Attribute path
     pub path: Vec<String>,
+    /// Fallback after `or`
     pub default: Option<NixExpression>,
 }
 
 /// String interpolation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StringInterpolationExpr {
+    /// Literal and interpolated parts in order
     pub parts: Vec<StringPart>,
 }
 
 /// Part of an interpolated string
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum StringPart {
+    /// Literal text
     Literal(String),
+    /// `${...}` interpolation
     Interpolation(NixExpression),
 }
 
 /// Path interpolation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PathInterpolationExpr {
+    /// Literal and interpolated parts in order
     pub parts: Vec<PathPart>,
 }
 
 /// Part of an interpolated path
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum PathPart {
+    /// Literal path text
     Literal(String),
+    /// `${...}` interpolation
     Interpolation(NixExpression),
 }
 
@@ -500,6 +623,17 @@ mod tests {
         assert_eq!(ast.source(), source);
     }
 
+    #[test]
+    fn test_span_of_root_expression() {
+        let source = "  { x = 1; }";
+        let ast = NixAst::parse(source).unwrap();
+        let expr = ast.expr().unwrap();
+        let span = Span::of(rowan::ast::AstNode::syntax(&expr));
+        assert_eq!(span, Span::new(2, 12));
+        assert_eq!(span.slice(source), "{ x = 1; }");
+        assert_eq!(span.to_string(), "2..12");
+    }
+
     #[test]
     fn test_expression_type_name() {
         let expr = NixExpression::Literal(LiteralExpr::Integer(42));
@@ -0,0 +1,1377 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! AST to Value Converter
+//!
+//! Converts rnix AST nodes to [`NixValue`] semantic representation.
+//!
+//! This bridges the gap between syntax (AST) and semantics (`NixValue`),
+//! enabling direct file parsing in the I/O layer.
+//!
+//! ## Static Subset
+//!
+//! The converter accepts every expression whose value is known without
+//! calling functions:
+//!
+//! - literals: strings (with static interpolation), integers, floats
+//!   (including negated ones), URIs, paths and lookup paths (`<nixpkgs>`)
+//! - `true`, `false` and `null`
+//! - attribute sets, including nested attribute paths (`a.b.c = 1;`),
+//!   quoted and `${...}` keys, `inherit` / `inherit (e)` and `rec { }`
+//! - lists, parentheses, `let ... in`, `with`, selections (`a.b or c`),
+//!   `?`, `!`, unary minus and `if` over static conditions
+//!
+//! Bindings are resolved lazily, so `rec` sets and `let` blocks may reference
+//! each other in any order. Anything outside this subset (function
+//! application, lambdas, binary operators, ...) yields a
+//! [`ConversionError`] carrying the span of the offending expression.
+
+use super::ast::{AstError, NixAst, Span};
+use super::eval::EvalError;
+use super::value_objects::*;
+use rnix::ast::{self, HasEntry, InterpolPart, LiteralKind, UnaryOpKind};
+use rowan::ast::AstNode;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+/// Errors that can occur while converting an AST to a `NixValue`
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum ConversionError {
+    /// The source could not be parsed
+    #[error("Parse error: {0}")]
+    Parse(#[from] AstError),
+
+    /// The source contains no expression
+    #[error("No expression found in AST")]
+    MissingExpression,
+
+    /// The expression cannot be reduced to a value without evaluation
+    #[error("Non-static {construct} at {span}")]
+    NonStatic {
+        /// Kind of expression (e.g. "function application")
+        construct: &'static str,
+        /// Location of the expression
+        span: Span,
+    },
+
+    /// Reference to a variable that is not bound in any enclosing scope
+    #[error("Undefined variable '{name}' at {span}")]
+    UnboundIdentifier {
+        /// Variable name
+        name: String,
+        /// Location of the reference
+        span: Span,
+    },
+
+    /// The same attribute is defined twice
+    #[error("Attribute '{name}' already defined at {span}")]
+    DuplicateAttribute {
+        /// Attribute name
+        name: String,
+        /// Location of the second definition
+        span: Span,
+    },
+
+    /// An attribute name does not reduce to a static string
+    #[error("Dynamic attribute name at {span}")]
+    DynamicAttribute {
+        /// Location of the attribute name
+        span: Span,
+    },
+
+    /// A selected attribute does not exist
+    #[error("Attribute '{name}' missing at {span}")]
+    MissingAttribute {
+        /// Attribute name
+        name: String,
+        /// Location of the selection
+        span: Span,
+    },
+
+    /// A value has the wrong type for the construct using it
+    #[error("Expected {expected}, got {got} at {span}")]
+    TypeMismatch {
+        /// Expected type name
+        expected: &'static str,
+        /// Actual type name
+        got: &'static str,
+        /// Location of the value
+        span: Span,
+    },
+
+    /// A literal could not be parsed (e.g. integer overflow)
+    #[error("Invalid literal '{text}' at {span}")]
+    InvalidLiteral {
+        /// Literal text
+        text: String,
+        /// Location of the literal
+        span: Span,
+    },
+
+    /// A binding depends on its own value
+    #[error("Infinite recursion in '{name}' at {span}")]
+    InfiniteRecursion {
+        /// Binding name
+        name: String,
+        /// Location of the binding
+        span: Span,
+    },
+
+    /// Evaluating the source failed
+    #[error("Evaluation error: {0}")]
+    Evaluation(#[from] EvalError),
+}
+
+impl ConversionError {
+    /// Location of the error in the source, if known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Parse(_) | Self::MissingExpression => None,
+            Self::NonStatic { span, .. }
+            | Self::UnboundIdentifier { span, .. }
+            | Self::DuplicateAttribute { span, .. }
+            | Self::DynamicAttribute { span }
+            | Self::MissingAttribute { span, .. }
+            | Self::TypeMismatch { span, .. }
+            | Self::InvalidLiteral { span, .. }
+            | Self::InfiniteRecursion { span, .. } => Some(*span),
+            Self::Evaluation(e) => e.span(),
+        }
+    }
+
+    /// Whether the error means "needs evaluation" rather than "is wrong"
+    ///
+    /// These are the errors a lenient converter skips over.
+    pub fn is_non_static(&self) -> bool {
+        matches!(
+            self,
+            Self::NonStatic { .. } | Self::UnboundIdentifier { .. } | Self::DynamicAttribute { .. }
+        )
+    }
+}
+
+/// Result type for conversion operations
+pub type ConversionResult<T> = std::result::Result<T, ConversionError>;
+
+// ============================================================================
+// Bindings and Scopes
+// ============================================================================
+
+/// An attribute binding awaiting evaluation
+///
+/// Nested attribute paths (`a.b = 1; a.c = 2;`) are merged into `Set`
+/// nodes so that `a` can be selected lazily, one level at a time.
+enum Binding {
+    Leaf(Leaf),
+    Set(BTreeMap<String, Binding>),
+}
+
+struct Leaf {
+    name: String,
+    span: Span,
+    source: LeafSource,
+    state: RefCell<LeafState>,
+}
+
+enum LeafSource {
+    /// `name = expr;`
+    Expr(ast::Expr),
+    /// `inherit name;` or `inherit (from) name;`
+    ///
+    /// `outer` is set for inherits at the top level of a `rec` set or `let`
+    /// block, which resolve in the enclosing scope rather than their own.
+    Inherit {
+        from: Option<ast::Expr>,
+        outer: bool,
+    },
+}
+
+enum LeafState {
+    Pending,
+    Forcing,
+    Done(NixValue),
+}
+
+impl Binding {
+    fn leaf(name: &str, span: Span, source: LeafSource) -> Self {
+        Binding::Leaf(Leaf {
+            name: name.to_string(),
+            span,
+            source,
+            state: RefCell::new(LeafState::Pending),
+        })
+    }
+}
+
+/// A lexical scope used while resolving identifiers
+struct Scope<'a> {
+    parent: Option<&'a Scope<'a>>,
+    frame: Frame,
+}
+
+enum Frame {
+    Bindings(BTreeMap<String, Binding>),
+    With(NixAttrset),
+}
+
+enum Found<'s> {
+    Binding(&'s Binding, &'s Scope<'s>),
+    Value(&'s NixValue),
+}
+
+impl Scope<'_> {
+    fn root() -> Scope<'static> {
+        Scope {
+            parent: None,
+            frame: Frame::Bindings(BTreeMap::new()),
+        }
+    }
+
+    /// Resolve a name, preferring lexical bindings over `with` scopes
+    fn lookup<'s>(&'s self, name: &str) -> Option<Found<'s>> {
+        let mut current: Option<&'s Scope<'s>> = Some(self);
+        while let Some(scope) = current {
+            if let Frame::Bindings(bindings) = &scope.frame {
+                if let Some(binding) = bindings.get(name) {
+                    return Some(Found::Binding(binding, scope));
+                }
+            }
+            current = scope.parent;
+        }
+
+        let mut current: Option<&'s Scope<'s>> = Some(self);
+        while let Some(scope) = current {
+            if let Frame::With(attrs) = &scope.frame {
+                if let Some(value) = attrs.get(name) {
+                    return Some(Found::Value(value));
+                }
+            }
+            current = scope.parent;
+        }
+
+        None
+    }
+}
+
+/// Outcome of selecting an attribute path that `or` can recover from
+enum Selected {
+    Value(NixValue),
+    Failed(ConversionError),
+}
+
+fn span_of(node: &impl AstNode<Language = rnix::NixLanguage>) -> Span {
+    Span::of(node.syntax())
+}
+
+/// The attribute set literal bound by `expr`, if it can be merged into
+fn plain_attrset(expr: &ast::Expr) -> Option<ast::AttrSet> {
+    match expr {
+        ast::Expr::AttrSet(set) if set.rec_token().is_none() => Some(set.clone()),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Converter
+// ============================================================================
+
+/// Converts rnix AST to `NixValue`
+#[derive(Debug, Clone, Default)]
+pub struct AstConverter {
+    /// Skip attributes whose values are not static instead of failing
+    lenient: bool,
+}
+
+impl AstConverter {
+    /// Create a new AST converter
+    pub fn new() -> Self {
+        Self { lenient: false }
+    }
+
+    /// Create a converter that drops non-static attributes
+    ///
+    /// Useful for files that mix data with functions (flakes, modules):
+    /// every attribute that reduces to a value is kept, the rest are
+    /// silently omitted.
+    pub fn lenient() -> Self {
+        Self { lenient: true }
+    }
+
+    /// Convert an AST to a `NixValue`
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`ConversionError`] with the span of the first expression
+    /// that is not static or not well-formed.
+    pub fn convert(&self, ast: &NixAst) -> ConversionResult<NixValue> {
+        let expr = ast.expr().ok_or(ConversionError::MissingExpression)?;
+        self.convert_expr(&expr, &Scope::root())
+    }
+
+    /// Convert an expression within a scope
+    fn convert_expr(&self, expr: &ast::Expr, scope: &Scope<'_>) -> ConversionResult<NixValue> {
+        match expr {
+            ast::Expr::AttrSet(set) => self.convert_attrset(set, scope),
+            ast::Expr::List(list) => self.convert_list(list, scope),
+            ast::Expr::Str(string) => self.convert_string(string, scope),
+            ast::Expr::Literal(literal) => Self::convert_literal(literal),
+            ast::Expr::Path(path) => self.convert_path(path, scope),
+            ast::Expr::Ident(ident) => self.convert_ident(ident, scope),
+            ast::Expr::Paren(paren) => self.convert_child(paren.expr(), expr, scope),
+            ast::Expr::Root(root) => self.convert_child(root.expr(), expr, scope),
+            ast::Expr::LetIn(let_in) => self.convert_let_in(let_in, scope),
+            ast::Expr::With(with) => self.convert_with(with, scope),
+            ast::Expr::Select(select) => self.convert_select(select, scope),
+            ast::Expr::HasAttr(has_attr) => self.convert_has_attr(has_attr, scope),
+            ast::Expr::UnaryOp(unary) => self.convert_unary(unary, scope),
+            ast::Expr::IfElse(if_else) => self.convert_if_else(if_else, scope),
+            ast::Expr::Apply(_) => Err(Self::non_static("function application", expr)),
+            ast::Expr::Lambda(_) => Err(Self::non_static("function", expr)),
+            ast::Expr::BinOp(_) => Err(Self::non_static("binary operation", expr)),
+            ast::Expr::Assert(_) => Err(Self::non_static("assertion", expr)),
+            ast::Expr::LegacyLet(_) => Err(Self::non_static("legacy let", expr)),
+            ast::Expr::Error(_) => {
+                Err(AstError::InvalidSyntax(format!("Syntax error at {}", span_of(expr))).into())
+            }
+        }
+    }
+
+    fn non_static(construct: &'static str, expr: &ast::Expr) -> ConversionError {
+        ConversionError::NonStatic {
+            construct,
+            span: span_of(expr),
+        }
+    }
+
+    /// Convert a required child expression of `parent`
+    fn convert_child(
+        &self,
+        child: Option<ast::Expr>,
+        parent: &impl AstNode<Language = rnix::NixLanguage>,
+        scope: &Scope<'_>,
+    ) -> ConversionResult<NixValue> {
+        match child {
+            Some(child) => self.convert_expr(&child, scope),
+            None => Err(AstError::MissingNode(format!("expression at {}", span_of(parent))).into()),
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Attribute sets, let and with
+    // ------------------------------------------------------------------------
+
+    /// Convert an attribute set node
+    fn convert_attrset(&self, set: &ast::AttrSet, scope: &Scope<'_>) -> ConversionResult<NixValue> {
+        if set.rec_token().is_none() {
+            let bindings = self.collect_bindings(set, scope, false)?;
+            return self.force_bindings(&bindings, scope).map(NixValue::Attrset);
+        }
+
+        let bindings = self.collect_bindings(set, scope, true)?;
+        let inner = Scope {
+            parent: Some(scope),
+            frame: Frame::Bindings(bindings),
+        };
+        let Frame::Bindings(bindings) = &inner.frame else {
+            unreachable!("scope was built from bindings")
+        };
+        let mut attrs = self.force_bindings(bindings, &inner)?;
+        attrs.recursive = true;
+        Ok(NixValue::Attrset(attrs))
+    }
+
+    /// Convert `let ... in body`
+    fn convert_let_in(&self, let_in: &ast::LetIn, scope: &Scope<'_>) -> ConversionResult<NixValue> {
+        let inner = Scope {
+            parent: Some(scope),
+            frame: Frame::Bindings(self.collect_bindings(let_in, scope, true)?),
+        };
+        self.convert_child(let_in.body(), let_in, &inner)
+    }
+
+    /// Convert `with namespace; body`
+    fn convert_with(&self, with: &ast::With, scope: &Scope<'_>) -> ConversionResult<NixValue> {
+        let namespace = match self.convert_child(with.namespace(), with, scope)? {
+            NixValue::Attrset(attrs) => attrs,
+            other => {
+                return Err(ConversionError::TypeMismatch {
+                    expected: "attrset",
+                    got: other.type_name(),
+                    span: with
+                        .namespace()
+                        .map_or_else(|| span_of(with), |ns| span_of(&ns)),
+                })
+            }
+        };
+        let inner = Scope {
+            parent: Some(scope),
+            frame: Frame::With(namespace),
+        };
+        self.convert_child(with.body(), with, &inner)
+    }
+
+    /// Gather the entries of a set or `let` block into a binding tree
+    ///
+    /// Attribute names are resolved in `scope`; values stay unevaluated.
+    fn collect_bindings(
+        &self,
+        node: &impl HasEntry,
+        scope: &Scope<'_>,
+        outer_inherits: bool,
+    ) -> ConversionResult<BTreeMap<String, Binding>> {
+        let mut bindings = BTreeMap::new();
+        for entry in node.entries() {
+            match self.insert_entry(&mut bindings, &entry, scope, outer_inherits) {
+                Err(err) if self.lenient && err.is_non_static() => {}
+                result => result?,
+            }
+        }
+        Ok(bindings)
+    }
+
+    fn insert_entry(
+        &self,
+        bindings: &mut BTreeMap<String, Binding>,
+        entry: &ast::Entry,
+        scope: &Scope<'_>,
+        outer_inherits: bool,
+    ) -> ConversionResult<()> {
+        match entry {
+            ast::Entry::AttrpathValue(binding) => {
+                let attrpath = binding.attrpath().ok_or_else(|| {
+                    AstError::MissingNode(format!("attribute path at {}", span_of(binding)))
+                })?;
+                let path = attrpath
+                    .attrs()
+                    .map(|attr| self.attr_name(&attr, scope))
+                    .collect::<ConversionResult<Vec<_>>>()?;
+                let value = binding.value().ok_or_else(|| {
+                    AstError::MissingNode(format!("attribute value at {}", span_of(binding)))
+                })?;
+                self.insert_path(bindings, &path, value, scope)
+            }
+            ast::Entry::Inherit(inherit) => {
+                let from = inherit.from().and_then(|from| from.expr());
+                for attr in inherit.attrs() {
+                    let (name, span) = self.attr_name(&attr, scope)?;
+                    if bindings.contains_key(&name) {
+                        return Err(ConversionError::DuplicateAttribute { name, span });
+                    }
+                    let source = LeafSource::Inherit {
+                        from: from.clone(),
+                        outer: outer_inherits,
+                    };
+                    bindings.insert(name.clone(), Binding::leaf(&name, span, source));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Insert `a.b.c = value` into the binding tree, merging nested sets
+    fn insert_path(
+        &self,
+        bindings: &mut BTreeMap<String, Binding>,
+        path: &[(String, Span)],
+        value: ast::Expr,
+        scope: &Scope<'_>,
+    ) -> ConversionResult<()> {
+        let Some(((name, span), rest)) = path.split_first() else {
+            return Err(
+                AstError::MissingNode(format!("attribute name at {}", span_of(&value))).into(),
+            );
+        };
+
+        if rest.is_empty() {
+            let Some(existing) = bindings.get_mut(name) else {
+                let source = LeafSource::Expr(value);
+                bindings.insert(name.clone(), Binding::leaf(name, *span, source));
+                return Ok(());
+            };
+            // `a = { x = 1; }; a = { y = 2; };` merges, like in Nix itself
+            let Some(set) = plain_attrset(&value) else {
+                return Err(ConversionError::DuplicateAttribute {
+                    name: name.clone(),
+                    span: *span,
+                });
+            };
+            let children = self.expand(existing, name, *span, scope)?;
+            for entry in set.entries() {
+                self.insert_entry(children, &entry, scope, false)?;
+            }
+            return Ok(());
+        }
+
+        let entry = bindings
+            .entry(name.clone())
+            .or_insert_with(|| Binding::Set(BTreeMap::new()));
+        let children = self.expand(entry, name, *span, scope)?;
+        self.insert_path(children, rest, value, scope)
+    }
+
+    /// Turn a binding into a mergeable set, expanding attribute set literals
+    fn expand<'b>(
+        &self,
+        binding: &'b mut Binding,
+        name: &str,
+        span: Span,
+        scope: &Scope<'_>,
+    ) -> ConversionResult<&'b mut BTreeMap<String, Binding>> {
+        if let Binding::Leaf(leaf) = binding {
+            let set = match &leaf.source {
+                LeafSource::Expr(expr) => plain_attrset(expr),
+                LeafSource::Inherit { .. } => None,
+            };
+            let Some(set) = set else {
+                return Err(ConversionError::DuplicateAttribute {
+                    name: name.to_string(),
+                    span,
+                });
+            };
+            let mut children = BTreeMap::new();
+            for entry in set.entries() {
+                self.insert_entry(&mut children, &entry, scope, false)?;
+            }
+            *binding = Binding::Set(children);
+        }
+
+        match binding {
+            Binding::Set(children) => Ok(children),
+            Binding::Leaf(_) => Err(ConversionError::DuplicateAttribute {
+                name: name.to_string(),
+                span,
+            }),
+        }
+    }
+
+    /// Resolve an attribute name to a static string
+    fn attr_name(&self, attr: &ast::Attr, scope: &Scope<'_>) -> ConversionResult<(String, Span)> {
+        let span = span_of(attr);
+        let value = match attr {
+            ast::Attr::Ident(ident) => return Ok((ident.syntax().text().to_string(), span)),
+            ast::Attr::Str(string) => self.convert_string(string, scope),
+            ast::Attr::Dynamic(dynamic) => self.convert_child(dynamic.expr(), dynamic, scope),
+        };
+
+        match value {
+            Ok(NixValue::String(s)) => Ok((s.value, span)),
+            Ok(other) => Err(ConversionError::TypeMismatch {
+                expected: "string",
+                got: other.type_name(),
+                span,
+            }),
+            Err(err) if err.is_non_static() => Err(ConversionError::DynamicAttribute { span }),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Evaluate every binding in a tree into an attribute set
+    fn force_bindings(
+        &self,
+        bindings: &BTreeMap<String, Binding>,
+        scope: &Scope<'_>,
+    ) -> ConversionResult<NixAttrset> {
+        let mut attrs = NixAttrset::new();
+        for (name, binding) in bindings {
+            match self.force_binding(binding, scope) {
+                Ok(value) => attrs.insert(name.clone(), value),
+                Err(err) if self.lenient && err.is_non_static() => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(attrs)
+    }
+
+    fn force_binding(&self, binding: &Binding, scope: &Scope<'_>) -> ConversionResult<NixValue> {
+        match binding {
+            Binding::Leaf(leaf) => self.force_leaf(leaf, scope),
+            Binding::Set(children) => self.force_bindings(children, scope).map(NixValue::Attrset),
+        }
+    }
+
+    /// Evaluate a leaf binding once, detecting self-reference
+    fn force_leaf(&self, leaf: &Leaf, scope: &Scope<'_>) -> ConversionResult<NixValue> {
+        let state = std::mem::replace(&mut *leaf.state.borrow_mut(), LeafState::Forcing);
+        match state {
+            LeafState::Done(value) => {
+                *leaf.state.borrow_mut() = LeafState::Done(value.clone());
+                return Ok(value);
+            }
+            LeafState::Forcing => {
+                return Err(ConversionError::InfiniteRecursion {
+                    name: leaf.name.clone(),
+                    span: leaf.span,
+                })
+            }
+            LeafState::Pending => {}
+        }
+
+        let result = match &leaf.source {
+            LeafSource::Expr(expr) => self.convert_expr(expr, scope),
+            LeafSource::Inherit {
+                from: Some(from), ..
+            } => {
+                let value = self.convert_expr(from, scope)?;
+                match Self::select_value(value, &[(leaf.name.clone(), leaf.span)]) {
+                    Selected::Value(value) => Ok(value),
+                    Selected::Failed(err) => Err(err),
+                }
+            }
+            LeafSource::Inherit { from: None, outer } => {
+                let lookup_scope = if *outer { scope.parent } else { Some(scope) };
+                match lookup_scope.and_then(|s| s.lookup(&leaf.name)) {
+                    Some(found) => self.force_found(&found),
+                    None => Err(ConversionError::UnboundIdentifier {
+                        name: leaf.name.clone(),
+                        span: leaf.span,
+                    }),
+                }
+            }
+        };
+
+        *leaf.state.borrow_mut() = match &result {
+            Ok(value) => LeafState::Done(value.clone()),
+            Err(_) => LeafState::Pending,
+        };
+        result
+    }
+
+    fn force_found(&self, found: &Found<'_>) -> ConversionResult<NixValue> {
+        match *found {
+            Found::Binding(binding, scope) => self.force_binding(binding, scope),
+            Found::Value(value) => Ok(value.clone()),
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Identifiers and selections
+    // ------------------------------------------------------------------------
+
+    /// Convert an identifier reference
+    fn convert_ident(&self, ident: &ast::Ident, scope: &Scope<'_>) -> ConversionResult<NixValue> {
+        let name = ident.syntax().text().to_string();
+        if let Some(found) = scope.lookup(&name) {
+            return self.force_found(&found);
+        }
+
+        match name.as_str() {
+            "true" => Ok(NixValue::Bool(NixBool::new(true))),
+            "false" => Ok(NixValue::Bool(NixBool::new(false))),
+            "null" => Ok(NixValue::Null(NixNull)),
+            _ => Err(ConversionError::UnboundIdentifier {
+                name,
+                span: span_of(ident),
+            }),
+        }
+    }
+
+    /// Convert `expr.a.b or default`
+    fn convert_select(
+        &self,
+        select: &ast::Select,
+        scope: &Scope<'_>,
+    ) -> ConversionResult<NixValue> {
+        let path = self.select_attrpath(select.attrpath(), select, scope)?;
+        match self.select(select.expr(), select, &path, scope)? {
+            Selected::Value(value) => Ok(value),
+            Selected::Failed(err) => match select.default_expr() {
+                Some(default) => self.convert_expr(&default, scope),
+                None => Err(err),
+            },
+        }
+    }
+
+    /// Convert `expr ? a.b`
+    fn convert_has_attr(
+        &self,
+        has_attr: &ast::HasAttr,
+        scope: &Scope<'_>,
+    ) -> ConversionResult<NixValue> {
+        let path = self.select_attrpath(has_attr.attrpath(), has_attr, scope)?;
+        let found = matches!(
+            self.select(has_attr.expr(), has_attr, &path, scope)?,
+            Selected::Value(_)
+        );
+        Ok(NixValue::Bool(NixBool::new(found)))
+    }
+
+    fn select_attrpath(
+        &self,
+        attrpath: Option<ast::Attrpath>,
+        parent: &impl AstNode<Language = rnix::NixLanguage>,
+        scope: &Scope<'_>,
+    ) -> ConversionResult<Vec<(String, Span)>> {
+        let attrpath = attrpath.ok_or_else(|| {
+            AstError::MissingNode(format!("attribute path at {}", span_of(parent)))
+        })?;
+        attrpath
+            .attrs()
+            .map(|attr| self.attr_name(&attr, scope))
+            .collect()
+    }
+
+    /// Select a path from an expression
+    ///
+    /// Identifiers bound to unevaluated bindings are walked lazily, so
+    /// `rec { a.b = 1; a.c = a.b; }` does not force all of `a` to read `a.b`.
+    fn select(
+        &self,
+        base: Option<ast::Expr>,
+        parent: &impl AstNode<Language = rnix::NixLanguage>,
+        path: &[(String, Span)],
+        scope: &Scope<'_>,
+    ) -> ConversionResult<Selected> {
+        if let Some(ast::Expr::Ident(ident)) = &base {
+            if let Some(Found::Binding(binding, binding_scope)) =
+                scope.lookup(&ident.syntax().text().to_string())
+            {
+                return self.select_binding(binding, binding_scope, path);
+            }
+        }
+
+        let value = self.convert_child(base, parent, scope)?;
+        Ok(Self::select_value(value, path))
+    }
+
+    fn select_binding(
+        &self,
+        binding: &Binding,
+        scope: &Scope<'_>,
+        path: &[(String, Span)],
+    ) -> ConversionResult<Selected> {
+        let mut current = binding;
+        for (index, (name, span)) in path.iter().enumerate() {
+            match current {
+                Binding::Set(children) => match children.get(name) {
+                    Some(child) => current = child,
+                    None => {
+                        return Ok(Selected::Failed(ConversionError::MissingAttribute {
+                            name: name.clone(),
+                            span: *span,
+                        }))
+                    }
+                },
+                Binding::Leaf(leaf) => {
+                    let value = self.force_leaf(leaf, scope)?;
+                    return Ok(Self::select_value(value, &path[index..]));
+                }
+            }
+        }
+        self.force_binding(current, scope).map(Selected::Value)
+    }
+
+    fn select_value(mut value: NixValue, path: &[(String, Span)]) -> Selected {
+        for (name, span) in path {
+            value = match value {
+                NixValue::Attrset(mut attrs) => match attrs.attributes.remove(name) {
+                    Some(next) => next,
+                    None => {
+                        return Selected::Failed(ConversionError::MissingAttribute {
+                            name: name.clone(),
+                            span: *span,
+                        })
+                    }
+                },
+                other => {
+                    return Selected::Failed(ConversionError::TypeMismatch {
+                        expected: "attrset",
+                        got: other.type_name(),
+                        span: *span,
+                    })
+                }
+            };
+        }
+        Selected::Value(value)
+    }
+
+    // ------------------------------------------------------------------------
+    // Operators
+    // ------------------------------------------------------------------------
+
+    /// Convert `-x` and `!x`
+    fn convert_unary(&self, unary: &ast::UnaryOp, scope: &Scope<'_>) -> ConversionResult<NixValue> {
+        if is_negated_min_int(unary) {
+            return Ok(NixValue::Integer(NixInteger::new(i64::MIN)));
+        }
+        let value = self.convert_child(unary.expr(), unary, scope)?;
+        let span = span_of(unary);
+        match (unary.operator(), value) {
+            (Some(UnaryOpKind::Negate), NixValue::Integer(i)) => i
+                .value
+                .checked_neg()
+                .map(|n| NixValue::Integer(NixInteger::new(n)))
+                .ok_or_else(|| ConversionError::InvalidLiteral {
+                    text: unary.syntax().text().to_string(),
+                    span,
+                }),
+            (Some(UnaryOpKind::Negate), NixValue::Float(f)) => {
+                Ok(NixValue::Float(NixFloat::new(-f.value)))
+            }
+            (Some(UnaryOpKind::Invert), NixValue::Bool(b)) => {
+                Ok(NixValue::Bool(NixBool::new(!b.value)))
+            }
+            (Some(UnaryOpKind::Negate), other) => Err(ConversionError::TypeMismatch {
+                expected: "number",
+                got: other.type_name(),
+                span,
+            }),
+            (_, other) => Err(ConversionError::TypeMismatch {
+                expected: "bool",
+                got: other.type_name(),
+                span,
+            }),
+        }
+    }
+
+    /// Convert `if cond then a else b` over a static condition
+    fn convert_if_else(
+        &self,
+        if_else: &ast::IfElse,
+        scope: &Scope<'_>,
+    ) -> ConversionResult<NixValue> {
+        match self.convert_child(if_else.condition(), if_else, scope)? {
+            NixValue::Bool(b) if b.value => self.convert_child(if_else.body(), if_else, scope),
+            NixValue::Bool(_) => self.convert_child(if_else.else_body(), if_else, scope),
+            other => Err(ConversionError::TypeMismatch {
+                expected: "bool",
+                got: other.type_name(),
+                span: if_else
+                    .condition()
+                    .map_or_else(|| span_of(if_else), |c| span_of(&c)),
+            }),
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Literals
+    // ------------------------------------------------------------------------
+
+    /// Convert a list node
+    fn convert_list(&self, list: &ast::List, scope: &Scope<'_>) -> ConversionResult<NixValue> {
+        let elements = list
+            .items()
+            .map(|item| self.convert_expr(&item, scope))
+            .collect::<ConversionResult<Vec<_>>>()?;
+        Ok(NixValue::List(NixList { elements }))
+    }
+
+    /// Convert a string node, unescaping and interpolating static parts
+    fn convert_string(&self, string: &ast::Str, scope: &Scope<'_>) -> ConversionResult<NixValue> {
+        let mut content = String::new();
+        for part in string.normalized_parts() {
+            match part {
+                InterpolPart::Literal(text) => content.push_str(&text),
+                InterpolPart::Interpolation(interpol) => {
+                    content.push_str(&self.interpolate(&interpol, scope)?);
+                }
+            }
+        }
+        Ok(NixValue::String(NixString::new(content)))
+    }
+
+    fn interpolate(&self, interpol: &ast::Interpol, scope: &Scope<'_>) -> ConversionResult<String> {
+        match self.convert_child(interpol.expr(), interpol, scope)? {
+            NixValue::String(s) => Ok(s.value),
+            other => Err(ConversionError::TypeMismatch {
+                expected: "string",
+                got: other.type_name(),
+                span: span_of(interpol),
+            }),
+        }
+    }
+
+    /// Convert a literal node (integer, float, URI)
+    fn convert_literal(literal: &ast::Literal) -> ConversionResult<NixValue> {
+        let invalid = || ConversionError::InvalidLiteral {
+            text: literal.syntax().text().to_string(),
+            span: span_of(literal),
+        };
+        match literal.kind() {
+            LiteralKind::Integer(i) => i
+                .value()
+                .map(|n| NixValue::Integer(NixInteger::new(n)))
+                .map_err(|_| invalid()),
+            LiteralKind::Float(f) => f
+                .value()
+                .map(|n| NixValue::Float(NixFloat::new(n)))
+                .map_err(|_| invalid()),
+            LiteralKind::Uri(_) => Ok(NixValue::String(NixString::new(
+                literal.syntax().text().to_string(),
+            ))),
+        }
+    }
+
+    /// Convert a path (`./foo`, `/etc`, `~/x`) or lookup path (`<nixpkgs>`)
+    fn convert_path(&self, path: &ast::Path, scope: &Scope<'_>) -> ConversionResult<NixValue> {
+        let mut text = String::new();
+        for part in path.parts() {
+            match part {
+                InterpolPart::Literal(content) => {
+                    text.push_str(rnix::ast::AstToken::syntax(&content).text());
+                }
+                InterpolPart::Interpolation(interpol) => {
+                    text.push_str(&self.interpolate(&interpol, scope)?);
+                }
+            }
+        }
+
+        let invalid = || ConversionError::InvalidLiteral {
+            text: text.clone(),
+            span: span_of(path),
+        };
+        match text.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+            Some(name) => NixLookupPath::new(name)
+                .map(NixValue::LookupPath)
+                .map_err(|_| invalid()),
+            None => NixPath::new(text.as_str())
+                .map(NixValue::Path)
+                .map_err(|_| invalid()),
+        }
+    }
+}
+
+/// Whether a negation is the literal `-9223372036854775808`
+///
+/// The operand alone overflows `i64`, so the smallest integer is only
+/// expressible by recognising the whole negation.
+pub(crate) fn is_negated_min_int(unary: &ast::UnaryOp) -> bool {
+    matches!(unary.operator(), Some(UnaryOpKind::Negate))
+        && matches!(
+            unary.expr(),
+            Some(ast::Expr::Literal(literal)) if literal.syntax().text() == "9223372036854775808"
+        )
+}
+
+// ============================================================================
+// Convenience Functions
+// ============================================================================
+
+/// Convert AST to `NixValue`
+///
+/// ## Errors
+///
+/// See [`AstConverter::convert`].
+pub fn ast_to_value(ast: &NixAst) -> ConversionResult<NixValue> {
+    let converter = AstConverter::new();
+    converter.convert(ast)
+}
+
+/// Parse Nix source and convert it to a `NixValue` in one step
+///
+/// ## Errors
+///
+/// Returns [`ConversionError::Parse`] if the source does not parse, or any
+/// other [`ConversionError`] if it is not static.
+pub fn parse_value(source: &str) -> ConversionResult<NixValue> {
+    ast_to_value(&NixAst::parse(source)?)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nix::parser::NixParser;
+
+    fn attr<'a>(value: &'a NixValue, path: &str) -> &'a NixValue {
+        path.split('.').fold(value, |v, key| {
+            v.as_attrset()
+                .unwrap()
+                .get(key)
+                .unwrap_or_else(|| panic!("missing attribute {key}"))
+        })
+    }
+
+    #[test]
+    fn test_convert_empty_attrset() {
+        let parser = NixParser::new();
+        let ast = parser.parse_str("{ }").unwrap();
+
+        let converter = AstConverter::new();
+        let result = converter.convert(&ast);
+        assert!(result.is_ok());
+
+        let value = result.unwrap();
+        match value {
+            NixValue::Attrset(attrs) => {
+                assert_eq!(attrs.attributes.len(), 0);
+            }
+            _ => panic!("Expected attrset"),
+        }
+    }
+
+    #[test]
+    fn test_convert_simple_attrset() {
+        let parser = NixParser::new();
+        let ast = parser.parse_str(r#"{ name = "test"; }"#).unwrap();
+
+        let converter = AstConverter::new();
+        let result = converter.convert(&ast);
+        assert!(result.is_ok());
+
+        let value = result.unwrap();
+        match value {
+            NixValue::Attrset(attrs) => {
+                assert!(attrs.attributes.contains_key("name"));
+                if let Some(NixValue::String(s)) = attrs.attributes.get("name") {
+                    assert_eq!(s.value, "test");
+                } else {
+                    panic!("Expected string value");
+                }
+            }
+            _ => panic!("Expected attrset"),
+        }
+    }
+
+    #[test]
+    fn test_convert_integer() {
+        let parser = NixParser::new();
+        let ast = parser.parse_str("42").unwrap();
+
+        let converter = AstConverter::new();
+        let result = converter.convert(&ast);
+        assert!(result.is_ok());
+
+        let value = result.unwrap();
+        match value {
+            NixValue::Integer(i) => assert_eq!(i.value, 42),
+            _ => panic!("Expected integer"),
+        }
+    }
+
+    #[test]
+    fn test_convert_list() {
+        let parser = NixParser::new();
+        let ast = parser.parse_str("[ 1 2 3 ]").unwrap();
+
+        let converter = AstConverter::new();
+        let result = converter.convert(&ast);
+        assert!(result.is_ok());
+
+        let value = result.unwrap();
+        match value {
+            NixValue::List(list) => {
+                assert_eq!(list.elements.len(), 3);
+            }
+            _ => panic!("Expected list"),
+        }
+    }
+
+    #[test]
+    fn test_convert_nested_attrset() {
+        let parser = NixParser::new();
+        let ast = parser
+            .parse_str(r#"{ outer = { inner = "value"; }; }"#)
+            .unwrap();
+
+        let converter = AstConverter::new();
+        let result = converter.convert(&ast);
+        assert!(result.is_ok());
+
+        let value = result.unwrap();
+        match value {
+            NixValue::Attrset(attrs) => {
+                assert!(attrs.attributes.contains_key("outer"));
+                if let Some(NixValue::Attrset(inner)) = attrs.attributes.get("outer") {
+                    assert!(inner.attributes.contains_key("inner"));
+                } else {
+                    panic!("Expected nested attrset");
+                }
+            }
+            _ => panic!("Expected attrset"),
+        }
+    }
+
+    #[test]
+    fn test_ast_to_value_convenience() {
+        let parser = NixParser::new();
+        let ast = parser.parse_str("{ x = 1; }").unwrap();
+
+        let result = ast_to_value(&ast);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_nested_attrpaths_merge() {
+        let value = parse_value(
+            r#"{
+                a.b.c = 1;
+                a.b.d = 2;
+                a.e = { f = 3; };
+                a.e.g = 4;
+                "quoted key".x = true;
+                ${"dyn" + ""} = 5;
+            }"#,
+        );
+        // `+` is not static, so the dynamic key is reported precisely
+        let err = value.unwrap_err();
+        assert!(matches!(err, ConversionError::DynamicAttribute { .. }));
+
+        let value = parse_value(
+            r#"{
+                a.b.c = 1;
+                a.b.d = 2;
+                a.e = { f = 3; };
+                a.e.g = 4;
+                "quoted key".x = true;
+                ${"dyn"} = 5;
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(attr(&value, "a.b.c").as_integer().unwrap(), 1);
+        assert_eq!(attr(&value, "a.b.d").as_integer().unwrap(), 2);
+        assert_eq!(attr(&value, "a.e.f").as_integer().unwrap(), 3);
+        assert_eq!(attr(&value, "a.e.g").as_integer().unwrap(), 4);
+        assert!(attr(&value, "quoted key.x").as_bool().unwrap());
+        assert_eq!(attr(&value, "dyn").as_integer().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_duplicate_attribute_has_span() {
+        let source = "{ a = 1; a = 2; }";
+        let err = parse_value(source).unwrap_err();
+        match err {
+            ConversionError::DuplicateAttribute { ref name, span } => {
+                assert_eq!(name, "a");
+                assert_eq!(span.slice(source), "a");
+                assert_eq!(span.start, 9);
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+
+        assert!(matches!(
+            parse_value("{ a.b = 1; a = 2; }").unwrap_err(),
+            ConversionError::DuplicateAttribute { .. }
+        ));
+    }
+
+    #[test]
+    fn test_rec_attrset() {
+        let value = parse_value(
+            r#"rec {
+                domain = "example.com";
+                host = "web.${domain}";
+                ports.http = 80;
+                ports.alt = ports.http;
+            }"#,
+        )
+        .unwrap();
+        assert!(value.as_attrset().unwrap().recursive);
+        assert_eq!(
+            attr(&value, "host").as_string().unwrap().value,
+            "web.example.com"
+        );
+        assert_eq!(attr(&value, "ports.alt").as_integer().unwrap(), 80);
+    }
+
+    #[test]
+    fn test_infinite_recursion_detected() {
+        let source = "rec { a = b; b = a; }";
+        match parse_value(source).unwrap_err() {
+            ConversionError::InfiniteRecursion { span, .. } => {
+                assert_eq!(span.slice(source), "a");
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_let_in_over_constants() {
+        let value = parse_value(
+            r#"let
+                base = { cores = 4; };
+                name = "node";
+                unused = builtins.fetchGit ./.;
+              in {
+                inherit name;
+                inherit (base) cores;
+                label = "${name}-${"01"}";
+              }"#,
+        )
+        .unwrap();
+        assert_eq!(attr(&value, "name").as_string().unwrap().value, "node");
+        assert_eq!(attr(&value, "cores").as_integer().unwrap(), 4);
+        assert_eq!(attr(&value, "label").as_string().unwrap().value, "node-01");
+    }
+
+    #[test]
+    fn test_inherit_in_rec_uses_enclosing_scope() {
+        let value = parse_value("let x = 1; in rec { inherit x; y = x; }").unwrap();
+        assert_eq!(attr(&value, "y").as_integer().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_with_select_and_has_attr() {
+        let value = parse_value(
+            r#"let cfg = { net.vlan = 10; }; in with cfg; {
+                vlan = net.vlan;
+                mtu = net.mtu or 1500;
+                hasVlan = cfg ? net.vlan;
+                enabled = !false;
+                mode = if cfg ? net then "tagged" else "plain";
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(attr(&value, "vlan").as_integer().unwrap(), 10);
+        assert_eq!(attr(&value, "mtu").as_integer().unwrap(), 1500);
+        assert!(attr(&value, "hasVlan").as_bool().unwrap());
+        assert!(attr(&value, "enabled").as_bool().unwrap());
+        assert_eq!(attr(&value, "mode").as_string().unwrap().value, "tagged");
+    }
+
+    #[test]
+    fn test_numeric_literals() {
+        let value = parse_value("[ (-3) 2.5 (-0.5) 1.5e3 ]").unwrap();
+        let list = value.as_list().unwrap();
+        assert_eq!(list.elements[0].as_integer().unwrap(), -3);
+        assert!((list.elements[1].as_float().unwrap() - 2.5).abs() < f64::EPSILON);
+        assert!((list.elements[2].as_float().unwrap() + 0.5).abs() < f64::EPSILON);
+        assert!((list.elements[3].as_float().unwrap() - 1500.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_paths_and_uris() {
+        let value = parse_value(
+            r"{
+                rel = ./hosts/router.nix;
+                abs = /etc/nixos;
+                home = ~/.config;
+                lookup = <nixpkgs>;
+                uri = https://example.com/x;
+            }",
+        )
+        .unwrap();
+        match attr(&value, "rel") {
+            NixValue::Path(p) => assert_eq!(p.as_str(), "./hosts/router.nix"),
+            other => panic!("expected path, got {other:?}"),
+        }
+        match attr(&value, "abs") {
+            NixValue::Path(p) => assert!(p.is_absolute()),
+            other => panic!("expected path, got {other:?}"),
+        }
+        assert!(matches!(attr(&value, "home"), NixValue::Path(_)));
+        match attr(&value, "lookup") {
+            NixValue::LookupPath(p) => assert_eq!(p.name, "nixpkgs"),
+            other => panic!("expected lookup path, got {other:?}"),
+        }
+        assert_eq!(
+            attr(&value, "uri").as_string().unwrap().value,
+            "https://example.com/x"
+        );
+    }
+
+    #[test]
+    fn test_string_escapes_and_indented_strings() {
+        let value = parse_value(
+            "{ a = \"tab\\tquote\\\"dollar\\${x}\"; b = ''\n    line1\n      line2\n  ''; }",
+        )
+        .unwrap();
+        assert_eq!(
+            attr(&value, "a").as_string().unwrap().value,
+            "tab\tquote\"dollar${x}"
+        );
+        assert_eq!(
+            attr(&value, "b").as_string().unwrap().value,
+            "line1\n  line2\n"
+        );
+    }
+
+    #[test]
+    fn test_booleans_and_null() {
+        let value = parse_value("{ a = true; b = false; c = null; }").unwrap();
+        assert!(attr(&value, "a").as_bool().unwrap());
+        assert!(!attr(&value, "b").as_bool().unwrap());
+        assert!(matches!(attr(&value, "c"), NixValue::Null(_)));
+    }
+
+    #[test]
+    fn test_non_static_errors_carry_spans() {
+        let source = "{ a = 1; b = f 2; }";
+        match parse_value(source).unwrap_err() {
+            ConversionError::NonStatic { construct, span } => {
+                assert_eq!(construct, "function application");
+                assert_eq!(span.slice(source), "f 2");
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+
+        let source = "{ a = pkgs.hello; }";
+        let err = parse_value(source).unwrap_err();
+        assert!(
+            matches!(err, ConversionError::UnboundIdentifier { ref name, .. } if name == "pkgs")
+        );
+        assert_eq!(err.span().unwrap().slice(source), "pkgs");
+
+        assert!(matches!(
+            parse_value("1 + 2").unwrap_err(),
+            ConversionError::NonStatic {
+                construct: "binary operation",
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse_value("x: x").unwrap_err(),
+            ConversionError::NonStatic {
+                construct: "function",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_missing_attribute_and_type_mismatch() {
+        assert!(matches!(
+            parse_value("let a = { }; in a.b").unwrap_err(),
+            ConversionError::MissingAttribute { ref name, .. } if name == "b"
+        ));
+        assert!(matches!(
+            parse_value(r#""n = ${1}""#).unwrap_err(),
+            ConversionError::TypeMismatch {
+                expected: "string",
+                got: "integer",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_lenient_skips_non_static_attributes() {
+        let ast = NixAst::parse(
+            r#"{
+                description = "demo";
+                inputs.nixpkgs.url = "github:NixOS/nixpkgs";
+                outputs = { self, nixpkgs }: { };
+                ${dynamic} = 1;
+            }"#,
+        )
+        .unwrap();
+
+        assert!(AstConverter::new().convert(&ast).is_err());
+
+        let value = AstConverter::lenient().convert(&ast).unwrap();
+        let attrs = value.as_attrset().unwrap();
+        assert_eq!(attrs.len(), 2);
+        assert_eq!(
+            attr(&value, "inputs.nixpkgs.url")
+                .as_string()
+                .unwrap()
+                .value,
+            "github:NixOS/nixpkgs"
+        );
+        assert!(!attrs.contains("outputs"));
+    }
+
+    #[test]
+    fn test_parse_error_is_reported() {
+        assert!(matches!(
+            parse_value("{ x = ").unwrap_err(),
+            ConversionError::Parse(_)
+        ));
+    }
+}
@@ -0,0 +1,497 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Async Flake Evaluation
+//!
+//! [`AsyncFlakeEvaluator`] is the tokio counterpart of
+//! [`FlakeEvaluator`](super::FlakeEvaluator) for services that must not
+//! block on a hung `nix` process:
+//!
+//! - every call has a timeout ([`DEFAULT_TIMEOUT`] unless configured)
+//! - evaluations can be cancelled, and dropping an evaluation future
+//!   kills the `nix` process
+//! - at most `max_concurrency` evaluations run at once, across all clones
+//!   of an evaluator
+//! - stderr lines are parsed into [`ProgressEvent`]s and streamed to a
+//!   channel while `nix` runs
+//!
+//! ## Usage
+//!
+//! ```rust,no_run
+//! use cim_domain_nix::nix::AsyncFlakeEvaluator;
+//! use std::time::Duration;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+//! let evaluator = AsyncFlakeEvaluator::new()
+//!     .with_timeout(Duration::from_secs(60))
+//!     .with_max_concurrency(4)
+//!     .with_progress(tx);
+//!
+//! tokio::spawn(async move {
+//!     while let Some(event) = rx.recv().await {
+//!         println!("{event}");
+//!     }
+//! });
+//!
+//! for (flake, result) in evaluator.evaluate_all(["./a", "./b", "./c"]).await {
+//!     println!("{flake}: {} systems", result?.packages.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::backend::{AsyncNixBackend, CliBackend};
+use super::flake_evaluator::{EvaluatedFlake, EvaluationError, EvaluationResult, FlakeEvaluator};
+use super::flake_metadata::FlakeMetadata;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+
+/// Timeout used unless [`AsyncFlakeEvaluator::with_timeout`] is called
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_mins(5);
+
+// ============================================================================
+// Progress Events
+// ============================================================================
+
+/// What a line of `nix` stderr reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressKind {
+    /// `evaluating ...`
+    Evaluating,
+    /// `downloading ...`, `fetching ...` or `unpacking ...`
+    Fetching,
+    /// `copying ...`
+    Copying,
+    /// `building ...` or a list of derivations to build
+    Building,
+    /// `warning: ...`
+    Warning,
+    /// `error: ...` or a continuation of an error trace
+    Error,
+    /// Any other line
+    Other,
+}
+
+/// A progress line from `nix` stderr
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgressEvent {
+    /// Flake being evaluated
+    pub flake: String,
+    /// What the line reports
+    pub kind: ProgressKind,
+    /// The line, without a `warning:` or `error:` prefix
+    pub message: String,
+}
+
+impl ProgressEvent {
+    /// Classify a stderr line; blank lines give `None`
+    pub fn parse(flake: impl Into<String>, line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+
+        let (kind, message) = if let Some(rest) = line.strip_prefix("warning:") {
+            (ProgressKind::Warning, rest.trim_start())
+        } else if let Some(rest) = line.strip_prefix("error:") {
+            (ProgressKind::Error, rest.trim_start())
+        } else if line.starts_with("evaluating ") {
+            (ProgressKind::Evaluating, line)
+        } else if ["downloading ", "fetching ", "unpacking "]
+            .iter()
+            .any(|prefix| line.starts_with(prefix))
+        {
+            (ProgressKind::Fetching, line)
+        } else if line.starts_with("copying ") {
+            (ProgressKind::Copying, line)
+        } else if line.starts_with("building ")
+            || (line.starts_with("these ") && line.contains("will be built"))
+            || line.starts_with("this derivation will be built")
+        {
+            (ProgressKind::Building, line)
+        } else if line.starts_with("… ") || line.starts_with("at ") {
+            (ProgressKind::Error, line)
+        } else {
+            (ProgressKind::Other, line)
+        };
+
+        Some(Self {
+            flake: flake.into(),
+            kind,
+            message: message.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for ProgressEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ProgressKind::Warning => write!(f, "{}: warning: {}", self.flake, self.message),
+            ProgressKind::Error => write!(f, "{}: error: {}", self.flake, self.message),
+            _ => write!(f, "{}: {}", self.flake, self.message),
+        }
+    }
+}
+
+// ============================================================================
+// Async Flake Evaluator
+// ============================================================================
+
+/// Evaluates flakes on tokio with timeouts, cancellation and bounded
+/// concurrency
+///
+/// Clones share the concurrency limit and progress channel.
+#[derive(Clone)]
+pub struct AsyncFlakeEvaluator {
+    backend: Arc<dyn AsyncNixBackend>,
+    timeout: Option<Duration>,
+    limit: Arc<Semaphore>,
+    progress: Option<mpsc::UnboundedSender<ProgressEvent>>,
+}
+
+impl AsyncFlakeEvaluator {
+    /// Create an evaluator that runs the `nix` CLI, with one evaluation per
+    /// CPU
+    pub fn new() -> Self {
+        Self::with_backend(CliBackend::default())
+    }
+
+    /// Create an evaluator that runs commands through the given backend
+    pub fn with_backend(backend: impl AsyncNixBackend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            timeout: Some(DEFAULT_TIMEOUT),
+            limit: Arc::new(Semaphore::new(num_cpus::get().max(1))),
+            progress: None,
+        }
+    }
+
+    /// Set the timeout for each evaluation
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Let evaluations run for as long as they take
+    #[must_use]
+    pub fn without_timeout(mut self) -> Self {
+        self.timeout = None;
+        self
+    }
+
+    /// Run at most `max` evaluations at once (at least one)
+    #[must_use]
+    pub fn with_max_concurrency(mut self, max: usize) -> Self {
+        self.limit = Arc::new(Semaphore::new(max.max(1)));
+        self
+    }
+
+    /// Send stderr progress of every evaluation to `sender`
+    #[must_use]
+    pub fn with_progress(mut self, sender: mpsc::UnboundedSender<ProgressEvent>) -> Self {
+        self.progress = Some(sender);
+        self
+    }
+
+    /// Timeout for each evaluation
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Evaluate a flake with the configured timeout
+    ///
+    /// Waiting for a concurrency slot does not count against the timeout.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`EvaluationError::Timeout`] if `nix` does not finish in
+    /// time, otherwise as for
+    /// [`FlakeEvaluator::evaluate`](super::FlakeEvaluator::evaluate).
+    pub async fn evaluate<P: AsRef<Path>>(
+        &self,
+        flake_path: P,
+    ) -> EvaluationResult<EvaluatedFlake> {
+        self.run(flake_path.as_ref(), self.timeout).await
+    }
+
+    /// Evaluate a flake with a timeout for this call only
+    ///
+    /// ## Errors
+    ///
+    /// As for [`evaluate`](Self::evaluate).
+    pub async fn evaluate_with_timeout<P: AsRef<Path>>(
+        &self,
+        flake_path: P,
+        timeout: Duration,
+    ) -> EvaluationResult<EvaluatedFlake> {
+        self.run(flake_path.as_ref(), Some(timeout)).await
+    }
+
+    /// Evaluate a flake until `cancel` completes
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`EvaluationError::Cancelled`] if `cancel` completes first,
+    /// otherwise as for [`evaluate`](Self::evaluate).
+    pub async fn evaluate_until<P, F>(
+        &self,
+        flake_path: P,
+        cancel: F,
+    ) -> EvaluationResult<EvaluatedFlake>
+    where
+        P: AsRef<Path>,
+        F: Future<Output = ()>,
+    {
+        tokio::select! {
+            result = self.evaluate(flake_path) => result,
+            () = cancel => Err(EvaluationError::Cancelled),
+        }
+    }
+
+    /// Evaluate several flakes, at most `max_concurrency` at a time
+    ///
+    /// Results are returned in the order the flakes were given.
+    pub async fn evaluate_all<I, P>(
+        &self,
+        flake_paths: I,
+    ) -> Vec<(String, EvaluationResult<EvaluatedFlake>)>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let evaluations = flake_paths.into_iter().map(|path| {
+            let flake = path.as_ref().to_string_lossy().into_owned();
+            async move {
+                let result = self.run(Path::new(&flake), self.timeout).await;
+                (flake, result)
+            }
+        });
+        futures::future::join_all(evaluations).await
+    }
+
+    /// Read the metadata of a flake with the configured timeout
+    ///
+    /// ## Errors
+    ///
+    /// As for [`evaluate`](Self::evaluate).
+    pub async fn metadata<P: AsRef<Path>>(&self, flake_path: P) -> EvaluationResult<FlakeMetadata> {
+        let stdout = self
+            .run_flake_command("metadata", flake_path.as_ref(), self.timeout)
+            .await?;
+        FlakeMetadata::from_json(&stdout)
+    }
+
+    async fn run(
+        &self,
+        flake_path: &Path,
+        timeout: Option<Duration>,
+    ) -> EvaluationResult<EvaluatedFlake> {
+        let stdout = self.run_flake_command("show", flake_path, timeout).await?;
+        FlakeEvaluator::parse_flake_show(&stdout)
+    }
+
+    /// Run `nix flake <command> --json <flake>` and return its stdout
+    async fn run_flake_command(
+        &self,
+        command: &str,
+        flake_path: &Path,
+        timeout: Option<Duration>,
+    ) -> EvaluationResult<String> {
+        let _permit = self
+            .limit
+            .acquire()
+            .await
+            .map_err(|_| EvaluationError::Cancelled)?;
+
+        let flake = flake_path.to_string_lossy();
+        let args = ["flake", command, "--json", flake.as_ref()].map(String::from);
+        let on_stderr = |line: &str| {
+            if let Some(progress) = &self.progress {
+                if let Some(event) = ProgressEvent::parse(flake.as_ref(), line) {
+                    // A dropped receiver only means nobody is watching
+                    let _ = progress.send(event);
+                }
+            }
+        };
+
+        let run = self.backend.run_async(&args, &on_stderr);
+        let output = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, run)
+                .await
+                .map_err(|_| EvaluationError::Timeout(timeout))??,
+            None => run.await?,
+        };
+        output.into_stdout()
+    }
+}
+
+impl Default for AsyncFlakeEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for AsyncFlakeEvaluator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncFlakeEvaluator")
+            .field("timeout", &self.timeout)
+            .field("available_permits", &self.limit.available_permits())
+            .finish_non_exhaustive()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nix::backend::{NixOutput, ReplayBackend};
+    use async_trait::async_trait;
+    use std::time::Instant;
+
+    /// Runs a shell script chosen by flake path instead of `nix`
+    struct ShellBackend(fn(&str) -> String);
+
+    #[async_trait]
+    impl AsyncNixBackend for ShellBackend {
+        async fn run_async(
+            &self,
+            args: &[String],
+            on_stderr: &(dyn for<'l> Fn(&'l str) + Send + Sync),
+        ) -> EvaluationResult<NixOutput> {
+            let script = (self.0)(&args[3]);
+            CliBackend::new("sh")
+                .run_async(&["-c".to_string(), script], on_stderr)
+                .await
+        }
+    }
+
+    #[test]
+    fn test_parse_progress() {
+        let kinds = [
+            (
+                "evaluating 'packages.x86_64-linux'...",
+                ProgressKind::Evaluating,
+            ),
+            (
+                "downloading 'https://github.com/nixos/nixpkgs'...",
+                ProgressKind::Fetching,
+            ),
+            (
+                "copying path '/nix/store/abc-source' from 'https://cache.nixos.org'...",
+                ProgressKind::Copying,
+            ),
+            ("these 2 derivations will be built:", ProgressKind::Building),
+            ("warning: Git tree '/src' is dirty", ProgressKind::Warning),
+            ("error: attribute 'foo' missing", ProgressKind::Error),
+            (
+                "… while evaluating the attribute 'packages'",
+                ProgressKind::Error,
+            ),
+            ("something else", ProgressKind::Other),
+        ];
+        for (line, kind) in kinds {
+            assert_eq!(
+                ProgressEvent::parse("f", line).unwrap().kind,
+                kind,
+                "{line}"
+            );
+        }
+
+        let warning = ProgressEvent::parse("f", "warning: Git tree '/src' is dirty").unwrap();
+        assert_eq!(warning.message, "Git tree '/src' is dirty");
+        assert_eq!(warning.to_string(), "f: warning: Git tree '/src' is dirty");
+        assert!(ProgressEvent::parse("f", "   ").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_replay_with_progress() {
+        let backend = ReplayBackend::new().with_recording(
+            ["flake", "show", "--json", "/src/a"],
+            NixOutput {
+                status: Some(0),
+                stdout: r#"{ "packages": { "x86_64-linux": { "default": {} } } }"#.to_string(),
+                stderr: "warning: Git tree '/src/a' is dirty\nevaluating 'packages'\n".to_string(),
+            },
+        );
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let evaluator = AsyncFlakeEvaluator::with_backend(backend).with_progress(tx);
+
+        let flake = evaluator.evaluate("/src/a").await.unwrap();
+        assert_eq!(flake.packages["x86_64-linux"].len(), 1);
+        assert_eq!(rx.recv().await.unwrap().kind, ProgressKind::Warning);
+        assert_eq!(rx.recv().await.unwrap().kind, ProgressKind::Evaluating);
+
+        let unavailable = AsyncFlakeEvaluator::with_backend(ReplayBackend::unavailable());
+        assert_eq!(
+            unavailable.evaluate("/src/a").await.unwrap_err(),
+            EvaluationError::NixNotAvailable
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_cancellation_kill_nix() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("finished");
+        let evaluator = AsyncFlakeEvaluator::with_backend(ShellBackend(|path| {
+            format!("sleep 0.5; touch {path}; echo '{{}}'")
+        }));
+
+        let started = Instant::now();
+        assert_eq!(
+            evaluator
+                .evaluate_with_timeout(&marker, Duration::from_millis(50))
+                .await
+                .unwrap_err(),
+            EvaluationError::Timeout(Duration::from_millis(50))
+        );
+        assert_eq!(
+            evaluator
+                .evaluate_until(&marker, tokio::time::sleep(Duration::from_millis(50)))
+                .await
+                .unwrap_err(),
+            EvaluationError::Cancelled
+        );
+        assert!(started.elapsed() < Duration::from_millis(500));
+
+        // Both shells were killed before they could touch the marker
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_bounded_concurrency() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let evaluator = AsyncFlakeEvaluator::with_backend(ShellBackend(|path| {
+            format!("echo \"evaluating '{path}'\" >&2; sleep 0.2; echo '{{}}'")
+        }))
+        .with_max_concurrency(2)
+        .with_progress(tx);
+
+        let started = Instant::now();
+        let results = evaluator.evaluate_all(["a", "b", "c", "d"]).await;
+        assert!(started.elapsed() >= Duration::from_millis(400));
+
+        let flakes: Vec<_> = results.iter().map(|(flake, _)| flake.as_str()).collect();
+        assert_eq!(flakes, ["a", "b", "c", "d"]);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+        drop(evaluator);
+        let mut evaluated = Vec::new();
+        while let Some(event) = rx.recv().await {
+            assert_eq!(event.kind, ProgressKind::Evaluating);
+            evaluated.push(event.flake);
+        }
+        evaluated.sort();
+        assert_eq!(evaluated, ["a", "b", "c", "d"]);
+    }
+}
@@ -0,0 +1,518 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Nix Command Backends
+//!
+//! Everything that runs the `nix` CLI goes through a [`NixBackend`], so it
+//! can be tested without a Nix installation:
+//!
+//! - [`CliBackend`] runs the real command
+//! - [`ReplayBackend`] serves recorded output, keyed by the arguments
+//! - [`RecordingBackend`] wraps another backend and records what it
+//!   returns, to produce fixtures for [`ReplayBackend`]
+//!
+//! [`CliBackend`] and [`ReplayBackend`] also implement [`AsyncNixBackend`],
+//! which runs commands on tokio and streams stderr as it is written.
+//!
+//! Recordings are stored as a JSON list of [`Recording`]s:
+//!
+//! ```json
+//! [
+//!   {
+//!     "args": ["flake", "show", "--json", "."],
+//!     "status": 0,
+//!     "stdout": "{\"packages\":{}}",
+//!     "stderr": ""
+//!   }
+//! ]
+//! ```
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use cim_domain_nix::nix::{FlakeEvaluator, ReplayBackend};
+//!
+//! let backend = ReplayBackend::new().with_output(
+//!     ["flake", "show", "--json", "."],
+//!     r#"{ "packages": { "x86_64-linux": { "default": { "type": "derivation" } } } }"#,
+//! );
+//! let flake = FlakeEvaluator::with_backend(backend).evaluate(".").unwrap();
+//! assert_eq!(flake.packages["x86_64-linux"].len(), 1);
+//! ```
+
+use super::flake_evaluator::{EvaluationError, EvaluationResult};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+
+// ============================================================================
+// Backend Trait
+// ============================================================================
+
+/// Output of a `nix` invocation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NixOutput {
+    /// Exit status (`None` if the process was killed by a signal)
+    pub status: Option<i32>,
+    /// Standard output
+    pub stdout: String,
+    /// Standard error
+    pub stderr: String,
+}
+
+impl NixOutput {
+    /// Successful output with the given stdout
+    pub fn success(stdout: impl Into<String>) -> Self {
+        Self {
+            status: Some(0),
+            stdout: stdout.into(),
+            stderr: String::new(),
+        }
+    }
+
+    /// Failed output with the given exit status and stderr
+    pub fn failure(status: i32, stderr: impl Into<String>) -> Self {
+        Self {
+            status: Some(status),
+            stdout: String::new(),
+            stderr: stderr.into(),
+        }
+    }
+
+    /// Whether the command exited with status 0
+    pub fn is_success(&self) -> bool {
+        self.status == Some(0)
+    }
+
+    /// Stdout of a successful command
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`EvaluationError::NixError`] with stderr if the command
+    /// failed.
+    pub fn into_stdout(self) -> EvaluationResult<String> {
+        if self.is_success() {
+            Ok(self.stdout)
+        } else {
+            Err(EvaluationError::NixError(self.stderr))
+        }
+    }
+}
+
+/// Runs `nix` commands
+pub trait NixBackend: Send + Sync {
+    /// Run `nix` with the given arguments
+    ///
+    /// A command that runs and fails is not an error: its status and
+    /// stderr are returned in the [`NixOutput`].
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`EvaluationError::NixNotAvailable`] if `nix` cannot be
+    /// found and [`EvaluationError::CommandFailed`] if it cannot be run.
+    fn run(&self, args: &[String]) -> EvaluationResult<NixOutput>;
+
+    /// Whether `nix` can be run
+    fn is_available(&self) -> bool {
+        self.run(&["--version".to_string()])
+            .is_ok_and(|output| output.is_success())
+    }
+}
+
+impl<B: NixBackend + ?Sized> NixBackend for std::sync::Arc<B> {
+    fn run(&self, args: &[String]) -> EvaluationResult<NixOutput> {
+        (**self).run(args)
+    }
+
+    fn is_available(&self) -> bool {
+        (**self).is_available()
+    }
+}
+
+/// Runs `nix` commands on tokio
+///
+/// Dropping the future returned by [`run_async`](Self::run_async) must stop
+/// the command, so callers can cancel and time out evaluations.
+#[async_trait]
+pub trait AsyncNixBackend: Send + Sync {
+    /// Run `nix` with the given arguments, passing each stderr line to
+    /// `on_stderr` as it is written
+    ///
+    /// ## Errors
+    ///
+    /// As for [`NixBackend::run`].
+    async fn run_async(
+        &self,
+        args: &[String],
+        on_stderr: &(dyn for<'l> Fn(&'l str) + Send + Sync),
+    ) -> EvaluationResult<NixOutput>;
+}
+
+// ============================================================================
+// CLI Backend
+// ============================================================================
+
+/// Runs the `nix` executable
+#[derive(Debug, Clone)]
+pub struct CliBackend {
+    command: String,
+}
+
+impl CliBackend {
+    /// Run the given executable instead of `nix`
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+        }
+    }
+
+    /// Executable that is run
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+}
+
+impl Default for CliBackend {
+    fn default() -> Self {
+        Self::new("nix")
+    }
+}
+
+impl NixBackend for CliBackend {
+    fn run(&self, args: &[String]) -> EvaluationResult<NixOutput> {
+        let output = Command::new(&self.command)
+            .args(args)
+            .output()
+            .map_err(|e| match e.kind() {
+                ErrorKind::NotFound => EvaluationError::NixNotAvailable,
+                _ => EvaluationError::CommandFailed(e.to_string()),
+            })?;
+        Ok(NixOutput {
+            status: output.status.code(),
+            stdout: String::from_utf8(output.stdout)
+                .map_err(|e| EvaluationError::ParseError(e.to_string()))?,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+#[async_trait]
+impl AsyncNixBackend for CliBackend {
+    async fn run_async(
+        &self,
+        args: &[String],
+        on_stderr: &(dyn for<'l> Fn(&'l str) + Send + Sync),
+    ) -> EvaluationResult<NixOutput> {
+        let mut child = tokio::process::Command::new(&self.command)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| match e.kind() {
+                ErrorKind::NotFound => EvaluationError::NixNotAvailable,
+                _ => EvaluationError::CommandFailed(e.to_string()),
+            })?;
+        let (Some(mut stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            return Err(EvaluationError::CommandFailed(
+                "child process has no output pipes".to_string(),
+            ));
+        };
+
+        let read_stdout = async {
+            let mut buf = Vec::new();
+            stdout.read_to_end(&mut buf).await.map(|_| buf)
+        };
+        let read_stderr = async {
+            let mut lines = BufReader::new(stderr).lines();
+            let mut all = String::new();
+            while let Some(line) = lines.next_line().await? {
+                on_stderr(&line);
+                all.push_str(&line);
+                all.push('\n');
+            }
+            Ok(all)
+        };
+        let (stdout, stderr, status) = tokio::try_join!(read_stdout, read_stderr, child.wait())
+            .map_err(|e| EvaluationError::CommandFailed(e.to_string()))?;
+
+        Ok(NixOutput {
+            status: status.code(),
+            stdout: String::from_utf8(stdout)
+                .map_err(|e| EvaluationError::ParseError(e.to_string()))?,
+            stderr,
+        })
+    }
+}
+
+// ============================================================================
+// Record / Replay
+// ============================================================================
+
+/// A recorded `nix` invocation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recording {
+    /// Arguments passed to `nix`
+    pub args: Vec<String>,
+    /// What the command returned
+    #[serde(flatten)]
+    pub output: NixOutput,
+}
+
+/// Serves recorded output instead of running `nix`
+///
+/// Commands without a recording fail with
+/// [`EvaluationError::CommandFailed`]; an unavailable backend fails every
+/// command with [`EvaluationError::NixNotAvailable`].
+#[derive(Debug, Clone, Default)]
+pub struct ReplayBackend {
+    recordings: HashMap<Vec<String>, NixOutput>,
+    unavailable: bool,
+}
+
+impl ReplayBackend {
+    /// Create a backend with no recordings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A backend that behaves as if `nix` were not installed
+    pub fn unavailable() -> Self {
+        Self {
+            unavailable: true,
+            ..Self::default()
+        }
+    }
+
+    /// Load recordings saved by [`RecordingBackend::save`]
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`EvaluationError::CommandFailed`] if the file cannot be read
+    /// and [`EvaluationError::ParseError`] if it is not a recording list.
+    pub fn from_file(path: impl AsRef<Path>) -> EvaluationResult<Self> {
+        let json = std::fs::read_to_string(path.as_ref()).map_err(|e| {
+            EvaluationError::CommandFailed(format!(
+                "Failed to read recordings {}: {e}",
+                path.as_ref().display()
+            ))
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// Load recordings from JSON
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`EvaluationError::ParseError`] if the JSON is not a
+    /// recording list.
+    pub fn from_json(json: &str) -> EvaluationResult<Self> {
+        let recordings: Vec<Recording> =
+            serde_json::from_str(json).map_err(|e| EvaluationError::ParseError(e.to_string()))?;
+        let mut backend = Self::new();
+        for recording in recordings {
+            backend.insert(recording);
+        }
+        Ok(backend)
+    }
+
+    /// Add a recording; a later recording for the same arguments wins
+    pub fn insert(&mut self, recording: Recording) {
+        self.recordings.insert(recording.args, recording.output);
+    }
+
+    /// Add a recording
+    #[must_use]
+    pub fn with_recording<S: Into<String>>(
+        mut self,
+        args: impl IntoIterator<Item = S>,
+        output: NixOutput,
+    ) -> Self {
+        self.insert(Recording {
+            args: args.into_iter().map(Into::into).collect(),
+            output,
+        });
+        self
+    }
+
+    /// Add a successful recording with the given stdout
+    #[must_use]
+    pub fn with_output<S: Into<String>>(
+        self,
+        args: impl IntoIterator<Item = S>,
+        stdout: impl Into<String>,
+    ) -> Self {
+        self.with_recording(args, NixOutput::success(stdout))
+    }
+}
+
+impl NixBackend for ReplayBackend {
+    fn run(&self, args: &[String]) -> EvaluationResult<NixOutput> {
+        if self.unavailable {
+            return Err(EvaluationError::NixNotAvailable);
+        }
+        self.recordings.get(args).cloned().ok_or_else(|| {
+            EvaluationError::CommandFailed(format!("no recording for `nix {}`", args.join(" ")))
+        })
+    }
+
+    fn is_available(&self) -> bool {
+        !self.unavailable
+    }
+}
+
+#[async_trait]
+impl AsyncNixBackend for ReplayBackend {
+    async fn run_async(
+        &self,
+        args: &[String],
+        on_stderr: &(dyn for<'l> Fn(&'l str) + Send + Sync),
+    ) -> EvaluationResult<NixOutput> {
+        let output = self.run(args)?;
+        output.stderr.lines().for_each(on_stderr);
+        Ok(output)
+    }
+}
+
+/// Records the output of another backend
+#[derive(Debug, Default)]
+pub struct RecordingBackend<B> {
+    inner: B,
+    recordings: Mutex<Vec<Recording>>,
+}
+
+impl<B: NixBackend> RecordingBackend<B> {
+    /// Record what `inner` returns
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            recordings: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Invocations recorded so far
+    pub fn recordings(&self) -> Vec<Recording> {
+        self.recordings
+            .lock()
+            .map(|recordings| recordings.clone())
+            .unwrap_or_default()
+    }
+
+    /// Recordings as JSON, in the format [`ReplayBackend::from_json`] reads
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`EvaluationError::ParseError`] if serialization fails.
+    pub fn to_json(&self) -> EvaluationResult<String> {
+        serde_json::to_string_pretty(&self.recordings())
+            .map_err(|e| EvaluationError::ParseError(e.to_string()))
+    }
+
+    /// Save the recordings for [`ReplayBackend::from_file`]
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`EvaluationError::CommandFailed`] if the file cannot be
+    /// written.
+    pub fn save(&self, path: impl AsRef<Path>) -> EvaluationResult<()> {
+        std::fs::write(path.as_ref(), self.to_json()? + "\n").map_err(|e| {
+            EvaluationError::CommandFailed(format!(
+                "Failed to write recordings {}: {e}",
+                path.as_ref().display()
+            ))
+        })
+    }
+}
+
+impl<B: NixBackend> NixBackend for RecordingBackend<B> {
+    fn run(&self, args: &[String]) -> EvaluationResult<NixOutput> {
+        let output = self.inner.run(args)?;
+        if let Ok(mut recordings) = self.recordings.lock() {
+            recordings.push(Recording {
+                args: args.to_vec(),
+                output: output.clone(),
+            });
+        }
+        Ok(output)
+    }
+
+    fn is_available(&self) -> bool {
+        self.inner.is_available()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| (*arg).to_string()).collect()
+    }
+
+    #[test]
+    fn test_cli_backend_missing_command() {
+        assert_eq!(CliBackend::default().command(), "nix");
+
+        let backend = CliBackend::new("/nonexistent/nix-for-tests");
+        assert_eq!(
+            backend.run(&args(&["--version"])),
+            Err(EvaluationError::NixNotAvailable)
+        );
+        assert!(!backend.is_available());
+    }
+
+    #[test]
+    fn test_replay() {
+        let backend = ReplayBackend::new()
+            .with_output(["eval", "--json", ".#x"], "1")
+            .with_recording(["build", "."], NixOutput::failure(1, "error: boom"));
+
+        assert_eq!(
+            backend
+                .run(&args(&["eval", "--json", ".#x"]))
+                .unwrap()
+                .into_stdout(),
+            Ok("1".to_string())
+        );
+        assert_eq!(
+            backend.run(&args(&["build", "."])).unwrap().into_stdout(),
+            Err(EvaluationError::NixError("error: boom".to_string()))
+        );
+        assert!(matches!(
+            backend.run(&args(&["eval", "--json", ".#y"])),
+            Err(EvaluationError::CommandFailed(message)) if message.contains(".#y")
+        ));
+        assert!(!ReplayBackend::unavailable().is_available());
+    }
+
+    #[test]
+    fn test_record_then_replay() {
+        let recorder =
+            RecordingBackend::new(ReplayBackend::new().with_output(["--version"], "nix 2.24"));
+        assert!(recorder.is_available());
+        recorder.run(&args(&["--version"])).unwrap();
+        assert!(recorder.run(&args(&["missing"])).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("recordings.json");
+        recorder.save(&file).unwrap();
+        assert!(std::fs::read_to_string(&file)
+            .unwrap()
+            .contains(r#""stdout": "nix 2.24""#));
+
+        let replay = ReplayBackend::from_file(&file).unwrap();
+        assert_eq!(
+            replay.run(&args(&["--version"])).unwrap(),
+            NixOutput::success("nix 2.24")
+        );
+    }
+}
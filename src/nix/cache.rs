@@ -0,0 +1,753 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Content-Addressed Cache
+//!
+//! Caches parse and evaluation results keyed by a [`ContentHash`] of their
+//! input, so unchanged files are never parsed twice and `nix` is not re-run
+//! for an unchanged flake:
+//!
+//! - [`MemoryCache`] keeps entries for the life of the process
+//! - [`DiskCache`] keeps entries as JSON files in a directory, so the CLI
+//!   and watcher share them across runs
+//!
+//! A key changes whenever the content does, so stale entries are never
+//! returned. When a file is read through [`NixCache::file_key`] the entry
+//! for its previous content is also evicted, which keeps the cache from
+//! growing with every edit.
+//!
+//! ```rust
+//! use cim_domain_nix::nix::{ContentHash, NixCache};
+//!
+//! let cache = NixCache::in_memory();
+//! let key = ContentHash::of(b"{ x = 1; }");
+//!
+//! let parse = || Ok::<_, std::convert::Infallible>(vec![1, 2, 3]);
+//! assert_eq!(cache.get_or_try_insert_with("demo", &key, parse).unwrap(), vec![1, 2, 3]);
+//! assert_eq!(cache.get_or_try_insert_with("demo", &key, parse).unwrap(), vec![1, 2, 3]);
+//! assert_eq!((cache.stats().hits, cache.stats().misses), (1, 1));
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Version of the entry format; bumping it invalidates on-disk caches
+pub const CACHE_VERSION: u32 = 1;
+
+/// Namespace for content hashes
+const HASH_NAMESPACE: Uuid = Uuid::from_u128(0x6c0f_2a4e_8d1b_4c57_9e3a_5b7d_1f20_c8e4);
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+/// Errors from the cache
+#[derive(Debug, Error)]
+pub enum CacheError {
+    /// The cache directory or an input file could not be accessed
+    #[error("Cache I/O error for {path}: {source}")]
+    Io {
+        /// File or directory
+        path: PathBuf,
+        /// Underlying error
+        source: std::io::Error,
+    },
+
+    /// A value could not be serialized
+    #[error("Cache serialization error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Result type for the cache
+pub type CacheResult<T> = Result<T, CacheError>;
+
+// ============================================================================
+// Content Hash
+// ============================================================================
+
+/// Hash of the content a cache entry was computed from
+///
+/// A name-based (SHA-1) UUID of the content, so hashes are stable across
+/// runs and platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ContentHash(Uuid);
+
+impl ContentHash {
+    /// Hash some content
+    pub fn of(content: impl AsRef<[u8]>) -> Self {
+        Self(Uuid::new_v5(&HASH_NAMESPACE, content.as_ref()))
+    }
+
+    /// Hash several pieces of content together
+    ///
+    /// Each part is length-prefixed, so `["ab", "c"]` and `["a", "bc"]`
+    /// hash differently.
+    pub fn of_parts<I, P>(parts: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        let mut buf = Vec::new();
+        for part in parts {
+            let part = part.as_ref();
+            buf.extend_from_slice(&(part.len() as u64).to_le_bytes());
+            buf.extend_from_slice(part);
+        }
+        Self::of(buf)
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.simple())
+    }
+}
+
+impl FromStr for ContentHash {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(s).map(Self)
+    }
+}
+
+impl Serialize for ContentHash {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ContentHash {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Key for evaluations of the flake in a local directory
+///
+/// Covers the files the flake can read, with their relative paths: in a
+/// git work tree the files git tracks or would track (ignored files such as
+/// `target/` are left out), otherwise only the flake's Nix inputs
+/// (`flake.nix`, `flake.lock` and every `*.nix` file), skipping hidden
+/// entries and symlinks, so build outputs and dependency trees are not
+/// hashed. Returns `None` if the directory has no `flake.nix`, e.g. for a
+/// flake reference like `github:NixOS/nixpkgs`.
+///
+/// ## Errors
+///
+/// Returns [`CacheError::Io`] if a file cannot be read.
+pub fn flake_key(dir: impl AsRef<Path>) -> CacheResult<Option<ContentHash>> {
+    let dir = dir.as_ref();
+    if !dir.join("flake.nix").is_file() {
+        return Ok(None);
+    }
+
+    let mut files = if let Some(files) = git_files(dir) {
+        files
+    } else {
+        let mut files = Vec::new();
+        collect_flake_files(dir, dir, &mut files)?;
+        files
+    };
+    files.sort();
+
+    let mut parts = Vec::with_capacity(files.len() * 2);
+    for relative in files {
+        let path = dir.join(&relative);
+        let content = std::fs::read(&path).map_err(|source| CacheError::Io { path, source })?;
+        parts.push(relative.to_string_lossy().into_owned().into_bytes());
+        parts.push(content);
+    }
+    Ok(Some(ContentHash::of_parts(parts)))
+}
+
+/// Files under `dir` that git tracks or would track; `None` outside a work
+/// tree or without `git`
+fn git_files(dir: &Path) -> Option<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let mut files: Vec<PathBuf> = output
+        .stdout
+        .split(|&byte| byte == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(String::from_utf8_lossy(path).into_owned()))
+        // Deleted files are still listed until the deletion is staged
+        .filter(|path| dir.join(path).is_file())
+        .collect();
+    files.dedup();
+    Some(files)
+}
+
+/// `flake.lock` and the `.nix` files under `dir`, outside hidden entries
+fn collect_flake_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> CacheResult<()> {
+    let io = |source| CacheError::Io {
+        path: dir.to_path_buf(),
+        source,
+    };
+    for entry in std::fs::read_dir(dir).map_err(io)? {
+        let entry = entry.map_err(io)?;
+        let file_type = entry.file_type().map_err(io)?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') {
+            continue;
+        }
+        if file_type.is_dir() {
+            collect_flake_files(root, &path, files)?;
+        } else if file_type.is_file() && (name.ends_with(".nix") || name == "flake.lock") {
+            if let Ok(relative) = path.strip_prefix(root) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Backends
+// ============================================================================
+
+/// Storage for serialized cache entries
+pub trait CacheBackend: Send + Sync {
+    /// Entry for a key, if stored
+    fn get(&self, namespace: &str, key: &ContentHash) -> Option<Vec<u8>>;
+
+    /// Store an entry
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the entry cannot be stored.
+    fn put(&self, namespace: &str, key: &ContentHash, value: &[u8]) -> CacheResult<()>;
+
+    /// Remove an entry, if stored
+    fn remove(&self, namespace: &str, key: &ContentHash);
+
+    /// Remove every entry
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if stored entries cannot be removed.
+    fn clear(&self) -> CacheResult<()>;
+}
+
+/// Keeps entries in memory
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<(String, ContentHash), Vec<u8>>>,
+}
+
+impl MemoryCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of stored entries
+    pub fn len(&self) -> usize {
+        self.entries.lock().map_or(0, |entries| entries.len())
+    }
+
+    /// Whether no entries are stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheBackend for MemoryCache {
+    fn get(&self, namespace: &str, key: &ContentHash) -> Option<Vec<u8>> {
+        let entries = self.entries.lock().ok()?;
+        entries.get(&(namespace.to_string(), *key)).cloned()
+    }
+
+    fn put(&self, namespace: &str, key: &ContentHash, value: &[u8]) -> CacheResult<()> {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert((namespace.to_string(), *key), value.to_vec());
+        }
+        Ok(())
+    }
+
+    fn remove(&self, namespace: &str, key: &ContentHash) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(&(namespace.to_string(), *key));
+        }
+    }
+
+    fn clear(&self) -> CacheResult<()> {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Keeps entries as `<dir>/v<version>/<namespace>/<hash>.json`
+///
+/// Entries are written to a temporary file and renamed into place, so
+/// concurrent readers never see a partial entry.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    root: PathBuf,
+}
+
+impl DiskCache {
+    /// Use (and create) a cache directory
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`CacheError::Io`] if the directory cannot be created.
+    pub fn new(dir: impl AsRef<Path>) -> CacheResult<Self> {
+        let root = dir.as_ref().join(format!("v{CACHE_VERSION}"));
+        std::fs::create_dir_all(&root).map_err(|source| CacheError::Io {
+            path: root.clone(),
+            source,
+        })?;
+        Ok(Self { root })
+    }
+
+    /// Directory entries of the current version are kept in
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn entry_path(&self, namespace: &str, key: &ContentHash) -> PathBuf {
+        self.root.join(namespace).join(format!("{key}.json"))
+    }
+}
+
+impl CacheBackend for DiskCache {
+    fn get(&self, namespace: &str, key: &ContentHash) -> Option<Vec<u8>> {
+        std::fs::read(self.entry_path(namespace, key)).ok()
+    }
+
+    fn put(&self, namespace: &str, key: &ContentHash, value: &[u8]) -> CacheResult<()> {
+        let path = self.entry_path(namespace, key);
+        let io = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| CacheError::Io { path, source }
+        };
+        let dir = self.root.join(namespace);
+        std::fs::create_dir_all(&dir).map_err(io(&dir))?;
+
+        let tmp = dir.join(format!("{key}.json.{}.tmp", std::process::id()));
+        std::fs::write(&tmp, value).map_err(io(&tmp))?;
+        std::fs::rename(&tmp, &path).map_err(io(&path))
+    }
+
+    fn remove(&self, namespace: &str, key: &ContentHash) {
+        // A missing entry is already removed
+        let _ = std::fs::remove_file(self.entry_path(namespace, key));
+    }
+
+    fn clear(&self) -> CacheResult<()> {
+        match std::fs::remove_dir_all(&self.root) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(source) => {
+                return Err(CacheError::Io {
+                    path: self.root.clone(),
+                    source,
+                })
+            }
+        }
+        std::fs::create_dir_all(&self.root).map_err(|source| CacheError::Io {
+            path: self.root.clone(),
+            source,
+        })
+    }
+}
+
+// ============================================================================
+// Cache
+// ============================================================================
+
+/// Cache hit and miss counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups that had to compute the value
+    pub misses: u64,
+    /// Entries stored
+    pub writes: u64,
+    /// Entries evicted because their file changed or was invalidated
+    pub invalidations: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups answered from the cache (0 if none were made)
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            #[allow(clippy::cast_precision_loss)]
+            let rate = self.hits as f64 / lookups as f64;
+            rate
+        }
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses ({:.0}% hit rate), {} writes, {} invalidations",
+            self.hits,
+            self.misses,
+            self.hit_rate() * 100.0,
+            self.writes,
+            self.invalidations
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    writes: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+/// Cache of parse and evaluation results
+///
+/// Values are stored as JSON in a [`CacheBackend`]. Clones share the
+/// backend and statistics.
+#[derive(Clone)]
+pub struct NixCache {
+    backend: Arc<dyn CacheBackend>,
+    /// Key last used for each file, per namespace
+    files: Arc<Mutex<HashMap<(String, PathBuf), ContentHash>>>,
+    counters: Arc<Counters>,
+}
+
+impl NixCache {
+    /// Cache entries in memory
+    pub fn in_memory() -> Self {
+        Self::with_backend(MemoryCache::new())
+    }
+
+    /// Cache entries in a directory
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`CacheError::Io`] if the directory cannot be created.
+    pub fn on_disk(dir: impl AsRef<Path>) -> CacheResult<Self> {
+        Ok(Self::with_backend(DiskCache::new(dir)?))
+    }
+
+    /// Cache entries in the given backend
+    pub fn with_backend(backend: impl CacheBackend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            files: Arc::default(),
+            counters: Arc::default(),
+        }
+    }
+
+    /// Cached value for a key
+    ///
+    /// An entry that no longer deserializes as `T` is removed and counts
+    /// as a miss.
+    pub fn get<T: DeserializeOwned>(&self, namespace: &str, key: &ContentHash) -> Option<T> {
+        let value = self.backend.get(namespace, key).and_then(|bytes| {
+            let value = serde_json::from_slice(&bytes).ok();
+            if value.is_none() {
+                self.backend.remove(namespace, key);
+            }
+            value
+        });
+        let counter = if value.is_some() {
+            &self.counters.hits
+        } else {
+            &self.counters.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    /// Store a value
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the value cannot be serialized or stored.
+    pub fn insert<T: Serialize>(
+        &self,
+        namespace: &str,
+        key: &ContentHash,
+        value: &T,
+    ) -> CacheResult<()> {
+        let bytes = serde_json::to_vec(value)?;
+        self.backend.put(namespace, key, &bytes)?;
+        self.counters.writes.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Cached value for a key, computing and storing it on a miss
+    ///
+    /// Failing to store the value is not an error: the cache is only an
+    /// optimisation.
+    ///
+    /// ## Errors
+    ///
+    /// Returns the error of `compute`; errors are not cached.
+    pub fn get_or_try_insert_with<T, E>(
+        &self,
+        namespace: &str,
+        key: &ContentHash,
+        compute: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        if let Some(value) = self.get(namespace, key) {
+            return Ok(value);
+        }
+        let value = compute()?;
+        let _ = self.insert(namespace, key, &value);
+        Ok(value)
+    }
+
+    /// Key for a file's content, evicting the entry for its previous
+    /// content if the file changed
+    pub fn file_key(
+        &self,
+        namespace: &str,
+        path: impl AsRef<Path>,
+        content: impl AsRef<[u8]>,
+    ) -> ContentHash {
+        let key = ContentHash::of(content);
+        let previous = self.files.lock().ok().and_then(|mut files| {
+            files.insert((namespace.to_string(), path.as_ref().to_path_buf()), key)
+        });
+        if let Some(previous) = previous.filter(|previous| *previous != key) {
+            self.evict(namespace, &previous);
+        }
+        key
+    }
+
+    /// Evict the entries for a file in every namespace
+    pub fn invalidate(&self, path: impl AsRef<Path>) {
+        let Ok(mut files) = self.files.lock() else {
+            return;
+        };
+        let keys: Vec<_> = files
+            .keys()
+            .filter(|(_, file)| file == path.as_ref())
+            .cloned()
+            .collect();
+        let evicted: Vec<(String, ContentHash)> = keys
+            .into_iter()
+            .filter_map(|entry| files.remove(&entry).map(|key| (entry.0, key)))
+            .collect();
+        drop(files);
+        for (namespace, key) in evicted {
+            self.evict(&namespace, &key);
+        }
+    }
+
+    /// Remove every entry
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the backend cannot remove its entries.
+    pub fn clear(&self) -> CacheResult<()> {
+        if let Ok(mut files) = self.files.lock() {
+            files.clear();
+        }
+        self.backend.clear()
+    }
+
+    /// Hit and miss counts so far
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            writes: self.counters.writes.load(Ordering::Relaxed),
+            invalidations: self.counters.invalidations.load(Ordering::Relaxed),
+        }
+    }
+
+    fn evict(&self, namespace: &str, key: &ContentHash) {
+        self.backend.remove(namespace, key);
+        self.counters.invalidations.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for NixCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NixCache")
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_count(cache: &NixCache, path: &str, content: &str, parses: &mut u32) -> usize {
+        let key = cache.file_key("lines", path, content);
+        cache
+            .get_or_try_insert_with("lines", &key, || {
+                *parses += 1;
+                Ok::<_, std::convert::Infallible>(content.lines().count())
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(ContentHash::of("a"), ContentHash::of("a"));
+        assert_ne!(ContentHash::of("a"), ContentHash::of("b"));
+        assert_ne!(
+            ContentHash::of_parts(["ab", "c"]),
+            ContentHash::of_parts(["a", "bc"])
+        );
+
+        let hash = ContentHash::of("a");
+        assert_eq!(hash.to_string().len(), 32);
+        assert_eq!(hash.to_string().parse::<ContentHash>().unwrap(), hash);
+    }
+
+    #[test]
+    fn test_memory_cache_invalidation() {
+        let cache = NixCache::in_memory();
+        let mut parses = 0;
+
+        assert_eq!(parse_count(&cache, "a.nix", "1\n2", &mut parses), 2);
+        assert_eq!(parse_count(&cache, "a.nix", "1\n2", &mut parses), 2);
+        assert_eq!(parses, 1);
+
+        // Changing the file evicts the old entry and parses again
+        assert_eq!(parse_count(&cache, "a.nix", "1\n2\n3", &mut parses), 3);
+        assert_eq!(parses, 2);
+        cache.invalidate("a.nix");
+        assert_eq!(parse_count(&cache, "a.nix", "1\n2\n3", &mut parses), 3);
+        assert_eq!(parses, 3);
+
+        let stats = cache.stats();
+        assert_eq!(
+            stats,
+            CacheStats {
+                hits: 1,
+                misses: 3,
+                writes: 3,
+                invalidations: 2
+            }
+        );
+        assert!((stats.hit_rate() - 0.25).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_disk_cache_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = ContentHash::of("{ x = 1; }");
+
+        let cache = NixCache::on_disk(dir.path()).unwrap();
+        cache
+            .insert("values", &key, &vec!["x".to_string()])
+            .unwrap();
+        let entry = dir
+            .path()
+            .join(format!("v{CACHE_VERSION}/values/{key}.json"));
+        assert!(entry.exists());
+
+        let restarted = NixCache::on_disk(dir.path()).unwrap();
+        assert_eq!(
+            restarted.get::<Vec<String>>("values", &key),
+            Some(vec!["x".to_string()])
+        );
+
+        // An entry of the wrong shape is dropped rather than returned
+        assert_eq!(restarted.get::<u32>("values", &key), None);
+        assert!(!entry.exists());
+
+        restarted.insert("values", &key, &1).unwrap();
+        restarted.clear().unwrap();
+        assert_eq!(restarted.get::<u32>("values", &key), None);
+        assert_eq!(restarted.stats().hits, 1);
+    }
+
+    #[test]
+    fn test_flake_key() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(flake_key(dir.path()).unwrap(), None);
+
+        std::fs::write(dir.path().join("flake.nix"), "{ outputs = _: { }; }").unwrap();
+        std::fs::create_dir(dir.path().join("hosts")).unwrap();
+        std::fs::write(dir.path().join("hosts/web.nix"), "{ }").unwrap();
+        std::fs::write(dir.path().join("VERSION"), "1.0.0").unwrap();
+        std::fs::create_dir(dir.path().join(".direnv")).unwrap();
+        std::fs::write(dir.path().join(".direnv/env"), "a").unwrap();
+        std::fs::create_dir(dir.path().join("target")).unwrap();
+        std::fs::write(dir.path().join("target/out"), "a").unwrap();
+        let key = flake_key(dir.path()).unwrap().unwrap();
+
+        // Outside git, hidden files and files other than Nix inputs
+        // (build outputs, dependency trees) are not part of the key
+        std::fs::write(dir.path().join(".direnv/env"), "b").unwrap();
+        std::fs::write(dir.path().join("target/out"), "b").unwrap();
+        std::fs::write(dir.path().join("VERSION"), "1.0.1").unwrap();
+        assert_eq!(flake_key(dir.path()).unwrap(), Some(key));
+
+        std::fs::write(dir.path().join("flake.lock"), "{}").unwrap();
+        let locked = flake_key(dir.path()).unwrap().unwrap();
+        assert_ne!(locked, key);
+
+        std::fs::write(dir.path().join("hosts/web.nix"), "{ x = 1; }").unwrap();
+        assert_ne!(flake_key(dir.path()).unwrap(), Some(locked));
+    }
+
+    #[test]
+    fn test_flake_key_git_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let initialized = Command::new("git")
+            .arg("init")
+            .arg(dir.path())
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !initialized {
+            return;
+        }
+        std::fs::write(dir.path().join("flake.nix"), "{ outputs = _: { }; }").unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]").unwrap();
+        std::fs::create_dir(dir.path().join("target")).unwrap();
+        std::fs::write(dir.path().join("target/out"), "a").unwrap();
+        let key = flake_key(dir.path()).unwrap().unwrap();
+
+        std::fs::write(dir.path().join("target/out"), "b").unwrap();
+        assert_eq!(flake_key(dir.path()).unwrap(), Some(key));
+
+        std::fs::write(dir.path().join("Cargo.toml"), "[workspace]").unwrap();
+        assert_ne!(flake_key(dir.path()).unwrap(), Some(key));
+    }
+}
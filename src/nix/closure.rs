@@ -0,0 +1,690 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Closure Analysis
+//!
+//! Analyzes saved `nix path-info --recursive --json --closure-size` output
+//! without access to the store:
+//!
+//! - **Sizes**: the NAR size of each path, its closure size, and its
+//!   *retained* size, the part of the closure that would disappear if the
+//!   path were dropped (computed with dominators over the reference graph)
+//! - **Largest contributors** to the closure of the root paths
+//! - **Why-depends chains**: how one path ends up referencing another
+//! - **Suggestions**: development outputs in the closure and packages
+//!   present in several versions
+//!
+//! Both output formats of `nix path-info --json` are accepted: the array of
+//! objects with a `path` field (Nix < 2.19) and the object keyed by store
+//! path (Nix ≥ 2.19).
+//!
+//! ## Usage
+//!
+//! ```rust,no_run
+//! use cim_domain_nix::nix::Closure;
+//!
+//! # fn main() -> Result<(), cim_domain_nix::nix::ClosureError> {
+//! // nix path-info --recursive --json --closure-size ./result > closure.json
+//! let closure = Closure::from_file("closure.json")?;
+//! println!("{}", closure.report(10));
+//! # Ok(())
+//! # }
+//! ```
+
+use petgraph::algo::dominators;
+use petgraph::graph::{DiGraph, NodeIndex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt::{self, Write as _};
+use std::path::Path;
+use thiserror::Error;
+
+/// Errors reading closure information
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum ClosureError {
+    /// The file could not be read
+    #[error("IO error: {0}")]
+    Io(String),
+
+    /// The JSON does not match the `nix path-info` format
+    #[error("Invalid path-info JSON: {0}")]
+    Json(String),
+}
+
+/// Result type for closure operations
+pub type ClosureResult<T> = std::result::Result<T, ClosureError>;
+
+// ============================================================================
+// Path Info
+// ============================================================================
+
+/// One entry of `nix path-info --json`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathInfo {
+    /// Store path
+    #[serde(default)]
+    pub path: String,
+    /// Hash of the NAR serialisation
+    #[serde(default)]
+    pub nar_hash: Option<String>,
+    /// Size of the NAR serialisation in bytes
+    #[serde(default)]
+    pub nar_size: u64,
+    /// Closure size reported by Nix (`--closure-size`)
+    #[serde(default)]
+    pub closure_size: Option<u64>,
+    /// Store paths this path references
+    #[serde(default)]
+    pub references: BTreeSet<String>,
+    /// Derivation that built the path
+    #[serde(default)]
+    pub deriver: Option<String>,
+}
+
+impl PathInfo {
+    /// Name part of the store path (`hello-2.12.1`)
+    pub fn name(&self) -> &str {
+        store_name(&self.path)
+    }
+}
+
+/// `<hash>-<name>` → `<name>`
+fn store_name(path: &str) -> &str {
+    let base = path.rsplit('/').next().unwrap_or(path);
+    base.split_once('-').map_or(base, |(_, name)| name)
+}
+
+/// `openssl-3.0.13-dev` → (`openssl`, `3.0.13`)
+fn name_and_version(name: &str) -> (String, Option<String>) {
+    let parts: Vec<&str> = name.split('-').collect();
+    match parts
+        .iter()
+        .position(|part| part.starts_with(|c: char| c.is_ascii_digit()))
+    {
+        Some(index) if index > 0 => (parts[..index].join("-"), Some(parts[index].to_string())),
+        _ => (name.to_string(), None),
+    }
+}
+
+// ============================================================================
+// Closure
+// ============================================================================
+
+/// Sizes of one store path within the closure
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathSizes {
+    /// Store path
+    pub path: String,
+    /// NAR size of the path itself
+    pub nar_size: u64,
+    /// Size of the path and everything it references
+    pub closure_size: u64,
+    /// Size only reachable through this path
+    pub retained_size: u64,
+}
+
+/// A way to make the closure smaller
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "suggestion", rename_all = "snake_case")]
+pub enum ClosureSuggestion {
+    /// A development, documentation or debug output is part of the closure
+    DevelopmentOutput {
+        /// Store path of the output
+        path: String,
+        /// Size only reachable through it
+        retained_size: u64,
+    },
+    /// A package is present in several versions
+    MultipleVersions {
+        /// Package name
+        name: String,
+        /// Store paths, one per version
+        paths: Vec<String>,
+    },
+}
+
+impl fmt::Display for ClosureSuggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DevelopmentOutput {
+                path,
+                retained_size,
+            } => write!(
+                f,
+                "'{}' looks like a development output; dropping it saves {}",
+                store_name(path),
+                format_size(*retained_size)
+            ),
+            Self::MultipleVersions { name, paths } => {
+                let versions: Vec<&str> = paths.iter().map(|path| store_name(path)).collect();
+                write!(
+                    f,
+                    "'{name}' is present in {} versions: {}",
+                    paths.len(),
+                    versions.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// A closure imported from `nix path-info --json`
+#[derive(Debug, Clone)]
+pub struct Closure {
+    paths: BTreeMap<String, PathInfo>,
+    graph: DiGraph<String, ()>,
+    index: HashMap<String, NodeIndex>,
+    /// Referenced paths that are not part of the output
+    missing: BTreeSet<String>,
+    /// Paths the output lists as invalid (`null`)
+    invalid: BTreeSet<String>,
+}
+
+impl Closure {
+    /// Parse `nix path-info --json` output
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`ClosureError::Json`] if the JSON is malformed or an entry
+    /// has no store path.
+    pub fn from_json(json: &str) -> ClosureResult<Self> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| ClosureError::Json(e.to_string()))?;
+        let mut infos = Vec::new();
+        let mut invalid = BTreeSet::new();
+        match value {
+            Value::Array(entries) => {
+                for entry in entries {
+                    let info: PathInfo = serde_json::from_value(entry)
+                        .map_err(|e| ClosureError::Json(e.to_string()))?;
+                    if info.path.is_empty() {
+                        return Err(ClosureError::Json("entry without 'path'".to_string()));
+                    }
+                    infos.push(info);
+                }
+            }
+            Value::Object(entries) => {
+                for (path, entry) in entries {
+                    if entry.is_null() {
+                        invalid.insert(path);
+                        continue;
+                    }
+                    let mut info: PathInfo = serde_json::from_value(entry)
+                        .map_err(|e| ClosureError::Json(format!("{path}: {e}")))?;
+                    info.path = path;
+                    infos.push(info);
+                }
+            }
+            _ => {
+                return Err(ClosureError::Json(
+                    "expected an array or an object".to_string(),
+                ))
+            }
+        }
+        let mut closure = Self::new(infos);
+        closure.invalid = invalid;
+        Ok(closure)
+    }
+
+    /// Read a saved `nix path-info --json` output
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`ClosureError::Io`] if the file cannot be read; see
+    /// [`from_json`](Self::from_json).
+    pub fn from_file(path: impl AsRef<Path>) -> ClosureResult<Self> {
+        let json = std::fs::read_to_string(path).map_err(|e| ClosureError::Io(e.to_string()))?;
+        Self::from_json(&json)
+    }
+
+    /// Build the reference graph of a set of paths
+    pub fn new(infos: impl IntoIterator<Item = PathInfo>) -> Self {
+        let paths: BTreeMap<String, PathInfo> = infos
+            .into_iter()
+            .map(|info| (info.path.clone(), info))
+            .collect();
+        let mut graph = DiGraph::new();
+        let index: HashMap<String, NodeIndex> = paths
+            .keys()
+            .map(|path| (path.clone(), graph.add_node(path.clone())))
+            .collect();
+        let mut missing = BTreeSet::new();
+        for (path, info) in &paths {
+            for reference in &info.references {
+                if reference == path {
+                    continue;
+                }
+                match index.get(reference) {
+                    Some(&to) => {
+                        graph.add_edge(index[path], to, ());
+                    }
+                    None => {
+                        missing.insert(reference.clone());
+                    }
+                }
+            }
+        }
+        Self {
+            paths,
+            graph,
+            index,
+            missing,
+            invalid: BTreeSet::new(),
+        }
+    }
+
+    /// Path information by store path
+    pub fn get(&self, path: &str) -> Option<&PathInfo> {
+        self.paths.get(path)
+    }
+
+    /// All paths, sorted
+    pub fn paths(&self) -> impl Iterator<Item = &PathInfo> {
+        self.paths.values()
+    }
+
+    /// Number of paths
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Whether the closure has no paths
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Referenced paths missing from the output
+    pub fn missing(&self) -> &BTreeSet<String> {
+        &self.missing
+    }
+
+    /// Paths the output reports as invalid
+    pub fn invalid(&self) -> &BTreeSet<String> {
+        &self.invalid
+    }
+
+    /// Paths no other path references, i.e. what was queried
+    pub fn roots(&self) -> Vec<&str> {
+        self.graph
+            .node_indices()
+            .filter(|&node| {
+                self.graph
+                    .neighbors_directed(node, petgraph::Direction::Incoming)
+                    .next()
+                    .is_none()
+            })
+            .map(|node| self.graph[node].as_str())
+            .collect()
+    }
+
+    /// Total NAR size of all paths
+    pub fn total_size(&self) -> u64 {
+        self.paths.values().map(|info| info.nar_size).sum()
+    }
+
+    /// Paths reachable from `path`, including itself
+    pub fn closure_of(&self, path: &str) -> BTreeSet<&str> {
+        let mut seen = BTreeSet::new();
+        let Some(&start) = self.index.get(path) else {
+            return seen;
+        };
+        let mut queue = VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            if seen.insert(self.graph[node].as_str()) {
+                queue.extend(self.graph.neighbors(node));
+            }
+        }
+        seen
+    }
+
+    /// Closure size of a path, computed from the references
+    pub fn closure_size(&self, path: &str) -> u64 {
+        self.closure_of(path)
+            .into_iter()
+            .map(|path| self.paths[path].nar_size)
+            .sum()
+    }
+
+    /// Sizes of every path, largest retained size first
+    pub fn sizes(&self) -> Vec<PathSizes> {
+        let retained = self.retained_sizes();
+        let mut sizes: Vec<PathSizes> = self
+            .paths
+            .values()
+            .map(|info| PathSizes {
+                path: info.path.clone(),
+                nar_size: info.nar_size,
+                closure_size: self.closure_size(&info.path),
+                retained_size: retained[info.path.as_str()],
+            })
+            .collect();
+        sizes.sort_by(|a, b| {
+            b.retained_size
+                .cmp(&a.retained_size)
+                .then_with(|| b.nar_size.cmp(&a.nar_size))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        sizes
+    }
+
+    /// The `count` paths retaining the most, excluding the roots
+    pub fn largest_contributors(&self, count: usize) -> Vec<PathSizes> {
+        let roots: BTreeSet<&str> = self.roots().into_iter().collect();
+        self.sizes()
+            .into_iter()
+            .filter(|sizes| !roots.contains(sizes.path.as_str()))
+            .take(count)
+            .collect()
+    }
+
+    /// Size each path keeps alive on its own: its NAR size plus every path
+    /// it dominates in the reference graph
+    fn retained_sizes(&self) -> HashMap<&str, u64> {
+        // A virtual root above the real roots gives a single entry point
+        let mut graph = self.graph.map(|_, _| (), |_, ()| ());
+        let top = graph.add_node(());
+        for root in self.roots() {
+            graph.add_edge(top, self.index[root], ());
+        }
+        let dominators = dominators::simple_fast(&graph, top);
+
+        let mut retained: HashMap<&str, u64> = HashMap::new();
+        for node in self.graph.node_indices() {
+            let size = self.paths[&self.graph[node]].nar_size;
+            let mut current = Some(node);
+            while let Some(dominator) = current.filter(|&n| n != top) {
+                *retained.entry(self.graph[dominator].as_str()).or_default() += size;
+                current = dominators.immediate_dominator(dominator);
+            }
+        }
+        for path in self.paths.keys() {
+            retained.entry(path.as_str()).or_default();
+        }
+        retained
+    }
+
+    /// Shortest chain of references from `from` to `to`, both included, as
+    /// `nix why-depends` reports it
+    pub fn why_depends(&self, from: &str, to: &str) -> Option<Vec<&str>> {
+        let (&start, &goal) = (self.index.get(from)?, self.index.get(to)?);
+        let mut previous: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            if node == goal {
+                let mut chain = vec![self.graph[goal].as_str()];
+                let mut current = goal;
+                while let Some(&prev) = previous.get(&current) {
+                    chain.push(self.graph[prev].as_str());
+                    current = prev;
+                }
+                chain.reverse();
+                return Some(chain);
+            }
+            for next in self.graph.neighbors(node) {
+                if next != start && !previous.contains_key(&next) {
+                    previous.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Ways to make the closure smaller
+    pub fn suggestions(&self) -> Vec<ClosureSuggestion> {
+        const DEV_OUTPUTS: [&str; 6] = ["-dev", "-doc", "-man", "-devdoc", "-debug", "-static"];
+        let retained = self.retained_sizes();
+        let mut suggestions: Vec<ClosureSuggestion> = self
+            .paths
+            .values()
+            .filter(|info| {
+                DEV_OUTPUTS
+                    .iter()
+                    .any(|suffix| info.name().ends_with(suffix))
+            })
+            .map(|info| ClosureSuggestion::DevelopmentOutput {
+                path: info.path.clone(),
+                retained_size: retained[info.path.as_str()],
+            })
+            .collect();
+
+        let mut versions: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+        for info in self.paths.values() {
+            if let (name, Some(version)) = name_and_version(info.name()) {
+                versions
+                    .entry(name)
+                    .or_default()
+                    .entry(version)
+                    .or_insert_with(|| info.path.clone());
+            }
+        }
+        suggestions.extend(
+            versions
+                .into_iter()
+                .filter(|(_, paths)| paths.len() > 1)
+                .map(|(name, paths)| ClosureSuggestion::MultipleVersions {
+                    name,
+                    paths: paths.into_values().collect(),
+                }),
+        );
+        suggestions
+    }
+
+    /// Text report of the closure: totals, the `top` largest contributors
+    /// and suggestions
+    pub fn report(&self, top: usize) -> String {
+        let mut out = String::new();
+        let roots = self.roots();
+        let _ = writeln!(
+            out,
+            "Closure of {}: {} paths, {}",
+            roots
+                .iter()
+                .map(|root| store_name(root))
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.len(),
+            format_size(self.total_size())
+        );
+
+        let contributors = self.largest_contributors(top);
+        if !contributors.is_empty() {
+            let _ = writeln!(out);
+            let _ = writeln!(
+                out,
+                "{:>10}  {:>10}  {:>10}  Path",
+                "Self", "Closure", "Retained"
+            );
+            for sizes in contributors {
+                let _ = writeln!(
+                    out,
+                    "{:>10}  {:>10}  {:>10}  {}",
+                    format_size(sizes.nar_size),
+                    format_size(sizes.closure_size),
+                    format_size(sizes.retained_size),
+                    store_name(&sizes.path)
+                );
+            }
+        }
+
+        let suggestions = self.suggestions();
+        if !suggestions.is_empty() {
+            let _ = writeln!(out);
+            let _ = writeln!(out, "Suggestions:");
+            for suggestion in suggestions {
+                let _ = writeln!(out, "  - {suggestion}");
+            }
+        }
+        out
+    }
+}
+
+/// Human-readable size in binary units (`1.5 MiB`)
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut unit = 0;
+    let mut scaled = bytes;
+    while scaled >= 1024 && unit < UNITS.len() - 1 {
+        scaled /= 1024;
+        unit += 1;
+    }
+    if unit == 0 {
+        return format!("{bytes} B");
+    }
+    // One decimal, computed in integers to stay exact
+    let divisor = 1u128 << (10 * unit);
+    let tenths = (u128::from(bytes) * 10 + divisor / 2) / divisor;
+    format!("{}.{} {}", tenths / 10, tenths % 10, UNITS[unit])
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn path(name: &str) -> String {
+        format!("/nix/store/{}-{name}", "a".repeat(32))
+    }
+
+    /// app → { libfoo, openssl 3, glibc }, libfoo → { openssl 1.1, glibc,
+    /// libfoo-dev }, everything → glibc
+    fn fixture() -> String {
+        let entry = |name: &str, size: u64, refs: &[&str]| {
+            let mut references: Vec<String> = refs.iter().map(|r| path(r)).collect();
+            references.push(path(name));
+            serde_json::json!({
+                "path": path(name),
+                "narHash": "sha256-AAAA",
+                "narSize": size,
+                "references": references,
+                "closureSize": 0,
+            })
+        };
+        serde_json::to_string(&serde_json::json!([
+            entry(
+                "app-1.0",
+                2 * MIB,
+                &["libfoo-2.1", "openssl-3.0.13", "glibc-2.39"]
+            ),
+            entry(
+                "libfoo-2.1",
+                3 * MIB,
+                &["openssl-1.1.1w", "glibc-2.39", "libfoo-2.1-dev"]
+            ),
+            entry("libfoo-2.1-dev", 5 * MIB, &["glibc-2.39"]),
+            entry("openssl-3.0.13", 6 * MIB, &["glibc-2.39"]),
+            entry("openssl-1.1.1w", 4 * MIB, &["glibc-2.39"]),
+            entry("glibc-2.39", 30 * MIB, &[]),
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_formats() {
+        let closure = Closure::from_json(&fixture()).unwrap();
+        assert_eq!(closure.len(), 6);
+        assert_eq!(closure.roots(), vec![path("app-1.0").as_str()]);
+
+        // The same data keyed by path, as Nix 2.19+ writes it
+        let mut object = serde_json::Map::new();
+        let entries: Vec<Value> = serde_json::from_str(&fixture()).unwrap();
+        for mut entry in entries {
+            let key = entry["path"].as_str().unwrap().to_string();
+            entry.as_object_mut().unwrap().remove("path");
+            object.insert(key, entry);
+        }
+        object.insert(path("gone"), Value::Null);
+        let keyed = Closure::from_json(&Value::Object(object).to_string()).unwrap();
+        assert_eq!(keyed.len(), 6);
+        assert_eq!(keyed.invalid().len(), 1);
+        assert_eq!(keyed.get(&path("glibc-2.39")).unwrap().nar_size, 30 * MIB);
+
+        assert!(matches!(
+            Closure::from_json("[{\"narSize\": 1}]"),
+            Err(ClosureError::Json(_))
+        ));
+        assert!(matches!(
+            Closure::from_json("3"),
+            Err(ClosureError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_sizes() {
+        let closure = Closure::from_json(&fixture()).unwrap();
+        assert_eq!(closure.total_size(), 50 * MIB);
+        assert_eq!(closure.closure_size(&path("app-1.0")), 50 * MIB);
+        assert_eq!(closure.closure_size(&path("libfoo-2.1")), 42 * MIB);
+
+        let sizes = closure.largest_contributors(3);
+        let summary: Vec<(&str, u64)> = sizes
+            .iter()
+            .map(|s| (store_name(&s.path), s.retained_size / MIB))
+            .collect();
+        // Dropping libfoo also drops its -dev output and openssl 1.1, but
+        // glibc stays because the app references it directly
+        assert_eq!(
+            summary,
+            vec![
+                ("glibc-2.39", 30),
+                ("libfoo-2.1", 12),
+                ("openssl-3.0.13", 6)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_why_depends() {
+        let closure = Closure::from_json(&fixture()).unwrap();
+        let chain = closure
+            .why_depends(&path("app-1.0"), &path("openssl-1.1.1w"))
+            .unwrap();
+        let names: Vec<&str> = chain.iter().map(|p| store_name(p)).collect();
+        assert_eq!(names, ["app-1.0", "libfoo-2.1", "openssl-1.1.1w"]);
+
+        assert_eq!(
+            closure.why_depends(&path("glibc-2.39"), &path("app-1.0")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_suggestions_and_report() {
+        let closure = Closure::from_json(&fixture()).unwrap();
+        let suggestions = closure.suggestions();
+        assert_eq!(
+            suggestions,
+            vec![
+                ClosureSuggestion::DevelopmentOutput {
+                    path: path("libfoo-2.1-dev"),
+                    retained_size: 5 * MIB,
+                },
+                ClosureSuggestion::MultipleVersions {
+                    name: "openssl".to_string(),
+                    paths: vec![path("openssl-1.1.1w"), path("openssl-3.0.13")],
+                },
+            ]
+        );
+
+        let report = closure.report(2);
+        assert!(report.starts_with("Closure of app-1.0: 6 paths, 50.0 MiB\n"));
+        assert!(report.contains("  30.0 MiB    30.0 MiB    30.0 MiB  glibc-2.39\n"));
+        assert!(
+            report.contains("'openssl' is present in 2 versions: openssl-1.1.1w, openssl-3.0.13")
+        );
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(50 * MIB), "50.0 MiB");
+        assert_eq!(format_size(u64::MAX), "16777216.0 TiB");
+    }
+}
@@ -0,0 +1,685 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Derivation Files
+//!
+//! Reads and writes the `ATerm` format of store derivations
+//! (`/nix/store/<hash>-<name>.drv`):
+//!
+//! ```text
+//! Derive([("out","/nix/store/...-hello-2.12","","")],
+//!        [("/nix/store/...-bash-5.2.drv",["out"])],
+//!        ["/nix/store/...-builder.sh"],
+//!        "x86_64-linux","/nix/store/...-bash/bin/bash",["-e","builder.sh"],
+//!        [("name","hello"),("out","/nix/store/...-hello-2.12")])
+//! ```
+//!
+//! [`Derivation`] keeps everything the file says, so
+//! [`Derivation::to_aterm`] reproduces a `.drv` written by Nix byte for
+//! byte. [`Derivation::to_nix_derivation`] converts to the domain's
+//! [`NixDerivation`], and [`DerivationGraph`] follows input derivations
+//! between files to inspect build graphs without calling `nix`.
+//!
+//! Derivations using the experimental dynamic-derivations format
+//! (`DrvWithVersion`) are rejected.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use cim_domain_nix::nix::Derivation;
+//!
+//! let drv = Derivation::parse(
+//!     r#"Derive([("out","/nix/store/p8k9-hello","","")],[],[],"x86_64-linux","/bin/sh",["-c","echo > $out"],[("name","hello"),("out","/nix/store/p8k9-hello")])"#,
+//! ).unwrap();
+//! assert_eq!(drv.name(), Some("hello"));
+//! assert_eq!(drv.args, ["-c", "echo > $out"]);
+//! ```
+
+use super::objects::NixDerivation;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Errors reading derivation files
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum DerivationError {
+    /// The file could not be read
+    #[error("IO error: {0}")]
+    Io(String),
+
+    /// The text is not a valid `ATerm` derivation
+    #[error("Invalid derivation at byte {offset}: expected {expected}")]
+    Syntax {
+        /// Byte offset of the error
+        offset: usize,
+        /// What the parser expected
+        expected: &'static str,
+    },
+
+    /// The derivation uses a format this parser does not support
+    #[error("Unsupported derivation format: {0}")]
+    Unsupported(String),
+}
+
+/// Result type for derivation operations
+pub type DerivationResult<T> = std::result::Result<T, DerivationError>;
+
+// ============================================================================
+// Derivation
+// ============================================================================
+
+/// An output of a derivation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DerivationOutput {
+    /// Store path (empty for content-addressed outputs)
+    pub path: String,
+    /// Hash algorithm of a fixed output (`sha256`, `r:sha256`), or empty
+    pub hash_algo: String,
+    /// Expected hash of a fixed output, or empty
+    pub hash: String,
+}
+
+impl DerivationOutput {
+    /// Whether the output's content hash is fixed in advance
+    pub fn is_fixed_output(&self) -> bool {
+        !self.hash.is_empty()
+    }
+}
+
+/// A store derivation as written in a `.drv` file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Derivation {
+    /// Outputs by name
+    pub outputs: BTreeMap<String, DerivationOutput>,
+    /// Input derivations and the outputs used from each
+    pub input_drvs: BTreeMap<String, BTreeSet<String>>,
+    /// Input sources (store paths that are not built)
+    pub input_srcs: BTreeSet<String>,
+    /// System the derivation builds on
+    pub system: String,
+    /// Builder executable
+    pub builder: String,
+    /// Builder arguments
+    pub args: Vec<String>,
+    /// Builder environment
+    pub env: BTreeMap<String, String>,
+}
+
+impl Derivation {
+    /// Parse a derivation from `ATerm` text
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`DerivationError::Syntax`] for malformed input and
+    /// [`DerivationError::Unsupported`] for dynamic derivations.
+    pub fn parse(text: &str) -> DerivationResult<Self> {
+        let mut parser = Parser { text, pos: 0 };
+        let derivation = parser.derivation()?;
+        parser.skip_whitespace();
+        if parser.pos != text.len() {
+            return Err(parser.error("end of input"));
+        }
+        Ok(derivation)
+    }
+
+    /// Read a `.drv` file
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`DerivationError::Io`] if the file cannot be read; see
+    /// [`parse`](Self::parse).
+    pub fn from_file(path: impl AsRef<Path>) -> DerivationResult<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| DerivationError::Io(e.to_string()))?;
+        Self::parse(&text)
+    }
+
+    /// Write the derivation in `ATerm` format, as Nix does
+    pub fn to_aterm(&self) -> String {
+        let mut out = String::from("Derive([");
+        let mut first = true;
+        for (name, output) in &self.outputs {
+            separator(&mut out, &mut first);
+            out.push('(');
+            push_strings(
+                &mut out,
+                [name, &output.path, &output.hash_algo, &output.hash],
+            );
+            out.push(')');
+        }
+        out.push_str("],[");
+        first = true;
+        for (path, outputs) in &self.input_drvs {
+            separator(&mut out, &mut first);
+            out.push('(');
+            push_string(&mut out, path);
+            out.push_str(",[");
+            push_strings(&mut out, outputs);
+            out.push_str("])");
+        }
+        out.push_str("],[");
+        push_strings(&mut out, &self.input_srcs);
+        out.push_str("],");
+        push_strings(&mut out, [&self.system, &self.builder]);
+        out.push_str(",[");
+        push_strings(&mut out, &self.args);
+        out.push_str("],[");
+        first = true;
+        for (key, value) in &self.env {
+            separator(&mut out, &mut first);
+            out.push('(');
+            push_strings(&mut out, [key, value]);
+            out.push(')');
+        }
+        out.push_str("])");
+        out
+    }
+
+    /// The `name` attribute of the derivation
+    pub fn name(&self) -> Option<&str> {
+        self.env.get("name").map(String::as_str)
+    }
+
+    /// Whether this is a fixed-output derivation (e.g. a fetcher)
+    pub fn is_fixed_output(&self) -> bool {
+        self.outputs.values().any(DerivationOutput::is_fixed_output)
+    }
+
+    /// Convert to a [`NixDerivation`]
+    ///
+    /// Which outputs of each input derivation are used, and the hashes of
+    /// fixed outputs, are not part of [`NixDerivation`] and are dropped.
+    pub fn to_nix_derivation(&self, drv_path: Option<String>) -> NixDerivation {
+        let mut derivation = NixDerivation::new(
+            self.name().unwrap_or_default().to_string(),
+            self.system.clone(),
+            self.builder.clone(),
+        );
+        derivation.outputs = self
+            .outputs
+            .iter()
+            .map(|(name, output)| (name.clone(), output.path.clone()))
+            .collect();
+        derivation.input_drvs = self.input_drvs.keys().cloned().collect();
+        derivation.input_srcs = self.input_srcs.iter().cloned().collect();
+        derivation.args.clone_from(&self.args);
+        derivation.env = self
+            .env
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        derivation.drv_path = drv_path;
+        derivation
+    }
+}
+
+/// Builds a derivation from the domain model; every input derivation is
+/// assumed to be used through its `out` output.
+impl From<&NixDerivation> for Derivation {
+    fn from(derivation: &NixDerivation) -> Self {
+        Self {
+            outputs: derivation
+                .outputs
+                .iter()
+                .map(|(name, path)| {
+                    let output = DerivationOutput {
+                        path: path.clone(),
+                        ..DerivationOutput::default()
+                    };
+                    (name.clone(), output)
+                })
+                .collect(),
+            input_drvs: derivation
+                .input_drvs
+                .iter()
+                .map(|path| (path.clone(), BTreeSet::from(["out".to_string()])))
+                .collect(),
+            input_srcs: derivation.input_srcs.iter().cloned().collect(),
+            system: derivation.system.clone(),
+            builder: derivation.builder.clone(),
+            args: derivation.args.clone(),
+            env: derivation
+                .env
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for Derivation {
+    type Err = DerivationError;
+
+    fn from_str(text: &str) -> DerivationResult<Self> {
+        Self::parse(text)
+    }
+}
+
+impl fmt::Display for Derivation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_aterm())
+    }
+}
+
+/// Read a `.drv` file into a [`NixDerivation`] with its store path set
+///
+/// ## Errors
+///
+/// See [`Derivation::from_file`].
+pub fn parse_drv_file(path: impl AsRef<Path>) -> DerivationResult<NixDerivation> {
+    let path = path.as_ref();
+    let derivation = Derivation::from_file(path)?;
+    Ok(derivation.to_nix_derivation(Some(path.display().to_string())))
+}
+
+fn separator(out: &mut String, first: &mut bool) {
+    if !std::mem::take(first) {
+        out.push(',');
+    }
+}
+
+fn push_strings<S: AsRef<str>>(out: &mut String, strings: impl IntoIterator<Item = S>) {
+    let mut first = true;
+    for string in strings {
+        separator(out, &mut first);
+        push_string(out, string.as_ref());
+    }
+}
+
+fn push_string(out: &mut String, string: &str) {
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// ============================================================================
+// Parser
+// ============================================================================
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, expected: &'static str) -> DerivationError {
+        DerivationError::Syntax {
+            offset: self.pos,
+            expected,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, token: &'static str) -> bool {
+        if self.text[self.pos..].starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &'static str) -> DerivationResult<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(token))
+        }
+    }
+
+    fn derivation(&mut self) -> DerivationResult<Derivation> {
+        self.skip_whitespace();
+        if self.text[self.pos..].starts_with("DrvWithVersion(") {
+            return Err(DerivationError::Unsupported(
+                "dynamic derivations (DrvWithVersion)".to_string(),
+            ));
+        }
+        self.expect("Derive(")?;
+
+        let mut derivation = Derivation::default();
+        for (name, path, hash_algo, hash) in self.list(|p| {
+            p.expect("(")?;
+            let name = p.string()?;
+            p.expect(",")?;
+            let path = p.string()?;
+            p.expect(",")?;
+            let hash_algo = p.string()?;
+            p.expect(",")?;
+            let hash = p.string()?;
+            p.expect(")")?;
+            Ok((name, path, hash_algo, hash))
+        })? {
+            derivation.outputs.insert(
+                name,
+                DerivationOutput {
+                    path,
+                    hash_algo,
+                    hash,
+                },
+            );
+        }
+        self.expect(",")?;
+        derivation.input_drvs = self
+            .list(|p| {
+                p.expect("(")?;
+                let path = p.string()?;
+                p.expect(",")?;
+                if p.text[p.pos..].starts_with('(') {
+                    return Err(DerivationError::Unsupported(
+                        "dynamic derivation outputs".to_string(),
+                    ));
+                }
+                let outputs = p.list(Parser::string)?.into_iter().collect();
+                p.expect(")")?;
+                Ok((path, outputs))
+            })?
+            .into_iter()
+            .collect();
+        self.expect(",")?;
+        derivation.input_srcs = self.list(Parser::string)?.into_iter().collect();
+        self.expect(",")?;
+        derivation.system = self.string()?;
+        self.expect(",")?;
+        derivation.builder = self.string()?;
+        self.expect(",")?;
+        derivation.args = self.list(Parser::string)?;
+        self.expect(",")?;
+        derivation.env = self
+            .list(|p| {
+                p.expect("(")?;
+                let key = p.string()?;
+                p.expect(",")?;
+                let value = p.string()?;
+                p.expect(")")?;
+                Ok((key, value))
+            })?
+            .into_iter()
+            .collect();
+        self.expect(")")?;
+        Ok(derivation)
+    }
+
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> DerivationResult<T>,
+    ) -> DerivationResult<Vec<T>> {
+        self.expect("[")?;
+        let mut items = Vec::new();
+        if self.eat("]") {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.eat("]") {
+                return Ok(items);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn string(&mut self) -> DerivationResult<String> {
+        self.expect("\"")?;
+        let mut value = String::new();
+        let mut chars = self.text[self.pos..].char_indices();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += offset + 1;
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 'r')) => value.push('\r'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, c)) => value.push(c),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        self.pos = self.text.len();
+        Err(self.error("closing quote"))
+    }
+}
+
+// ============================================================================
+// Derivation Graph
+// ============================================================================
+
+/// Derivations reachable from a root `.drv` file through input derivations
+///
+/// Input derivations are looked up by file name in the root's directory,
+/// so a copy of the relevant part of a store can be inspected anywhere.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DerivationGraph {
+    /// Store path of the root derivation
+    pub root: String,
+    /// Derivations by store path
+    pub derivations: BTreeMap<String, Derivation>,
+    /// Input derivations whose files were not found
+    pub missing: BTreeSet<String>,
+}
+
+impl DerivationGraph {
+    /// Load a derivation and, transitively, its input derivations
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a file that exists cannot be read or parsed.
+    pub fn load(root: impl AsRef<Path>) -> DerivationResult<Self> {
+        let root = root.as_ref();
+        let dir = root.parent().map_or_else(PathBuf::new, Path::to_path_buf);
+        let root_path = root.display().to_string();
+        let mut graph = Self {
+            root: root_path.clone(),
+            ..Self::default()
+        };
+
+        let mut queue = VecDeque::from([(root_path, root.to_path_buf())]);
+        while let Some((store_path, file)) = queue.pop_front() {
+            if graph.derivations.contains_key(&store_path) {
+                continue;
+            }
+            if !file.exists() {
+                graph.missing.insert(store_path);
+                continue;
+            }
+            let derivation = Derivation::from_file(&file)?;
+            for input in derivation.input_drvs.keys() {
+                if let Some(name) = Path::new(input).file_name() {
+                    queue.push_back((input.clone(), dir.join(name)));
+                }
+            }
+            graph.derivations.insert(store_path, derivation);
+        }
+        Ok(graph)
+    }
+
+    /// Derivations in build order: every derivation after its inputs
+    pub fn build_order(&self) -> Vec<&str> {
+        let mut order = Vec::new();
+        let mut done = BTreeSet::new();
+        let mut stack = vec![(self.root.as_str(), false)];
+        while let Some((path, expanded)) = stack.pop() {
+            if expanded {
+                order.push(path);
+                continue;
+            }
+            if !done.insert(path) {
+                continue;
+            }
+            let Some(derivation) = self.derivations.get(path) else {
+                continue;
+            };
+            stack.push((path, true));
+            for input in derivation.input_drvs.keys().rev() {
+                if !done.contains(input.as_str()) {
+                    stack.push((input.as_str(), false));
+                }
+            }
+        }
+        order
+    }
+
+    /// Derivations that use `path` directly
+    pub fn dependents_of<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a str> {
+        self.derivations
+            .iter()
+            .filter(move |(_, derivation)| derivation.input_drvs.contains_key(path))
+            .map(|(dependent, _)| dependent.as_str())
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO: &str = r#"Derive([("out","/nix/store/1d7h8h0kzyrk6f3yvknbrqz76l3i10sn-hello-2.12.1","","")],[("/nix/store/4z9bq6ffi7p24bhadqa4gjdqm4wcbqxp-bash-5.2p26.drv",["out"]),("/nix/store/8dxjdlmvvm7ksw9ssqgyzqdkqhx7qb6s-stdenv-linux.drv",["out"]),("/nix/store/ph3i7d9kzrhdd9fcjpa84q6mv6ns1l14-hello-2.12.1.tar.gz.drv",["out"])],["/nix/store/v6x3cs394jgqfbi0a42pam708flxaphh-default-builder.sh"],"x86_64-linux","/nix/store/5lr5n3qa4day8l1ivbwlcby2nknczqkq-bash-5.2p26/bin/bash",["-e","/nix/store/v6x3cs394jgqfbi0a42pam708flxaphh-default-builder.sh"],[("buildInputs",""),("builder","/nix/store/5lr5n3qa4day8l1ivbwlcby2nknczqkq-bash-5.2p26/bin/bash"),("name","hello-2.12.1"),("out","/nix/store/1d7h8h0kzyrk6f3yvknbrqz76l3i10sn-hello-2.12.1"),("pname","hello"),("postInstall","echo \"done\" \\\n  >> $out/log\n\tok"),("src","/nix/store/dw402azxjrgrzrk6j0p66wkqrab5mwgw-hello-2.12.1.tar.gz"),("system","x86_64-linux"),("version","2.12.1")])"#;
+
+    const TARBALL: &str = r#"Derive([("out","/nix/store/dw402azxjrgrzrk6j0p66wkqrab5mwgw-hello-2.12.1.tar.gz","sha256","8d99142afd92576f30b0cd7cb42a8dc6809998bc5d607d88761f512e26c7db20")],[],[],"x86_64-linux","builtin:fetchurl",[],[("name","hello-2.12.1.tar.gz"),("out","/nix/store/dw402azxjrgrzrk6j0p66wkqrab5mwgw-hello-2.12.1.tar.gz"),("outputHash","sha256-jZkUKv2SV28wsM18tCqNxoCZmLxdYH2Idh9RLibH2yA="),("outputHashMode","flat"),("url","https://ftp.gnu.org/gnu/hello/hello-2.12.1.tar.gz")])"#;
+
+    #[test]
+    fn test_parse() {
+        let drv = Derivation::parse(HELLO).unwrap();
+
+        assert_eq!(drv.name(), Some("hello-2.12.1"));
+        assert_eq!(drv.system, "x86_64-linux");
+        assert_eq!(drv.outputs.len(), 1);
+        assert!(drv.outputs["out"].path.ends_with("-hello-2.12.1"));
+        assert_eq!(drv.input_drvs.len(), 3);
+        assert!(drv
+            .input_drvs
+            .values()
+            .all(|outputs| outputs.contains("out")));
+        assert_eq!(drv.input_srcs.len(), 1);
+        assert_eq!(drv.args[0], "-e");
+        assert_eq!(
+            drv.env["postInstall"],
+            "echo \"done\" \\\n  >> $out/log\n\tok"
+        );
+        assert!(!drv.is_fixed_output());
+
+        let tarball = Derivation::parse(TARBALL).unwrap();
+        assert!(tarball.is_fixed_output());
+        assert_eq!(tarball.outputs["out"].hash_algo, "sha256");
+        assert_eq!(tarball.builder, "builtin:fetchurl");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for text in [HELLO, TARBALL] {
+            let drv: Derivation = text.parse().unwrap();
+            assert_eq!(drv.to_aterm(), text);
+            assert_eq!(Derivation::parse(&drv.to_string()).unwrap(), drv);
+        }
+    }
+
+    #[test]
+    fn test_nix_derivation_conversion() {
+        let drv = Derivation::parse(HELLO).unwrap();
+        let nix = drv.to_nix_derivation(Some("/nix/store/x-hello-2.12.1.drv".to_string()));
+
+        assert_eq!(nix.name, "hello-2.12.1");
+        assert_eq!(nix.builder, drv.builder);
+        assert_eq!(nix.input_drvs.len(), 3);
+        assert_eq!(nix.env["pname"], "hello");
+        assert_eq!(
+            nix.drv_path.as_deref(),
+            Some("/nix/store/x-hello-2.12.1.drv")
+        );
+
+        assert_eq!(Derivation::from(&nix), drv);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Derivation::parse("Derive([(\"out\""),
+            Err(DerivationError::Syntax {
+                offset: 14,
+                expected: ","
+            })
+        );
+        assert!(matches!(
+            Derivation::parse(r#"Derive([],[],[],"x","y",[],[("a","b)])"#),
+            Err(DerivationError::Syntax {
+                expected: "closing quote",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Derivation::parse(&format!("{TARBALL} trailing")),
+            Err(DerivationError::Syntax {
+                expected: "end of input",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Derivation::parse(r#"DrvWithVersion("xp-dyn-drv",[],[],[],"x","y",[],[])"#),
+            Err(DerivationError::Unsupported(_))
+        ));
+        assert!(matches!(
+            Derivation::parse(r#"Derive([],[("/nix/store/a.drv",(["out"],[]))],[],"x","y",[],[])"#),
+            Err(DerivationError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_graph() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = |name: &str| format!("/nix/store/{name}");
+        let leaf = Derivation::parse(TARBALL).unwrap();
+        let mut middle = Derivation::parse(TARBALL).unwrap();
+        middle
+            .input_drvs
+            .insert(store("aaa-leaf.drv"), BTreeSet::from(["out".to_string()]));
+        let mut root = Derivation::parse(HELLO).unwrap();
+        root.input_drvs = BTreeMap::from([
+            (store("aaa-leaf.drv"), BTreeSet::from(["out".to_string()])),
+            (store("bbb-middle.drv"), BTreeSet::from(["out".to_string()])),
+            (store("ccc-absent.drv"), BTreeSet::from(["out".to_string()])),
+        ]);
+        std::fs::write(dir.path().join("aaa-leaf.drv"), leaf.to_aterm()).unwrap();
+        std::fs::write(dir.path().join("bbb-middle.drv"), middle.to_aterm()).unwrap();
+        let root_path = dir.path().join("zzz-root.drv");
+        std::fs::write(&root_path, root.to_aterm()).unwrap();
+
+        let graph = DerivationGraph::load(&root_path).unwrap();
+        assert_eq!(graph.derivations.len(), 3);
+        assert_eq!(graph.missing, BTreeSet::from([store("ccc-absent.drv")]));
+
+        let root_key = root_path.display().to_string();
+        assert_eq!(
+            graph.build_order(),
+            vec![
+                store("aaa-leaf.drv").as_str(),
+                store("bbb-middle.drv").as_str(),
+                root_key.as_str()
+            ]
+        );
+        let mut dependents: Vec<&str> = graph.dependents_of("/nix/store/aaa-leaf.drv").collect();
+        dependents.sort_unstable();
+        assert_eq!(
+            dependents,
+            vec![store("bbb-middle.drv").as_str(), root_key.as_str()]
+        );
+    }
+}
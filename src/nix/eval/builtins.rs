@@ -0,0 +1,1622 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Builtins and the `lib` subset
+//!
+//! Only pure functions are implemented. Builtins that reach outside the
+//! expression (the store, the filesystem, the network, the environment)
+//! exist so that they fail with [`EvalError::Unsupported`] and a useful
+//! span instead of as undefined variables.
+
+use super::interpreter::{type_error, Machine};
+use super::value::{Attrs, Env, PrimOp, Thunk, Value};
+use super::{EvalError, EvalResult};
+use crate::nix::ast::Span;
+use rnix::ast;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// A builtin function of fixed arity
+pub(crate) struct Builtin {
+    pub(crate) name: &'static str,
+    pub(crate) arity: usize,
+    func: fn(&Args<'_, '_>) -> EvalResult<Value>,
+}
+
+impl Builtin {
+    const fn new(
+        name: &'static str,
+        arity: usize,
+        func: fn(&Args<'_, '_>) -> EvalResult<Value>,
+    ) -> Self {
+        Self { name, arity, func }
+    }
+
+    /// Run the builtin once all its arguments have been supplied
+    pub(crate) fn call(
+        &self,
+        machine: &Machine<'_>,
+        args: &[Thunk],
+        span: Span,
+    ) -> EvalResult<Value> {
+        (self.func)(&Args {
+            machine,
+            thunks: args,
+            span,
+            name: self.name,
+        })
+    }
+}
+
+/// Arguments of a builtin call
+struct Args<'a, 'c> {
+    machine: &'a Machine<'c>,
+    thunks: &'a [Thunk],
+    /// Location of the call
+    span: Span,
+    name: &'static str,
+}
+
+impl Args<'_, '_> {
+    /// Location of argument `i`, falling back to the call
+    fn span_of(&self, i: usize) -> Span {
+        self.thunks[i].origin().unwrap_or(self.span)
+    }
+
+    fn thunk(&self, i: usize) -> Thunk {
+        self.thunks[i].clone()
+    }
+
+    fn value(&self, i: usize) -> EvalResult<Value> {
+        self.machine.force(&self.thunks[i])
+    }
+
+    fn int(&self, i: usize) -> EvalResult<i64> {
+        match self.value(i)? {
+            Value::Int(n) => Ok(n),
+            other => Err(type_error("int", &other, self.span_of(i))),
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn number(&self, i: usize) -> EvalResult<f64> {
+        match self.value(i)? {
+            Value::Int(n) => Ok(n as f64),
+            Value::Float(f) => Ok(f),
+            other => Err(type_error("number", &other, self.span_of(i))),
+        }
+    }
+
+    fn bool(&self, i: usize) -> EvalResult<bool> {
+        match self.value(i)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(type_error("bool", &other, self.span_of(i))),
+        }
+    }
+
+    fn string(&self, i: usize) -> EvalResult<String> {
+        let value = self.value(i)?;
+        self.machine
+            .coerce_to_string(&value, self.span_of(i), false)
+    }
+
+    fn list(&self, i: usize) -> EvalResult<Rc<Vec<Thunk>>> {
+        match self.value(i)? {
+            Value::List(items) => Ok(items),
+            other => Err(type_error("list", &other, self.span_of(i))),
+        }
+    }
+
+    fn attrs(&self, i: usize) -> EvalResult<Rc<Attrs>> {
+        match self.value(i)? {
+            Value::Attrs(attrs) => Ok(attrs),
+            other => Err(type_error("set", &other, self.span_of(i))),
+        }
+    }
+
+    /// Strings of a list argument
+    fn strings(&self, i: usize) -> EvalResult<Vec<String>> {
+        self.list(i)?
+            .iter()
+            .map(|item| self.force_string(item))
+            .collect()
+    }
+
+    fn force(&self, thunk: &Thunk) -> EvalResult<Value> {
+        self.machine.force(thunk)
+    }
+
+    fn force_string(&self, thunk: &Thunk) -> EvalResult<String> {
+        let value = self.force(thunk)?;
+        self.machine
+            .coerce_to_string(&value, thunk.origin().unwrap_or(self.span), false)
+    }
+
+    fn force_attrs(&self, thunk: &Thunk) -> EvalResult<Rc<Attrs>> {
+        self.machine.force_attrs(thunk, self.span)
+    }
+
+    fn call(&self, func: &Value, args: Vec<Thunk>) -> EvalResult<Value> {
+        self.machine.call(func, args, self.span)
+    }
+
+    fn call_bool(&self, func: &Value, args: Vec<Thunk>) -> EvalResult<bool> {
+        match self.call(func, args)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(type_error("bool", &other, self.span)),
+        }
+    }
+
+    /// A call that is only made when its result is needed
+    fn lazy_call(&self, func: &Value, args: Vec<Thunk>) -> Thunk {
+        Thunk::call(func.clone(), args, self.span)
+    }
+
+    /// Fail if a list or string of `len` would exceed the size limit
+    fn check_size(&self, len: usize) -> EvalResult<()> {
+        self.machine.check_size(len, self.span)
+    }
+
+    /// Join strings, checking the size before allocating
+    fn join(&self, parts: &[String], sep: &str) -> EvalResult<Value> {
+        let len = parts.iter().map(String::len).sum::<usize>()
+            + sep.len() * parts.len().saturating_sub(1);
+        self.check_size(len)?;
+        Ok(Value::String(parts.join(sep)))
+    }
+
+    fn invalid(&self, text: impl Into<String>) -> EvalError {
+        EvalError::InvalidValue {
+            text: text.into(),
+            span: self.span,
+        }
+    }
+}
+
+fn int(n: usize) -> Value {
+    Value::Int(i64::try_from(n).unwrap_or(i64::MAX))
+}
+
+fn attrs_of<const N: usize>(entries: [(&str, Thunk); N]) -> Value {
+    Value::attrs(
+        entries
+            .into_iter()
+            .map(|(name, thunk)| (name.to_string(), thunk))
+            .collect(),
+    )
+}
+
+// ============================================================================
+// Installation
+// ============================================================================
+
+/// Builtins that are also in scope without the `builtins.` prefix
+const GLOBALS: &[&str] = &[
+    "abort",
+    "baseNameOf",
+    "derivation",
+    "dirOf",
+    "fetchTarball",
+    "import",
+    "isNull",
+    "map",
+    "removeAttrs",
+    "throw",
+    "toString",
+];
+
+/// Define the global scope: `builtins`, its global aliases and `lib`
+pub(crate) fn install(globals: &Env) {
+    let mut builtins: Attrs = BUILTINS
+        .iter()
+        .map(|builtin| (builtin.name.to_string(), primop(builtin)))
+        .collect();
+    for (name, value) in [
+        ("true", Value::Bool(true)),
+        ("false", Value::Bool(false)),
+        ("null", Value::Null),
+    ] {
+        builtins.insert(name.to_string(), Thunk::value(value.clone()));
+        globals.define(name, Thunk::value(value));
+    }
+    for name in GLOBALS {
+        globals.define(*name, builtins[*name].clone());
+    }
+    globals.define("builtins", Thunk::value(Value::attrs(builtins)));
+    globals.define("lib", Thunk::value(lib()));
+}
+
+/// The `lib` subset, also returned by `import <nixpkgs/lib>`
+pub(crate) fn lib() -> Value {
+    let mut lib: Attrs = LIB
+        .iter()
+        .map(|builtin| (builtin.name.to_string(), primop(builtin)))
+        .collect();
+    for name in LIB_FROM_BUILTINS {
+        if let Some(builtin) = BUILTINS.iter().find(|b| b.name == *name) {
+            lib.insert((*name).to_string(), primop(builtin));
+        }
+    }
+    Value::attrs(lib)
+}
+
+fn primop(builtin: &'static Builtin) -> Thunk {
+    Thunk::value(Value::PrimOp(Rc::new(PrimOp {
+        builtin,
+        args: Vec::new(),
+    })))
+}
+
+// ============================================================================
+// builtins.*
+// ============================================================================
+
+static BUILTINS: &[Builtin] = &[
+    // Arithmetic and comparison
+    Builtin::new("add", 2, add),
+    Builtin::new("sub", 2, sub),
+    Builtin::new("mul", 2, mul),
+    Builtin::new("div", 2, div),
+    Builtin::new("lessThan", 2, less_than),
+    Builtin::new("bitAnd", 2, bit_and),
+    Builtin::new("bitOr", 2, bit_or),
+    Builtin::new("bitXor", 2, bit_xor),
+    Builtin::new("ceil", 1, ceil),
+    Builtin::new("floor", 1, floor),
+    // Lists
+    Builtin::new("length", 1, length),
+    Builtin::new("head", 1, head),
+    Builtin::new("tail", 1, tail),
+    Builtin::new("elemAt", 2, elem_at),
+    Builtin::new("elem", 2, elem),
+    Builtin::new("filter", 2, filter),
+    Builtin::new("map", 2, map),
+    Builtin::new("foldl'", 3, foldl),
+    Builtin::new("concatLists", 1, concat_lists),
+    Builtin::new("concatMap", 2, concat_map),
+    Builtin::new("genList", 2, gen_list),
+    Builtin::new("all", 2, all),
+    Builtin::new("any", 2, any),
+    Builtin::new("sort", 2, sort),
+    Builtin::new("partition", 2, partition),
+    Builtin::new("groupBy", 2, group_by),
+    // Attribute sets
+    Builtin::new("attrNames", 1, attr_names),
+    Builtin::new("attrValues", 1, attr_values),
+    Builtin::new("hasAttr", 2, has_attr),
+    Builtin::new("getAttr", 2, get_attr),
+    Builtin::new("removeAttrs", 2, remove_attrs),
+    Builtin::new("intersectAttrs", 2, intersect_attrs),
+    Builtin::new("listToAttrs", 1, list_to_attrs),
+    Builtin::new("mapAttrs", 2, map_attrs),
+    Builtin::new("catAttrs", 2, cat_attrs),
+    Builtin::new("zipAttrsWith", 2, zip_attrs_with),
+    Builtin::new("functionArgs", 1, function_args),
+    // Types
+    Builtin::new("typeOf", 1, type_of),
+    Builtin::new("isAttrs", 1, is_attrs),
+    Builtin::new("isList", 1, is_list),
+    Builtin::new("isString", 1, is_string),
+    Builtin::new("isInt", 1, is_int),
+    Builtin::new("isFloat", 1, is_float),
+    Builtin::new("isBool", 1, is_bool),
+    Builtin::new("isNull", 1, is_null),
+    Builtin::new("isPath", 1, is_path),
+    Builtin::new("isFunction", 1, is_function),
+    // Strings
+    Builtin::new("toString", 1, to_string),
+    Builtin::new("stringLength", 1, string_length),
+    Builtin::new("substring", 3, substring),
+    Builtin::new("replaceStrings", 3, replace_strings),
+    Builtin::new("concatStringsSep", 2, concat_strings_sep),
+    Builtin::new("match", 2, regex_match),
+    Builtin::new("split", 2, regex_split),
+    Builtin::new("compareVersions", 2, compare_versions),
+    Builtin::new("splitVersion", 1, split_version),
+    Builtin::new("baseNameOf", 1, base_name_of),
+    Builtin::new("dirOf", 1, dir_of),
+    Builtin::new("unsafeDiscardStringContext", 1, identity),
+    Builtin::new("toJSON", 1, to_json),
+    Builtin::new("fromJSON", 1, from_json),
+    // Control
+    Builtin::new("throw", 1, throw),
+    Builtin::new("abort", 1, abort),
+    Builtin::new("seq", 2, seq),
+    Builtin::new("deepSeq", 2, deep_seq),
+    Builtin::new("trace", 2, trace),
+    Builtin::new("tryEval", 1, try_eval),
+    Builtin::new("import", 1, import),
+    // Impure
+    Builtin::new("derivation", 1, unsupported),
+    Builtin::new("fetchGit", 1, unsupported),
+    Builtin::new("fetchTarball", 1, unsupported),
+    Builtin::new("fetchTree", 1, unsupported),
+    Builtin::new("fetchurl", 1, unsupported),
+    Builtin::new("getEnv", 1, unsupported),
+    Builtin::new("getFlake", 1, unsupported),
+    Builtin::new("path", 1, unsupported),
+    Builtin::new("pathExists", 1, unsupported),
+    Builtin::new("readDir", 1, unsupported),
+    Builtin::new("readFile", 1, unsupported),
+    Builtin::new("storePath", 1, unsupported),
+    Builtin::new("filterSource", 2, unsupported),
+    Builtin::new("toFile", 2, unsupported),
+];
+
+fn unsupported(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Err(EvalError::Unsupported {
+        name: a.name.to_string(),
+        span: a.span,
+    })
+}
+
+fn identity(a: &Args<'_, '_>) -> EvalResult<Value> {
+    a.value(0)
+}
+
+fn add(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Machine::arithmetic(ast::BinOpKind::Add, &a.value(0)?, &a.value(1)?, a.span)
+}
+
+fn sub(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Machine::arithmetic(ast::BinOpKind::Sub, &a.value(0)?, &a.value(1)?, a.span)
+}
+
+fn mul(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Machine::arithmetic(ast::BinOpKind::Mul, &a.value(0)?, &a.value(1)?, a.span)
+}
+
+fn div(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Machine::arithmetic(ast::BinOpKind::Div, &a.value(0)?, &a.value(1)?, a.span)
+}
+
+fn less_than(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let less = a.machine.less_than(&a.value(0)?, &a.value(1)?, a.span)?;
+    Ok(Value::Bool(less))
+}
+
+fn bit_and(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(Value::Int(a.int(0)? & a.int(1)?))
+}
+
+fn bit_or(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(Value::Int(a.int(0)? | a.int(1)?))
+}
+
+fn bit_xor(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(Value::Int(a.int(0)? ^ a.int(1)?))
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn ceil(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(Value::Int(a.number(0)?.ceil() as i64))
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn floor(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(Value::Int(a.number(0)?.floor() as i64))
+}
+
+// ----------------------------------------------------------------------------
+// Lists
+// ----------------------------------------------------------------------------
+
+fn length(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(int(a.list(0)?.len()))
+}
+
+fn head(a: &Args<'_, '_>) -> EvalResult<Value> {
+    match a.list(0)?.first() {
+        Some(first) => a.force(first),
+        None => Err(EvalError::IndexOutOfBounds {
+            index: 0,
+            length: 0,
+            span: a.span,
+        }),
+    }
+}
+
+fn tail(a: &Args<'_, '_>) -> EvalResult<Value> {
+    match a.list(0)?.split_first() {
+        Some((_, rest)) => Ok(Value::list(rest.to_vec())),
+        None => Err(EvalError::IndexOutOfBounds {
+            index: 0,
+            length: 0,
+            span: a.span,
+        }),
+    }
+}
+
+fn elem_at(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let items = a.list(0)?;
+    let index = a.int(1)?;
+    match usize::try_from(index).ok().and_then(|i| items.get(i)) {
+        Some(item) => a.force(item),
+        None => Err(EvalError::IndexOutOfBounds {
+            index,
+            length: items.len(),
+            span: a.span,
+        }),
+    }
+}
+
+fn elem(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let needle = a.value(0)?;
+    for item in a.list(1)?.iter() {
+        if a.machine.equal(&needle, &a.force(item)?)? {
+            return Ok(Value::Bool(true));
+        }
+    }
+    Ok(Value::Bool(false))
+}
+
+fn filter(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let pred = a.value(0)?;
+    let mut kept = Vec::new();
+    for item in a.list(1)?.iter() {
+        if a.call_bool(&pred, vec![item.clone()])? {
+            kept.push(item.clone());
+        }
+    }
+    Ok(Value::list(kept))
+}
+
+fn map(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let func = a.value(0)?;
+    let items = a.list(1)?;
+    Ok(Value::list(
+        items
+            .iter()
+            .map(|item| a.lazy_call(&func, vec![item.clone()]))
+            .collect(),
+    ))
+}
+
+fn foldl(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let op = a.value(0)?;
+    let mut acc = a.value(1)?;
+    for item in a.list(2)?.iter() {
+        acc = a.call(&op, vec![Thunk::value(acc), item.clone()])?;
+    }
+    Ok(acc)
+}
+
+fn concat_lists(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let mut items = Vec::new();
+    for list in a.list(0)?.iter() {
+        match a.force(list)? {
+            Value::List(inner) => {
+                a.check_size(items.len() + inner.len())?;
+                items.extend(inner.iter().cloned());
+            }
+            other => return Err(type_error("list", &other, a.span)),
+        }
+    }
+    Ok(Value::list(items))
+}
+
+fn concat_map(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let func = a.value(0)?;
+    let mut items = Vec::new();
+    for item in a.list(1)?.iter() {
+        match a.call(&func, vec![item.clone()])? {
+            Value::List(inner) => {
+                a.check_size(items.len() + inner.len())?;
+                items.extend(inner.iter().cloned());
+            }
+            other => return Err(type_error("list", &other, a.span)),
+        }
+    }
+    Ok(Value::list(items))
+}
+
+fn gen_list(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let func = a.value(0)?;
+    let count = a.int(1)?;
+    let count = usize::try_from(count).map_err(|_| a.invalid(count.to_string()))?;
+    a.check_size(count)?;
+    Ok(Value::list(
+        (0..count)
+            .map(|i| a.lazy_call(&func, vec![Thunk::value(int(i))]))
+            .collect(),
+    ))
+}
+
+fn all(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let pred = a.value(0)?;
+    for item in a.list(1)?.iter() {
+        if !a.call_bool(&pred, vec![item.clone()])? {
+            return Ok(Value::Bool(false));
+        }
+    }
+    Ok(Value::Bool(true))
+}
+
+fn any(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let pred = a.value(0)?;
+    for item in a.list(1)?.iter() {
+        if a.call_bool(&pred, vec![item.clone()])? {
+            return Ok(Value::Bool(true));
+        }
+    }
+    Ok(Value::Bool(false))
+}
+
+fn sort(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let less = a.value(0)?;
+    let items = a.list(1)?.to_vec();
+    let mut less = |x: &Thunk, y: &Thunk| a.call_bool(&less, vec![x.clone(), y.clone()]);
+    Ok(Value::list(merge_sort(items, &mut less)?))
+}
+
+/// Stable sort with a comparison that can fail
+fn merge_sort(
+    mut items: Vec<Thunk>,
+    less: &mut dyn FnMut(&Thunk, &Thunk) -> EvalResult<bool>,
+) -> EvalResult<Vec<Thunk>> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(items, less)?;
+    let right = merge_sort(right, less)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        if less(r, l)? {
+            merged.extend(right.next());
+        } else {
+            merged.extend(left.next());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+fn partition(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let pred = a.value(0)?;
+    let (mut right, mut wrong) = (Vec::new(), Vec::new());
+    for item in a.list(1)?.iter() {
+        if a.call_bool(&pred, vec![item.clone()])? {
+            right.push(item.clone());
+        } else {
+            wrong.push(item.clone());
+        }
+    }
+    Ok(attrs_of([
+        ("right", Thunk::value(Value::list(right))),
+        ("wrong", Thunk::value(Value::list(wrong))),
+    ]))
+}
+
+fn group_by(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let func = a.value(0)?;
+    let mut groups: BTreeMap<String, Vec<Thunk>> = BTreeMap::new();
+    for item in a.list(1)?.iter() {
+        let key = match a.call(&func, vec![item.clone()])? {
+            Value::String(key) => key,
+            other => return Err(type_error("string", &other, a.span)),
+        };
+        groups.entry(key).or_default().push(item.clone());
+    }
+    Ok(Value::attrs(
+        groups
+            .into_iter()
+            .map(|(key, items)| (key, Thunk::value(Value::list(items))))
+            .collect(),
+    ))
+}
+
+// ----------------------------------------------------------------------------
+// Attribute sets
+// ----------------------------------------------------------------------------
+
+fn attr_names(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(Value::list(
+        a.attrs(0)?
+            .keys()
+            .map(|name| Thunk::string(name.as_str()))
+            .collect(),
+    ))
+}
+
+fn attr_values(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(Value::list(a.attrs(0)?.values().cloned().collect()))
+}
+
+fn has_attr(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let name = a.string(0)?;
+    Ok(Value::Bool(a.attrs(1)?.contains_key(&name)))
+}
+
+fn get_attr(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let name = a.string(0)?;
+    match a.attrs(1)?.get(&name) {
+        Some(value) => a.force(value),
+        None => Err(EvalError::MissingAttribute { name, span: a.span }),
+    }
+}
+
+fn remove_attrs(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let mut attrs = (*a.attrs(0)?).clone();
+    for name in a.strings(1)? {
+        attrs.remove(&name);
+    }
+    Ok(Value::attrs(attrs))
+}
+
+fn intersect_attrs(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let keep = a.attrs(0)?;
+    Ok(Value::attrs(
+        a.attrs(1)?
+            .iter()
+            .filter(|(name, _)| keep.contains_key(*name))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+    ))
+}
+
+fn list_to_attrs(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let mut attrs = Attrs::new();
+    for item in a.list(0)?.iter() {
+        let pair = a.force_attrs(item)?;
+        let name = match pair.get("name") {
+            Some(name) => a.force_string(name)?,
+            None => {
+                return Err(EvalError::MissingAttribute {
+                    name: "name".to_string(),
+                    span: a.span,
+                })
+            }
+        };
+        let Some(value) = pair.get("value") else {
+            return Err(EvalError::MissingAttribute {
+                name: "value".to_string(),
+                span: a.span,
+            });
+        };
+        // The first definition of a name wins
+        attrs.entry(name).or_insert_with(|| value.clone());
+    }
+    Ok(Value::attrs(attrs))
+}
+
+fn map_attrs(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let func = a.value(0)?;
+    Ok(Value::attrs(
+        a.attrs(1)?
+            .iter()
+            .map(|(name, value)| {
+                let args = vec![Thunk::string(name.as_str()), value.clone()];
+                (name.clone(), a.lazy_call(&func, args))
+            })
+            .collect(),
+    ))
+}
+
+fn cat_attrs(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let name = a.string(0)?;
+    let mut values = Vec::new();
+    for item in a.list(1)?.iter() {
+        if let Some(value) = a.force_attrs(item)?.get(&name) {
+            values.push(value.clone());
+        }
+    }
+    Ok(Value::list(values))
+}
+
+fn zip_attrs_with(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let func = a.value(0)?;
+    let mut values: BTreeMap<String, Vec<Thunk>> = BTreeMap::new();
+    for item in a.list(1)?.iter() {
+        for (name, value) in a.force_attrs(item)?.iter() {
+            values.entry(name.clone()).or_default().push(value.clone());
+        }
+    }
+    Ok(Value::attrs(
+        values
+            .into_iter()
+            .map(|(name, items)| {
+                let args = vec![
+                    Thunk::string(name.as_str()),
+                    Thunk::value(Value::list(items)),
+                ];
+                (name, a.lazy_call(&func, args))
+            })
+            .collect(),
+    ))
+}
+
+fn function_args(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let mut formals = Attrs::new();
+    match a.value(0)? {
+        Value::Lambda(lambda) => {
+            if let ast::Param::Pattern(pattern) = &lambda.param {
+                for entry in pattern.pat_entries() {
+                    if let Some(ident) = entry.ident() {
+                        let name = rowan::ast::AstNode::syntax(&ident).text().to_string();
+                        let has_default = Value::Bool(entry.default().is_some());
+                        formals.insert(name, Thunk::value(has_default));
+                    }
+                }
+            }
+        }
+        Value::PrimOp(_) => {}
+        other => return Err(type_error("function", &other, a.span_of(0))),
+    }
+    Ok(Value::attrs(formals))
+}
+
+// ----------------------------------------------------------------------------
+// Types
+// ----------------------------------------------------------------------------
+
+fn type_of(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(Value::String(a.value(0)?.type_name().to_string()))
+}
+
+fn is_type(a: &Args<'_, '_>, type_name: &str) -> EvalResult<Value> {
+    Ok(Value::Bool(a.value(0)?.type_name() == type_name))
+}
+
+fn is_attrs(a: &Args<'_, '_>) -> EvalResult<Value> {
+    is_type(a, "set")
+}
+
+fn is_list(a: &Args<'_, '_>) -> EvalResult<Value> {
+    is_type(a, "list")
+}
+
+fn is_string(a: &Args<'_, '_>) -> EvalResult<Value> {
+    is_type(a, "string")
+}
+
+fn is_int(a: &Args<'_, '_>) -> EvalResult<Value> {
+    is_type(a, "int")
+}
+
+fn is_float(a: &Args<'_, '_>) -> EvalResult<Value> {
+    is_type(a, "float")
+}
+
+fn is_bool(a: &Args<'_, '_>) -> EvalResult<Value> {
+    is_type(a, "bool")
+}
+
+fn is_null(a: &Args<'_, '_>) -> EvalResult<Value> {
+    is_type(a, "null")
+}
+
+fn is_path(a: &Args<'_, '_>) -> EvalResult<Value> {
+    is_type(a, "path")
+}
+
+fn is_function(a: &Args<'_, '_>) -> EvalResult<Value> {
+    is_type(a, "lambda")
+}
+
+// ----------------------------------------------------------------------------
+// Strings
+// ----------------------------------------------------------------------------
+
+fn to_string(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let value = a.value(0)?;
+    a.machine
+        .coerce_to_string(&value, a.span, true)
+        .map(Value::String)
+}
+
+fn string_length(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(int(a.string(0)?.len()))
+}
+
+/// Byte-based, like Nix
+fn substring(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let start = a.int(0)?;
+    let len = a.int(1)?;
+    let s = a.string(2)?;
+    let start = usize::try_from(start).map_err(|_| a.invalid(start.to_string()))?;
+
+    let bytes = s.as_bytes();
+    let start = start.min(bytes.len());
+    let end = match usize::try_from(len) {
+        Ok(len) => start.saturating_add(len).min(bytes.len()),
+        // A negative length means "to the end"
+        Err(_) => bytes.len(),
+    };
+    Ok(Value::String(
+        String::from_utf8_lossy(&bytes[start..end]).into_owned(),
+    ))
+}
+
+fn replace_strings(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let from = a.strings(0)?;
+    let to = a.strings(1)?;
+    let s = a.string(2)?;
+    if from.len() != to.len() {
+        return Err(a.invalid("'from' and 'to' lists differ in length"));
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s.as_str();
+    loop {
+        if let Some((pattern, replacement)) = from
+            .iter()
+            .zip(&to)
+            .find(|(pattern, _)| rest.starts_with(pattern.as_str()))
+        {
+            a.check_size(out.len() + replacement.len())?;
+            out.push_str(replacement);
+            if !pattern.is_empty() {
+                rest = &rest[pattern.len()..];
+                continue;
+            }
+        }
+        // No match, or an empty pattern: copy one character
+        let mut chars = rest.chars();
+        match chars.next() {
+            Some(c) => out.push(c),
+            None => break,
+        }
+        rest = chars.as_str();
+    }
+    Ok(Value::String(out))
+}
+
+fn concat_strings_sep(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let sep = a.string(0)?;
+    a.join(&a.strings(1)?, &sep)
+}
+
+fn regex(a: &Args<'_, '_>, pattern: &str) -> EvalResult<regex::Regex> {
+    regex::Regex::new(pattern).map_err(|_| a.invalid(pattern))
+}
+
+fn captures(caps: &regex::Captures<'_>) -> Value {
+    Value::list(
+        caps.iter()
+            .skip(1)
+            .map(|group| match group {
+                Some(m) => Thunk::string(m.as_str()),
+                None => Thunk::value(Value::Null),
+            })
+            .collect(),
+    )
+}
+
+/// `builtins.match`: the capture groups of a full match, or `null`
+fn regex_match(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let re = regex(a, &format!("^(?:{})$", a.string(0)?))?;
+    let s = a.string(1)?;
+    Ok(re.captures(&s).map_or(Value::Null, |caps| captures(&caps)))
+}
+
+/// `builtins.split`: text between matches interleaved with capture groups
+fn regex_split(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let re = regex(a, &a.string(0)?)?;
+    let s = a.string(1)?;
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for caps in re.captures_iter(&s) {
+        let whole = caps.get(0).expect("group 0 is the whole match");
+        parts.push(Thunk::string(&s[last..whole.start()]));
+        parts.push(Thunk::value(captures(&caps)));
+        last = whole.end();
+    }
+    parts.push(Thunk::string(&s[last..]));
+    Ok(Value::list(parts))
+}
+
+/// Split a version into components the way Nix does
+///
+/// Components are separated by `.` and `-`, and a new one starts wherever
+/// digits and non-digits meet (`1.2pre3` is `1`, `2`, `pre`, `3`).
+fn version_components(version: &str) -> Vec<&str> {
+    let mut components = Vec::new();
+    let mut rest = version;
+    loop {
+        rest = rest.trim_start_matches(['.', '-']);
+        let Some(first) = rest.chars().next() else {
+            break;
+        };
+        let end = if first.is_ascii_digit() {
+            rest.find(|c: char| !c.is_ascii_digit())
+        } else {
+            rest.find(|c: char| c.is_ascii_digit() || c == '.' || c == '-')
+        }
+        .unwrap_or(rest.len());
+        components.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    components
+}
+
+fn component_less(c1: &str, c2: &str) -> bool {
+    let n1 = c1.parse::<u64>().ok();
+    let n2 = c2.parse::<u64>().ok();
+    match (n1, n2) {
+        (Some(n1), Some(n2)) => n1 < n2,
+        (_, Some(_)) if c1.is_empty() => true,
+        _ if c1 == "pre" && c2 != "pre" => true,
+        _ if c2 == "pre" => false,
+        // `2.3a` < `2.3.1`
+        (_, Some(_)) => true,
+        (Some(_), _) => false,
+        _ => c1 < c2,
+    }
+}
+
+fn compare_versions(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let (v1, v2) = (a.string(0)?, a.string(1)?);
+    let (c1, c2) = (version_components(&v1), version_components(&v2));
+    for i in 0..c1.len().max(c2.len()) {
+        let x = c1.get(i).copied().unwrap_or("");
+        let y = c2.get(i).copied().unwrap_or("");
+        if component_less(x, y) {
+            return Ok(Value::Int(-1));
+        }
+        if component_less(y, x) {
+            return Ok(Value::Int(1));
+        }
+    }
+    Ok(Value::Int(0))
+}
+
+fn split_version(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let version = a.string(0)?;
+    Ok(Value::list(
+        version_components(&version)
+            .into_iter()
+            .map(Thunk::string)
+            .collect(),
+    ))
+}
+
+/// Text of a string or path argument
+fn path_text(a: &Args<'_, '_>) -> EvalResult<(String, bool)> {
+    match a.value(0)? {
+        Value::Path(p) => Ok((p, true)),
+        Value::String(s) => Ok((s, false)),
+        other => Err(type_error("string or path", &other, a.span_of(0))),
+    }
+}
+
+fn base_name_of(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let (text, _) = path_text(a)?;
+    let trimmed = text.trim_end_matches('/');
+    let base = trimmed.rsplit('/').next().unwrap_or(trimmed);
+    Ok(Value::String(base.to_string()))
+}
+
+fn dir_of(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let (text, is_path) = path_text(a)?;
+    let dir = match text.trim_end_matches('/').rfind('/') {
+        Some(0) => "/".to_string(),
+        Some(i) => text[..i].to_string(),
+        None => ".".to_string(),
+    };
+    Ok(if is_path {
+        Value::Path(dir)
+    } else {
+        Value::String(dir)
+    })
+}
+
+fn to_json(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let json = json_of(a, &a.value(0)?, a.span_of(0))?;
+    Ok(Value::String(json.to_string()))
+}
+
+fn json_of(a: &Args<'_, '_>, value: &Value, span: Span) -> EvalResult<serde_json::Value> {
+    let _depth = a.machine.enter(span)?;
+    Ok(match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => serde_json::Value::Bool(*b),
+        Value::Int(i) => serde_json::Value::from(*i),
+        Value::Float(f) => serde_json::Value::from(*f),
+        Value::String(s) => serde_json::Value::String(s.clone()),
+        Value::List(items) => {
+            let mut elements = Vec::with_capacity(items.len());
+            for item in items.iter() {
+                let span = item.origin().unwrap_or(span);
+                elements.push(json_of(a, &a.force(item)?, span)?);
+            }
+            serde_json::Value::Array(elements)
+        }
+        Value::Attrs(attrs)
+            if attrs.contains_key("__toString") || attrs.contains_key("outPath") =>
+        {
+            serde_json::Value::String(a.machine.coerce_to_string(value, span, false)?)
+        }
+        Value::Attrs(attrs) => {
+            let mut object = serde_json::Map::new();
+            for (name, item) in attrs.iter() {
+                let span = item.origin().unwrap_or(span);
+                object.insert(name.clone(), json_of(a, &a.force(item)?, span)?);
+            }
+            serde_json::Value::Object(object)
+        }
+        Value::Path(_) | Value::LookupPath(_) => {
+            return Err(EvalError::Unsupported {
+                name: "copying paths to the store".to_string(),
+                span,
+            })
+        }
+        Value::Lambda(_) | Value::PrimOp(_) => {
+            return Err(EvalError::NotData {
+                type_name: value.type_name(),
+                span,
+            })
+        }
+    })
+}
+
+fn from_json(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let text = a.string(0)?;
+    let json: serde_json::Value =
+        serde_json::from_str(&text).map_err(|e| a.invalid(e.to_string()))?;
+    Ok(value_of_json(json))
+}
+
+fn value_of_json(json: serde_json::Value) -> Value {
+    match json {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Bool(b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Int(i),
+            None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(s) => Value::String(s),
+        serde_json::Value::Array(items) => Value::list(
+            items
+                .into_iter()
+                .map(|item| Thunk::value(value_of_json(item)))
+                .collect(),
+        ),
+        serde_json::Value::Object(object) => Value::attrs(
+            object
+                .into_iter()
+                .map(|(name, item)| (name, Thunk::value(value_of_json(item))))
+                .collect(),
+        ),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Control
+// ----------------------------------------------------------------------------
+
+fn throw(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Err(EvalError::Throw {
+        message: a.string(0)?,
+        span: a.span,
+    })
+}
+
+fn abort(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Err(EvalError::Abort {
+        message: a.string(0)?,
+        span: a.span,
+    })
+}
+
+fn seq(a: &Args<'_, '_>) -> EvalResult<Value> {
+    a.value(0)?;
+    a.value(1)
+}
+
+fn deep_seq(a: &Args<'_, '_>) -> EvalResult<Value> {
+    a.machine.deep_force(&a.value(0)?, a.span_of(0))?;
+    a.value(1)
+}
+
+fn trace(a: &Args<'_, '_>) -> EvalResult<Value> {
+    match a.value(0)? {
+        Value::String(message) => tracing::debug!("trace: {message}"),
+        other => tracing::debug!("trace: <{}>", other.type_name()),
+    }
+    a.value(1)
+}
+
+/// Catches `throw` and failed assertions, like Nix
+fn try_eval(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let (success, value) = match a.value(0) {
+        Ok(value) => (true, value),
+        Err(EvalError::Throw { .. } | EvalError::AssertionFailed { .. }) => {
+            (false, Value::Bool(false))
+        }
+        Err(err) => return Err(err),
+    };
+    Ok(attrs_of([
+        ("success", Thunk::value(Value::Bool(success))),
+        ("value", Thunk::value(value)),
+    ]))
+}
+
+/// Only `import <nixpkgs/lib>` is pure enough to support
+fn import(a: &Args<'_, '_>) -> EvalResult<Value> {
+    match a.value(0)? {
+        Value::LookupPath(path) if path == "nixpkgs/lib" => Ok(lib()),
+        _ => unsupported(a),
+    }
+}
+
+// ============================================================================
+// lib.*
+// ============================================================================
+
+/// `lib` functions that are the builtins of the same name
+const LIB_FROM_BUILTINS: &[&str] = &[
+    "all",
+    "any",
+    "attrNames",
+    "attrValues",
+    "catAttrs",
+    "concatLists",
+    "concatMap",
+    "concatStringsSep",
+    "elem",
+    "elemAt",
+    "filter",
+    "foldl'",
+    "functionArgs",
+    "genList",
+    "groupBy",
+    "hasAttr",
+    "head",
+    "intersectAttrs",
+    "isAttrs",
+    "isBool",
+    "isFunction",
+    "isInt",
+    "isList",
+    "isString",
+    "length",
+    "listToAttrs",
+    "map",
+    "mapAttrs",
+    "partition",
+    "removeAttrs",
+    "replaceStrings",
+    "sort",
+    "splitVersion",
+    "stringLength",
+    "substring",
+    "tail",
+    "toString",
+    "typeOf",
+    "zipAttrsWith",
+];
+
+static LIB: &[Builtin] = &[
+    Builtin::new("id", 1, identity),
+    Builtin::new("const", 2, lib_const),
+    Builtin::new("flip", 3, flip),
+    Builtin::new("pipe", 2, pipe),
+    Builtin::new("boolToString", 1, bool_to_string),
+    // Attribute sets
+    Builtin::new("genAttrs", 2, gen_attrs),
+    Builtin::new("mapAttrs'", 2, map_attrs_prime),
+    Builtin::new("mapAttrsToList", 2, map_attrs_to_list),
+    Builtin::new("nameValuePair", 2, name_value_pair),
+    Builtin::new("filterAttrs", 2, filter_attrs),
+    Builtin::new("attrByPath", 3, attr_by_path),
+    Builtin::new("getAttrFromPath", 2, get_attr_from_path),
+    Builtin::new("hasAttrByPath", 2, has_attr_by_path),
+    Builtin::new("setAttrByPath", 2, set_attr_by_path),
+    Builtin::new("recursiveUpdate", 2, recursive_update),
+    Builtin::new("optionalAttrs", 2, optional_attrs),
+    // Lists
+    Builtin::new("optional", 2, optional),
+    Builtin::new("optionals", 2, optionals),
+    Builtin::new("flatten", 1, flatten),
+    Builtin::new("unique", 1, unique),
+    Builtin::new("range", 2, range),
+    Builtin::new("foldl", 3, foldl),
+    Builtin::new("foldr", 3, foldr),
+    Builtin::new("imap0", 2, imap0),
+    Builtin::new("imap1", 2, imap1),
+    Builtin::new("last", 1, last),
+    Builtin::new("zipListsWith", 3, zip_lists_with),
+    // Strings
+    Builtin::new("optionalString", 2, optional_string),
+    Builtin::new("concatStrings", 1, concat_strings),
+    Builtin::new("concatMapStrings", 2, concat_map_strings),
+    Builtin::new("concatMapStringsSep", 3, concat_map_strings_sep),
+    Builtin::new("toUpper", 1, to_upper),
+    Builtin::new("toLower", 1, to_lower),
+    Builtin::new("hasPrefix", 2, has_prefix),
+    Builtin::new("hasSuffix", 2, has_suffix),
+    Builtin::new("removePrefix", 2, remove_prefix),
+    Builtin::new("removeSuffix", 2, remove_suffix),
+    Builtin::new("splitString", 2, split_string),
+];
+
+fn lib_const(a: &Args<'_, '_>) -> EvalResult<Value> {
+    a.value(0)
+}
+
+fn flip(a: &Args<'_, '_>) -> EvalResult<Value> {
+    a.call(&a.value(0)?, vec![a.thunk(2), a.thunk(1)])
+}
+
+fn pipe(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let mut value = a.value(0)?;
+    for func in a.list(1)?.iter() {
+        value = a.call(&a.force(func)?, vec![Thunk::value(value)])?;
+    }
+    Ok(value)
+}
+
+fn bool_to_string(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(Value::String(a.bool(0)?.to_string()))
+}
+
+fn gen_attrs(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let func = a.value(1)?;
+    Ok(Value::attrs(
+        a.strings(0)?
+            .into_iter()
+            .map(|name| {
+                let value = a.lazy_call(&func, vec![Thunk::string(name.as_str())]);
+                (name, value)
+            })
+            .collect(),
+    ))
+}
+
+fn map_attrs_prime(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let func = a.value(0)?;
+    let mut attrs = Attrs::new();
+    for (name, value) in a.attrs(1)?.iter() {
+        let pair = match a.call(&func, vec![Thunk::string(name.as_str()), value.clone()])? {
+            Value::Attrs(pair) => pair,
+            other => return Err(type_error("set", &other, a.span)),
+        };
+        let (Some(name), Some(value)) = (pair.get("name"), pair.get("value")) else {
+            return Err(a.invalid("expected a name-value pair"));
+        };
+        attrs.insert(a.force_string(name)?, value.clone());
+    }
+    Ok(Value::attrs(attrs))
+}
+
+fn map_attrs_to_list(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let func = a.value(0)?;
+    Ok(Value::list(
+        a.attrs(1)?
+            .iter()
+            .map(|(name, value)| {
+                a.lazy_call(&func, vec![Thunk::string(name.as_str()), value.clone()])
+            })
+            .collect(),
+    ))
+}
+
+#[allow(clippy::unnecessary_wraps)]
+fn name_value_pair(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(attrs_of([("name", a.thunk(0)), ("value", a.thunk(1))]))
+}
+
+fn filter_attrs(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let pred = a.value(0)?;
+    let mut attrs = Attrs::new();
+    for (name, value) in a.attrs(1)?.iter() {
+        if a.call_bool(&pred, vec![Thunk::string(name.as_str()), value.clone()])? {
+            attrs.insert(name.clone(), value.clone());
+        }
+    }
+    Ok(Value::attrs(attrs))
+}
+
+/// Follow an attribute path, `None` if some attribute is missing
+fn select_path(a: &Args<'_, '_>, path: &[String], set: Value) -> EvalResult<Option<Value>> {
+    let mut value = set;
+    for name in path {
+        let next = match &value {
+            Value::Attrs(attrs) => attrs.get(name).cloned(),
+            _ => None,
+        };
+        match next {
+            Some(thunk) => value = a.force(&thunk)?,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(value))
+}
+
+fn attr_by_path(a: &Args<'_, '_>) -> EvalResult<Value> {
+    match select_path(a, &a.strings(0)?, a.value(2)?)? {
+        Some(value) => Ok(value),
+        None => a.value(1),
+    }
+}
+
+fn get_attr_from_path(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let path = a.strings(0)?;
+    select_path(a, &path, a.value(1)?)?.ok_or_else(|| EvalError::MissingAttribute {
+        name: path.join("."),
+        span: a.span,
+    })
+}
+
+fn has_attr_by_path(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let found = select_path(a, &a.strings(0)?, a.value(1)?)?;
+    Ok(Value::Bool(found.is_some()))
+}
+
+fn set_attr_by_path(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let mut value = a.thunk(1);
+    for name in a.strings(0)?.into_iter().rev() {
+        value = Thunk::value(Value::attrs(Attrs::from([(name, value)])));
+    }
+    a.force(&value)
+}
+
+fn recursive_update(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let (lhs, rhs) = (a.attrs(0)?, a.attrs(1)?);
+    let merged = merge_recursive(a, &lhs, &rhs)?;
+    Ok(Value::attrs(merged))
+}
+
+fn merge_recursive(a: &Args<'_, '_>, lhs: &Attrs, rhs: &Attrs) -> EvalResult<Attrs> {
+    let _depth = a.machine.enter(a.span)?;
+    let mut merged = lhs.clone();
+    for (name, right) in rhs {
+        let value = match lhs.get(name) {
+            Some(left) => match (a.force(left)?, a.force(right)?) {
+                (Value::Attrs(l), Value::Attrs(r)) => {
+                    Thunk::value(Value::attrs(merge_recursive(a, &l, &r)?))
+                }
+                _ => right.clone(),
+            },
+            None => right.clone(),
+        };
+        merged.insert(name.clone(), value);
+    }
+    Ok(merged)
+}
+
+fn optional_attrs(a: &Args<'_, '_>) -> EvalResult<Value> {
+    if a.bool(0)? {
+        a.value(1)
+    } else {
+        Ok(Value::attrs(Attrs::new()))
+    }
+}
+
+fn optional(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let items = if a.bool(0)? {
+        vec![a.thunk(1)]
+    } else {
+        Vec::new()
+    };
+    Ok(Value::list(items))
+}
+
+fn optionals(a: &Args<'_, '_>) -> EvalResult<Value> {
+    if a.bool(0)? {
+        a.value(1)
+    } else {
+        Ok(Value::list(Vec::new()))
+    }
+}
+
+fn flatten(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let mut items = Vec::new();
+    flatten_into(a, &a.thunk(0), &mut items)?;
+    Ok(Value::list(items))
+}
+
+fn flatten_into(a: &Args<'_, '_>, thunk: &Thunk, items: &mut Vec<Thunk>) -> EvalResult<()> {
+    let _depth = a.machine.enter(a.span)?;
+    if let Value::List(inner) = a.force(thunk)? {
+        for item in inner.iter() {
+            flatten_into(a, item, items)?;
+        }
+    } else {
+        a.check_size(items.len() + 1)?;
+        items.push(thunk.clone());
+    }
+    Ok(())
+}
+
+fn unique(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let mut seen: Vec<(Value, Thunk)> = Vec::new();
+    for item in a.list(0)?.iter() {
+        let value = a.force(item)?;
+        let mut duplicate = false;
+        for (other, _) in &seen {
+            if a.machine.equal(&value, other)? {
+                duplicate = true;
+                break;
+            }
+        }
+        if !duplicate {
+            seen.push((value, item.clone()));
+        }
+    }
+    Ok(Value::list(
+        seen.into_iter().map(|(_, item)| item).collect(),
+    ))
+}
+
+fn range(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let (first, last) = (a.int(0)?, a.int(1)?);
+    if last >= first {
+        let len = last.abs_diff(first).saturating_add(1);
+        a.check_size(usize::try_from(len).unwrap_or(usize::MAX))?;
+    }
+    Ok(Value::list(
+        (first..=last)
+            .map(|i| Thunk::value(Value::Int(i)))
+            .collect(),
+    ))
+}
+
+fn foldr(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let op = a.value(0)?;
+    let mut acc = a.value(1)?;
+    for item in a.list(2)?.iter().rev() {
+        acc = a.call(&op, vec![item.clone(), Thunk::value(acc)])?;
+    }
+    Ok(acc)
+}
+
+fn imap(a: &Args<'_, '_>, offset: usize) -> EvalResult<Value> {
+    let func = a.value(0)?;
+    Ok(Value::list(
+        a.list(1)?
+            .iter()
+            .enumerate()
+            .map(|(i, item)| a.lazy_call(&func, vec![Thunk::value(int(i + offset)), item.clone()]))
+            .collect(),
+    ))
+}
+
+fn imap0(a: &Args<'_, '_>) -> EvalResult<Value> {
+    imap(a, 0)
+}
+
+fn imap1(a: &Args<'_, '_>) -> EvalResult<Value> {
+    imap(a, 1)
+}
+
+fn last(a: &Args<'_, '_>) -> EvalResult<Value> {
+    match a.list(0)?.last() {
+        Some(item) => a.force(item),
+        None => Err(EvalError::IndexOutOfBounds {
+            index: 0,
+            length: 0,
+            span: a.span,
+        }),
+    }
+}
+
+fn zip_lists_with(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let func = a.value(0)?;
+    let (left, right) = (a.list(1)?, a.list(2)?);
+    Ok(Value::list(
+        left.iter()
+            .zip(right.iter())
+            .map(|(l, r)| a.lazy_call(&func, vec![l.clone(), r.clone()]))
+            .collect(),
+    ))
+}
+
+fn optional_string(a: &Args<'_, '_>) -> EvalResult<Value> {
+    if a.bool(0)? {
+        a.string(1).map(Value::String)
+    } else {
+        Ok(Value::String(String::new()))
+    }
+}
+
+fn concat_strings(a: &Args<'_, '_>) -> EvalResult<Value> {
+    a.join(&a.strings(0)?, "")
+}
+
+fn mapped_strings(a: &Args<'_, '_>, func: usize, list: usize) -> EvalResult<Vec<String>> {
+    let func = a.value(func)?;
+    let mut strings = Vec::new();
+    for item in a.list(list)?.iter() {
+        let value = a.call(&func, vec![item.clone()])?;
+        strings.push(a.machine.coerce_to_string(&value, a.span, false)?);
+    }
+    Ok(strings)
+}
+
+fn concat_map_strings(a: &Args<'_, '_>) -> EvalResult<Value> {
+    a.join(&mapped_strings(a, 0, 1)?, "")
+}
+
+fn concat_map_strings_sep(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let sep = a.string(0)?;
+    a.join(&mapped_strings(a, 1, 2)?, &sep)
+}
+
+fn to_upper(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(Value::String(a.string(0)?.to_uppercase()))
+}
+
+fn to_lower(a: &Args<'_, '_>) -> EvalResult<Value> {
+    Ok(Value::String(a.string(0)?.to_lowercase()))
+}
+
+fn has_prefix(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let prefix = a.string(0)?;
+    Ok(Value::Bool(a.string(1)?.starts_with(&prefix)))
+}
+
+fn has_suffix(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let suffix = a.string(0)?;
+    Ok(Value::Bool(a.string(1)?.ends_with(&suffix)))
+}
+
+fn remove_prefix(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let prefix = a.string(0)?;
+    let s = a.string(1)?;
+    Ok(Value::String(
+        s.strip_prefix(prefix.as_str()).unwrap_or(&s).to_string(),
+    ))
+}
+
+fn remove_suffix(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let suffix = a.string(0)?;
+    let s = a.string(1)?;
+    Ok(Value::String(
+        s.strip_suffix(suffix.as_str()).unwrap_or(&s).to_string(),
+    ))
+}
+
+fn split_string(a: &Args<'_, '_>) -> EvalResult<Value> {
+    let sep = a.string(0)?;
+    let s = a.string(1)?;
+    if sep.is_empty() {
+        return Err(a.invalid("empty separator"));
+    }
+    Ok(Value::list(
+        s.split(sep.as_str()).map(Thunk::string).collect(),
+    ))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_components() {
+        assert_eq!(
+            version_components("1.2pre3-rc"),
+            ["1", "2", "pre", "3", "rc"]
+        );
+        assert!(component_less("pre", "0"));
+        assert!(component_less("2", "10"));
+        assert!(component_less("a", "1"));
+    }
+
+    #[test]
+    fn test_tables_have_unique_names() {
+        for table in [BUILTINS, LIB] {
+            for (i, builtin) in table.iter().enumerate() {
+                assert!(
+                    table[i + 1..].iter().all(|b| b.name != builtin.name),
+                    "duplicate builtin {}",
+                    builtin.name
+                );
+            }
+        }
+        for name in GLOBALS.iter().chain(LIB_FROM_BUILTINS) {
+            assert!(
+                BUILTINS.iter().any(|b| b.name == *name),
+                "unknown builtin {name}"
+            );
+        }
+    }
+}
@@ -0,0 +1,1113 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! The evaluation machine
+//!
+//! Expressions are evaluated to weak head normal form; attribute values,
+//! list elements and function arguments stay [`Thunk`]s until something
+//! needs them. The result is forced completely only when it is converted
+//! to a [`NixValue`] at the end.
+
+use super::builtins;
+use super::value::{Attrs, Env, Lambda, PrimOp, Thunk, ThunkState, Value};
+use super::{EvalConfig, EvalError, EvalResult};
+use crate::nix::ast::{AstError, Span};
+use crate::nix::ast_converter::is_negated_min_int;
+use crate::nix::value_objects::{
+    NixAttrset, NixBool, NixFloat, NixInteger, NixList, NixLookupPath, NixNull, NixPath, NixString,
+    NixValue,
+};
+use rnix::ast::{self, BinOpKind, HasEntry, InterpolPart, LiteralKind, UnaryOpKind};
+use rowan::ast::AstNode;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashSet};
+use std::rc::Rc;
+
+pub(crate) fn span_of(node: &impl AstNode<Language = rnix::NixLanguage>) -> Span {
+    Span::of(node.syntax())
+}
+
+pub(crate) fn type_error(expected: &'static str, got: &Value, span: Span) -> EvalError {
+    EvalError::TypeError {
+        expected,
+        got: got.type_name(),
+        span,
+    }
+}
+
+fn ident_name(ident: &ast::Ident) -> String {
+    ident.syntax().text().to_string()
+}
+
+/// A required child node of `parent`
+fn required<T>(
+    child: Option<T>,
+    parent: &impl AstNode<Language = rnix::NixLanguage>,
+) -> EvalResult<T> {
+    child.ok_or_else(|| AstError::MissingNode(format!("expression at {}", span_of(parent))).into())
+}
+
+/// The attribute set literal bound by `expr`, if it can be merged into
+fn plain_attrset(expr: &ast::Expr) -> Option<ast::AttrSet> {
+    match expr {
+        ast::Expr::AttrSet(set) if set.rec_token().is_none() => Some(set.clone()),
+        _ => None,
+    }
+}
+
+/// An attribute while its set is being built
+enum Node {
+    /// Value expression, evaluated in the set's value scope
+    Expr(ast::Expr),
+    /// Value from an `inherit`
+    Thunk(Thunk),
+    /// Nested set merged from several definitions (`a.b = 1; a.c = 2;`)
+    Set(BTreeMap<String, Node>),
+}
+
+/// Scopes used while building an attribute set
+///
+/// For `rec` sets and `let`, values and `inherit (e)` sources see the new
+/// bindings while dynamic keys and plain `inherit` do not.
+#[derive(Clone, Copy)]
+struct Scopes<'e> {
+    keys: &'e Env,
+    values: &'e Env,
+    inherits: &'e Env,
+}
+
+impl<'e> Scopes<'e> {
+    fn same(env: &'e Env) -> Self {
+        Self {
+            keys: env,
+            values: env,
+            inherits: env,
+        }
+    }
+}
+
+/// Decrements the depth counter when an evaluation frame ends
+pub(crate) struct DepthGuard<'m>(&'m Cell<usize>);
+
+impl Drop for DepthGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
+
+/// State of a single evaluation
+pub(crate) struct Machine<'c> {
+    config: &'c EvalConfig,
+    steps: Cell<u64>,
+    depth: Cell<usize>,
+    /// Every scope created, cleared on drop to break reference cycles
+    scopes: RefCell<Vec<Env>>,
+    globals: Env,
+}
+
+impl Drop for Machine<'_> {
+    fn drop(&mut self) {
+        for env in self.scopes.borrow().iter() {
+            env.clear();
+        }
+        self.globals.clear();
+    }
+}
+
+impl<'c> Machine<'c> {
+    pub(crate) fn new(config: &'c EvalConfig) -> Self {
+        let globals = Env::root();
+        builtins::install(&globals);
+        Self {
+            config,
+            steps: Cell::new(0),
+            depth: Cell::new(0),
+            scopes: RefCell::new(Vec::new()),
+            globals,
+        }
+    }
+
+    /// Evaluate a file's root expression and force it into a [`NixValue`]
+    ///
+    /// With a `wrapper`, the file's value is passed to that function and its
+    /// result is forced instead.
+    pub(crate) fn eval_root(
+        &self,
+        expr: &ast::Expr,
+        wrapper: Option<&ast::Expr>,
+        args: &[(String, NixValue)],
+    ) -> EvalResult<NixValue> {
+        let span = span_of(expr);
+        let mut value = self.eval(expr, &self.globals)?;
+        if self.config.auto_call {
+            if let Some(call_args) = Self::auto_call_args(&value, args) {
+                value = self.apply(&value, Thunk::value(Value::attrs(call_args)), span)?;
+            }
+        }
+        if let Some(wrapper) = wrapper {
+            let func = self.eval(wrapper, &self.globals)?;
+            value = self.apply(&func, Thunk::value(value), span_of(wrapper))?;
+        }
+        self.to_nix(&value, span)
+    }
+
+    /// Arguments for a file written as `{ lib, ... }: ...`
+    ///
+    /// Only arguments the function accepts are passed, like `nix-build
+    /// --arg` does.
+    fn auto_call_args(value: &Value, args: &[(String, NixValue)]) -> Option<Attrs> {
+        let Value::Lambda(lambda) = value else {
+            return None;
+        };
+        let ast::Param::Pattern(pattern) = &lambda.param else {
+            return None;
+        };
+        let accepts_all = pattern.ellipsis_token().is_some();
+        let formals: HashSet<String> = pattern
+            .pat_entries()
+            .filter_map(|entry| entry.ident())
+            .map(|ident| ident_name(&ident))
+            .collect();
+
+        let provided = std::iter::once(("lib".to_string(), builtins::lib())).chain(
+            args.iter()
+                .map(|(name, value)| (name.clone(), Self::from_nix(value))),
+        );
+        Some(
+            provided
+                .filter(|(name, _)| accepts_all || formals.contains(name))
+                .map(|(name, value)| (name, Thunk::value(value)))
+                .collect(),
+        )
+    }
+
+    // ------------------------------------------------------------------------
+    // Limits and scopes
+    // ------------------------------------------------------------------------
+
+    /// Count one evaluation step
+    pub(crate) fn tick(&self, span: Span) -> EvalResult<()> {
+        let steps = self.steps.get() + 1;
+        if steps > self.config.max_steps {
+            return Err(EvalError::StepLimitExceeded {
+                limit: self.config.max_steps,
+                span,
+            });
+        }
+        self.steps.set(steps);
+        Ok(())
+    }
+
+    /// Enter a nested evaluation frame
+    pub(crate) fn enter(&self, span: Span) -> EvalResult<DepthGuard<'_>> {
+        let depth = self.depth.get() + 1;
+        if depth > self.config.max_depth {
+            return Err(EvalError::DepthLimitExceeded {
+                limit: self.config.max_depth,
+                span,
+            });
+        }
+        self.depth.set(depth);
+        Ok(DepthGuard(&self.depth))
+    }
+
+    /// Fail if a list or string of `len` would exceed the size limit
+    pub(crate) fn check_size(&self, len: usize, span: Span) -> EvalResult<()> {
+        if len > self.config.max_size {
+            return Err(EvalError::SizeLimitExceeded {
+                limit: self.config.max_size,
+                span,
+            });
+        }
+        Ok(())
+    }
+
+    /// A new registered scope nested in `parent`
+    fn scope(&self, parent: &Env) -> Env {
+        let env = parent.child();
+        self.scopes.borrow_mut().push(env.clone());
+        env
+    }
+
+    // ------------------------------------------------------------------------
+    // Forcing and calling
+    // ------------------------------------------------------------------------
+
+    /// Evaluate a thunk to weak head normal form, caching the result
+    pub(crate) fn force(&self, thunk: &Thunk) -> EvalResult<Value> {
+        let span = thunk.origin().unwrap_or(Span::new(0, 0));
+        let state = thunk.replace(ThunkState::Forcing(span));
+        let result = match &state {
+            ThunkState::Done(value) => {
+                let value = value.clone();
+                thunk.replace(state);
+                return Ok(value);
+            }
+            ThunkState::Forcing(span) => {
+                let span = *span;
+                thunk.replace(state);
+                return Err(EvalError::InfiniteRecursion { span });
+            }
+            ThunkState::Deferred { expr, env } => self.eval(expr, env),
+            ThunkState::Call { func, args, span } => self.call(func, args.clone(), *span),
+            ThunkState::Select { from, name, span } => {
+                self.force_attrs(from, *span)
+                    .and_then(|attrs| match attrs.get(name) {
+                        Some(value) => self.force(value),
+                        None => Err(EvalError::MissingAttribute {
+                            name: name.clone(),
+                            span: *span,
+                        }),
+                    })
+            }
+        };
+        match result {
+            Ok(value) => {
+                thunk.replace(ThunkState::Done(value.clone()));
+                Ok(value)
+            }
+            Err(err) => {
+                // Leave the thunk as it was so `tryEval` can retry it
+                thunk.replace(state);
+                Err(err)
+            }
+        }
+    }
+
+    pub(crate) fn force_attrs(&self, thunk: &Thunk, span: Span) -> EvalResult<Rc<Attrs>> {
+        match self.force(thunk)? {
+            Value::Attrs(attrs) => Ok(attrs),
+            other => Err(type_error("set", &other, thunk.origin().unwrap_or(span))),
+        }
+    }
+
+    /// Force a value and everything it contains
+    pub(crate) fn deep_force(&self, value: &Value, span: Span) -> EvalResult<()> {
+        let _depth = self.enter(span)?;
+        let items: Vec<Thunk> = match value {
+            Value::List(items) => items.iter().cloned().collect(),
+            Value::Attrs(attrs) => attrs.values().cloned().collect(),
+            _ => return Ok(()),
+        };
+        for item in &items {
+            let value = self.force(item)?;
+            self.deep_force(&value, item.origin().unwrap_or(span))?;
+        }
+        Ok(())
+    }
+
+    /// Apply a function to one argument
+    pub(crate) fn apply(&self, func: &Value, arg: Thunk, span: Span) -> EvalResult<Value> {
+        self.tick(span)?;
+        let _depth = self.enter(span)?;
+        match func {
+            Value::Lambda(lambda) => self.apply_lambda(lambda, arg, span),
+            Value::PrimOp(primop) => {
+                let mut args = primop.args.clone();
+                args.push(arg);
+                if args.len() < primop.builtin.arity {
+                    Ok(Value::PrimOp(Rc::new(PrimOp {
+                        builtin: primop.builtin,
+                        args,
+                    })))
+                } else {
+                    primop.builtin.call(self, &args, span)
+                }
+            }
+            Value::Attrs(attrs) => match attrs.get("__functor") {
+                Some(functor) => {
+                    let functor = self.force(functor)?;
+                    let bound = self.apply(&functor, Thunk::value(func.clone()), span)?;
+                    self.apply(&bound, arg, span)
+                }
+                None => Err(type_error("function", func, span)),
+            },
+            other => Err(type_error("function", other, span)),
+        }
+    }
+
+    /// Apply a function to several arguments in turn
+    pub(crate) fn call(&self, func: &Value, args: Vec<Thunk>, span: Span) -> EvalResult<Value> {
+        let mut value = func.clone();
+        for arg in args {
+            value = self.apply(&value, arg, span)?;
+        }
+        Ok(value)
+    }
+
+    fn apply_lambda(&self, lambda: &Lambda, arg: Thunk, span: Span) -> EvalResult<Value> {
+        let env = self.scope(&lambda.env);
+        match &lambda.param {
+            ast::Param::IdentParam(param) => {
+                env.define(ident_name(&required(param.ident(), param)?), arg);
+            }
+            ast::Param::Pattern(pattern) => {
+                let attrs = self.force_attrs(&arg, span)?;
+                if let Some(bind) = pattern.pat_bind() {
+                    env.define(ident_name(&required(bind.ident(), &bind)?), arg.clone());
+                }
+                let mut formals = HashSet::new();
+                for entry in pattern.pat_entries() {
+                    let name = ident_name(&required(entry.ident(), &entry)?);
+                    let thunk = match (attrs.get(&name), entry.default()) {
+                        (Some(value), _) => value.clone(),
+                        (None, Some(default)) => Thunk::deferred(default, env.clone()),
+                        (None, None) => return Err(EvalError::MissingArgument { name, span }),
+                    };
+                    env.define(name.clone(), thunk);
+                    formals.insert(name);
+                }
+                if pattern.ellipsis_token().is_none() {
+                    if let Some(name) = attrs.keys().find(|name| !formals.contains(*name)) {
+                        return Err(EvalError::UnexpectedArgument {
+                            name: name.clone(),
+                            span,
+                        });
+                    }
+                }
+            }
+        }
+        self.eval(&lambda.body, &env)
+    }
+
+    // ------------------------------------------------------------------------
+    // Expressions
+    // ------------------------------------------------------------------------
+
+    /// Evaluate an expression to weak head normal form
+    pub(crate) fn eval(&self, expr: &ast::Expr, env: &Env) -> EvalResult<Value> {
+        let span = span_of(expr);
+        self.tick(span)?;
+        let _depth = self.enter(span)?;
+        match expr {
+            ast::Expr::Literal(literal) => Self::eval_literal(literal),
+            ast::Expr::Str(string) => self.eval_string(string, env).map(Value::String),
+            ast::Expr::Path(path) => self.eval_path(path, env),
+            ast::Expr::Ident(ident) => self.eval_ident(ident, env),
+            ast::Expr::AttrSet(set) => self.eval_attrset(set, env),
+            ast::Expr::List(list) => Ok(Value::list(
+                list.items()
+                    .map(|item| Thunk::deferred(item, env.clone()))
+                    .collect(),
+            )),
+            ast::Expr::LetIn(let_in) => {
+                let inner = self.bind_recursive(let_in, env)?;
+                self.eval(&required(let_in.body(), let_in)?, &inner)
+            }
+            ast::Expr::LegacyLet(legacy) => {
+                let inner = self.bind_recursive(legacy, env)?;
+                match inner.lookup("body") {
+                    Some(body) => self.force(&body),
+                    None => Err(EvalError::MissingAttribute {
+                        name: "body".to_string(),
+                        span,
+                    }),
+                }
+            }
+            ast::Expr::With(with) => {
+                let namespace = Thunk::deferred(required(with.namespace(), with)?, env.clone());
+                self.eval(&required(with.body(), with)?, &env.with(namespace))
+            }
+            ast::Expr::Select(select) => self.eval_select(select, env),
+            ast::Expr::HasAttr(has_attr) => self.eval_has_attr(has_attr, env),
+            ast::Expr::Apply(apply) => {
+                let func = self.eval(&required(apply.lambda(), apply)?, env)?;
+                let arg = Thunk::deferred(required(apply.argument(), apply)?, env.clone());
+                self.apply(&func, arg, span)
+            }
+            ast::Expr::Lambda(lambda) => Ok(Value::Lambda(Rc::new(Lambda {
+                param: required(lambda.param(), lambda)?,
+                body: required(lambda.body(), lambda)?,
+                env: env.clone(),
+            }))),
+            ast::Expr::IfElse(if_else) => {
+                let condition = self.eval_bool(&required(if_else.condition(), if_else)?, env)?;
+                let branch = if condition {
+                    if_else.body()
+                } else {
+                    if_else.else_body()
+                };
+                self.eval(&required(branch, if_else)?, env)
+            }
+            ast::Expr::Assert(assert) => {
+                let condition = required(assert.condition(), assert)?;
+                if self.eval_bool(&condition, env)? {
+                    self.eval(&required(assert.body(), assert)?, env)
+                } else {
+                    Err(EvalError::AssertionFailed {
+                        span: span_of(&condition),
+                    })
+                }
+            }
+            ast::Expr::UnaryOp(unary) => self.eval_unary(unary, env),
+            ast::Expr::BinOp(binop) => self.eval_binop(binop, env),
+            ast::Expr::Paren(paren) => self.eval(&required(paren.expr(), paren)?, env),
+            ast::Expr::Root(root) => self.eval(&required(root.expr(), root)?, env),
+            ast::Expr::Error(_) => {
+                Err(AstError::InvalidSyntax(format!("syntax error at {span}")).into())
+            }
+        }
+    }
+
+    fn eval_bool(&self, expr: &ast::Expr, env: &Env) -> EvalResult<bool> {
+        match self.eval(expr, env)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(type_error("bool", &other, span_of(expr))),
+        }
+    }
+
+    fn eval_literal(literal: &ast::Literal) -> EvalResult<Value> {
+        let invalid = || EvalError::InvalidValue {
+            text: literal.syntax().text().to_string(),
+            span: span_of(literal),
+        };
+        match literal.kind() {
+            LiteralKind::Integer(i) => i.value().map(Value::Int).map_err(|_| invalid()),
+            LiteralKind::Float(f) => f.value().map(Value::Float).map_err(|_| invalid()),
+            LiteralKind::Uri(_) => Ok(Value::String(literal.syntax().text().to_string())),
+        }
+    }
+
+    fn eval_string(&self, string: &ast::Str, env: &Env) -> EvalResult<String> {
+        let mut content = String::new();
+        for part in string.normalized_parts() {
+            match part {
+                InterpolPart::Literal(text) => content.push_str(&text),
+                InterpolPart::Interpolation(interpol) => {
+                    let text = self.interpolate(&interpol, env)?;
+                    self.check_size(content.len() + text.len(), span_of(string))?;
+                    content.push_str(&text);
+                }
+            }
+        }
+        Ok(content)
+    }
+
+    fn interpolate(&self, interpol: &ast::Interpol, env: &Env) -> EvalResult<String> {
+        let value = self.eval(&required(interpol.expr(), interpol)?, env)?;
+        self.coerce_to_string(&value, span_of(interpol), false)
+    }
+
+    /// Evaluate a path (`./foo`, `/etc`) or lookup path (`<nixpkgs>`)
+    ///
+    /// Paths are kept as written; they are never resolved against the
+    /// filesystem.
+    fn eval_path(&self, path: &ast::Path, env: &Env) -> EvalResult<Value> {
+        let mut text = String::new();
+        for part in path.parts() {
+            match part {
+                InterpolPart::Literal(content) => {
+                    text.push_str(rnix::ast::AstToken::syntax(&content).text());
+                }
+                InterpolPart::Interpolation(interpol) => {
+                    text.push_str(&self.interpolate(&interpol, env)?);
+                }
+            }
+        }
+        Ok(
+            match text.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+                Some(name) => Value::LookupPath(name.to_string()),
+                None => Value::Path(text),
+            },
+        )
+    }
+
+    fn eval_ident(&self, ident: &ast::Ident, env: &Env) -> EvalResult<Value> {
+        let name = ident_name(ident);
+        if let Some(thunk) = env.lookup(&name) {
+            return self.force(&thunk);
+        }
+        let span = span_of(ident);
+        for namespace in env.with_scopes() {
+            if let Some(thunk) = self.force_attrs(&namespace, span)?.get(&name) {
+                return self.force(thunk);
+            }
+        }
+        Err(EvalError::UndefinedVariable { name, span })
+    }
+
+    // ------------------------------------------------------------------------
+    // Attribute sets and let
+    // ------------------------------------------------------------------------
+
+    fn eval_attrset(&self, set: &ast::AttrSet, env: &Env) -> EvalResult<Value> {
+        if set.rec_token().is_none() {
+            let nodes = self.collect_entries(set, Scopes::same(env))?;
+            return Ok(Value::attrs(Self::materialize(nodes, env)));
+        }
+        let inner = self.scope(env);
+        let attrs = self.bind(set, env, &inner)?;
+        Ok(Value::attrs(attrs))
+    }
+
+    /// Bindings of a `let` in a new recursive scope
+    fn bind_recursive(&self, node: &impl HasEntry, env: &Env) -> EvalResult<Env> {
+        let inner = self.scope(env);
+        self.bind(node, env, &inner)?;
+        Ok(inner)
+    }
+
+    /// Define the entries of `node` in `inner`, which is nested in `outer`
+    fn bind(&self, node: &impl HasEntry, outer: &Env, inner: &Env) -> EvalResult<Attrs> {
+        let scopes = Scopes {
+            keys: outer,
+            values: inner,
+            inherits: outer,
+        };
+        let attrs = Self::materialize(self.collect_entries(node, scopes)?, inner);
+        for (name, thunk) in &attrs {
+            inner.define(name.clone(), thunk.clone());
+        }
+        Ok(attrs)
+    }
+
+    fn collect_entries(
+        &self,
+        node: &impl HasEntry,
+        scopes: Scopes<'_>,
+    ) -> EvalResult<BTreeMap<String, Node>> {
+        let mut nodes = BTreeMap::new();
+        for entry in node.entries() {
+            self.add_entry(&mut nodes, &entry, scopes)?;
+        }
+        Ok(nodes)
+    }
+
+    fn add_entry(
+        &self,
+        nodes: &mut BTreeMap<String, Node>,
+        entry: &ast::Entry,
+        scopes: Scopes<'_>,
+    ) -> EvalResult<()> {
+        match entry {
+            ast::Entry::AttrpathValue(binding) => {
+                let attrpath = required(binding.attrpath(), binding)?;
+                let mut path = Vec::new();
+                for attr in attrpath.attrs() {
+                    match self.attr_name(&attr, scopes.keys)? {
+                        Some(name) => path.push((name, span_of(&attr))),
+                        // `${null} = ...;` defines nothing
+                        None => return Ok(()),
+                    }
+                }
+                let value = required(binding.value(), binding)?;
+                self.insert_node(nodes, &path, Node::Expr(value), scopes)
+            }
+            ast::Entry::Inherit(inherit) => {
+                let from = match inherit.from() {
+                    Some(from) => Some(Thunk::deferred(
+                        required(from.expr(), &from)?,
+                        scopes.values.clone(),
+                    )),
+                    None => None,
+                };
+                for attr in inherit.attrs() {
+                    let span = span_of(&attr);
+                    let thunk = match (&from, &attr) {
+                        (Some(from), _) => {
+                            let name = self.attr_name(&attr, scopes.keys)?.ok_or_else(|| {
+                                EvalError::InvalidValue {
+                                    text: attr.syntax().text().to_string(),
+                                    span,
+                                }
+                            })?;
+                            Thunk::select(from.clone(), name, span)
+                        }
+                        (None, ast::Attr::Ident(ident)) => Thunk::deferred(
+                            ast::Expr::Ident(ident.clone()),
+                            scopes.inherits.clone(),
+                        ),
+                        (None, _) => {
+                            return Err(EvalError::InvalidValue {
+                                text: attr.syntax().text().to_string(),
+                                span,
+                            })
+                        }
+                    };
+                    let name = match &attr {
+                        ast::Attr::Ident(ident) => ident_name(ident),
+                        _ => self.attr_name(&attr, scopes.keys)?.unwrap_or_default(),
+                    };
+                    if nodes.contains_key(&name) {
+                        return Err(EvalError::DuplicateAttribute { name, span });
+                    }
+                    nodes.insert(name, Node::Thunk(thunk));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn insert_node(
+        &self,
+        nodes: &mut BTreeMap<String, Node>,
+        path: &[(String, Span)],
+        node: Node,
+        scopes: Scopes<'_>,
+    ) -> EvalResult<()> {
+        let Some(((name, span), rest)) = path.split_first() else {
+            return Ok(());
+        };
+        let nested = Scopes::same(scopes.values);
+
+        if !rest.is_empty() {
+            let existing = nodes
+                .entry(name.clone())
+                .or_insert_with(|| Node::Set(BTreeMap::new()));
+            let children = self.expand(existing, name, *span, nested)?;
+            return self.insert_node(children, rest, node, nested);
+        }
+
+        let Some(existing) = nodes.get_mut(name) else {
+            nodes.insert(name.clone(), node);
+            return Ok(());
+        };
+        // `a = { x = 1; }; a.y = 2;` and `a.y = 2; a = { x = 1; };` merge
+        let set = match &node {
+            Node::Expr(expr) => plain_attrset(expr),
+            _ => None,
+        };
+        let Some(set) = set else {
+            return Err(EvalError::DuplicateAttribute {
+                name: name.clone(),
+                span: *span,
+            });
+        };
+        let children = self.expand(existing, name, *span, nested)?;
+        for entry in set.entries() {
+            self.add_entry(children, &entry, nested)?;
+        }
+        Ok(())
+    }
+
+    /// Turn an existing attribute into a nested set that can be merged into
+    fn expand<'n>(
+        &self,
+        existing: &'n mut Node,
+        name: &str,
+        span: Span,
+        scopes: Scopes<'_>,
+    ) -> EvalResult<&'n mut BTreeMap<String, Node>> {
+        if let Node::Expr(expr) = existing {
+            if let Some(set) = plain_attrset(expr) {
+                *existing = Node::Set(self.collect_entries(&set, scopes)?);
+            }
+        }
+        match existing {
+            Node::Set(children) => Ok(children),
+            _ => Err(EvalError::DuplicateAttribute {
+                name: name.to_string(),
+                span,
+            }),
+        }
+    }
+
+    fn materialize(nodes: BTreeMap<String, Node>, env: &Env) -> Attrs {
+        nodes
+            .into_iter()
+            .map(|(name, node)| {
+                let thunk = match node {
+                    Node::Expr(expr) => Thunk::deferred(expr, env.clone()),
+                    Node::Thunk(thunk) => thunk,
+                    Node::Set(children) => {
+                        Thunk::value(Value::attrs(Self::materialize(children, env)))
+                    }
+                };
+                (name, thunk)
+            })
+            .collect()
+    }
+
+    /// Name of an attribute; `None` for a dynamic attribute that is `null`
+    fn attr_name(&self, attr: &ast::Attr, env: &Env) -> EvalResult<Option<String>> {
+        match attr {
+            ast::Attr::Ident(ident) => Ok(Some(ident_name(ident))),
+            ast::Attr::Str(string) => self.eval_string(string, env).map(Some),
+            ast::Attr::Dynamic(dynamic) => {
+                match self.eval(&required(dynamic.expr(), dynamic)?, env)? {
+                    Value::String(name) => Ok(Some(name)),
+                    Value::Null => Ok(None),
+                    other => Err(type_error("string", &other, span_of(dynamic))),
+                }
+            }
+        }
+    }
+
+    fn eval_select(&self, select: &ast::Select, env: &Env) -> EvalResult<Value> {
+        let mut value = self.eval(&required(select.expr(), select)?, env)?;
+        for attr in required(select.attrpath(), select)?.attrs() {
+            let name = self.attr_name(&attr, env)?;
+            let found = match (&value, &name) {
+                (Value::Attrs(attrs), Some(name)) => attrs.get(name).cloned(),
+                _ => None,
+            };
+            if let Some(thunk) = found {
+                value = self.force(&thunk)?;
+                continue;
+            }
+            if let Some(default) = select.default_expr() {
+                return self.eval(&default, env);
+            }
+            let span = span_of(&attr);
+            return Err(match value {
+                Value::Attrs(_) => EvalError::MissingAttribute {
+                    name: name.unwrap_or_default(),
+                    span,
+                },
+                other => type_error("set", &other, span),
+            });
+        }
+        Ok(value)
+    }
+
+    fn eval_has_attr(&self, has_attr: &ast::HasAttr, env: &Env) -> EvalResult<Value> {
+        let mut value = self.eval(&required(has_attr.expr(), has_attr)?, env)?;
+        for attr in required(has_attr.attrpath(), has_attr)?.attrs() {
+            let found = match (&value, self.attr_name(&attr, env)?) {
+                (Value::Attrs(attrs), Some(name)) => attrs.get(&name).cloned(),
+                _ => None,
+            };
+            match found {
+                Some(thunk) => value = self.force(&thunk)?,
+                None => return Ok(Value::Bool(false)),
+            }
+        }
+        Ok(Value::Bool(true))
+    }
+
+    // ------------------------------------------------------------------------
+    // Operators
+    // ------------------------------------------------------------------------
+
+    fn eval_unary(&self, unary: &ast::UnaryOp, env: &Env) -> EvalResult<Value> {
+        let span = span_of(unary);
+        if is_negated_min_int(unary) {
+            return Ok(Value::Int(i64::MIN));
+        }
+        let operand = self.eval(&required(unary.expr(), unary)?, env)?;
+        match (unary.operator(), operand) {
+            (Some(UnaryOpKind::Negate), Value::Int(i)) => i
+                .checked_neg()
+                .map(Value::Int)
+                .ok_or(EvalError::IntegerOverflow { span }),
+            (Some(UnaryOpKind::Negate), Value::Float(f)) => Ok(Value::Float(-f)),
+            (Some(UnaryOpKind::Negate), other) => Err(type_error("number", &other, span)),
+            (Some(UnaryOpKind::Invert), Value::Bool(b)) => Ok(Value::Bool(!b)),
+            (Some(UnaryOpKind::Invert), other) => Err(type_error("bool", &other, span)),
+            (None, _) => Err(AstError::MissingNode(format!("operator at {span}")).into()),
+        }
+    }
+
+    fn eval_binop(&self, binop: &ast::BinOp, env: &Env) -> EvalResult<Value> {
+        let span = span_of(binop);
+        let operator = binop
+            .operator()
+            .ok_or_else(|| AstError::MissingNode(format!("operator at {span}")))?;
+        let lhs = required(binop.lhs(), binop)?;
+        let rhs = required(binop.rhs(), binop)?;
+
+        // Logical operators short-circuit
+        match operator {
+            BinOpKind::And => {
+                return Ok(Value::Bool(
+                    self.eval_bool(&lhs, env)? && self.eval_bool(&rhs, env)?,
+                ))
+            }
+            BinOpKind::Or => {
+                return Ok(Value::Bool(
+                    self.eval_bool(&lhs, env)? || self.eval_bool(&rhs, env)?,
+                ))
+            }
+            BinOpKind::Implication => {
+                return Ok(Value::Bool(
+                    !self.eval_bool(&lhs, env)? || self.eval_bool(&rhs, env)?,
+                ))
+            }
+            _ => {}
+        }
+
+        let left = self.eval(&lhs, env)?;
+        let right = self.eval(&rhs, env)?;
+        match operator {
+            BinOpKind::Equal => Ok(Value::Bool(self.equal(&left, &right)?)),
+            BinOpKind::NotEqual => Ok(Value::Bool(!self.equal(&left, &right)?)),
+            BinOpKind::Less => Ok(Value::Bool(self.less_than(&left, &right, span)?)),
+            BinOpKind::LessOrEq => Ok(Value::Bool(!self.less_than(&right, &left, span)?)),
+            BinOpKind::More => Ok(Value::Bool(self.less_than(&right, &left, span)?)),
+            BinOpKind::MoreOrEq => Ok(Value::Bool(!self.less_than(&left, &right, span)?)),
+            BinOpKind::Concat => match (left, right) {
+                (Value::List(l), Value::List(r)) => {
+                    self.check_size(l.len() + r.len(), span)?;
+                    Ok(Value::list(l.iter().chain(r.iter()).cloned().collect()))
+                }
+                (Value::List(_), other) | (other, _) => Err(type_error("list", &other, span)),
+            },
+            BinOpKind::Update => match (left, right) {
+                (Value::Attrs(l), Value::Attrs(r)) => {
+                    let mut attrs = (*l).clone();
+                    attrs.extend(r.iter().map(|(k, v)| (k.clone(), v.clone())));
+                    Ok(Value::attrs(attrs))
+                }
+                (Value::Attrs(_), other) | (other, _) => Err(type_error("set", &other, span)),
+            },
+            BinOpKind::Add => self.add(left, right, span),
+            _ => Self::arithmetic(operator, &left, &right, span),
+        }
+    }
+
+    /// `+` on numbers, strings and paths
+    pub(crate) fn add(&self, left: Value, right: Value, span: Span) -> EvalResult<Value> {
+        match (left, right) {
+            (Value::String(l), right @ (Value::String(_) | Value::Attrs(_))) => {
+                let right = self.coerce_to_string(&right, span, false)?;
+                self.check_size(l.len() + right.len(), span)?;
+                Ok(Value::String(l + &right))
+            }
+            (Value::Path(l), right @ (Value::String(_) | Value::Attrs(_))) => Ok(Value::Path(
+                l + &self.coerce_to_string(&right, span, false)?,
+            )),
+            (Value::Path(l), Value::Path(r)) => Ok(Value::Path(l + &r)),
+            (left @ (Value::Int(_) | Value::Float(_)), right) => {
+                Self::arithmetic(BinOpKind::Add, &left, &right, span)
+            }
+            (Value::String(_), other) => Err(type_error("string", &other, span)),
+            (Value::Path(_), other) => Err(type_error("string or path", &other, span)),
+            (other, _) => Err(type_error("number, string or path", &other, span)),
+        }
+    }
+
+    /// `+`, `-`, `*` and `/` on numbers
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn arithmetic(
+        operator: BinOpKind,
+        left: &Value,
+        right: &Value,
+        span: Span,
+    ) -> EvalResult<Value> {
+        let overflow = EvalError::IntegerOverflow { span };
+        match (left, right) {
+            (Value::Int(l), Value::Int(r)) => {
+                let result = match operator {
+                    BinOpKind::Add => l.checked_add(*r),
+                    BinOpKind::Sub => l.checked_sub(*r),
+                    BinOpKind::Mul => l.checked_mul(*r),
+                    _ if *r == 0 => return Err(EvalError::DivisionByZero { span }),
+                    _ => l.checked_div(*r),
+                };
+                result.map(Value::Int).ok_or(overflow)
+            }
+            (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => {
+                let as_float = |v: &Value| match v {
+                    Value::Int(i) => *i as f64,
+                    Value::Float(f) => *f,
+                    _ => unreachable!("checked by the enclosing match"),
+                };
+                let (l, r) = (as_float(left), as_float(right));
+                Ok(Value::Float(match operator {
+                    BinOpKind::Add => l + r,
+                    BinOpKind::Sub => l - r,
+                    BinOpKind::Mul => l * r,
+                    _ if r == 0.0 => return Err(EvalError::DivisionByZero { span }),
+                    _ => l / r,
+                }))
+            }
+            (Value::Int(_) | Value::Float(_), other) | (other, _) => {
+                Err(type_error("number", other, span))
+            }
+        }
+    }
+
+    /// Deep equality as `==` defines it
+    #[allow(clippy::cast_precision_loss, clippy::float_cmp)]
+    pub(crate) fn equal(&self, left: &Value, right: &Value) -> EvalResult<bool> {
+        Ok(match (left, right) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Int(l), Value::Int(r)) => l == r,
+            (Value::Int(l), Value::Float(r)) => *l as f64 == *r,
+            (Value::Float(l), Value::Int(r)) => *l == *r as f64,
+            (Value::Float(l), Value::Float(r)) => l == r,
+            (Value::String(l), Value::String(r))
+            | (Value::Path(l), Value::Path(r))
+            | (Value::LookupPath(l), Value::LookupPath(r)) => l == r,
+            (Value::List(l), Value::List(r)) => {
+                if l.len() != r.len() {
+                    return Ok(false);
+                }
+                for (l, r) in l.iter().zip(r.iter()) {
+                    if !self.equal(&self.force(l)?, &self.force(r)?)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            (Value::Attrs(l), Value::Attrs(r)) => {
+                if l.len() != r.len() || !l.keys().eq(r.keys()) {
+                    return Ok(false);
+                }
+                for (l, r) in l.values().zip(r.values()) {
+                    if !self.equal(&self.force(l)?, &self.force(r)?)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            _ => false,
+        })
+    }
+
+    /// `<` on numbers, strings, paths and lists
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn less_than(&self, left: &Value, right: &Value, span: Span) -> EvalResult<bool> {
+        match (left, right) {
+            (Value::Int(l), Value::Int(r)) => Ok(l < r),
+            (Value::Int(l), Value::Float(r)) => Ok((*l as f64) < *r),
+            (Value::Float(l), Value::Int(r)) => Ok(*l < *r as f64),
+            (Value::Float(l), Value::Float(r)) => Ok(l < r),
+            (Value::String(l), Value::String(r)) | (Value::Path(l), Value::Path(r)) => Ok(l < r),
+            (Value::List(l), Value::List(r)) => {
+                for (l, r) in l.iter().zip(r.iter()) {
+                    let (l, r) = (self.force(l)?, self.force(r)?);
+                    if self.less_than(&l, &r, span)? {
+                        return Ok(true);
+                    }
+                    if !self.equal(&l, &r)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(l.len() < r.len())
+            }
+            (
+                Value::Int(_)
+                | Value::Float(_)
+                | Value::String(_)
+                | Value::Path(_)
+                | Value::List(_),
+                other,
+            )
+            | (other, _) => Err(type_error("comparable value", other, span)),
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Conversions
+    // ------------------------------------------------------------------------
+
+    /// Coerce a value to a string, as interpolation (`full == false`) or
+    /// `toString` (`full == true`) do
+    pub(crate) fn coerce_to_string(
+        &self,
+        value: &Value,
+        span: Span,
+        full: bool,
+    ) -> EvalResult<String> {
+        match value {
+            Value::String(s) => Ok(s.clone()),
+            Value::Path(p) if full => Ok(p.clone()),
+            Value::Path(_) | Value::LookupPath(_) => Err(EvalError::Unsupported {
+                name: "copying paths to the store".to_string(),
+                span,
+            }),
+            Value::Attrs(attrs) => {
+                if let Some(to_string) = attrs.get("__toString") {
+                    let to_string = self.force(to_string)?;
+                    let result = self.apply(&to_string, Thunk::value(value.clone()), span)?;
+                    self.coerce_to_string(&result, span, full)
+                } else if let Some(out_path) = attrs.get("outPath") {
+                    self.coerce_to_string(&self.force(out_path)?, span, full)
+                } else {
+                    Err(type_error("string", value, span))
+                }
+            }
+            Value::Int(i) if full => Ok(i.to_string()),
+            Value::Float(f) if full => Ok(format!("{f:.6}")),
+            Value::Bool(b) if full => Ok(if *b { "1" } else { "" }.to_string()),
+            Value::Null if full => Ok(String::new()),
+            Value::List(items) if full => {
+                let mut text = String::new();
+                for (i, item) in items.iter().enumerate() {
+                    let part = self.coerce_to_string(&self.force(item)?, span, true)?;
+                    self.check_size(text.len() + usize::from(i > 0) + part.len(), span)?;
+                    if i > 0 {
+                        text.push(' ');
+                    }
+                    text.push_str(&part);
+                }
+                Ok(text)
+            }
+            other => Err(type_error("string", other, span)),
+        }
+    }
+
+    /// Force a value completely and convert it
+    fn to_nix(&self, value: &Value, span: Span) -> EvalResult<NixValue> {
+        let _depth = self.enter(span)?;
+        let invalid = |text: &str| EvalError::InvalidValue {
+            text: text.to_string(),
+            span,
+        };
+        Ok(match value {
+            Value::Null => NixValue::Null(NixNull::new()),
+            Value::Bool(b) => NixValue::Bool(NixBool::new(*b)),
+            Value::Int(i) => NixValue::Integer(NixInteger::new(*i)),
+            Value::Float(f) => NixValue::Float(NixFloat::new(*f)),
+            Value::String(s) => NixValue::String(NixString::new(s.as_str())),
+            Value::Path(p) => NixValue::Path(NixPath::new(p.as_str()).map_err(|_| invalid(p))?),
+            Value::LookupPath(p) => {
+                NixValue::LookupPath(NixLookupPath::new(p.as_str()).map_err(|_| invalid(p))?)
+            }
+            Value::List(items) => {
+                let mut elements = Vec::with_capacity(items.len());
+                for item in items.iter() {
+                    let value = self.force(item)?;
+                    elements.push(self.to_nix(&value, item.origin().unwrap_or(span))?);
+                }
+                NixValue::List(NixList::from_vec(elements))
+            }
+            Value::Attrs(attrs) => {
+                let mut set = NixAttrset::new();
+                for (name, thunk) in attrs.iter() {
+                    let value = self.force(thunk)?;
+                    if value.is_function() && self.config.drop_functions {
+                        continue;
+                    }
+                    set.insert(
+                        name.clone(),
+                        self.to_nix(&value, thunk.origin().unwrap_or(span))?,
+                    );
+                }
+                NixValue::Attrset(set)
+            }
+            Value::Lambda(_) | Value::PrimOp(_) => {
+                return Err(EvalError::NotData {
+                    type_name: value.type_name(),
+                    span,
+                })
+            }
+        })
+    }
+
+    /// Convert an argument supplied by the caller
+    pub(crate) fn from_nix(value: &NixValue) -> Value {
+        match value {
+            NixValue::String(s) => Value::String(s.value.clone()),
+            NixValue::Integer(i) => Value::Int(i.value),
+            NixValue::Float(f) => Value::Float(f.value),
+            NixValue::Bool(b) => Value::Bool(b.value),
+            NixValue::Null(_) => Value::Null,
+            NixValue::Path(p) => Value::Path(p.as_str().to_string()),
+            NixValue::LookupPath(p) => Value::LookupPath(p.name.clone()),
+            NixValue::List(list) => Value::list(
+                list.elements
+                    .iter()
+                    .map(|v| Thunk::value(Self::from_nix(v)))
+                    .collect(),
+            ),
+            NixValue::Attrset(set) => Value::attrs(
+                set.attributes
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Thunk::value(Self::from_nix(v))))
+                    .collect(),
+            ),
+        }
+    }
+}
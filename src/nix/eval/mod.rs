@@ -0,0 +1,714 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Pure Nix Evaluator
+//!
+//! A small lazy evaluator for the pure subset of the Nix language, used to
+//! read configuration files that are more than plain data: `let` bindings,
+//! `//` merges, `lib.genAttrs`, `map`, string interpolation and small helper
+//! functions all resolve to ordinary [`NixValue`]s.
+//!
+//! ## Scope
+//!
+//! - The whole expression language: functions, pattern arguments with
+//!   defaults, `rec`, `let`, `with`, `inherit`, operators, `assert`
+//! - A curated set of pure `builtins` plus a `lib` subset modelled on
+//!   nixpkgs (`genAttrs`, `mapAttrs`, `recursiveUpdate`, `optionals`, ...)
+//! - No derivations, no store, no IO: `import`, `readFile`, `fetchGit`,
+//!   `derivation` and friends fail with [`EvalError::Unsupported`]
+//!
+//! Every evaluation is bounded by [`EvalConfig::max_steps`],
+//! [`EvalConfig::max_depth`] and [`EvalConfig::max_size`], so untrusted input
+//! cannot hang the caller or exhaust its memory.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use cim_domain_nix::nix::eval::Evaluator;
+//!
+//! let value = Evaluator::new()
+//!     .eval_str(r#"
+//!         let
+//!           mkHost = name: { hostname = name; type = "server"; };
+//!         in {
+//!           nodes = lib.genAttrs [ "web01" "web02" ] mkHost;
+//!         }
+//!     "#)
+//!     .unwrap();
+//!
+//! let nodes = value.as_attrset().unwrap().get("nodes").unwrap();
+//! assert_eq!(nodes.as_attrset().unwrap().len(), 2);
+//! ```
+//!
+//! `lib` is in scope everywhere. Files written as functions
+//! (`{ lib, ... }: { ... }`) are called automatically with `lib` and any
+//! arguments added via [`Evaluator::with_arg`].
+
+mod builtins;
+mod interpreter;
+mod value;
+
+use super::ast::{AstError, NixAst, Span};
+use super::value_objects::NixValue;
+use interpreter::Machine;
+use thiserror::Error;
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+/// Errors that can occur during evaluation
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum EvalError {
+    /// The source could not be parsed
+    #[error("Parse error: {0}")]
+    Parse(#[from] AstError),
+
+    /// The source contains no expression
+    #[error("No expression found in source")]
+    MissingExpression,
+
+    /// Reference to an unbound variable
+    #[error("Undefined variable '{name}' at {span}")]
+    UndefinedVariable {
+        /// Variable name
+        name: String,
+        /// Location of the reference
+        span: Span,
+    },
+
+    /// A value has the wrong type
+    #[error("Expected {expected}, got {got} at {span}")]
+    TypeError {
+        /// Expected type
+        expected: &'static str,
+        /// Actual type (as reported by `builtins.typeOf`)
+        got: &'static str,
+        /// Location of the offending expression
+        span: Span,
+    },
+
+    /// A selected attribute does not exist
+    #[error("Attribute '{name}' missing at {span}")]
+    MissingAttribute {
+        /// Attribute name
+        name: String,
+        /// Location of the selection
+        span: Span,
+    },
+
+    /// The same attribute is defined twice
+    #[error("Attribute '{name}' already defined at {span}")]
+    DuplicateAttribute {
+        /// Attribute name
+        name: String,
+        /// Location of the second definition
+        span: Span,
+    },
+
+    /// A function was called without one of its required arguments
+    #[error("Function called without required argument '{name}' at {span}")]
+    MissingArgument {
+        /// Argument name
+        name: String,
+        /// Location of the call
+        span: Span,
+    },
+
+    /// A function without `...` was called with an argument it does not take
+    #[error("Function called with unexpected argument '{name}' at {span}")]
+    UnexpectedArgument {
+        /// Argument name
+        name: String,
+        /// Location of the call
+        span: Span,
+    },
+
+    /// A value depends on itself
+    #[error("Infinite recursion at {span}")]
+    InfiniteRecursion {
+        /// Location of the expression being evaluated
+        span: Span,
+    },
+
+    /// Integer division by zero
+    #[error("Division by zero at {span}")]
+    DivisionByZero {
+        /// Location of the division
+        span: Span,
+    },
+
+    /// Integer arithmetic overflowed
+    #[error("Integer overflow at {span}")]
+    IntegerOverflow {
+        /// Location of the operation
+        span: Span,
+    },
+
+    /// List index out of range
+    #[error("Index {index} out of bounds for list of length {length} at {span}")]
+    IndexOutOfBounds {
+        /// Requested index
+        index: i64,
+        /// List length
+        length: usize,
+        /// Location of the access
+        span: Span,
+    },
+
+    /// An `assert` condition was false
+    #[error("Assertion failed at {span}")]
+    AssertionFailed {
+        /// Location of the assertion
+        span: Span,
+    },
+
+    /// `builtins.throw` was called
+    #[error("{message} at {span}")]
+    Throw {
+        /// Thrown message
+        message: String,
+        /// Location of the call
+        span: Span,
+    },
+
+    /// `builtins.abort` was called
+    #[error("Evaluation aborted: {message} at {span}")]
+    Abort {
+        /// Abort message
+        message: String,
+        /// Location of the call
+        span: Span,
+    },
+
+    /// The expression needs something outside pure evaluation
+    #[error("'{name}' is not available in pure evaluation at {span}")]
+    Unsupported {
+        /// Builtin or feature name
+        name: String,
+        /// Location of the use
+        span: Span,
+    },
+
+    /// A literal or builtin argument could not be interpreted
+    #[error("Invalid value '{text}' at {span}")]
+    InvalidValue {
+        /// Offending text
+        text: String,
+        /// Location of the value
+        span: Span,
+    },
+
+    /// The result contains a function, which is not data
+    #[error("Cannot convert {type_name} to a Nix value at {span}")]
+    NotData {
+        /// Type of the value
+        type_name: &'static str,
+        /// Location of the expression that produced it
+        span: Span,
+    },
+
+    /// The evaluation ran longer than [`EvalConfig::max_steps`]
+    #[error("Evaluation exceeded {limit} steps at {span}")]
+    StepLimitExceeded {
+        /// Configured limit
+        limit: u64,
+        /// Location reached when the limit was hit
+        span: Span,
+    },
+
+    /// The evaluation nested deeper than [`EvalConfig::max_depth`]
+    #[error("Evaluation exceeded maximum depth {limit} at {span}")]
+    DepthLimitExceeded {
+        /// Configured limit
+        limit: usize,
+        /// Location reached when the limit was hit
+        span: Span,
+    },
+
+    /// A list or string grew longer than [`EvalConfig::max_size`]
+    #[error("Evaluation built a value larger than {limit} at {span}")]
+    SizeLimitExceeded {
+        /// Configured limit
+        limit: usize,
+        /// Location of the expression that built it
+        span: Span,
+    },
+}
+
+impl EvalError {
+    /// Location of the error in the source, if known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Parse(_) | Self::MissingExpression => None,
+            Self::UndefinedVariable { span, .. }
+            | Self::TypeError { span, .. }
+            | Self::MissingAttribute { span, .. }
+            | Self::DuplicateAttribute { span, .. }
+            | Self::MissingArgument { span, .. }
+            | Self::UnexpectedArgument { span, .. }
+            | Self::InfiniteRecursion { span }
+            | Self::DivisionByZero { span }
+            | Self::IntegerOverflow { span }
+            | Self::IndexOutOfBounds { span, .. }
+            | Self::AssertionFailed { span }
+            | Self::Throw { span, .. }
+            | Self::Abort { span, .. }
+            | Self::Unsupported { span, .. }
+            | Self::InvalidValue { span, .. }
+            | Self::NotData { span, .. }
+            | Self::StepLimitExceeded { span, .. }
+            | Self::DepthLimitExceeded { span, .. }
+            | Self::SizeLimitExceeded { span, .. } => Some(*span),
+        }
+    }
+}
+
+/// Result type for evaluation
+pub type EvalResult<T> = std::result::Result<T, EvalError>;
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// Evaluation limits and options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalConfig {
+    /// Maximum number of evaluation steps (expressions and calls)
+    pub max_steps: u64,
+    /// Maximum nesting depth of evaluation
+    pub max_depth: usize,
+    /// Maximum length of a list, or of a string in bytes
+    ///
+    /// Checked before the value is built, so `builtins.genList f 100000000`
+    /// fails instead of allocating.
+    pub max_size: usize,
+    /// Call a file that is a function with its pattern arguments
+    pub auto_call: bool,
+    /// Omit attributes whose values are functions from the result
+    ///
+    /// Without this, a function anywhere in the result is an
+    /// [`EvalError::NotData`] error.
+    pub drop_functions: bool,
+}
+
+impl Default for EvalConfig {
+    fn default() -> Self {
+        Self {
+            max_steps: 1_000_000,
+            max_depth: 256,
+            max_size: 1_000_000,
+            auto_call: true,
+            drop_functions: false,
+        }
+    }
+}
+
+// ============================================================================
+// Evaluator
+// ============================================================================
+
+/// Evaluates pure Nix expressions to [`NixValue`]s
+#[derive(Debug, Clone, Default)]
+pub struct Evaluator {
+    config: EvalConfig,
+    args: Vec<(String, NixValue)>,
+}
+
+impl Evaluator {
+    /// Create an evaluator with default limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an evaluator with custom configuration
+    pub fn with_config(config: EvalConfig) -> Self {
+        Self {
+            config,
+            args: Vec::new(),
+        }
+    }
+
+    /// Provide an argument for files written as functions
+    ///
+    /// `lib` is always provided; other arguments (`hostName`, `system`, ...)
+    /// must be added here.
+    #[must_use]
+    pub fn with_arg(mut self, name: impl Into<String>, value: NixValue) -> Self {
+        self.args.push((name.into(), value));
+        self
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &EvalConfig {
+        &self.config
+    }
+
+    /// Parse and evaluate Nix source
+    ///
+    /// ## Errors
+    ///
+    /// Returns an [`EvalError`] with the span of the failing expression.
+    pub fn eval_str(&self, source: &str) -> EvalResult<NixValue> {
+        self.eval_ast(&NixAst::parse(source)?)
+    }
+
+    /// Evaluate a parsed AST
+    ///
+    /// ## Errors
+    ///
+    /// Returns an [`EvalError`] with the span of the failing expression.
+    pub fn eval_ast(&self, ast: &NixAst) -> EvalResult<NixValue> {
+        let expr = ast.expr().ok_or(EvalError::MissingExpression)?;
+        let machine = Machine::new(&self.config);
+        machine.eval_root(&expr, None, &self.args)
+    }
+
+    /// Evaluate a parsed AST and pass its value to the function `wrapper`
+    ///
+    /// Only the wrapper's result is forced, so it can pick the parts of a
+    /// file that evaluate (e.g. attribute names) and leave the rest alone.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an [`EvalError`] with the span of the failing expression;
+    /// spans inside `wrapper` refer to the wrapper source.
+    pub fn eval_ast_with(&self, ast: &NixAst, wrapper: &str) -> EvalResult<NixValue> {
+        let expr = ast.expr().ok_or(EvalError::MissingExpression)?;
+        let wrapper = NixAst::parse(wrapper)?;
+        let wrapper = wrapper.expr().ok_or(EvalError::MissingExpression)?;
+        let machine = Machine::new(&self.config);
+        machine.eval_root(&expr, Some(&wrapper), &self.args)
+    }
+}
+
+/// Evaluate Nix source with the default evaluator
+///
+/// ## Errors
+///
+/// See [`Evaluator::eval_str`].
+pub fn evaluate(source: &str) -> EvalResult<NixValue> {
+    Evaluator::new().eval_str(source)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nix::value_objects::NixAttrset;
+
+    fn eval(source: &str) -> NixValue {
+        evaluate(source).unwrap_or_else(|e| panic!("{source}: {e}"))
+    }
+
+    fn int(source: &str) -> i64 {
+        eval(source).as_integer().unwrap()
+    }
+
+    fn string(source: &str) -> String {
+        eval(source).as_string().unwrap().value.clone()
+    }
+
+    fn boolean(source: &str) -> bool {
+        eval(source).as_bool().unwrap()
+    }
+
+    #[test]
+    fn test_arithmetic_and_comparison() {
+        assert_eq!(int("1 + 2 * 3 - 4 / 2"), 5);
+        assert!((eval("1 + 0.5").as_float().unwrap() - 1.5).abs() < f64::EPSILON);
+        assert!(boolean("1 < 2 && !(2 <= 1) && \"a\" < \"b\" && [ 1 2 ] < [ 1 3 ]"));
+        assert!(boolean("{ a = [ 1 { b = 2; } ]; } == { a = [ 1 { b = 2; } ]; }"));
+        assert!(boolean("1 == 1.0 && null != false"));
+        assert!(boolean("false -> true"));
+    }
+
+    #[test]
+    fn test_strings_and_interpolation() {
+        assert_eq!(string(r#"let n = 3; in "host-${toString n}""#), "host-3");
+        assert_eq!(string(r#""a" + "b""#), "ab");
+        assert_eq!(string("toString [ 1 \"x\" null true ]"), "1 x  1");
+        assert_eq!(string(r#"let s = { __toString = self: "me"; }; in "${s}""#), "me");
+    }
+
+    #[test]
+    fn test_functions_and_patterns() {
+        assert_eq!(int("(x: y: x - y) 5 3"), 2);
+        assert_eq!(int("({ a, b ? a * 2, ... }: a + b) { a = 1; c = 0; }"), 3);
+        assert_eq!(int("(args@{ a, ... }: args.b) { a = 1; b = 7; }"), 7);
+        assert!(matches!(
+            evaluate("({ a }: a) { a = 1; b = 2; }").unwrap_err(),
+            EvalError::UnexpectedArgument { ref name, .. } if name == "b"
+        ));
+        assert!(matches!(
+            evaluate("({ a }: a) { }").unwrap_err(),
+            EvalError::MissingArgument { ref name, .. } if name == "a"
+        ));
+    }
+
+    #[test]
+    fn test_let_rec_with_and_merges() {
+        let value = eval(
+            r#"
+            let
+              defaults = { cores = 2; memory = 4096; };
+              big = defaults // { cores = 8; };
+            in rec {
+              inherit (big) cores;
+              memory = big.memory;
+              total = cores * 1000 + memory;
+              tags = with { a = "x"; }; [ a ];
+              nested.deep.value = total;
+            }
+            "#,
+        );
+        let attrs = value.as_attrset().unwrap();
+        assert_eq!(attrs.get("cores").unwrap().as_integer().unwrap(), 8);
+        assert_eq!(attrs.get("total").unwrap().as_integer().unwrap(), 12096);
+        let nested = attrs.get("nested").unwrap().as_attrset().unwrap();
+        let deep = nested.get("deep").unwrap().as_attrset().unwrap();
+        assert_eq!(deep.get("value").unwrap().as_integer().unwrap(), 12096);
+    }
+
+    #[test]
+    fn test_laziness() {
+        assert_eq!(int("let x = throw \"unused\"; in 1"), 1);
+        assert_eq!(int("(builtins.head [ 1 (throw \"lazy\") ])"), 1);
+        assert_eq!(int("{ a = 1; b = abort \"no\"; }.a"), 1);
+        assert_eq!(int("let xs = builtins.genList (i: i) 10; in builtins.elemAt xs 9"), 9);
+    }
+
+    #[test]
+    fn test_builtins() {
+        assert_eq!(int("builtins.length (map (x: x * 2) [ 1 2 3 ])"), 3);
+        assert_eq!(int("builtins.foldl' (a: b: a + b) 0 [ 1 2 3 4 ]"), 10);
+        assert_eq!(
+            string("builtins.concatStringsSep \",\" (builtins.attrNames { b = 1; a = 2; })"),
+            "a,b"
+        );
+        assert_eq!(
+            string(r#"builtins.replaceStrings [ "." ] [ "-" ] "a.b.c""#),
+            "a-b-c"
+        );
+        assert_eq!(string("builtins.typeOf { }"), "set");
+        assert_eq!(string("builtins.substring 1 3 \"abcdef\""), "bcd");
+        assert_eq!(
+            string(r#"builtins.toJSON { a = [ 1 true null ]; b = "x"; }"#),
+            r#"{"a":[1,true,null],"b":"x"}"#
+        );
+        assert_eq!(
+            int(r#"builtins.length (builtins.fromJSON "{\"a\": [1, 2]}").a"#),
+            2
+        );
+        assert!(boolean("builtins.sort (a: b: a < b) [ 3 1 2 ] == [ 1 2 3 ]"));
+        assert!(boolean("(builtins.tryEval (throw \"x\")).success == false"));
+        assert_eq!(int("builtins.compareVersions \"1.10\" \"1.9\""), 1);
+        assert!(boolean(
+            "builtins.listToAttrs [ { name = \"a\"; value = 1; } ] == { a = 1; }"
+        ));
+    }
+
+    #[test]
+    fn test_lib_subset() {
+        let value = eval(
+            r#"
+            let hosts = [ "web01" "db01" ];
+            in {
+              nodes = lib.genAttrs hosts (name: { hostname = name; });
+              names = lib.mapAttrsToList (n: v: v.hostname) (lib.genAttrs hosts (n: { hostname = n; }));
+              merged = lib.recursiveUpdate { a = { b = 1; c = 2; }; } { a = { c = 3; }; };
+              extra = lib.optionals true [ "x" ] ++ lib.optional false "y";
+              label = lib.optionalString true "on" + lib.toUpper "x";
+              kept = lib.filterAttrs (n: v: v > 1) { a = 1; b = 2; };
+              range = lib.range 1 3;
+              split = lib.splitString "." "a.b.c";
+              prefixed = lib.hasPrefix "web" "web01";
+            }
+            "#,
+        );
+        let attrs = value.as_attrset().unwrap();
+        let nodes = attrs.get("nodes").unwrap().as_attrset().unwrap();
+        assert!(nodes.contains("web01") && nodes.contains("db01"));
+        assert_eq!(attrs.get("names").unwrap().as_list().unwrap().len(), 2);
+        let merged = attrs.get("merged").unwrap().as_attrset().unwrap();
+        let inner = merged.get("a").unwrap().as_attrset().unwrap();
+        assert_eq!(inner.get("b").unwrap().as_integer().unwrap(), 1);
+        assert_eq!(inner.get("c").unwrap().as_integer().unwrap(), 3);
+        assert_eq!(attrs.get("extra").unwrap().as_list().unwrap().len(), 1);
+        assert_eq!(attrs.get("label").unwrap().as_string().unwrap().value, "onX");
+        assert_eq!(attrs.get("kept").unwrap().as_attrset().unwrap().len(), 1);
+        assert_eq!(attrs.get("range").unwrap().as_list().unwrap().len(), 3);
+        assert_eq!(attrs.get("split").unwrap().as_list().unwrap().len(), 3);
+        assert!(attrs.get("prefixed").unwrap().as_bool().unwrap());
+    }
+
+    #[test]
+    fn test_auto_call_with_arguments() {
+        let value = Evaluator::new()
+            .with_arg("site", NixValue::String(crate::nix::NixString::new("lab")))
+            .eval_str("{ lib, site, region ? \"eu\", ... }: { name = \"${site}-${region}\"; }")
+            .unwrap();
+        let attrs = value.as_attrset().unwrap();
+        assert_eq!(attrs.get("name").unwrap().as_string().unwrap().value, "lab-eu");
+
+        let value = eval("{ lib, ... }: lib.genAttrs [ \"a\" ] (n: n)");
+        assert_eq!(value.as_attrset().unwrap().len(), 1);
+
+        assert!(matches!(
+            evaluate("{ missing }: missing").unwrap_err(),
+            EvalError::MissingArgument { .. }
+        ));
+    }
+
+    #[test]
+    fn test_eval_with_wrapper() {
+        let ast = NixAst::parse("{ names = [ \"a\" \"b\" ]; broken = throw \"no\"; }").unwrap();
+        let value = Evaluator::new()
+            .eval_ast_with(&ast, "file: builtins.length file.names")
+            .unwrap();
+        assert_eq!(value.as_integer().unwrap(), 2);
+
+        assert!(Evaluator::new().eval_ast(&ast).is_err());
+        assert!(Evaluator::new()
+            .eval_ast_with(&ast, "file: file.other")
+            .is_err());
+    }
+
+    #[test]
+    fn test_import_of_nixpkgs_lib() {
+        assert_eq!(
+            int("let lib = import <nixpkgs/lib>; in builtins.length (lib.range 0 4)"),
+            5
+        );
+        assert!(matches!(
+            evaluate("import ./other.nix").unwrap_err(),
+            EvalError::Unsupported { .. }
+        ));
+    }
+
+    #[test]
+    fn test_impure_builtins_rejected() {
+        let source = "{ src = builtins.fetchGit ./.; }";
+        match evaluate(source).unwrap_err() {
+            EvalError::Unsupported { name, span } => {
+                assert_eq!(name, "fetchGit");
+                assert_eq!(span.slice(source), "builtins.fetchGit ./.");
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+        assert!(matches!(
+            evaluate("derivation { }").unwrap_err(),
+            EvalError::Unsupported { .. }
+        ));
+    }
+
+    #[test]
+    fn test_errors_carry_spans() {
+        let source = "let a = { x = 1; }; in a.y";
+        match evaluate(source).unwrap_err() {
+            EvalError::MissingAttribute { name, span } => {
+                assert_eq!(name, "y");
+                assert_eq!(span.slice(source), "y");
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+
+        let source = "{ port = 80 + \"x\"; }";
+        let err = evaluate(source).unwrap_err();
+        assert!(matches!(err, EvalError::TypeError { .. }));
+        assert_eq!(err.span().unwrap().slice(source), "80 + \"x\"");
+
+        assert!(matches!(
+            evaluate("throw \"bad host\"").unwrap_err(),
+            EvalError::Throw { ref message, .. } if message == "bad host"
+        ));
+        assert!(matches!(
+            evaluate("assert 1 == 2; 3").unwrap_err(),
+            EvalError::AssertionFailed { .. }
+        ));
+        assert!(matches!(
+            evaluate("1 / 0").unwrap_err(),
+            EvalError::DivisionByZero { .. }
+        ));
+        assert!(matches!(
+            evaluate("undefinedThing").unwrap_err(),
+            EvalError::UndefinedVariable { .. }
+        ));
+        assert!(matches!(
+            evaluate("let x = x + 1; in x").unwrap_err(),
+            EvalError::InfiniteRecursion { .. }
+        ));
+    }
+
+    #[test]
+    fn test_step_and_depth_limits() {
+        let long = "builtins.foldl' (a: b: a + b) 0 (builtins.genList (i: i) 100000)";
+        let config = EvalConfig {
+            max_steps: 10_000,
+            ..EvalConfig::default()
+        };
+        assert!(matches!(
+            Evaluator::with_config(config).eval_str(long).unwrap_err(),
+            EvalError::StepLimitExceeded { limit: 10_000, .. }
+        ));
+
+        let deep = "let f = n: if n == 0 then 0 else 1 + f (n - 1); in f 100000";
+        assert!(matches!(
+            evaluate(deep).unwrap_err(),
+            EvalError::DepthLimitExceeded { limit: 256, .. }
+        ));
+    }
+
+    #[test]
+    fn test_size_limit() {
+        for source in [
+            "builtins.genList (x: x) 100000000",
+            "lib.range 1 100000000",
+            "let l = builtins.genList (x: x) 1000; in builtins.foldl' (acc: _: acc ++ acc) l l",
+            "let s = \"aaaaaaaaaa\"; in builtins.foldl' (acc: _: acc + acc) s (lib.range 1 30)",
+            "builtins.concatStringsSep \"\" (builtins.genList (_: \"x\") 1000001)",
+        ] {
+            assert!(
+                matches!(
+                    evaluate(source).unwrap_err(),
+                    EvalError::SizeLimitExceeded {
+                        limit: 1_000_000,
+                        ..
+                    }
+                ),
+                "{source}"
+            );
+        }
+        assert_eq!(int("builtins.length (builtins.genList (x: x) 1000000)"), 1_000_000);
+    }
+
+    #[test]
+    fn test_functions_in_result() {
+        assert!(matches!(
+            evaluate("{ f = x: x; }").unwrap_err(),
+            EvalError::NotData { type_name: "lambda", .. }
+        ));
+
+        let config = EvalConfig {
+            drop_functions: true,
+            ..EvalConfig::default()
+        };
+        let value = Evaluator::with_config(config)
+            .eval_str("{ a = 1; f = x: x; }")
+            .unwrap();
+        assert_eq!(value.as_attrset().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_paths_and_lookup_paths_survive() {
+        let value = eval("{ p = ./hosts + \"/web.nix\"; l = <nixpkgs>; }");
+        let attrs: &NixAttrset = value.as_attrset().unwrap();
+        match attrs.get("p").unwrap() {
+            NixValue::Path(p) => assert_eq!(p.as_str(), "./hosts/web.nix"),
+            other => panic!("expected path, got {other:?}"),
+        }
+        assert!(matches!(attrs.get("l").unwrap(), NixValue::LookupPath(_)));
+    }
+}
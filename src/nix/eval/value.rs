@@ -0,0 +1,230 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Runtime values, thunks and environments of the evaluator
+//!
+//! These types are internal: evaluation results leave the evaluator as
+//! [`NixValue`](crate::nix::NixValue)s once they have been forced.
+
+use super::builtins::Builtin;
+use crate::nix::ast::Span;
+use rnix::ast;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+// ============================================================================
+// Values
+// ============================================================================
+
+/// Attribute set contents; values stay lazy
+pub(crate) type Attrs = BTreeMap<String, Thunk>;
+
+/// A value in weak head normal form
+#[derive(Clone)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Path(String),
+    LookupPath(String),
+    List(Rc<Vec<Thunk>>),
+    Attrs(Rc<Attrs>),
+    Lambda(Rc<Lambda>),
+    PrimOp(Rc<PrimOp>),
+}
+
+impl Value {
+    /// Type name as reported by `builtins.typeOf`
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Path(_) | Value::LookupPath(_) => "path",
+            Value::List(_) => "list",
+            Value::Attrs(_) => "set",
+            Value::Lambda(_) | Value::PrimOp(_) => "lambda",
+        }
+    }
+
+    pub(crate) fn is_function(&self) -> bool {
+        matches!(self, Value::Lambda(_) | Value::PrimOp(_))
+    }
+
+    pub(crate) fn attrs(attrs: Attrs) -> Self {
+        Value::Attrs(Rc::new(attrs))
+    }
+
+    pub(crate) fn list(items: Vec<Thunk>) -> Self {
+        Value::List(Rc::new(items))
+    }
+}
+
+/// A user-defined function (`x: body` or `{ a, b ? 1, ... }: body`)
+pub(crate) struct Lambda {
+    pub(crate) param: ast::Param,
+    pub(crate) body: ast::Expr,
+    pub(crate) env: Env,
+}
+
+/// A builtin, possibly partially applied
+pub(crate) struct PrimOp {
+    pub(crate) builtin: &'static Builtin,
+    pub(crate) args: Vec<Thunk>,
+}
+
+// ============================================================================
+// Thunks
+// ============================================================================
+
+/// A lazily evaluated value, shared between all its references
+#[derive(Clone)]
+pub(crate) struct Thunk(Rc<ThunkCell>);
+
+struct ThunkCell {
+    /// Where the value comes from, for errors raised after forcing
+    origin: Option<Span>,
+    state: RefCell<ThunkState>,
+}
+
+pub(crate) enum ThunkState {
+    /// An expression closed over its environment
+    Deferred { expr: ast::Expr, env: Env },
+    /// A function call made lazily by a builtin (e.g. `lib.genAttrs`)
+    Call {
+        func: Value,
+        args: Vec<Thunk>,
+        span: Span,
+    },
+    /// `inherit (from) name;`
+    Select {
+        from: Thunk,
+        name: String,
+        span: Span,
+    },
+    /// Being evaluated; forcing it again is infinite recursion
+    Forcing(Span),
+    /// Evaluated
+    Done(Value),
+}
+
+impl Thunk {
+    pub(crate) fn value(value: Value) -> Self {
+        Self::new(None, ThunkState::Done(value))
+    }
+
+    pub(crate) fn deferred(expr: ast::Expr, env: Env) -> Self {
+        let origin = Span::of(rowan::ast::AstNode::syntax(&expr));
+        Self::new(Some(origin), ThunkState::Deferred { expr, env })
+    }
+
+    pub(crate) fn call(func: Value, args: Vec<Thunk>, span: Span) -> Self {
+        Self::new(Some(span), ThunkState::Call { func, args, span })
+    }
+
+    pub(crate) fn select(from: Thunk, name: String, span: Span) -> Self {
+        Self::new(Some(span), ThunkState::Select { from, name, span })
+    }
+
+    pub(crate) fn string(s: impl Into<String>) -> Self {
+        Self::value(Value::String(s.into()))
+    }
+
+    fn new(origin: Option<Span>, state: ThunkState) -> Self {
+        Thunk(Rc::new(ThunkCell {
+            origin,
+            state: RefCell::new(state),
+        }))
+    }
+
+    /// Location of the expression this thunk was created from
+    pub(crate) fn origin(&self) -> Option<Span> {
+        self.0.origin
+    }
+
+    /// Swap in a new state, returning the old one
+    pub(crate) fn replace(&self, state: ThunkState) -> ThunkState {
+        std::mem::replace(&mut *self.0.state.borrow_mut(), state)
+    }
+}
+
+// ============================================================================
+// Environments
+// ============================================================================
+
+/// A chain of lexical scopes
+///
+/// Recursive bindings (`rec`, `let`) make thunks refer back to their own
+/// environment, so every environment is registered with the evaluation and
+/// cleared when it ends to break the reference cycles.
+#[derive(Clone)]
+pub(crate) struct Env(Rc<Frame>);
+
+struct Frame {
+    vars: RefCell<HashMap<String, Thunk>>,
+    /// Namespace of a `with` expression
+    with: Option<Thunk>,
+    parent: Option<Env>,
+}
+
+impl Env {
+    pub(crate) fn root() -> Self {
+        Self::frame(None, None)
+    }
+
+    /// A new empty scope nested in this one
+    pub(crate) fn child(&self) -> Self {
+        Self::frame(Some(self.clone()), None)
+    }
+
+    /// A `with namespace;` scope nested in this one
+    pub(crate) fn with(&self, namespace: Thunk) -> Self {
+        Self::frame(Some(self.clone()), Some(namespace))
+    }
+
+    fn frame(parent: Option<Env>, with: Option<Thunk>) -> Self {
+        Env(Rc::new(Frame {
+            vars: RefCell::new(HashMap::new()),
+            with,
+            parent,
+        }))
+    }
+
+    pub(crate) fn define(&self, name: impl Into<String>, thunk: Thunk) {
+        self.0.vars.borrow_mut().insert(name.into(), thunk);
+    }
+
+    /// Find a lexically bound variable
+    pub(crate) fn lookup(&self, name: &str) -> Option<Thunk> {
+        let mut current = Some(self);
+        while let Some(env) = current {
+            if let Some(thunk) = env.0.vars.borrow().get(name) {
+                return Some(thunk.clone());
+            }
+            current = env.0.parent.as_ref();
+        }
+        None
+    }
+
+    /// `with` namespaces in scope, innermost first
+    pub(crate) fn with_scopes(&self) -> Vec<Thunk> {
+        let mut scopes = Vec::new();
+        let mut current = Some(self);
+        while let Some(env) = current {
+            if let Some(namespace) = &env.0.with {
+                scopes.push(namespace.clone());
+            }
+            current = env.0.parent.as_ref();
+        }
+        scopes
+    }
+
+    /// Drop all bindings, breaking cycles through this scope
+    pub(crate) fn clear(&self) {
+        self.0.vars.borrow_mut().clear();
+    }
+}
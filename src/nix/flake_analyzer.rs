@@ -0,0 +1,1023 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Flake Analyzer
+//!
+//! Extracts the structure of a Nix flake without evaluating it.
+//!
+//! With [`FlakeAnalyzer::with_evaluation`], the flake is also run through
+//! the [pure evaluator](super::eval): `outputs` is called with stub inputs
+//! whose `lib.nixosSystem` returns its arguments, so computed
+//! `nixosConfigurations` (`genAttrs`, `mapAttrs`, `listToAttrs`, ...) are
+//! found too. Inputs are never fetched.
+//!
+//! - **Description, inputs** are read from the static part of `flake.nix`
+//! - **Packages, dev shells, apps, checks, NixOS configurations** are read
+//!   from the `outputs` function by the [`flake_outputs`](super::flake_outputs)
+//!   analyser, which understands direct per-system attributes, flake-utils,
+//!   `genAttrs` helpers and flake-parts
+//! - **Build dependencies, versions, shell environment** are read from the
+//!   arguments of `mkDerivation`-style and `mkShell` calls when they are
+//!   written inline
+//!
+//! [`FlakeAnalyzer::to_infrastructure`] maps the analysis onto the
+//! infrastructure domain: every `nixosConfigurations.<host>` becomes a
+//! `ComputeResource` with a [`SystemDescription`] of the NixOS system it
+//! runs, and the flake's packages and apps become
+//! [`SoftwareArtifact`]s attached to the hosts whose configuration refers to
+//! them.
+
+use super::ast::{NixAst, Result};
+use super::ast_converter::{AstConverter, ConversionResult};
+use super::eval::{EvalConfig, Evaluator};
+use super::flake_outputs::{
+    analyze_outputs, attr_value, key_of, list_elements, static_string, strip_parens, Bound, Call,
+    Category, Definition, Env, FlakeOutputs, Key, NixosConfiguration,
+};
+use super::value_objects::*;
+use crate::adapters::host_reader::HostFacts;
+use cim_infrastructure::{ComputeResource, Hostname, InfrastructureError};
+use rnix::ast::{self, HasEntry, InterpolPart};
+use rowan::ast::AstNode;
+use std::collections::{BTreeMap, HashMap};
+
+// ============================================================================
+// Flake Analysis Result
+// ============================================================================
+
+/// Result of analyzing a flake
+#[derive(Debug, Clone)]
+pub struct FlakeAnalysis {
+    /// Flake description
+    pub description: Option<String>,
+
+    /// External dependencies (inputs)
+    pub inputs: Vec<FlakeInput>,
+
+    /// Packages defined in the flake
+    pub packages: Vec<FlakePackage>,
+
+    /// Development shells
+    pub dev_shells: Vec<FlakeDevShell>,
+
+    /// System architecture, when the flake targets exactly one system
+    pub system: Option<String>,
+
+    /// All outputs found by static analysis, per system
+    pub outputs: FlakeOutputs,
+}
+
+/// External flake input
+#[derive(Debug, Clone)]
+pub struct FlakeInput {
+    /// Input name (e.g., "nixpkgs", "rust-overlay")
+    pub name: String,
+
+    /// URL or flake reference
+    pub url: Option<String>,
+
+    /// Follows attribute (for input inheritance)
+    pub follows: Option<String>,
+}
+
+/// Package definition from flake
+#[derive(Debug, Clone)]
+pub struct FlakePackage {
+    /// Output name (`packages.<system>.<name>`)
+    pub name: String,
+
+    /// Package name (pname)
+    pub pname: Option<String>,
+
+    /// Package version
+    pub version: Option<String>,
+
+    /// Build dependencies
+    pub build_inputs: Vec<String>,
+
+    /// Native build dependencies
+    pub native_build_inputs: Vec<String>,
+
+    /// Whether tests are enabled (`doCheck = true`)
+    pub do_check: bool,
+
+    /// Systems the package is defined for (empty if unknown)
+    pub systems: Vec<String>,
+}
+
+/// Development shell definition
+#[derive(Debug, Clone)]
+pub struct FlakeDevShell {
+    /// Shell name (usually "default")
+    pub name: String,
+
+    /// Packages available in shell
+    pub packages: Vec<String>,
+
+    /// Build dependencies
+    pub build_inputs: Vec<String>,
+
+    /// Native build dependencies
+    pub native_build_inputs: Vec<String>,
+
+    /// Environment variables
+    pub environment: HashMap<String, String>,
+
+    /// Shell hook script
+    pub shell_hook: Option<String>,
+
+    /// Systems the shell is defined for (empty if unknown)
+    pub systems: Vec<String>,
+}
+
+// ============================================================================
+// Infrastructure Mapping
+// ============================================================================
+
+/// Kind of software a flake provides
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SoftwareKind {
+    /// `packages.<system>.<name>`
+    Package,
+    /// `apps.<system>.<name>`
+    App,
+}
+
+/// A package or app of the flake, as deployable software
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoftwareArtifact {
+    /// Output name
+    pub name: String,
+
+    /// Package or app
+    pub kind: SoftwareKind,
+
+    /// Package name (pname), when written inline
+    pub pname: Option<String>,
+
+    /// Package version, when written inline
+    pub version: Option<String>,
+
+    /// Systems the output is defined for (empty if unknown)
+    pub systems: Vec<String>,
+}
+
+/// Operating system of a host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperatingSystem {
+    /// NixOS
+    NixOS,
+}
+
+/// How a host's configuration is deployed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ManagementMethod {
+    /// `nixos-rebuild` (or a tool driving it) building from the flake
+    NixosRebuild,
+}
+
+/// What a host runs and how it is managed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemDescription {
+    /// Operating system
+    pub operating_system: OperatingSystem,
+
+    /// Nix system (`x86_64-linux`), when known
+    pub system: Option<String>,
+
+    /// Deployment method
+    pub management: ManagementMethod,
+}
+
+impl SystemDescription {
+    /// A NixOS system deployed with `nixos-rebuild`
+    pub fn nixos(system: Option<String>) -> Self {
+        Self {
+            operating_system: OperatingSystem::NixOS,
+            system,
+            management: ManagementMethod::NixosRebuild,
+        }
+    }
+}
+
+/// A NixOS host defined by `nixosConfigurations.<name>`
+#[derive(Debug, Clone)]
+pub struct FlakeHost {
+    /// Configuration name, used as the hostname
+    pub configuration: String,
+
+    /// Operating system, system and management of the host
+    pub description: SystemDescription,
+
+    /// The host as an infrastructure resource
+    pub resource: ComputeResource,
+
+    /// Packages and apps of this flake the host's configuration refers to
+    pub software: Vec<SoftwareArtifact>,
+}
+
+/// What a flake contributes to the infrastructure
+#[derive(Debug, Clone, Default)]
+pub struct FlakeInfrastructure {
+    /// Hosts, sorted by configuration name
+    pub hosts: Vec<FlakeHost>,
+
+    /// Packages and apps no host refers to
+    pub unattached: Vec<SoftwareArtifact>,
+}
+
+impl FlakeInfrastructure {
+    /// Hosts whose configuration refers to the given package or app
+    pub fn hosts_with(&self, software: &str) -> impl Iterator<Item = &FlakeHost> + '_ {
+        let software = software.to_string();
+        self.hosts
+            .iter()
+            .filter(move |host| host.software.iter().any(|s| s.name == software))
+    }
+}
+
+// ============================================================================
+// Flake Analyzer
+// ============================================================================
+
+/// Wrapper applied to an evaluated `flake.nix`
+///
+/// Every input is a stub offering `lib`, with `nixosSystem` returning the
+/// arguments it was given; only configuration names and string `system`
+/// arguments are forced.
+const EVALUATION_WRAPPER: &str = r#"flake:
+let
+  lib = import <nixpkgs/lib>;
+  input = { lib = lib // { nixosSystem = args: args; }; };
+  inputs = builtins.mapAttrs (_: _: input) (flake.inputs or { });
+  self = outputs // { inherit inputs; outPath = "."; };
+  outputs = flake.outputs (inputs // { inherit self; });
+  systemOf = config:
+    let system = builtins.tryEval (config.system or null);
+    in if system.success && builtins.isString system.value then system.value else null;
+in {
+  description = flake.description or null;
+  inputs = flake.inputs or { };
+  nixosConfigurations = builtins.mapAttrs (_: systemOf) (outputs.nixosConfigurations or { });
+}"#;
+
+/// Analyzes Nix flakes to extract infrastructure information
+pub struct FlakeAnalyzer {
+    evaluate: bool,
+}
+
+impl FlakeAnalyzer {
+    /// Create a new flake analyzer
+    pub fn new() -> Self {
+        Self { evaluate: false }
+    }
+
+    /// Also evaluate the flake with the pure evaluator
+    ///
+    /// Finds `nixosConfigurations` that static analysis cannot, at the cost
+    /// of failing on flakes the evaluator does not support.
+    #[must_use]
+    pub fn with_evaluation(mut self) -> Self {
+        self.evaluate = true;
+        self
+    }
+
+    /// Analyze a flake from its NixValue representation
+    ///
+    /// A [`NixValue`] cannot hold the `outputs` function, so only the
+    /// description and inputs are filled in; use
+    /// [`analyze_source`](Self::analyze_source) to analyze outputs too.
+    pub fn analyze(&self, value: &NixValue) -> Result<FlakeAnalysis> {
+        let mut analysis = FlakeAnalysis {
+            description: None,
+            inputs: Vec::new(),
+            packages: Vec::new(),
+            dev_shells: Vec::new(),
+            system: None,
+            outputs: FlakeOutputs::default(),
+        };
+
+        // Extract top-level flake structure
+        if let NixValue::Attrset(attrs) = value {
+            // Extract description
+            if let Some(NixValue::String(desc)) = attrs.get("description") {
+                analysis.description = Some(desc.value.clone());
+            }
+
+            // Extract inputs
+            if let Some(NixValue::Attrset(inputs)) = attrs.get("inputs") {
+                analysis.inputs = self.extract_inputs(inputs);
+            }
+        }
+
+        Ok(analysis)
+    }
+
+    /// Analyze a flake from the source of its `flake.nix`
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the source does not parse or its static part
+    /// (description, inputs) is invalid, or, with
+    /// [`with_evaluation`](Self::with_evaluation), if evaluation fails.
+    pub fn analyze_source(&self, source: &str) -> ConversionResult<FlakeAnalysis> {
+        let ast = NixAst::parse(source)?;
+        let value = if self.evaluate {
+            let config = EvalConfig {
+                auto_call: false,
+                ..EvalConfig::default()
+            };
+            Evaluator::with_config(config).eval_ast_with(&ast, EVALUATION_WRAPPER)?
+        } else {
+            AstConverter::lenient().convert(&ast)?
+        };
+        let mut analysis = self.analyze(&value)?;
+
+        if let Some(outputs) = ast.expr().as_ref().and_then(outputs_expr) {
+            self.analyze_outputs(&outputs, &mut analysis);
+        }
+        if self.evaluate {
+            merge_evaluated_configurations(&value, &mut analysis.outputs);
+        }
+
+        Ok(analysis)
+    }
+
+    /// Map an analysis onto the infrastructure domain
+    ///
+    /// Each `nixosConfigurations.<host>` becomes a [`FlakeHost`] whose
+    /// [`ComputeResource`] is named after the configuration and typed by
+    /// [`HostFacts::resource_type`] from its inline modules, so containers
+    /// and QEMU guests are virtual machines. Packages and apps are attached
+    /// to the hosts whose inline modules refer to them through `self`;
+    /// modules in other files are not followed.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a configuration name is not a valid hostname.
+    pub fn to_infrastructure(
+        &self,
+        analysis: &FlakeAnalysis,
+    ) -> std::result::Result<FlakeInfrastructure, InfrastructureError> {
+        let mut software: BTreeMap<(SoftwareKind, String), SoftwareArtifact> = analysis
+            .packages
+            .iter()
+            .map(|package| {
+                let artifact = SoftwareArtifact {
+                    name: package.name.clone(),
+                    kind: SoftwareKind::Package,
+                    pname: package.pname.clone(),
+                    version: package.version.clone(),
+                    systems: package.systems.clone(),
+                };
+                ((SoftwareKind::Package, package.name.clone()), artifact)
+            })
+            .collect();
+        let outputs = &analysis.outputs;
+        let per_system = outputs
+            .systems
+            .iter()
+            .map(|(system, outputs)| (Some(system), outputs))
+            .chain(std::iter::once((None, &outputs.unknown_system)));
+        for (system, outputs) in per_system {
+            for app in &outputs.apps {
+                let entry = software
+                    .entry((SoftwareKind::App, app.clone()))
+                    .or_insert_with(|| artifact(app, SoftwareKind::App));
+                if let Some(system) = system {
+                    merge_systems(&mut entry.systems, vec![system.clone()]);
+                }
+            }
+        }
+
+        let mut attached = Vec::new();
+        let mut hosts = Vec::new();
+        for configuration in &outputs.nixos_configurations {
+            let system = configuration
+                .system
+                .clone()
+                .or_else(|| analysis.system.clone());
+            let references = configuration
+                .packages
+                .iter()
+                .map(|name| (SoftwareKind::Package, name.clone()))
+                .chain(
+                    configuration
+                        .apps
+                        .iter()
+                        .map(|name| (SoftwareKind::App, name.clone())),
+                );
+            let host_software: Vec<SoftwareArtifact> = references
+                .map(|key| {
+                    let found = software.get(&key).cloned();
+                    let found = found.unwrap_or_else(|| artifact(&key.1, key.0));
+                    attached.push(key);
+                    found
+                })
+                .collect();
+
+            let facts = HostFacts {
+                name: configuration.name.clone(),
+                system: system.clone(),
+                services: configuration.services.iter().cloned().collect(),
+                container: configuration.container,
+                ..HostFacts::default()
+            };
+            let mut resource =
+                ComputeResource::new(Hostname::new(&configuration.name)?, facts.resource_type())?;
+            resource.add_metadata(
+                "source",
+                &format!("nixosConfigurations.{}", configuration.name),
+            )?;
+            if let Some(system) = &system {
+                resource.add_metadata("system", system)?;
+            }
+            for (key, kind) in [
+                ("packages", SoftwareKind::Package),
+                ("apps", SoftwareKind::App),
+            ] {
+                let names: Vec<&str> = host_software
+                    .iter()
+                    .filter(|s| s.kind == kind)
+                    .map(|s| s.name.as_str())
+                    .collect();
+                if !names.is_empty() {
+                    resource.add_metadata(key, &names.join(","))?;
+                }
+            }
+
+            hosts.push(FlakeHost {
+                configuration: configuration.name.clone(),
+                description: SystemDescription::nixos(system),
+                resource,
+                software: host_software,
+            });
+        }
+
+        let unattached = software
+            .into_iter()
+            .filter(|(key, _)| !attached.contains(key))
+            .map(|(_, artifact)| artifact)
+            .collect();
+        Ok(FlakeInfrastructure { hosts, unattached })
+    }
+
+    /// Extract flake inputs
+    fn extract_inputs(&self, inputs: &NixAttrset) -> Vec<FlakeInput> {
+        let mut result = Vec::new();
+
+        for (name, value) in &inputs.attributes {
+            let mut input = FlakeInput {
+                name: name.clone(),
+                url: None,
+                follows: None,
+            };
+
+            if let NixValue::Attrset(input_attrs) = value {
+                if let Some(NixValue::String(url)) = input_attrs.get("url") {
+                    input.url = Some(url.value.clone());
+                }
+                if let Some(NixValue::String(follows)) = input_attrs.get("follows") {
+                    input.follows = Some(follows.value.clone());
+                }
+            }
+
+            result.push(input);
+        }
+
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        result
+    }
+
+    /// Analyze the `outputs` function
+    fn analyze_outputs(&self, outputs: &ast::Expr, analysis: &mut FlakeAnalysis) {
+        let (found, definitions) = analyze_outputs(outputs);
+
+        for definition in &definitions {
+            match definition.category {
+                Category::Packages => {
+                    let package = package_of(definition);
+                    match analysis
+                        .packages
+                        .iter_mut()
+                        .find(|p| p.name == package.name)
+                    {
+                        Some(existing) => merge_systems(&mut existing.systems, package.systems),
+                        None => analysis.packages.push(package),
+                    }
+                }
+                Category::DevShells => {
+                    let shell = dev_shell_of(definition);
+                    match analysis
+                        .dev_shells
+                        .iter_mut()
+                        .find(|s| s.name == shell.name)
+                    {
+                        Some(existing) => merge_systems(&mut existing.systems, shell.systems),
+                        None => analysis.dev_shells.push(shell),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let systems: Vec<&str> = found.system_names().collect();
+        if let [system] = systems.as_slice() {
+            analysis.system = Some((*system).to_string());
+        }
+        analysis.outputs = found;
+    }
+}
+
+impl Default for FlakeAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Add the configurations found by evaluation to the static ones
+///
+/// Static results are kept; evaluation only adds missing configurations and
+/// systems.
+fn merge_evaluated_configurations(value: &NixValue, outputs: &mut FlakeOutputs) {
+    let Some(NixValue::Attrset(configurations)) = value
+        .as_attrset()
+        .ok()
+        .and_then(|attrs| attrs.get("nixosConfigurations"))
+    else {
+        return;
+    };
+    for (name, system) in configurations
+        .keys()
+        .filter_map(|name| Some((name, configurations.get(name)?)))
+    {
+        let system = system.as_string().ok().map(|s| s.value.clone());
+        match outputs
+            .nixos_configurations
+            .iter_mut()
+            .find(|configuration| &configuration.name == name)
+        {
+            Some(existing) => {
+                if existing.system.is_none() {
+                    existing.system = system;
+                }
+            }
+            None => outputs.nixos_configurations.push(NixosConfiguration {
+                name: name.clone(),
+                system,
+                ..NixosConfiguration::default()
+            }),
+        }
+    }
+    outputs
+        .nixos_configurations
+        .sort_by(|a, b| a.name.cmp(&b.name));
+}
+
+// ============================================================================
+// Definitions
+// ============================================================================
+
+/// The `outputs = ...;` expression of a flake
+fn outputs_expr(root: &ast::Expr) -> Option<ast::Expr> {
+    let ast::Expr::AttrSet(set) = strip_parens(root) else {
+        return None;
+    };
+    set.entries().find_map(|entry| match entry {
+        ast::Entry::AttrpathValue(binding) => {
+            let mut keys = binding.attrpath()?.attrs();
+            match (keys.next().map(|attr| key_of(&attr)), keys.next()) {
+                (Some(Key::Static(name)), None) if name == "outputs" => binding.value(),
+                _ => None,
+            }
+        }
+        ast::Entry::Inherit(_) => None,
+    })
+}
+
+fn package_of(definition: &Definition) -> FlakePackage {
+    let mut package = FlakePackage {
+        name: definition.name.clone(),
+        pname: None,
+        version: None,
+        build_inputs: Vec::new(),
+        native_build_inputs: Vec::new(),
+        do_check: false,
+        systems: definition.systems.clone().unwrap_or_default(),
+    };
+    let Some((set, env)) = call_arguments(definition) else {
+        return package;
+    };
+    let string = |name: &str| attr_value(&set, &env, &[name]).and_then(|v| static_string(&v));
+
+    package.pname = string("pname").or_else(|| string("name"));
+    package.version = string("version");
+    package.build_inputs = package_names(&set, &env, "buildInputs");
+    package.native_build_inputs = package_names(&set, &env, "nativeBuildInputs");
+    package.do_check = attr_value(&set, &env, &["doCheck"])
+        .is_some_and(|value| strip_parens(&value.expr).syntax().text() == "true");
+    package
+}
+
+fn dev_shell_of(definition: &Definition) -> FlakeDevShell {
+    let mut shell = FlakeDevShell {
+        name: definition.name.clone(),
+        packages: Vec::new(),
+        build_inputs: Vec::new(),
+        native_build_inputs: Vec::new(),
+        environment: HashMap::new(),
+        shell_hook: None,
+        systems: definition.systems.clone().unwrap_or_default(),
+    };
+    let Some((set, env)) = call_arguments(definition) else {
+        return shell;
+    };
+
+    shell.packages = package_names(&set, &env, "packages");
+    shell.build_inputs = package_names(&set, &env, "buildInputs");
+    shell.native_build_inputs = package_names(&set, &env, "nativeBuildInputs");
+    shell.shell_hook = attr_value(&set, &env, &["shellHook"]).and_then(|v| source_string(&v));
+
+    for entry in set.entries() {
+        let ast::Entry::AttrpathValue(binding) = entry else {
+            continue;
+        };
+        let keys: Vec<Key> = binding
+            .attrpath()
+            .into_iter()
+            .flat_map(|path| path.attrs())
+            .map(|attr| key_of(&attr))
+            .collect();
+        let Some(value) = binding.value() else {
+            continue;
+        };
+        let value = Bound {
+            expr: value,
+            env: env.clone(),
+        };
+        match keys.as_slice() {
+            [Key::Static(name)] if is_environment_variable(name) => {
+                if let Some(text) = static_string(&value) {
+                    shell.environment.insert(name.clone(), text);
+                }
+            }
+            [Key::Static(env_attr), Key::Static(name)] if env_attr == "env" => {
+                if let Some(text) = static_string(&value) {
+                    shell.environment.insert(name.clone(), text);
+                }
+            }
+            [Key::Static(env_attr)] if env_attr == "env" => {
+                if let ast::Expr::AttrSet(vars) = strip_parens(&value.expr) {
+                    for entry in vars.entries() {
+                        let ast::Entry::AttrpathValue(var) = entry else {
+                            continue;
+                        };
+                        let mut attrs = var.attrpath().into_iter().flat_map(|path| path.attrs());
+                        let (Some(attr), None) = (attrs.next(), attrs.next()) else {
+                            continue;
+                        };
+                        let (Key::Static(name), Some(expr)) = (key_of(&attr), var.value()) else {
+                            continue;
+                        };
+                        let bound = Bound {
+                            expr,
+                            env: env.clone(),
+                        };
+                        if let Some(text) = static_string(&bound) {
+                            shell.environment.insert(name, text);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    shell
+}
+
+/// Arguments of the builder call defining an output
+/// (`pkgs.rustPlatform.buildRustPackage { ... }`)
+fn call_arguments(definition: &Definition) -> Option<(ast::AttrSet, Env)> {
+    Call::of_value(definition.value.as_ref()?)?.attrset_arg()
+}
+
+/// Names of the packages in a list attribute, without the `pkgs.` prefix
+fn package_names(set: &ast::AttrSet, env: &Env, attr: &str) -> Vec<String> {
+    let Some(list) = attr_value(set, env, &[attr]) else {
+        return Vec::new();
+    };
+    list_elements(&list)
+        .iter()
+        .map(|element| {
+            let text = strip_parens(&element.expr).syntax().text().to_string();
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            match text.strip_prefix("pkgs.") {
+                Some(name) => name.to_string(),
+                None => text,
+            }
+        })
+        .collect()
+}
+
+/// A string with its interpolations kept as source (`${pkgs.hello}/bin`)
+fn source_string(bound: &Bound) -> Option<String> {
+    let ast::Expr::Str(string) = strip_parens(&bound.expr) else {
+        return static_string(bound);
+    };
+    let mut text = String::new();
+    for part in string.normalized_parts() {
+        match part {
+            InterpolPart::Literal(literal) => text.push_str(&literal),
+            InterpolPart::Interpolation(interpol) => {
+                text.push_str(&interpol.syntax().text().to_string());
+            }
+        }
+    }
+    Some(text)
+}
+
+/// `RUST_LOG = "debug";` in `mkShell` arguments
+fn is_environment_variable(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// An artifact known only by its output name
+fn artifact(name: &str, kind: SoftwareKind) -> SoftwareArtifact {
+    SoftwareArtifact {
+        name: name.to_string(),
+        kind,
+        pname: None,
+        version: None,
+        systems: Vec::new(),
+    }
+}
+
+fn merge_systems(systems: &mut Vec<String>, more: Vec<String>) {
+    systems.extend(more);
+    systems.sort();
+    systems.dedup();
+}
+
+// ============================================================================
+// Convenience Functions
+// ============================================================================
+
+/// Analyze a flake from a NixValue
+pub fn analyze_flake(value: &NixValue) -> Result<FlakeAnalysis> {
+    let analyzer = FlakeAnalyzer::new();
+    analyzer.analyze(value)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nix::ast_converter::ConversionError;
+    use crate::nix::parser::NixParser;
+    use cim_infrastructure::ResourceType;
+
+    #[test]
+    fn test_analyze_simple_flake() {
+        let flake_content = r#"{
+            description = "Test flake";
+
+            inputs = {
+                nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable";
+            };
+
+            outputs = { self, nixpkgs }: {};
+        }"#;
+
+        let parser = NixParser::new();
+        let ast = parser.parse_str(flake_content).unwrap();
+        let value = AstConverter::lenient().convert(&ast).unwrap();
+
+        let analyzer = FlakeAnalyzer::new();
+        let analysis = analyzer.analyze(&value).unwrap();
+
+        assert_eq!(analysis.description, Some("Test flake".to_string()));
+        assert_eq!(analysis.inputs.len(), 1);
+        assert_eq!(analysis.inputs[0].name, "nixpkgs");
+        assert_eq!(
+            analysis.inputs[0].url,
+            Some("github:NixOS/nixpkgs/nixos-unstable".to_string())
+        );
+    }
+
+    #[test]
+    fn test_analyze_source_flake_utils() {
+        let flake_content = r#"{
+            description = "Rust service";
+
+            inputs = {
+                nixpkgs.url = "github:NixOS/nixpkgs";
+                flake-utils.url = "github:numtide/flake-utils";
+                rust-overlay.inputs.nixpkgs.follows = "nixpkgs";
+            };
+
+            outputs = { self, nixpkgs, flake-utils, ... }:
+              flake-utils.lib.eachDefaultSystem (system:
+                let
+                  pkgs = nixpkgs.legacyPackages.${system};
+                  version = "0.3.1";
+                in {
+                  packages.default = pkgs.rustPlatform.buildRustPackage {
+                    pname = "service";
+                    inherit version;
+                    src = ./.;
+                    buildInputs = [ pkgs.openssl ];
+                    nativeBuildInputs = with pkgs; [ pkg-config ];
+                    doCheck = true;
+                  };
+
+                  devShells.default = pkgs.mkShell {
+                    packages = [ pkgs.cargo pkgs.rustc ];
+                    RUST_LOG = "debug";
+                    env.DATABASE_URL = "postgres://localhost/dev";
+                    shellHook = "export PATH=${pkgs.cargo}/bin:$PATH";
+                  };
+                });
+        }"#;
+
+        let analysis = FlakeAnalyzer::new().analyze_source(flake_content).unwrap();
+
+        assert_eq!(analysis.description.as_deref(), Some("Rust service"));
+        assert_eq!(analysis.inputs.len(), 3);
+        assert_eq!(analysis.inputs[2].name, "rust-overlay");
+        assert_eq!(analysis.system, None);
+
+        assert_eq!(analysis.packages.len(), 1);
+        let package = &analysis.packages[0];
+        assert_eq!(package.name, "default");
+        assert_eq!(package.pname.as_deref(), Some("service"));
+        assert_eq!(package.version.as_deref(), Some("0.3.1"));
+        assert_eq!(package.build_inputs, vec!["openssl"]);
+        assert_eq!(package.native_build_inputs, vec!["pkg-config"]);
+        assert!(package.do_check);
+        assert_eq!(package.systems.len(), 4);
+
+        assert_eq!(analysis.dev_shells.len(), 1);
+        let shell = &analysis.dev_shells[0];
+        assert_eq!(shell.packages, vec!["cargo", "rustc"]);
+        assert_eq!(shell.environment["RUST_LOG"], "debug");
+        assert_eq!(
+            shell.environment["DATABASE_URL"],
+            "postgres://localhost/dev"
+        );
+        assert_eq!(
+            shell.shell_hook.as_deref(),
+            Some("export PATH=${pkgs.cargo}/bin:$PATH")
+        );
+    }
+
+    #[test]
+    fn test_analyze_source_single_system() {
+        let flake_content = r#"{
+            outputs = { self, nixpkgs }: {
+                packages.x86_64-linux.hello = nixpkgs.legacyPackages.x86_64-linux.hello;
+                packages.x86_64-linux.default = self.packages.x86_64-linux.hello;
+            };
+        }"#;
+
+        let analysis = FlakeAnalyzer::new().analyze_source(flake_content).unwrap();
+
+        assert_eq!(analysis.system.as_deref(), Some("x86_64-linux"));
+        let names: Vec<_> = analysis.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["hello", "default"]);
+        assert_eq!(analysis.packages[0].systems, vec!["x86_64-linux"]);
+        assert_eq!(analysis.packages[0].pname, None);
+    }
+
+    #[test]
+    fn test_analyze_source_errors() {
+        let analyzer = FlakeAnalyzer::new();
+        assert!(analyzer.analyze_source("{ outputs = ").is_err());
+
+        let analysis = analyzer.analyze_source("{ description = \"x\"; }").unwrap();
+        assert!(analysis.outputs.is_empty());
+    }
+
+    #[test]
+    fn test_analyze_source_with_evaluation() {
+        let flake_content = r#"{
+            description = "Fleet";
+            inputs.nixpkgs.url = "github:NixOS/nixpkgs/nixos-24.05";
+
+            outputs = { self, nixpkgs }: {
+                nixosConfigurations = nixpkgs.lib.genAttrs [ "web01" "web02" ] (name:
+                    nixpkgs.lib.nixosSystem {
+                        system = "x86_64-linux";
+                        modules = [ { networking.hostName = name; } ];
+                    });
+            };
+        }"#;
+
+        let analysis = FlakeAnalyzer::new().analyze_source(flake_content).unwrap();
+        assert!(analysis.outputs.nixos_configurations.is_empty());
+
+        let analysis = FlakeAnalyzer::new()
+            .with_evaluation()
+            .analyze_source(flake_content)
+            .unwrap();
+        assert_eq!(analysis.description.as_deref(), Some("Fleet"));
+        assert_eq!(analysis.inputs[0].name, "nixpkgs");
+        let configurations = &analysis.outputs.nixos_configurations;
+        let names: Vec<&str> = configurations.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["web01", "web02"]);
+        assert!(configurations
+            .iter()
+            .all(|c| c.system.as_deref() == Some("x86_64-linux")));
+
+        let error = FlakeAnalyzer::new()
+            .with_evaluation()
+            .analyze_source("{ outputs = { self }: { nixosConfigurations = throw \"no\"; }; }")
+            .unwrap_err();
+        assert!(matches!(error, ConversionError::Evaluation(_)));
+    }
+
+    #[test]
+    fn test_to_infrastructure() {
+        let flake_content = r#"{
+            outputs = { self, nixpkgs }: let system = "x86_64-linux"; in {
+                packages.${system} = {
+                    api = nixpkgs.legacyPackages.${system}.stdenv.mkDerivation {
+                        pname = "api";
+                        version = "1.2.0";
+                    };
+                    docs = nixpkgs.legacyPackages.${system}.hello;
+                };
+                apps.${system}.migrate = { type = "app"; program = "migrate"; };
+                nixosConfigurations.web = nixpkgs.lib.nixosSystem {
+                    inherit system;
+                    modules = [ {
+                        environment.systemPackages = [ self.packages.${system}.api ];
+                        systemd.services.migrate.script = "${self.apps.${system}.migrate.program}";
+                    } ];
+                };
+                nixosConfigurations.db = nixpkgs.lib.nixosSystem {
+                    system = "aarch64-linux";
+                    modules = [ ./db.nix ];
+                };
+                nixosConfigurations.ci = nixpkgs.lib.nixosSystem {
+                    inherit system;
+                    modules = [ { boot.isContainer = true; } ];
+                };
+                nixosConfigurations.vm = nixpkgs.lib.nixosSystem {
+                    inherit system;
+                    modules = [ ./vm.nix { services.qemuGuest.enable = true; } ];
+                };
+            };
+        }"#;
+
+        let analyzer = FlakeAnalyzer::new();
+        let analysis = analyzer.analyze_source(flake_content).unwrap();
+        let infrastructure = analyzer.to_infrastructure(&analysis).unwrap();
+
+        let hosts: Vec<_> = infrastructure
+            .hosts
+            .iter()
+            .map(|h| h.configuration.as_str())
+            .collect();
+        assert_eq!(hosts, vec!["ci", "db", "vm", "web"]);
+
+        let web = &infrastructure.hosts[3];
+        assert_eq!(web.resource.hostname.as_str(), "web");
+        assert_eq!(web.resource.resource_type, ResourceType::PhysicalServer);
+        assert_eq!(
+            web.description,
+            SystemDescription::nixos(Some("x86_64-linux".into()))
+        );
+        assert!(!web.resource.metadata.contains_key("operating_system"));
+        assert_eq!(web.resource.metadata["system"], "x86_64-linux");
+        assert_eq!(web.resource.metadata["packages"], "api");
+        assert_eq!(web.resource.metadata["apps"], "migrate");
+        let api = &web.software[0];
+        assert_eq!(
+            (api.kind, api.version.as_deref()),
+            (SoftwareKind::Package, Some("1.2.0"))
+        );
+
+        let db = &infrastructure.hosts[1];
+        assert_eq!(db.resource.resource_type, ResourceType::PhysicalServer);
+        assert_eq!(db.description.system.as_deref(), Some("aarch64-linux"));
+        assert!(db.software.is_empty());
+        assert!(!db.resource.metadata.contains_key("packages"));
+
+        for vm in [&infrastructure.hosts[0], &infrastructure.hosts[2]] {
+            assert_eq!(vm.resource.resource_type, ResourceType::VirtualMachine);
+        }
+
+        let unattached: Vec<_> = infrastructure
+            .unattached
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(unattached, vec!["docs"]);
+        assert_eq!(infrastructure.hosts_with("api").count(), 1);
+    }
+}
@@ -0,0 +1,876 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Flake Evaluator
+//!
+//! Evaluates Nix flakes using the Nix CLI: `nix flake show` for the outputs
+//! (packages, devShells, NixOS configurations, overlays, templates and any
+//! other output) and `nix flake metadata` for where the flake comes from.
+//!
+//! This complements `FlakeAnalyzer` by providing evaluated (not just static) data.
+
+use super::backend::{CliBackend, NixBackend};
+use super::cache::{flake_key, NixCache};
+use super::flake_metadata::FlakeMetadata;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+
+// ============================================================================
+// Evaluated Flake Structure
+// ============================================================================
+
+/// Complete evaluated flake information
+///
+/// Every output of `nix flake show --json` is kept: well-known outputs in
+/// their own fields and anything else in [`other`](Self::other).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EvaluatedFlake {
+    /// Flake description
+    pub description: Option<String>,
+
+    /// Packages per system
+    pub packages: HashMap<String, HashMap<String, PackageInfo>>,
+
+    /// Development shells per system
+    pub dev_shells: HashMap<String, HashMap<String, DevShellInfo>>,
+
+    /// Checks per system
+    pub checks: HashMap<String, HashMap<String, CheckInfo>>,
+
+    /// Apps per system
+    pub apps: HashMap<String, HashMap<String, AppInfo>>,
+
+    /// Formatter per system
+    #[serde(default)]
+    pub formatter: HashMap<String, PackageInfo>,
+
+    /// Legacy packages per system (omitted unless shown with `--legacy`)
+    #[serde(default)]
+    pub legacy_packages: HashMap<String, OutputTree>,
+
+    /// NixOS configurations by host name
+    #[serde(default)]
+    pub nixos_configurations: HashMap<String, OutputInfo>,
+
+    /// NixOS modules
+    #[serde(default)]
+    pub nixos_modules: HashMap<String, OutputInfo>,
+
+    /// Nixpkgs overlays
+    #[serde(default)]
+    pub overlays: HashMap<String, OutputInfo>,
+
+    /// Flake templates
+    #[serde(default)]
+    pub templates: HashMap<String, OutputInfo>,
+
+    /// Hydra jobsets, nested by job name and system
+    #[serde(default)]
+    pub hydra_jobs: HashMap<String, OutputTree>,
+
+    /// Home Manager configurations by user
+    #[serde(default)]
+    pub home_configurations: HashMap<String, OutputInfo>,
+
+    /// Outputs of any other name, such as `lib` or `darwinConfigurations`
+    #[serde(default)]
+    pub other: HashMap<String, OutputTree>,
+}
+
+impl EvaluatedFlake {
+    /// Every output by attribute path (`packages.x86_64-linux.default`)
+    pub fn inventory(&self) -> BTreeMap<String, OutputInfo> {
+        let mut inventory = BTreeMap::new();
+        let mut add = |path: &[&str], info: OutputInfo| {
+            inventory.insert(attr_path(path), info);
+        };
+
+        for (system, packages) in &self.packages {
+            for (name, package) in packages {
+                add(&["packages", system, name], package.into());
+            }
+        }
+        for (system, shells) in &self.dev_shells {
+            for (name, shell) in shells {
+                add(
+                    &["devShells", system, name],
+                    OutputInfo::new(&shell.shell_type, None, shell.description.clone()),
+                );
+            }
+        }
+        for (system, checks) in &self.checks {
+            for (name, check) in checks {
+                add(
+                    &["checks", system, name],
+                    OutputInfo::new(&check.check_type, None, check.description.clone()),
+                );
+            }
+        }
+        for (system, apps) in &self.apps {
+            for (name, app) in apps {
+                add(
+                    &["apps", system, name],
+                    OutputInfo::new(&app.app_type, None, app.description.clone()),
+                );
+            }
+        }
+        for (system, formatter) in &self.formatter {
+            add(&["formatter", system], formatter.into());
+        }
+        let named = [
+            ("nixosConfigurations", &self.nixos_configurations),
+            ("nixosModules", &self.nixos_modules),
+            ("overlays", &self.overlays),
+            ("templates", &self.templates),
+            ("homeConfigurations", &self.home_configurations),
+        ];
+        for (output, entries) in named {
+            for (name, info) in entries {
+                add(&[output, name], info.clone());
+            }
+        }
+        let trees = [
+            ("legacyPackages", &self.legacy_packages),
+            ("hydraJobs", &self.hydra_jobs),
+        ];
+        for (output, entries) in trees {
+            for (name, tree) in entries {
+                tree.collect(&mut vec![output, name], &mut add);
+            }
+        }
+        for (output, tree) in &self.other {
+            tree.collect(&mut vec![output], &mut add);
+        }
+        inventory
+    }
+}
+
+/// Join attribute names into a Nix attribute path, quoting names that
+/// contain dots
+fn attr_path(path: &[&str]) -> String {
+    path.iter()
+        .map(|name| {
+            if name.contains('.') {
+                format!("\"{name}\"")
+            } else {
+                (*name).to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Type of an output as reported by `nix flake show`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum OutputType {
+    /// `derivation`
+    Derivation,
+    /// `app`
+    App,
+    /// `nixos-configuration`
+    NixosConfiguration,
+    /// `nixos-module`
+    NixosModule,
+    /// `nixpkgs-overlay`
+    NixpkgsOverlay,
+    /// `template`
+    Template,
+    /// `unknown`: an output Nix does not know how to show
+    Unknown,
+    /// Not evaluated, e.g. packages for other systems without
+    /// `--all-systems` (shown as `{}`)
+    Omitted,
+    /// Any other type
+    Other(String),
+}
+
+impl OutputType {
+    /// Type name as printed by `nix flake show`
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Derivation => "derivation",
+            Self::App => "app",
+            Self::NixosConfiguration => "nixos-configuration",
+            Self::NixosModule => "nixos-module",
+            Self::NixpkgsOverlay => "nixpkgs-overlay",
+            Self::Template => "template",
+            Self::Unknown => "unknown",
+            Self::Omitted => "omitted",
+            Self::Other(other) => other,
+        }
+    }
+}
+
+impl From<String> for OutputType {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "derivation" => Self::Derivation,
+            "app" => Self::App,
+            "nixos-configuration" => Self::NixosConfiguration,
+            "nixos-module" => Self::NixosModule,
+            "nixpkgs-overlay" => Self::NixpkgsOverlay,
+            "template" => Self::Template,
+            "unknown" => Self::Unknown,
+            "omitted" => Self::Omitted,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<OutputType> for String {
+    fn from(value: OutputType) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for OutputType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A single output shown by `nix flake show`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputInfo {
+    /// Output type
+    #[serde(rename = "type")]
+    pub output_type: OutputType,
+
+    /// Derivation name (`hello-2.12.1`), for derivations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Description if available
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl OutputInfo {
+    fn new(output_type: &str, name: Option<String>, description: Option<String>) -> Self {
+        Self {
+            output_type: OutputType::from(output_type.to_string()),
+            name,
+            description,
+        }
+    }
+
+    /// Read an output from `nix flake show --json`; `{}` is an omitted
+    /// output
+    fn from_json(value: &serde_json::Value) -> Self {
+        Self::new(
+            &output_type(value, "unknown"),
+            json_string(value, "name"),
+            json_string(value, "description"),
+        )
+    }
+
+    /// Whether `nix flake show` skipped this output
+    pub fn is_omitted(&self) -> bool {
+        self.output_type == OutputType::Omitted
+    }
+}
+
+impl From<&PackageInfo> for OutputInfo {
+    fn from(package: &PackageInfo) -> Self {
+        Self::new(
+            &package.pkg_type,
+            package.derivation_name.clone(),
+            package.description.clone(),
+        )
+    }
+}
+
+/// An arbitrarily nested output, such as `legacyPackages` or `hydraJobs`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OutputTree {
+    /// A shown output
+    Output(OutputInfo),
+    /// An attribute set of outputs
+    Attrs(BTreeMap<String, OutputTree>),
+}
+
+impl OutputTree {
+    /// Read a tree from `nix flake show --json`
+    ///
+    /// Objects with a `type` are outputs, `{}` is an omitted output and
+    /// any other object is an attribute set.
+    fn from_json(value: &serde_json::Value) -> Self {
+        match value.as_object() {
+            Some(attrs) if !attrs.is_empty() && !attrs.contains_key("type") => Self::Attrs(
+                attrs
+                    .iter()
+                    .map(|(name, value)| (name.clone(), Self::from_json(value)))
+                    .collect(),
+            ),
+            _ => Self::Output(OutputInfo::from_json(value)),
+        }
+    }
+
+    /// Number of outputs in the tree
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Output(_) => 1,
+            Self::Attrs(attrs) => attrs.values().map(Self::len).sum(),
+        }
+    }
+
+    /// Whether the tree is an empty attribute set
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn collect<'a>(&'a self, path: &mut Vec<&'a str>, add: &mut impl FnMut(&[&str], OutputInfo)) {
+        match self {
+            Self::Output(info) => add(path, info.clone()),
+            Self::Attrs(attrs) => {
+                for (name, tree) in attrs {
+                    path.push(name);
+                    tree.collect(path, add);
+                    path.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Package information from evaluated flake
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageInfo {
+    /// Package name
+    pub name: String,
+
+    /// Package type (from nix flake show; `omitted` if not evaluated)
+    #[serde(rename = "type")]
+    pub pkg_type: String,
+
+    /// Derivation name (`hello-2.12.1`)
+    #[serde(default)]
+    pub derivation_name: Option<String>,
+
+    /// Description if available
+    pub description: Option<String>,
+}
+
+/// Development shell information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevShellInfo {
+    /// Shell name
+    pub name: String,
+
+    /// Shell type
+    #[serde(rename = "type")]
+    pub shell_type: String,
+
+    /// Description if available
+    pub description: Option<String>,
+}
+
+/// Check information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckInfo {
+    /// Check name
+    pub name: String,
+
+    /// Check type
+    #[serde(rename = "type")]
+    pub check_type: String,
+
+    /// Description if available
+    pub description: Option<String>,
+}
+
+/// App information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppInfo {
+    /// App name
+    pub name: String,
+
+    /// App type
+    #[serde(rename = "type")]
+    pub app_type: String,
+
+    /// Description if available
+    pub description: Option<String>,
+}
+
+// ============================================================================
+// Flake Evaluator
+// ============================================================================
+
+/// Cache namespace for `nix flake show` results
+const FLAKE_SHOW_NAMESPACE: &str = "flake-show";
+
+/// Evaluates flakes using Nix CLI
+///
+/// All commands go through a [`NixBackend`], so the evaluator can run
+/// against recorded output with a [`ReplayBackend`](super::ReplayBackend).
+#[derive(Clone)]
+pub struct FlakeEvaluator {
+    /// Backend that runs nix commands (default: the `nix` CLI)
+    backend: Arc<dyn NixBackend>,
+    /// Cache of evaluations of unchanged local flakes
+    cache: Option<NixCache>,
+}
+
+impl FlakeEvaluator {
+    /// Create a new flake evaluator
+    pub fn new() -> Self {
+        Self::with_backend(CliBackend::default())
+    }
+
+    /// Create evaluator with custom nix command
+    pub fn with_command(command: String) -> Self {
+        Self::with_backend(CliBackend::new(command))
+    }
+
+    /// Create evaluator that runs commands through the given backend
+    pub fn with_backend(backend: impl NixBackend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            cache: None,
+        }
+    }
+
+    /// Reuse evaluations of local flakes whose files have not changed
+    ///
+    /// Entries are keyed by [`flake_key`], which covers the flake's files
+    /// (in a git work tree, every file git tracks). Flakes given by reference
+    /// (`github:...`) are always evaluated.
+    #[must_use]
+    pub fn with_cache(mut self, cache: NixCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Backend the evaluator runs commands through
+    pub fn backend(&self) -> &dyn NixBackend {
+        self.backend.as_ref()
+    }
+
+    /// Evaluate a flake at the given path
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`EvaluationError::NixNotAvailable`] if nix cannot be run,
+    /// [`EvaluationError::NixError`] if evaluation fails and
+    /// [`EvaluationError::ParseError`] if the output is not valid JSON.
+    pub fn evaluate<P: AsRef<Path>>(&self, flake_path: P) -> EvaluationResult<EvaluatedFlake> {
+        let flake_path = flake_path.as_ref();
+        if let Some(cache) = &self.cache {
+            // Unreadable flakes are left for nix to report
+            if let Ok(Some(key)) = flake_key(flake_path) {
+                return cache.get_or_try_insert_with(FLAKE_SHOW_NAMESPACE, &key, || {
+                    self.evaluate_uncached(flake_path)
+                });
+            }
+        }
+        self.evaluate_uncached(flake_path)
+    }
+
+    fn evaluate_uncached(&self, flake_path: &Path) -> EvaluationResult<EvaluatedFlake> {
+        let path_str = flake_path.to_string_lossy();
+
+        // Run nix flake show --json
+        let args = ["flake", "show", "--json", path_str.as_ref()].map(String::from);
+        let stdout = self.backend.run(&args)?.into_stdout()?;
+
+        // Parse JSON output
+        Self::parse_flake_show(&stdout)
+    }
+
+    /// Read the metadata of the flake at the given path
+    ///
+    /// ## Errors
+    ///
+    /// As for [`evaluate`](Self::evaluate).
+    pub fn metadata<P: AsRef<Path>>(&self, flake_path: P) -> EvaluationResult<FlakeMetadata> {
+        let path_str = flake_path.as_ref().to_string_lossy();
+
+        // Run nix flake metadata --json
+        let args = ["flake", "metadata", "--json", path_str.as_ref()].map(String::from);
+        let stdout = self.backend.run(&args)?.into_stdout()?;
+
+        FlakeMetadata::from_json(&stdout)
+    }
+
+    /// Parse the output of `nix flake show --json`
+    pub(crate) fn parse_flake_show(json_str: &str) -> EvaluationResult<EvaluatedFlake> {
+        let json: serde_json::Value = serde_json::from_str(json_str)
+            .map_err(|e| EvaluationError::ParseError(e.to_string()))?;
+        let outputs = json.as_object().ok_or_else(|| {
+            EvaluationError::ParseError("expected a JSON object of flake outputs".to_string())
+        })?;
+
+        let mut flake = EvaluatedFlake::default();
+        for (output, value) in outputs {
+            match output.as_str() {
+                "description" => flake.description = value.as_str().map(ToString::to_string),
+                "packages" => flake.packages = per_system(value, package_info),
+                "devShells" => {
+                    flake.dev_shells = per_system(value, |name, info| DevShellInfo {
+                        name: name.to_string(),
+                        shell_type: output_type(info, "derivation"),
+                        description: json_string(info, "description"),
+                    });
+                }
+                "checks" => {
+                    flake.checks = per_system(value, |name, info| CheckInfo {
+                        name: name.to_string(),
+                        check_type: output_type(info, "derivation"),
+                        description: json_string(info, "description"),
+                    });
+                }
+                "apps" => {
+                    flake.apps = per_system(value, |name, info| AppInfo {
+                        name: name.to_string(),
+                        app_type: output_type(info, "app"),
+                        description: json_string(info, "description"),
+                    });
+                }
+                "formatter" => flake.formatter = per_name(value, package_info),
+                "legacyPackages" => {
+                    flake.legacy_packages = per_name(value, |_, tree| OutputTree::from_json(tree));
+                }
+                "nixosConfigurations" => {
+                    flake.nixos_configurations =
+                        per_name(value, |_, info| OutputInfo::from_json(info));
+                }
+                "nixosModules" => {
+                    flake.nixos_modules = per_name(value, |_, info| OutputInfo::from_json(info));
+                }
+                "overlays" => {
+                    flake.overlays = per_name(value, |_, info| OutputInfo::from_json(info));
+                }
+                "templates" => {
+                    flake.templates = per_name(value, |_, info| OutputInfo::from_json(info));
+                }
+                "hydraJobs" => {
+                    flake.hydra_jobs = per_name(value, |_, tree| OutputTree::from_json(tree));
+                }
+                "homeConfigurations" => {
+                    flake.home_configurations =
+                        per_name(value, |_, info| OutputInfo::from_json(info));
+                }
+                _ => {
+                    flake
+                        .other
+                        .insert(output.clone(), OutputTree::from_json(value));
+                }
+            }
+        }
+        Ok(flake)
+    }
+
+    /// Check if nix command is available
+    pub fn is_available(&self) -> bool {
+        self.backend.is_available()
+    }
+}
+
+impl Default for FlakeEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for FlakeEvaluator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FlakeEvaluator").finish_non_exhaustive()
+    }
+}
+
+/// Parse an `<output>.<name>` section of `nix flake show --json`
+fn per_name<T>(
+    output: &serde_json::Value,
+    parse: impl Fn(&str, &serde_json::Value) -> T,
+) -> HashMap<String, T> {
+    output
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, info)| (name.clone(), parse(name, info)))
+        .collect()
+}
+
+/// Parse an `<output>.<system>.<name>` section of `nix flake show --json`
+fn per_system<T>(
+    output: &serde_json::Value,
+    parse: impl Fn(&str, &serde_json::Value) -> T,
+) -> HashMap<String, HashMap<String, T>> {
+    per_name(output, |_, outputs| per_name(outputs, &parse))
+}
+
+fn package_info(name: &str, info: &serde_json::Value) -> PackageInfo {
+    PackageInfo {
+        name: name.to_string(),
+        pkg_type: output_type(info, "derivation"),
+        derivation_name: json_string(info, "name"),
+        description: json_string(info, "description"),
+    }
+}
+
+/// Type of a shown output; `{}` means `nix flake show` omitted it
+fn output_type(value: &serde_json::Value, default: &str) -> String {
+    let omitted = value.as_object().is_some_and(serde_json::Map::is_empty);
+    value
+        .get("type")
+        .and_then(|t| t.as_str())
+        .unwrap_or(if omitted { "omitted" } else { default })
+        .to_string()
+}
+
+fn json_string(value: &serde_json::Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|d| d.as_str())
+        .map(ToString::to_string)
+}
+
+// ============================================================================
+// Error Types
+// ============================================================================
+
+/// Evaluation errors
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EvaluationError {
+    /// Nix command failed to execute
+    #[error("Command failed: {0}")]
+    CommandFailed(String),
+
+    /// Nix evaluation error
+    #[error("Nix error: {0}")]
+    NixError(String),
+
+    /// Failed to parse JSON output
+    #[error("Parse error: {0}")]
+    ParseError(String),
+
+    /// Nix not available
+    #[error("Nix command not available")]
+    NixNotAvailable,
+
+    /// Evaluation did not finish in time; the nix process was killed
+    #[error("Evaluation timed out after {0:?}")]
+    Timeout(Duration),
+
+    /// Evaluation was cancelled; the nix process was killed
+    #[error("Evaluation cancelled")]
+    Cancelled,
+}
+
+/// Result type for flake evaluation
+pub type EvaluationResult<T> = Result<T, EvaluationError>;
+
+// ============================================================================
+// Convenience Functions
+// ============================================================================
+
+/// Evaluate a flake at the given path
+///
+/// ## Errors
+///
+/// See [`FlakeEvaluator::evaluate`].
+pub fn evaluate_flake<P: AsRef<Path>>(path: P) -> EvaluationResult<EvaluatedFlake> {
+    let evaluator = FlakeEvaluator::new();
+    evaluator.evaluate(path)
+}
+
+/// Check if Nix is available
+pub fn nix_available() -> bool {
+    FlakeEvaluator::new().is_available()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nix::backend::{NixOutput, RecordingBackend, ReplayBackend};
+
+    const SHOW_ARGS: [&str; 4] = ["flake", "show", "--json", "/src/flake"];
+
+    #[test]
+    fn test_evaluate_replayed_flake() {
+        let backend = ReplayBackend::new().with_output(
+            SHOW_ARGS,
+            r#"{
+              "description": "demo",
+              "packages": {
+                "x86_64-linux": {
+                  "default": { "type": "derivation", "description": "hello" },
+                  "tool": {}
+                }
+              },
+              "devShells": { "x86_64-linux": { "default": { "type": "derivation" } } },
+              "apps": { "aarch64-linux": { "run": {} } },
+              "nixosConfigurations": { "host": {} }
+            }"#,
+        );
+        let flake = FlakeEvaluator::with_backend(backend)
+            .evaluate("/src/flake")
+            .unwrap();
+
+        assert_eq!(flake.description.as_deref(), Some("demo"));
+        let packages = &flake.packages["x86_64-linux"];
+        assert_eq!(packages["default"].description.as_deref(), Some("hello"));
+        assert_eq!(packages["tool"].pkg_type, "omitted");
+        assert_eq!(flake.dev_shells["x86_64-linux"].len(), 1);
+        assert_eq!(flake.apps["aarch64-linux"]["run"].app_type, "omitted");
+        assert!(flake.checks.is_empty());
+    }
+
+    #[test]
+    fn test_every_output_category() {
+        let flake = FlakeEvaluator::parse_flake_show(
+            r#"{
+              "packages": {
+                "x86_64-linux": { "hello": { "name": "hello-2.12.1", "type": "derivation" } },
+                "aarch64-darwin": { "hello": {} }
+              },
+              "formatter": { "x86_64-linux": { "name": "nixfmt-1.0", "type": "derivation" } },
+              "legacyPackages": { "x86_64-linux": {} },
+              "nixosConfigurations": { "web.example": { "type": "nixos-configuration" } },
+              "nixosModules": { "default": { "type": "nixos-module" } },
+              "overlays": { "default": { "type": "nixpkgs-overlay" } },
+              "templates": { "rust": { "description": "Rust project", "type": "template" } },
+              "hydraJobs": { "tests": { "x86_64-linux": { "name": "vm-test", "type": "derivation" } } },
+              "homeConfigurations": { "alice": { "type": "unknown" } },
+              "lib": { "type": "unknown" },
+              "darwinConfigurations": { "mac": { "type": "darwin-configuration" } }
+            }"#,
+        )
+        .unwrap();
+
+        let hello = &flake.packages["x86_64-linux"]["hello"];
+        assert_eq!(hello.derivation_name.as_deref(), Some("hello-2.12.1"));
+        assert_eq!(flake.formatter["x86_64-linux"].pkg_type, "derivation");
+        assert!(matches!(
+            &flake.legacy_packages["x86_64-linux"],
+            OutputTree::Output(info) if info.is_omitted()
+        ));
+        assert_eq!(
+            flake.nixos_configurations["web.example"].output_type,
+            OutputType::NixosConfiguration
+        );
+        assert_eq!(
+            flake.overlays["default"].output_type,
+            OutputType::NixpkgsOverlay
+        );
+        assert_eq!(
+            flake.templates["rust"].description.as_deref(),
+            Some("Rust project")
+        );
+        assert_eq!(flake.hydra_jobs["tests"].len(), 1);
+        assert_eq!(
+            flake.home_configurations["alice"].output_type,
+            OutputType::Unknown
+        );
+
+        let inventory = flake.inventory();
+        assert_eq!(inventory.len(), 12);
+        assert!(inventory["packages.aarch64-darwin.hello"].is_omitted());
+        assert_eq!(
+            inventory["hydraJobs.tests.x86_64-linux"].name.as_deref(),
+            Some("vm-test")
+        );
+        assert!(inventory.contains_key("nixosConfigurations.\"web.example\""));
+        assert_eq!(
+            inventory["darwinConfigurations.mac"].output_type,
+            OutputType::Other("darwin-configuration".to_string())
+        );
+        assert_eq!(inventory["lib"].output_type, OutputType::Unknown);
+
+        let round_trip: EvaluatedFlake =
+            serde_json::from_str(&serde_json::to_string(&flake).unwrap()).unwrap();
+        assert_eq!(round_trip.inventory(), inventory);
+    }
+
+    #[test]
+    fn test_replayed_metadata() {
+        let evaluator = FlakeEvaluator::with_backend(ReplayBackend::new().with_output(
+            ["flake", "metadata", "--json", "/src/flake"],
+            r#"{ "revision": "abc", "url": "git+file:///src/flake" }"#,
+        ));
+        let metadata = evaluator.metadata("/src/flake").unwrap();
+        assert_eq!(metadata.revision.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_cached_evaluation() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("flake.nix"), "{ outputs = _: { }; }").unwrap();
+        let path = dir.path().to_string_lossy().into_owned();
+        let recorder = Arc::new(RecordingBackend::new(
+            ReplayBackend::new().with_output(["flake", "show", "--json", path.as_str()], "{}"),
+        ));
+        let cache = NixCache::in_memory();
+        let evaluator = FlakeEvaluator::with_backend(recorder.clone()).with_cache(cache.clone());
+
+        evaluator.evaluate(&path).unwrap();
+        evaluator.evaluate(&path).unwrap();
+        assert_eq!(recorder.recordings().len(), 1);
+        assert_eq!(cache.stats().hits, 1);
+
+        // Changing the lock file invalidates the evaluation
+        std::fs::write(dir.path().join("flake.lock"), "{}").unwrap();
+        evaluator.evaluate(&path).unwrap();
+        assert_eq!(recorder.recordings().len(), 2);
+    }
+
+    #[test]
+    fn test_evaluation_errors() {
+        let unavailable = FlakeEvaluator::with_backend(ReplayBackend::unavailable());
+        assert!(!unavailable.is_available());
+        assert_eq!(
+            unavailable.evaluate("/src/flake").unwrap_err(),
+            EvaluationError::NixNotAvailable
+        );
+
+        let failing = FlakeEvaluator::with_backend(
+            ReplayBackend::new()
+                .with_recording(SHOW_ARGS, NixOutput::failure(1, "error: no flake.nix"))
+                .with_output(["flake", "show", "--json", "/bad"], "not json"),
+        );
+        assert_eq!(
+            failing.evaluate("/src/flake").unwrap_err(),
+            EvaluationError::NixError("error: no flake.nix".to_string())
+        );
+        assert!(matches!(
+            failing.evaluate("/bad"),
+            Err(EvaluationError::ParseError(_))
+        ));
+        assert!(matches!(
+            failing.evaluate("/unrecorded"),
+            Err(EvaluationError::CommandFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_nix_availability() {
+        let unavailable = FlakeEvaluator::with_backend(ReplayBackend::unavailable());
+        assert!(!unavailable.is_available());
+
+        let installed = FlakeEvaluator::with_backend(
+            ReplayBackend::new().with_output(["--version"], "nix (Nix) 2.24.9"),
+        );
+        assert!(installed.is_available());
+    }
+
+    #[test]
+    #[ignore = "requires nix"]
+    fn test_evaluate_current_flake() {
+        let evaluator = FlakeEvaluator::new();
+        assert!(evaluator.is_available(), "nix is not installed");
+
+        let flake = evaluator.evaluate(".").unwrap();
+        assert!(!flake.packages.is_empty());
+        assert!(!flake.dev_shells.is_empty());
+    }
+}
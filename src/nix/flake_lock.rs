@@ -0,0 +1,863 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Flake Lock Files
+//!
+//! A complete model of `flake.lock` (versions 5 to 7) that reads and writes
+//! every field Nix emits, plus resolution of `follows` into a concrete
+//! dependency graph.
+//!
+//! ## Format
+//!
+//! ```json
+//! {
+//!   "nodes": {
+//!     "root": { "inputs": { "nixpkgs": "nixpkgs", "utils": "utils" } },
+//!     "utils": {
+//!       "inputs": { "nixpkgs": ["nixpkgs"] },
+//!       "locked": { "type": "github", "owner": "numtide", "repo": "flake-utils", "rev": "..." },
+//!       "original": { "type": "github", "owner": "numtide", "repo": "flake-utils" }
+//!     }
+//!   },
+//!   "root": "root",
+//!   "version": 7
+//! }
+//! ```
+//!
+//! An input either names a node directly (`"nixpkgs"`) or *follows* an input
+//! path starting at the root (`["nixpkgs"]`, `["home-manager", "nixpkgs"]`).
+//! [`FlakeLock::graph`] resolves every input to the node it really uses.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use cim_domain_nix::nix::flake_lock::FlakeLock;
+//!
+//! let lock = FlakeLock::from_json(r#"{
+//!   "nodes": {
+//!     "nixpkgs": {
+//!       "locked": { "type": "github", "owner": "NixOS", "repo": "nixpkgs", "rev": "abc" },
+//!       "original": { "type": "github", "owner": "NixOS", "repo": "nixpkgs" }
+//!     },
+//!     "root": { "inputs": { "nixpkgs": "nixpkgs" } }
+//!   },
+//!   "root": "root",
+//!   "version": 7
+//! }"#).unwrap();
+//!
+//! let graph = lock.graph().unwrap();
+//! assert_eq!(graph.resolve("root", "nixpkgs"), Some("nixpkgs"));
+//! assert_eq!(lock.nodes["nixpkgs"].locked.as_ref().unwrap().to_string(), "github:NixOS/nixpkgs/abc");
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use thiserror::Error;
+
+/// Lock file versions this model reads
+pub const SUPPORTED_LOCK_VERSIONS: RangeInclusive<u32> = 5..=7;
+
+/// Errors that can occur while reading or resolving a lock file
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum LockError {
+    /// The lock file could not be read
+    #[error("IO error: {0}")]
+    Io(String),
+
+    /// The lock file is not valid JSON or does not match the format
+    #[error("Invalid flake.lock: {0}")]
+    Json(String),
+
+    /// The lock file version is not supported
+    #[error("Unsupported flake.lock version {0}")]
+    UnsupportedVersion(u32),
+
+    /// An input refers to a node that does not exist
+    #[error("Node '{node}' referenced by '{referenced_by}' does not exist")]
+    MissingNode {
+        /// Missing node name
+        node: String,
+        /// Node holding the reference
+        referenced_by: String,
+    },
+
+    /// A follows path names an input that does not exist
+    #[error("Input path '{}' does not exist", .0.join("/"))]
+    MissingInput(Vec<String>),
+
+    /// Follows paths that refer to each other
+    #[error("Follows cycle through '{}'", .0.join("/"))]
+    FollowsCycle(Vec<String>),
+}
+
+/// Result type for lock file operations
+pub type LockResult<T> = std::result::Result<T, LockError>;
+
+// ============================================================================
+// Lock File
+// ============================================================================
+
+/// A `flake.lock` file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlakeLock {
+    /// Locked nodes by name
+    pub nodes: BTreeMap<String, LockNode>,
+    /// Name of the node for the flake itself (usually `"root"`)
+    pub root: String,
+    /// Lock file version
+    pub version: u32,
+}
+
+/// A node in the lock file: the root flake or one locked input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockNode {
+    /// Whether the input is a flake (`flake = false` inputs are plain sources)
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub flake: bool,
+    /// Inputs of this node
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inputs: BTreeMap<String, InputRef>,
+    /// Reference pinned to a specific revision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked: Option<FlakeRef>,
+    /// Reference as written in `flake.nix`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original: Option<FlakeRef>,
+    /// Input path of the flake a relative `path:` input is relative to
+    /// (version 7)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Vec<String>>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+/// How a node refers to one of its inputs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InputRef {
+    /// Name of the node locking the input
+    Node(String),
+    /// Input path, from the root, whose node this input uses
+    Follows(Vec<String>),
+}
+
+impl FlakeLock {
+    /// Parse a lock file from JSON
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`LockError::Json`] for malformed input and
+    /// [`LockError::UnsupportedVersion`] for versions outside
+    /// [`SUPPORTED_LOCK_VERSIONS`].
+    pub fn from_json(json: &str) -> LockResult<Self> {
+        let lock: Self = serde_json::from_str(json).map_err(|e| LockError::Json(e.to_string()))?;
+        if !SUPPORTED_LOCK_VERSIONS.contains(&lock.version) {
+            return Err(LockError::UnsupportedVersion(lock.version));
+        }
+        if !lock.nodes.contains_key(&lock.root) {
+            return Err(LockError::MissingNode {
+                node: lock.root.clone(),
+                referenced_by: "root".to_string(),
+            });
+        }
+        Ok(lock)
+    }
+
+    /// Read a lock file from disk
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`LockError::Io`] if the file cannot be read, or any error of
+    /// [`from_json`](Self::from_json).
+    pub fn from_file(path: impl AsRef<Path>) -> LockResult<Self> {
+        let json = std::fs::read_to_string(path).map_err(|e| LockError::Io(e.to_string()))?;
+        Self::from_json(&json)
+    }
+
+    /// Write the lock file as JSON, in the layout Nix uses
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`LockError::Json`] if an extra field cannot be serialized.
+    pub fn to_json(&self) -> LockResult<String> {
+        // Going through `Value` sorts keys like Nix does
+        let value = serde_json::to_value(self).map_err(|e| LockError::Json(e.to_string()))?;
+        let mut json =
+            serde_json::to_string_pretty(&value).map_err(|e| LockError::Json(e.to_string()))?;
+        json.push('\n');
+        Ok(json)
+    }
+
+    /// The root node
+    pub fn root_node(&self) -> Option<&LockNode> {
+        self.nodes.get(&self.root)
+    }
+
+    /// Resolve an input path from the root (`["home-manager", "nixpkgs"]`)
+    /// to the node it uses
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the path or a `follows` on the way does not
+    /// exist, or if `follows` paths form a cycle.
+    pub fn resolve_path(&self, path: &[String]) -> LockResult<&str> {
+        self.resolve_path_from(path, &mut Vec::new())
+    }
+
+    /// Resolve the input `name` of `node`
+    ///
+    /// ## Errors
+    ///
+    /// See [`resolve_path`](Self::resolve_path).
+    pub fn resolve_input(&self, node: &str, name: &str) -> LockResult<&str> {
+        let lock_node = self.nodes.get(node).ok_or_else(|| LockError::MissingNode {
+            node: node.to_string(),
+            referenced_by: name.to_string(),
+        })?;
+        let input = lock_node
+            .inputs
+            .get(name)
+            .ok_or_else(|| LockError::MissingInput(vec![node.to_string(), name.to_string()]))?;
+        self.resolve_ref(node, input, &mut Vec::new())
+    }
+
+    fn resolve_path_from<'a>(
+        &'a self,
+        path: &[String],
+        visiting: &mut Vec<Vec<String>>,
+    ) -> LockResult<&'a str> {
+        let mut current = self.root.as_str();
+        for (i, name) in path.iter().enumerate() {
+            let input = self
+                .nodes
+                .get(current)
+                .and_then(|node| node.inputs.get(name))
+                .ok_or_else(|| LockError::MissingInput(path[..=i].to_vec()))?;
+            current = self.resolve_ref(current, input, visiting)?;
+        }
+        Ok(current)
+    }
+
+    fn resolve_ref<'a>(
+        &'a self,
+        from: &str,
+        input: &'a InputRef,
+        visiting: &mut Vec<Vec<String>>,
+    ) -> LockResult<&'a str> {
+        match input {
+            InputRef::Node(node) => {
+                if self.nodes.contains_key(node) {
+                    Ok(node)
+                } else {
+                    Err(LockError::MissingNode {
+                        node: node.clone(),
+                        referenced_by: from.to_string(),
+                    })
+                }
+            }
+            InputRef::Follows(path) => {
+                if visiting.contains(path) {
+                    return Err(LockError::FollowsCycle(path.clone()));
+                }
+                visiting.push(path.clone());
+                let resolved = self.resolve_path_from(path, visiting);
+                visiting.pop();
+                resolved
+            }
+        }
+    }
+
+    /// Resolve every input of every node into a graph
+    ///
+    /// ## Errors
+    ///
+    /// Returns the first resolution error; see
+    /// [`resolve_path`](Self::resolve_path).
+    pub fn graph(&self) -> LockResult<LockGraph> {
+        let mut edges = Vec::new();
+        for (name, node) in &self.nodes {
+            for (input, reference) in &node.inputs {
+                let to = self.resolve_ref(name, reference, &mut Vec::new())?;
+                edges.push(LockEdge {
+                    from: name.clone(),
+                    input: input.clone(),
+                    to: to.to_string(),
+                    follows: match reference {
+                        InputRef::Node(_) => None,
+                        InputRef::Follows(path) => Some(path.clone()),
+                    },
+                });
+            }
+        }
+        Ok(LockGraph {
+            root: self.root.clone(),
+            nodes: self.nodes.keys().cloned().collect(),
+            edges,
+        })
+    }
+}
+
+// ============================================================================
+// Resolved Graph
+// ============================================================================
+
+/// The lock file with every `follows` resolved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockGraph {
+    /// Root node
+    pub root: String,
+    /// All nodes, including unreachable ones
+    pub nodes: BTreeSet<String>,
+    /// One edge per input of every node
+    pub edges: Vec<LockEdge>,
+}
+
+/// A resolved input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockEdge {
+    /// Node declaring the input
+    pub from: String,
+    /// Input name
+    pub input: String,
+    /// Node the input resolves to
+    pub to: String,
+    /// The `follows` path, if the input follows another one
+    pub follows: Option<Vec<String>>,
+}
+
+impl LockGraph {
+    /// Resolved inputs of a node
+    pub fn inputs_of<'a>(&'a self, node: &'a str) -> impl Iterator<Item = &'a LockEdge> {
+        self.edges.iter().filter(move |edge| edge.from == node)
+    }
+
+    /// Node the input `input` of `node` resolves to
+    pub fn resolve(&self, node: &str, input: &str) -> Option<&str> {
+        self.edges
+            .iter()
+            .find(|edge| edge.from == node && edge.input == input)
+            .map(|edge| edge.to.as_str())
+    }
+
+    /// Nodes reachable from the root, including the root
+    pub fn reachable(&self) -> BTreeSet<&str> {
+        let mut seen = BTreeSet::from([self.root.as_str()]);
+        let mut stack = vec![self.root.as_str()];
+        while let Some(node) = stack.pop() {
+            for edge in self.inputs_of(node) {
+                if seen.insert(edge.to.as_str()) {
+                    stack.push(&edge.to);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Nodes no input resolves to any more (left behind by `follows`)
+    pub fn unreachable(&self) -> BTreeSet<&str> {
+        let reachable = self.reachable();
+        self.nodes
+            .iter()
+            .map(String::as_str)
+            .filter(|node| !reachable.contains(node))
+            .collect()
+    }
+}
+
+// ============================================================================
+// Flake References
+// ============================================================================
+
+/// Kind of source a flake reference points to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum SourceType {
+    /// `github:owner/repo`
+    Github,
+    /// `gitlab:owner/repo`
+    Gitlab,
+    /// `sourcehut:~owner/repo`
+    Sourcehut,
+    /// `git+https://...`
+    Git,
+    /// `hg+https://...`
+    Mercurial,
+    /// `https://.../source.tar.gz`
+    Tarball,
+    /// `file+https://...`
+    File,
+    /// `path:/some/dir`
+    Path,
+    /// `flake:nixpkgs`, resolved through the registry
+    Indirect,
+    /// A type this model does not know
+    Other(String),
+}
+
+impl SourceType {
+    /// The `type` attribute value
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Github => "github",
+            Self::Gitlab => "gitlab",
+            Self::Sourcehut => "sourcehut",
+            Self::Git => "git",
+            Self::Mercurial => "mercurial",
+            Self::Tarball => "tarball",
+            Self::File => "file",
+            Self::Path => "path",
+            Self::Indirect => "indirect",
+            Self::Other(other) => other,
+        }
+    }
+}
+
+impl From<String> for SourceType {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "github" => Self::Github,
+            "gitlab" => Self::Gitlab,
+            "sourcehut" => Self::Sourcehut,
+            "git" => Self::Git,
+            "mercurial" => Self::Mercurial,
+            "tarball" => Self::Tarball,
+            "file" => Self::File,
+            "path" => Self::Path,
+            "indirect" => Self::Indirect,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<SourceType> for String {
+    fn from(value: SourceType) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl fmt::Display for SourceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A flake reference in attribute form (`locked` and `original`)
+///
+/// Fields this model does not name are kept in `extra`, so reading and
+/// writing a lock file loses nothing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlakeRef {
+    /// Source type
+    #[serde(rename = "type")]
+    pub source_type: SourceType,
+    /// Owner (github, gitlab, sourcehut)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Repository (github, gitlab, sourcehut)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Host of a self-hosted forge
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// URL (git, mercurial, tarball, file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Filesystem path (path)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Registry identifier (indirect)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Subdirectory containing `flake.nix`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+    /// Branch or tag
+    #[serde(default, rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub git_ref: Option<String>,
+    /// Commit hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// Number of commits up to `rev` (git, mercurial)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev_count: Option<u64>,
+    /// Commit time, seconds since the epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<u64>,
+    /// SRI hash of the source tree
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nar_hash: Option<String>,
+    /// Whether git submodules are fetched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submodules: Option<bool>,
+    /// Whether a shallow git clone is enough
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shallow: Option<bool>,
+    /// Other attributes
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl FlakeRef {
+    /// Create a reference with only a source type
+    pub fn new(source_type: SourceType) -> Self {
+        Self {
+            source_type,
+            owner: None,
+            repo: None,
+            host: None,
+            url: None,
+            path: None,
+            id: None,
+            dir: None,
+            git_ref: None,
+            rev: None,
+            rev_count: None,
+            last_modified: None,
+            nar_hash: None,
+            submodules: None,
+            shallow: None,
+            extra: BTreeMap::new(),
+        }
+    }
+
+    /// Create a `github:owner/repo` reference
+    pub fn github(owner: impl Into<String>, repo: impl Into<String>) -> Self {
+        Self {
+            owner: Some(owner.into()),
+            repo: Some(repo.into()),
+            ..Self::new(SourceType::Github)
+        }
+    }
+
+    /// Query parameters of the URL form, in the order Nix prints them
+    fn query(&self, forge: bool) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+        if forge {
+            if let Some(host) = &self.host {
+                query.push(("host", host.clone()));
+            }
+        } else {
+            if let Some(git_ref) = &self.git_ref {
+                query.push(("ref", git_ref.clone()));
+            }
+            if let Some(rev) = &self.rev {
+                query.push(("rev", rev.clone()));
+            }
+            if let Some(submodules) = self.submodules {
+                query.push(("submodules", u8::from(submodules).to_string()));
+            }
+            if let Some(shallow) = self.shallow {
+                query.push(("shallow", u8::from(shallow).to_string()));
+            }
+        }
+        if let Some(dir) = &self.dir {
+            query.push(("dir", dir.clone()));
+        }
+        query
+    }
+}
+
+/// Formats as a flake URL (`github:NixOS/nixpkgs/nixos-24.05`)
+impl fmt::Display for FlakeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let forge = matches!(
+            self.source_type,
+            SourceType::Github | SourceType::Gitlab | SourceType::Sourcehut
+        );
+        match &self.source_type {
+            SourceType::Github | SourceType::Gitlab | SourceType::Sourcehut => {
+                write!(
+                    f,
+                    "{}:{}/{}",
+                    self.source_type,
+                    self.owner.as_deref().unwrap_or_default(),
+                    self.repo.as_deref().unwrap_or_default()
+                )?;
+                if let Some(pin) = self.rev.as_ref().or(self.git_ref.as_ref()) {
+                    write!(f, "/{pin}")?;
+                }
+            }
+            SourceType::Git => write!(f, "git+{}", self.url.as_deref().unwrap_or_default())?,
+            SourceType::Mercurial => write!(f, "hg+{}", self.url.as_deref().unwrap_or_default())?,
+            SourceType::Tarball => f.write_str(self.url.as_deref().unwrap_or_default())?,
+            SourceType::File => write!(f, "file+{}", self.url.as_deref().unwrap_or_default())?,
+            SourceType::Path => write!(f, "path:{}", self.path.as_deref().unwrap_or_default())?,
+            SourceType::Indirect => {
+                write!(f, "flake:{}", self.id.as_deref().unwrap_or_default())?;
+                for pin in [&self.git_ref, &self.rev].into_iter().flatten() {
+                    write!(f, "/{pin}")?;
+                }
+            }
+            SourceType::Other(other) => {
+                write!(f, "{other}:{}", self.url.as_deref().unwrap_or_default())?;
+            }
+        }
+        let query = if matches!(self.source_type, SourceType::Indirect) {
+            Vec::new()
+        } else {
+            self.query(forge)
+        };
+        for (i, (key, value)) in query.iter().enumerate() {
+            let separator = if i == 0 { '?' } else { '&' };
+            write!(f, "{separator}{key}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FOLLOWS_LOCK: &str = r#"{
+      "nodes": {
+        "home-manager": {
+          "inputs": { "nixpkgs": ["nixpkgs"] },
+          "locked": {
+            "lastModified": 1700000000,
+            "narHash": "sha256-AAAA",
+            "owner": "nix-community",
+            "repo": "home-manager",
+            "rev": "1111",
+            "type": "github"
+          },
+          "original": { "owner": "nix-community", "repo": "home-manager", "type": "github" }
+        },
+        "nixpkgs": {
+          "locked": {
+            "lastModified": 1700000001,
+            "narHash": "sha256-BBBB",
+            "owner": "NixOS",
+            "repo": "nixpkgs",
+            "rev": "2222",
+            "type": "github"
+          },
+          "original": { "id": "nixpkgs", "ref": "nixos-unstable", "type": "indirect" }
+        },
+        "nixpkgs_2": {
+          "locked": { "owner": "NixOS", "repo": "nixpkgs", "rev": "3333", "type": "github" },
+          "original": { "owner": "NixOS", "repo": "nixpkgs", "type": "github" }
+        },
+        "root": {
+          "inputs": {
+            "home-manager": "home-manager",
+            "nixpkgs": "nixpkgs",
+            "secrets": "secrets",
+            "stylix": "stylix"
+          }
+        },
+        "secrets": {
+          "flake": false,
+          "locked": {
+            "lastModified": 1700000002,
+            "narHash": "sha256-CCCC",
+            "ref": "refs/heads/main",
+            "rev": "4444",
+            "revCount": 12,
+            "submodules": true,
+            "type": "git",
+            "url": "ssh://git@example.com/secrets"
+          },
+          "original": { "submodules": true, "type": "git", "url": "ssh://git@example.com/secrets" }
+        },
+        "stylix": {
+          "inputs": {
+            "home-manager": ["home-manager"],
+            "nixpkgs": ["home-manager", "nixpkgs"]
+          },
+          "locked": { "dir": "sub", "host": "gitlab.example.com", "owner": "a", "repo": "b", "rev": "5555", "type": "gitlab" },
+          "original": { "dir": "sub", "host": "gitlab.example.com", "owner": "a", "repo": "b", "type": "gitlab" }
+        }
+      },
+      "root": "root",
+      "version": 7
+    }"#;
+
+    #[test]
+    fn test_round_trip_repository_lock() {
+        let source = include_str!("../../flake.lock");
+        let lock = FlakeLock::from_json(source).unwrap();
+
+        assert_eq!(lock.version, 7);
+        assert_eq!(lock.nodes.len(), 6);
+        let nixpkgs = lock.nodes["nixpkgs"].locked.as_ref().unwrap();
+        assert_eq!(nixpkgs.source_type, SourceType::Github);
+        assert_eq!(nixpkgs.last_modified, Some(1_753_939_845));
+        assert!(nixpkgs.nar_hash.as_deref().unwrap().starts_with("sha256-"));
+
+        assert_eq!(lock.to_json().unwrap(), source);
+    }
+
+    #[test]
+    fn test_follows_resolution() {
+        let lock = FlakeLock::from_json(FOLLOWS_LOCK).unwrap();
+
+        assert_eq!(
+            lock.nodes["home-manager"].inputs["nixpkgs"],
+            InputRef::Follows(vec!["nixpkgs".to_string()])
+        );
+        assert_eq!(
+            lock.resolve_input("home-manager", "nixpkgs").unwrap(),
+            "nixpkgs"
+        );
+        assert_eq!(lock.resolve_input("stylix", "nixpkgs").unwrap(), "nixpkgs");
+        assert_eq!(
+            lock.resolve_path(&["stylix".to_string(), "home-manager".to_string()])
+                .unwrap(),
+            "home-manager"
+        );
+
+        let graph = lock.graph().unwrap();
+        assert_eq!(graph.resolve("stylix", "nixpkgs"), Some("nixpkgs"));
+        let edge = graph
+            .inputs_of("stylix")
+            .find(|edge| edge.input == "nixpkgs")
+            .unwrap();
+        assert_eq!(
+            edge.follows,
+            Some(vec!["home-manager".to_string(), "nixpkgs".to_string()])
+        );
+        assert_eq!(graph.unreachable(), BTreeSet::from(["nixpkgs_2"]));
+        assert_eq!(graph.reachable().len(), 5);
+    }
+
+    #[test]
+    fn test_source_fields() {
+        let lock = FlakeLock::from_json(FOLLOWS_LOCK).unwrap();
+
+        let secrets = &lock.nodes["secrets"];
+        assert!(!secrets.flake);
+        let locked = secrets.locked.as_ref().unwrap();
+        assert_eq!(locked.source_type, SourceType::Git);
+        assert_eq!(locked.rev_count, Some(12));
+        assert_eq!(locked.submodules, Some(true));
+        assert_eq!(
+            locked.to_string(),
+            "git+ssh://git@example.com/secrets?ref=refs/heads/main&rev=4444&submodules=1"
+        );
+
+        let stylix = lock.nodes["stylix"].locked.as_ref().unwrap();
+        assert_eq!(
+            stylix.to_string(),
+            "gitlab:a/b/5555?host=gitlab.example.com&dir=sub"
+        );
+        let original = lock.nodes["nixpkgs"].original.as_ref().unwrap();
+        assert_eq!(original.to_string(), "flake:nixpkgs/nixos-unstable");
+
+        // Unchanged through a write and re-read
+        let reread = FlakeLock::from_json(&lock.to_json().unwrap()).unwrap();
+        assert_eq!(reread, lock);
+    }
+
+    #[test]
+    fn test_other_source_types() {
+        let cases = [
+            (
+                r#"{ "type": "path", "path": "/src/flake", "narHash": "sha256-x" }"#,
+                "path:/src/flake",
+            ),
+            (
+                r#"{ "type": "tarball", "url": "https://example.com/a.tar.gz" }"#,
+                "https://example.com/a.tar.gz",
+            ),
+            (
+                r#"{ "type": "file", "url": "https://example.com/a.nix" }"#,
+                "file+https://example.com/a.nix",
+            ),
+            (
+                r#"{ "type": "mercurial", "url": "https://hg.example.com/r", "rev": "abc" }"#,
+                "hg+https://hg.example.com/r?rev=abc",
+            ),
+            (
+                r#"{ "type": "sourcehut", "owner": "~user", "repo": "r", "ref": "main" }"#,
+                "sourcehut:~user/r/main",
+            ),
+        ];
+        for (json, url) in cases {
+            let flake_ref: FlakeRef = serde_json::from_str(json).unwrap();
+            assert_eq!(flake_ref.to_string(), url);
+        }
+
+        let future: FlakeRef =
+            serde_json::from_str(r#"{ "type": "s3", "url": "s3://bucket", "region": "eu" }"#)
+                .unwrap();
+        assert_eq!(future.source_type, SourceType::Other("s3".to_string()));
+        assert_eq!(future.extra["region"], "eu");
+        let json = serde_json::to_value(&future).unwrap();
+        assert_eq!(json["type"], "s3");
+        assert_eq!(json["region"], "eu");
+    }
+
+    #[test]
+    fn test_relative_path_parent() {
+        let lock = FlakeLock::from_json(
+            r#"{
+              "nodes": {
+                "root": { "inputs": { "sub": "sub" } },
+                "sub": {
+                  "locked": { "path": "./sub", "type": "path" },
+                  "original": { "path": "./sub", "type": "path" },
+                  "parent": []
+                }
+              },
+              "root": "root",
+              "version": 7
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(lock.nodes["sub"].parent, Some(Vec::new()));
+        assert!(lock.to_json().unwrap().contains("\"parent\": []"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(FlakeLock::from_json("{"), Err(LockError::Json(_))));
+        assert_eq!(
+            FlakeLock::from_json(r#"{ "nodes": { "root": {} }, "root": "root", "version": 4 }"#),
+            Err(LockError::UnsupportedVersion(4))
+        );
+        assert!(matches!(
+            FlakeLock::from_json(r#"{ "nodes": {}, "root": "root", "version": 7 }"#),
+            Err(LockError::MissingNode { .. })
+        ));
+
+        let dangling = FlakeLock::from_json(
+            r#"{ "nodes": { "root": { "inputs": { "a": "gone" } } }, "root": "root", "version": 7 }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            dangling.graph(),
+            Err(LockError::MissingNode { node, .. }) if node == "gone"
+        ));
+
+        let cycle = FlakeLock::from_json(
+            r#"{ "nodes": { "root": { "inputs": { "a": ["b"], "b": ["a"] } } }, "root": "root", "version": 7 }"#,
+        )
+        .unwrap();
+        assert!(matches!(cycle.graph(), Err(LockError::FollowsCycle(_))));
+
+        let missing = FlakeLock::from_json(
+            r#"{ "nodes": { "root": { "inputs": { "a": ["b", "c"] } } }, "root": "root", "version": 7 }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            missing.resolve_input("root", "a"),
+            Err(LockError::MissingInput(vec!["b".to_string()]))
+        );
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Flake Metadata
+//!
+//! Typed model of `nix flake metadata --json`: where a flake was fetched
+//! from, the revision it was locked to and its full lock file.
+//!
+//! ```rust
+//! use cim_domain_nix::nix::FlakeMetadata;
+//!
+//! let metadata = FlakeMetadata::from_json(r#"{
+//!     "originalUrl": "github:NixOS/nixpkgs/nixos-24.05",
+//!     "url": "github:NixOS/nixpkgs/1a2b3c",
+//!     "revision": "1a2b3c",
+//!     "lastModified": 1718000000
+//! }"#).unwrap();
+//! assert_eq!(metadata.revision.as_deref(), Some("1a2b3c"));
+//! assert!(!metadata.is_dirty());
+//! ```
+
+use super::flake_evaluator::{EvaluationError, EvaluationResult};
+use super::flake_lock::{FlakeLock, FlakeRef};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Output of `nix flake metadata --json`
+///
+/// Fields this model does not name are kept in `extra`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlakeMetadata {
+    /// Flake description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Reference as given on the command line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_url: Option<String>,
+    /// Reference as given on the command line, as attributes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original: Option<FlakeRef>,
+    /// Reference after registry lookup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_url: Option<String>,
+    /// Reference after registry lookup, as attributes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<FlakeRef>,
+    /// Locked reference
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Locked reference, as attributes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked: Option<FlakeRef>,
+    /// Store path of the flake source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Commit hash of a clean source tree
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// Commit hash with a `-dirty` suffix for a tree with uncommitted changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dirty_revision: Option<String>,
+    /// Number of commits up to the revision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev_count: Option<u64>,
+    /// Commit time, seconds since the epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<u64>,
+    /// Cache key of the locked source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// Contents of `flake.lock`, with inputs Nix would add on the next lock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locks: Option<FlakeLock>,
+    /// Other attributes
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl FlakeMetadata {
+    /// Parse the output of `nix flake metadata --json`
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`EvaluationError::ParseError`] if the JSON does not match.
+    pub fn from_json(json: &str) -> EvaluationResult<Self> {
+        serde_json::from_str(json).map_err(|e| EvaluationError::ParseError(e.to_string()))
+    }
+
+    /// Whether the source tree has uncommitted changes
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_revision.is_some()
+    }
+
+    /// Names of the flake's direct inputs
+    pub fn inputs(&self) -> Vec<&str> {
+        self.locks
+            .as_ref()
+            .and_then(FlakeLock::root_node)
+            .map(|root| root.inputs.keys().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nix::flake_lock::SourceType;
+
+    const METADATA: &str = r#"{
+      "description": "A demo flake",
+      "dirtyRevision": "0123abcd-dirty",
+      "fingerprint": "f00d",
+      "lastModified": 1718000000,
+      "locked": { "lastModified": 1718000000, "narHash": "sha256-AAAA", "type": "git", "url": "file:///src/demo" },
+      "locks": {
+        "nodes": {
+          "nixpkgs": {
+            "locked": { "owner": "NixOS", "repo": "nixpkgs", "rev": "deadbeef", "narHash": "sha256-BBBB", "type": "github", "lastModified": 1717000000 },
+            "original": { "owner": "NixOS", "ref": "nixos-24.05", "repo": "nixpkgs", "type": "github" }
+          },
+          "root": { "inputs": { "nixpkgs": "nixpkgs" } }
+        },
+        "root": "root",
+        "version": 7
+      },
+      "original": { "type": "path", "path": "/src/demo" },
+      "originalUrl": "path:/src/demo",
+      "path": "/nix/store/abc-source",
+      "resolved": { "type": "path", "path": "/src/demo" },
+      "resolvedUrl": "path:/src/demo",
+      "revCount": 42,
+      "url": "git+file:///src/demo",
+      "someFutureField": [1, 2]
+    }"#;
+
+    #[test]
+    fn test_parse_metadata() {
+        let metadata = FlakeMetadata::from_json(METADATA).unwrap();
+
+        assert_eq!(metadata.description.as_deref(), Some("A demo flake"));
+        assert!(metadata.is_dirty());
+        assert_eq!(metadata.rev_count, Some(42));
+        assert_eq!(
+            metadata.locked.as_ref().unwrap().source_type,
+            SourceType::Git
+        );
+        assert_eq!(metadata.inputs(), ["nixpkgs"]);
+        assert_eq!(metadata.extra["someFutureField"], serde_json::json!([1, 2]));
+
+        let round_trip = serde_json::to_string(&metadata).unwrap();
+        assert_eq!(FlakeMetadata::from_json(&round_trip).unwrap(), metadata);
+        assert!(matches!(
+            FlakeMetadata::from_json("[]"),
+            Err(EvaluationError::ParseError(_))
+        ));
+    }
+}
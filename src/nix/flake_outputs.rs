@@ -0,0 +1,1710 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Static Analysis of Flake Outputs
+//!
+//! A flake's `outputs` is a function of its inputs, so it cannot be turned
+//! into a [`NixValue`](crate::nix::NixValue) without fetching and evaluating
+//! nixpkgs. This module reads the function body syntactically instead and
+//! recovers which packages, dev shells, apps, checks and NixOS
+//! configurations the flake defines, per system.
+//!
+//! ## Recognised Patterns
+//!
+//! ```nix
+//! # Direct
+//! packages.x86_64-linux.default = ...;
+//!
+//! # flake-utils
+//! flake-utils.lib.eachDefaultSystem (system: { packages.default = ...; })
+//! flake-utils.lib.eachSystem [ "x86_64-linux" ] (system: { ... })
+//!
+//! # genAttrs helpers
+//! let forAllSystems = nixpkgs.lib.genAttrs [ "x86_64-linux" "aarch64-linux" ];
+//! in { packages = forAllSystems (system: { default = ...; }); }
+//!
+//! # flake-parts
+//! flake-parts.lib.mkFlake { inherit inputs; } {
+//!   systems = [ "x86_64-linux" ];
+//!   perSystem = { pkgs, ... }: { packages.default = ...; };
+//!   flake.nixosConfigurations.host = ...;
+//! }
+//! ```
+//!
+//! Helpers bound with `let` are followed, `//` and `recursiveUpdate` merge
+//! both sides, and `nixpkgs.lib.nixosSystem { system = ...; }` provides the
+//! system of a NixOS configuration. Anything that would need evaluation
+//! (`import ./packages.nix`, `self.packages`, computed names) is reported in
+//! [`FlakeOutputs::unresolved`] rather than guessed.
+
+use super::ast::Span;
+use crate::adapters::host_reader::inline_module_facts;
+use rnix::ast::{self, BinOpKind, HasEntry, InterpolPart};
+use rowan::ast::AstNode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::rc::Rc;
+
+/// Systems `flake-utils.lib.eachDefaultSystem` expands to
+pub const DEFAULT_SYSTEMS: [&str; 4] = [
+    "aarch64-darwin",
+    "aarch64-linux",
+    "x86_64-darwin",
+    "x86_64-linux",
+];
+
+/// Nesting limit when following `let` bindings and helpers
+pub(crate) const MAX_DEPTH: usize = 64;
+
+// ============================================================================
+// Analysis Result
+// ============================================================================
+
+/// Output names defined for one system
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemOutputs {
+    /// `packages.<system>.<name>`
+    pub packages: BTreeSet<String>,
+    /// `devShells.<system>.<name>`
+    pub dev_shells: BTreeSet<String>,
+    /// `apps.<system>.<name>`
+    pub apps: BTreeSet<String>,
+    /// `checks.<system>.<name>`
+    pub checks: BTreeSet<String>,
+}
+
+impl SystemOutputs {
+    /// Check if no outputs are defined
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+            && self.dev_shells.is_empty()
+            && self.apps.is_empty()
+            && self.checks.is_empty()
+    }
+
+    fn names_mut(&mut self, category: Category) -> Option<&mut BTreeSet<String>> {
+        match category {
+            Category::Packages => Some(&mut self.packages),
+            Category::DevShells => Some(&mut self.dev_shells),
+            Category::Apps => Some(&mut self.apps),
+            Category::Checks => Some(&mut self.checks),
+            Category::NixosConfigurations => None,
+        }
+    }
+}
+
+/// A `nixosConfigurations.<name>` entry
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NixosConfiguration {
+    /// Configuration name (usually the host name)
+    pub name: String,
+    /// System from `nixosSystem { system = ...; }` or
+    /// `nixpkgs.hostPlatform`, when static
+    pub system: Option<String>,
+    /// This flake's packages the configuration refers to
+    /// (`self.packages.${system}.<name>`) in modules written inline
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub packages: BTreeSet<String>,
+    /// This flake's apps the configuration refers to
+    /// (`self.apps.${system}.<name>`) in modules written inline
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub apps: BTreeSet<String>,
+    /// Services enabled (`services.<name>.enable = true`) in modules written
+    /// inline
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub services: BTreeSet<String>,
+    /// Whether a module written inline sets `boot.isContainer = true`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub container: bool,
+}
+
+/// How a flake spreads its outputs over systems
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum OutputPattern {
+    /// Literal `packages.<system>.<name>` attributes
+    Direct,
+    /// `flake-utils.lib.eachDefaultSystem` / `eachSystem`
+    FlakeUtils,
+    /// `genAttrs`-based helpers such as `forAllSystems`
+    GenAttrs,
+    /// flake-parts `mkFlake` with `perSystem`
+    FlakeParts,
+}
+
+/// Part of the outputs that could not be analysed without evaluation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnresolvedOutput {
+    /// Output attribute path leading to the expression (e.g. `packages`)
+    pub path: String,
+    /// Location of the expression
+    pub span: Span,
+    /// Why the expression was not analysed
+    pub reason: String,
+}
+
+/// Outputs recovered from a flake's `outputs` function
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlakeOutputs {
+    /// Outputs per system
+    pub systems: BTreeMap<String, SystemOutputs>,
+    /// Outputs whose system is computed (e.g. `nixpkgs.lib.systems.flakeExposed`)
+    pub unknown_system: SystemOutputs,
+    /// `nixosConfigurations`, sorted by name
+    pub nixos_configurations: Vec<NixosConfiguration>,
+    /// Patterns seen while analysing
+    pub patterns: BTreeSet<OutputPattern>,
+    /// Expressions that were skipped
+    pub unresolved: Vec<UnresolvedOutput>,
+}
+
+impl FlakeOutputs {
+    /// Outputs defined for `system`
+    pub fn for_system(&self, system: &str) -> Option<&SystemOutputs> {
+        self.systems.get(system)
+    }
+
+    /// Systems with at least one output
+    pub fn system_names(&self) -> impl Iterator<Item = &str> {
+        self.systems.keys().map(String::as_str)
+    }
+
+    /// Check if nothing was found
+    pub fn is_empty(&self) -> bool {
+        self.systems.values().all(SystemOutputs::is_empty)
+            && self.unknown_system.is_empty()
+            && self.nixos_configurations.is_empty()
+    }
+
+    fn outputs_mut(&mut self, system: Option<&str>) -> &mut SystemOutputs {
+        match system {
+            Some(system) => self.systems.entry(system.to_string()).or_default(),
+            None => &mut self.unknown_system,
+        }
+    }
+}
+
+// ============================================================================
+// Definitions
+// ============================================================================
+
+/// Output categories the analyser tracks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Category {
+    Packages,
+    DevShells,
+    Apps,
+    Checks,
+    NixosConfigurations,
+}
+
+impl Category {
+    /// The category of a top-level output name, and whether it is the
+    /// pre-`default` singular form (`defaultPackage.<system>`)
+    fn from_output(name: &str) -> Option<(Self, bool)> {
+        match name {
+            "packages" => Some((Self::Packages, false)),
+            "devShells" => Some((Self::DevShells, false)),
+            "apps" => Some((Self::Apps, false)),
+            "checks" => Some((Self::Checks, false)),
+            "nixosConfigurations" => Some((Self::NixosConfigurations, false)),
+            "defaultPackage" => Some((Self::Packages, true)),
+            "devShell" => Some((Self::DevShells, true)),
+            "defaultApp" => Some((Self::Apps, true)),
+            _ => None,
+        }
+    }
+
+    fn is_per_system(self) -> bool {
+        self != Self::NixosConfigurations
+    }
+}
+
+/// An output definition, kept so callers can read details out of the
+/// defining expression
+pub(crate) struct Definition {
+    pub(crate) category: Category,
+    pub(crate) name: String,
+    /// `None` when the systems are not known statically
+    pub(crate) systems: Option<Vec<String>>,
+    pub(crate) value: Option<Bound>,
+}
+
+/// Analyse the `outputs` expression of a flake
+pub(crate) fn analyze_outputs(outputs: &ast::Expr) -> (FlakeOutputs, Vec<Definition>) {
+    let mut analyzer = Analyzer::default();
+    let env = Env::root();
+    match strip_parens(outputs) {
+        ast::Expr::Lambda(lambda) => {
+            let env = env.bind_params(lambda.param(), None);
+            if let Some(body) = lambda.body() {
+                analyzer.walk(&body, &Ctx::root(env));
+            }
+        }
+        other => analyzer.walk(&other, &Ctx::root(env)),
+    }
+
+    let mut outputs = analyzer.outputs;
+    outputs.nixos_configurations = analyzer.nixos.into_values().collect();
+    (outputs, analyzer.definitions)
+}
+
+// ============================================================================
+// Environments
+// ============================================================================
+
+/// What a variable is bound to
+#[derive(Clone)]
+pub(crate) enum Binding {
+    /// A `let` or `rec` binding, with the scope its value is written in
+    Expr(Bound),
+    /// The per-system variable of a system helper
+    System(Systems),
+    /// A function parameter or otherwise unknown value
+    Opaque,
+}
+
+/// An expression together with the scope it was written in
+#[derive(Clone)]
+pub(crate) struct Bound {
+    pub(crate) expr: ast::Expr,
+    pub(crate) env: Env,
+}
+
+/// Lexical scopes; only bindings the analyser can use are recorded
+#[derive(Clone, Default)]
+pub(crate) struct Env(Option<Rc<Frame>>);
+
+pub(crate) struct Frame {
+    vars: HashMap<String, Slot>,
+    parent: Env,
+}
+
+/// A variable as stored in its frame
+enum Slot {
+    /// A binding of this frame's `let`/`rec`, evaluated in the frame itself
+    ///
+    /// Storing the expression without its scope avoids a reference cycle
+    /// between the frame and its values; [`Env::lookup`] adds it back.
+    Local(ast::Expr),
+    Bound(Binding),
+}
+
+impl Env {
+    fn root() -> Self {
+        Self(None)
+    }
+
+    fn push(&self, vars: HashMap<String, Slot>) -> Self {
+        Self(Some(Rc::new(Frame {
+            vars,
+            parent: self.clone(),
+        })))
+    }
+
+    pub(crate) fn lookup(&self, name: &str) -> Option<Binding> {
+        let mut current = self.0.as_ref();
+        while let Some(frame) = current {
+            match frame.vars.get(name) {
+                Some(Slot::Local(expr)) => {
+                    return Some(Binding::Expr(Bound {
+                        expr: expr.clone(),
+                        env: Env(Some(Rc::clone(frame))),
+                    }))
+                }
+                Some(Slot::Bound(binding)) => return Some(binding.clone()),
+                None => current = frame.parent.0.as_ref(),
+            }
+        }
+        None
+    }
+
+    /// Scope of a lambda body; `system` is what the parameter standing for
+    /// the current system (`system:` or `{ system, ... }:`) is bound to
+    fn bind_params(&self, param: Option<ast::Param>, system: Option<&Systems>) -> Self {
+        let mut vars = HashMap::new();
+        let mut bind = |name: String, is_system_slot: bool| {
+            let binding = match system {
+                Some(systems) if is_system_slot => Binding::System(systems.clone()),
+                _ => Binding::Opaque,
+            };
+            vars.insert(name, Slot::Bound(binding));
+        };
+        match param {
+            Some(ast::Param::IdentParam(param)) => {
+                if let Some(ident) = param.ident() {
+                    bind(ident_name(&ident), true);
+                }
+            }
+            Some(ast::Param::Pattern(pattern)) => {
+                if let Some(ident) = pattern.pat_bind().and_then(|bind| bind.ident()) {
+                    bind(ident_name(&ident), false);
+                }
+                for ident in pattern.pat_entries().filter_map(|entry| entry.ident()) {
+                    let name = ident_name(&ident);
+                    let is_system = name == "system";
+                    bind(name, is_system);
+                }
+            }
+            None => {}
+        }
+        self.push(vars)
+    }
+
+    /// Scope of a lambda body applied to `arg`; pattern parameters are bound
+    /// to the matching attributes of `arg` when it is an attribute set
+    pub(crate) fn bind_arg(&self, param: Option<ast::Param>, arg: &Bound) -> Self {
+        let mut vars = HashMap::new();
+        match param {
+            Some(ast::Param::IdentParam(param)) => {
+                if let Some(ident) = param.ident() {
+                    vars.insert(ident_name(&ident), Slot::Bound(Binding::Expr(arg.clone())));
+                }
+            }
+            Some(ast::Param::Pattern(pattern)) => {
+                if let Some(ident) = pattern.pat_bind().and_then(|bind| bind.ident()) {
+                    vars.insert(ident_name(&ident), Slot::Bound(Binding::Expr(arg.clone())));
+                }
+                let set = attrset_of(arg);
+                for ident in pattern.pat_entries().filter_map(|entry| entry.ident()) {
+                    let name = ident_name(&ident);
+                    let binding = set
+                        .as_ref()
+                        .and_then(|(set, env)| attr_value(set, env, &[name.as_str()]))
+                        .map_or(Binding::Opaque, Binding::Expr);
+                    vars.insert(name, Slot::Bound(binding));
+                }
+            }
+            None => {}
+        }
+        self.push(vars)
+    }
+
+    /// Scope of a `let` body or `rec` set
+    fn bind_entries(&self, entries: &impl HasEntry) -> Self {
+        let mut vars = HashMap::new();
+        for entry in entries.entries() {
+            match entry {
+                ast::Entry::AttrpathValue(binding) => {
+                    let keys: Vec<Key> = binding
+                        .attrpath()
+                        .into_iter()
+                        .flat_map(|path| path.attrs())
+                        .map(|attr| key_of(&attr))
+                        .collect();
+                    let (Some(Key::Static(name)), Some(value)) = (keys.first(), binding.value())
+                    else {
+                        continue;
+                    };
+                    let slot = if keys.len() == 1 {
+                        Slot::Local(value)
+                    } else {
+                        // `a.b = ...;` defines an attribute set the analyser
+                        // does not reassemble
+                        Slot::Bound(Binding::Opaque)
+                    };
+                    vars.insert(name.clone(), slot);
+                }
+                ast::Entry::Inherit(inherit) => {
+                    let from = inherit.from().and_then(|from| from.expr());
+                    for attr in inherit.attrs() {
+                        if let Key::Static(name) = key_of(&attr) {
+                            let binding = match from {
+                                None => self.lookup(&name).unwrap_or(Binding::Opaque),
+                                Some(_) => Binding::Opaque,
+                            };
+                            vars.insert(name, Slot::Bound(binding));
+                        }
+                    }
+                }
+            }
+        }
+        self.push(vars)
+    }
+}
+
+// ============================================================================
+// Systems and Positions
+// ============================================================================
+
+/// The systems an expression is instantiated for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Systems {
+    Known(Vec<String>),
+    /// Computed, e.g. `nixpkgs.lib.systems.flakeExposed`
+    Unknown,
+}
+
+impl Systems {
+    fn default_systems() -> Self {
+        Self::Known(DEFAULT_SYSTEMS.iter().map(|s| (*s).to_string()).collect())
+    }
+
+    fn known(&self) -> Option<&[String]> {
+        match self {
+            Self::Known(systems) => Some(systems),
+            Self::Unknown => None,
+        }
+    }
+}
+
+/// An attribute name
+#[derive(Debug, Clone)]
+pub(crate) enum Key {
+    Static(String),
+    /// `${expr}`, with the interpolated expression when there is exactly one
+    Dynamic(Option<ast::Expr>),
+}
+
+impl Key {
+    fn display(&self) -> String {
+        match self {
+            Self::Static(name) => name.clone(),
+            Self::Dynamic(Some(expr)) => format!("${{{}}}", expr.syntax().text()),
+            Self::Dynamic(None) => "${...}".to_string(),
+        }
+    }
+}
+
+/// What the attribute names at the current position mean
+#[derive(Debug, Clone)]
+enum Pos {
+    /// Output names (`packages`, `nixosConfigurations`, ...)
+    Root,
+    /// System names below a per-system output
+    Systems { category: Category, legacy: bool },
+    /// Definition names; `systems` is `None` for system-independent outputs
+    Names {
+        category: Category,
+        systems: Option<Systems>,
+    },
+    /// Inside a definition, or an output the analyser does not track
+    Ignored,
+}
+
+#[derive(Clone)]
+struct Ctx {
+    pos: Pos,
+    /// Systems of the enclosing per-system function, if any
+    ambient: Option<Systems>,
+    env: Env,
+    /// Output attribute path, for reporting
+    path: Vec<String>,
+    depth: usize,
+}
+
+impl Ctx {
+    fn root(env: Env) -> Self {
+        Self {
+            pos: Pos::Root,
+            ambient: None,
+            env,
+            path: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    fn with_env(&self, env: Env) -> Self {
+        Self {
+            env,
+            depth: self.depth + 1,
+            ..self.clone()
+        }
+    }
+
+    fn at(&self, pos: Pos, ambient: Option<Systems>, env: Env) -> Self {
+        Self {
+            pos,
+            ambient,
+            env,
+            path: self.path.clone(),
+            depth: self.depth + 1,
+        }
+    }
+}
+
+// ============================================================================
+// Analyzer
+// ============================================================================
+
+#[derive(Default)]
+struct Analyzer {
+    outputs: FlakeOutputs,
+    nixos: BTreeMap<String, NixosConfiguration>,
+    definitions: Vec<Definition>,
+}
+
+impl Analyzer {
+    fn walk(&mut self, expr: &ast::Expr, ctx: &Ctx) {
+        if matches!(ctx.pos, Pos::Ignored) {
+            return;
+        }
+        if ctx.depth > MAX_DEPTH {
+            self.unresolved(ctx, expr, "nesting too deep to follow".to_string());
+            return;
+        }
+        match expr {
+            ast::Expr::Paren(paren) => {
+                if let Some(inner) = paren.expr() {
+                    self.walk(&inner, ctx);
+                }
+            }
+            ast::Expr::AttrSet(set) => self.walk_attrset(set, ctx),
+            ast::Expr::LetIn(let_in) => {
+                let env = ctx.env.bind_entries(let_in);
+                if let Some(body) = let_in.body() {
+                    self.walk(&body, &ctx.with_env(env));
+                }
+            }
+            ast::Expr::With(with) => {
+                if let Some(body) = with.body() {
+                    self.walk(&body, ctx);
+                }
+            }
+            ast::Expr::Assert(assert) => {
+                if let Some(body) = assert.body() {
+                    self.walk(&body, ctx);
+                }
+            }
+            ast::Expr::IfElse(if_else) => {
+                for branch in [if_else.body(), if_else.else_body()].into_iter().flatten() {
+                    self.walk(&branch, ctx);
+                }
+            }
+            ast::Expr::BinOp(op) if op.operator() == Some(BinOpKind::Update) => {
+                for side in [op.lhs(), op.rhs()].into_iter().flatten() {
+                    self.walk(&side, ctx);
+                }
+            }
+            ast::Expr::Ident(ident) => {
+                let name = ident_name(ident);
+                match ctx.env.lookup(&name) {
+                    Some(Binding::Expr(bound)) => self.walk(&bound.expr, &ctx.with_env(bound.env)),
+                    _ => self.unresolved(ctx, expr, format!("`{name}` is not a static binding")),
+                }
+            }
+            ast::Expr::Apply(_) => self.walk_apply(expr, ctx),
+            _ => self.unresolved(ctx, expr, format!("`{}` needs evaluation", snippet(expr))),
+        }
+    }
+
+    fn walk_attrset(&mut self, set: &ast::AttrSet, ctx: &Ctx) {
+        let env = if set.rec_token().is_some() {
+            ctx.env.bind_entries(set)
+        } else {
+            ctx.env.clone()
+        };
+        let ctx = Ctx { env, ..ctx.clone() };
+        for entry in set.entries() {
+            match entry {
+                ast::Entry::AttrpathValue(binding) => {
+                    let keys: Vec<Key> = binding
+                        .attrpath()
+                        .into_iter()
+                        .flat_map(|path| path.attrs())
+                        .map(|attr| key_of(&attr))
+                        .collect();
+                    let Some(value) = binding.value() else {
+                        continue;
+                    };
+                    let bound = Bound {
+                        expr: value.clone(),
+                        env: ctx.env.clone(),
+                    };
+                    let mut current = ctx.clone();
+                    for (i, key) in keys.iter().enumerate() {
+                        let is_last = i + 1 == keys.len();
+                        current = self.descend(&current, key, is_last.then_some(&bound));
+                    }
+                    self.walk(&value, &current);
+                }
+                ast::Entry::Inherit(inherit) => {
+                    let from = inherit.from().and_then(|from| from.expr());
+                    for attr in inherit.attrs() {
+                        let key = key_of(&attr);
+                        let bound = match (&from, &key) {
+                            (None, Key::Static(name)) => match ctx.env.lookup(name) {
+                                Some(Binding::Expr(bound)) => Some(bound),
+                                _ => None,
+                            },
+                            _ => None,
+                        };
+                        let next = self.descend(&ctx, &key, bound.as_ref());
+                        match (&next.pos, bound) {
+                            (Pos::Ignored, _) => {}
+                            (_, Some(bound)) => self.walk(&bound.expr, &next.with_env(bound.env)),
+                            (_, None) => self.unresolved(
+                                &next,
+                                inherit.syntax(),
+                                "inherited from an expression that needs evaluation".to_string(),
+                            ),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Move one attribute name down from `ctx`, recording a definition when
+    /// the name is an output name
+    fn descend(&mut self, ctx: &Ctx, key: &Key, value: Option<&Bound>) -> Ctx {
+        let mut next = ctx.clone();
+        next.path.push(key.display());
+        next.pos = match (&ctx.pos, key) {
+            (Pos::Root, Key::Static(name)) => match Category::from_output(name) {
+                None => Pos::Ignored,
+                Some((category, _)) if !category.is_per_system() => Pos::Names {
+                    category,
+                    systems: None,
+                },
+                Some((category, legacy)) => match &ctx.ambient {
+                    Some(systems) if legacy => {
+                        self.record(category, "default", Some(systems), value);
+                        Pos::Ignored
+                    }
+                    Some(systems) => Pos::Names {
+                        category,
+                        systems: Some(systems.clone()),
+                    },
+                    None => Pos::Systems { category, legacy },
+                },
+            },
+            (Pos::Systems { category, legacy }, key) => {
+                let systems = match key {
+                    Key::Static(system) => {
+                        self.outputs.patterns.insert(OutputPattern::Direct);
+                        Systems::Known(vec![system.clone()])
+                    }
+                    Key::Dynamic(expr) => Self::dynamic_systems(expr.as_ref(), ctx),
+                };
+                if *legacy {
+                    self.record(*category, "default", Some(&systems), value);
+                    Pos::Ignored
+                } else {
+                    Pos::Names {
+                        category: *category,
+                        systems: Some(systems),
+                    }
+                }
+            }
+            (Pos::Names { category, systems }, Key::Static(name)) => {
+                self.record(*category, name, systems.as_ref(), value);
+                Pos::Ignored
+            }
+            (Pos::Names { .. }, Key::Dynamic(_)) => {
+                if let Some(value) = value {
+                    self.unresolved(&next, &value.expr, "computed output name".to_string());
+                }
+                Pos::Ignored
+            }
+            (Pos::Root | Pos::Ignored, _) => Pos::Ignored,
+        };
+        next.depth += 1;
+        next
+    }
+
+    /// Systems named by `${expr}` in a system position
+    fn dynamic_systems(expr: Option<&ast::Expr>, ctx: &Ctx) -> Systems {
+        if let Some(ast::Expr::Ident(ident)) = expr.map(strip_parens) {
+            if let Some(Binding::System(systems)) = ctx.env.lookup(&ident_name(&ident)) {
+                return systems;
+            }
+        }
+        ctx.ambient.clone().unwrap_or(Systems::Unknown)
+    }
+
+    fn walk_apply(&mut self, expr: &ast::Expr, ctx: &Ctx) {
+        let Some(call) = Call::of(expr, &ctx.env) else {
+            self.unresolved(ctx, expr, format!("`{}` needs evaluation", snippet(expr)));
+            return;
+        };
+        let head = call.head.as_deref().unwrap_or_default();
+        match (&ctx.pos, head) {
+            (Pos::Root, "eachDefaultSystem" | "eachSystem") => {
+                let systems = if head == "eachDefaultSystem" {
+                    Systems::default_systems()
+                } else {
+                    call.args.first().map_or(Systems::Unknown, systems_of)
+                };
+                self.outputs.patterns.insert(OutputPattern::FlakeUtils);
+                if let Some(function) = call.args.last() {
+                    self.walk_system_function(function, Pos::Root, systems, ctx);
+                }
+            }
+            (Pos::Root, "mkFlake") => {
+                self.outputs.patterns.insert(OutputPattern::FlakeParts);
+                if let Some(module) = call.args.last() {
+                    self.walk_flake_parts(module, ctx);
+                }
+            }
+            (_, "recursiveUpdate" | "mkMerge") => {
+                for arg in &call.args {
+                    self.walk(&arg.expr, &ctx.with_env(arg.env.clone()));
+                }
+            }
+            (Pos::Systems { category, legacy }, _)
+                if head == "genAttrs" || call.wrapper.is_some() =>
+            {
+                let systems = if head == "genAttrs" {
+                    call.args.first().map_or(Systems::Unknown, systems_of)
+                } else {
+                    call.wrapper
+                        .as_ref()
+                        .and_then(find_gen_attrs_systems)
+                        .unwrap_or(Systems::Unknown)
+                };
+                self.outputs.patterns.insert(OutputPattern::GenAttrs);
+                let Some(function) = call.args.last() else {
+                    return;
+                };
+                if *legacy {
+                    self.record(*category, "default", Some(&systems), None);
+                    return;
+                }
+                let pos = Pos::Names {
+                    category: *category,
+                    systems: Some(systems.clone()),
+                };
+                self.walk_system_function(function, pos, systems, ctx);
+            }
+            _ => self.unresolved(
+                ctx,
+                expr,
+                format!("call to `{}` needs evaluation", snippet(expr)),
+            ),
+        }
+    }
+
+    /// Walk the body of a per-system function (`system: { ... }`)
+    fn walk_system_function(&mut self, function: &Bound, pos: Pos, systems: Systems, ctx: &Ctx) {
+        let Some(function) = resolve_expr(function) else {
+            self.unresolved(
+                ctx,
+                &function.expr,
+                "per-system function needs evaluation".to_string(),
+            );
+            return;
+        };
+        let ast::Expr::Lambda(lambda) = strip_parens(&function.expr) else {
+            self.unresolved(
+                ctx,
+                &function.expr,
+                "per-system function needs evaluation".to_string(),
+            );
+            return;
+        };
+        let mut env = function.env.bind_params(lambda.param(), Some(&systems));
+        let mut body = lambda.body();
+        // Curried helpers such as `mapAttrs (system: pkgs: ...)`
+        while let Some(ast::Expr::Lambda(inner)) = body.as_ref().map(strip_parens) {
+            env = env.bind_params(inner.param(), None);
+            body = inner.body();
+        }
+        if let Some(body) = body {
+            self.walk(&body, &ctx.at(pos, Some(systems), env));
+        }
+    }
+
+    /// Walk a flake-parts module: `systems`, `perSystem` and `flake`
+    fn walk_flake_parts(&mut self, module: &Bound, ctx: &Ctx) {
+        let Some((set, env)) = attrset_of(module) else {
+            self.unresolved(
+                ctx,
+                &module.expr,
+                "flake-parts module needs evaluation".to_string(),
+            );
+            return;
+        };
+        let entries: Vec<(Vec<Key>, ast::Expr)> = set
+            .entries()
+            .filter_map(|entry| match entry {
+                ast::Entry::AttrpathValue(binding) => Some((
+                    binding
+                        .attrpath()
+                        .into_iter()
+                        .flat_map(|path| path.attrs())
+                        .map(|attr| key_of(&attr))
+                        .collect(),
+                    binding.value()?,
+                )),
+                ast::Entry::Inherit(_) => None,
+            })
+            .collect();
+        let first = |keys: &[Key]| match keys.first() {
+            Some(Key::Static(name)) => name.clone(),
+            _ => String::new(),
+        };
+
+        let systems = entries
+            .iter()
+            .find(|(keys, _)| keys.len() == 1 && first(keys) == "systems")
+            .map_or(Systems::Unknown, |(_, value)| {
+                systems_of(&Bound {
+                    expr: value.clone(),
+                    env: env.clone(),
+                })
+            });
+
+        for (keys, value) in &entries {
+            let bound = Bound {
+                expr: value.clone(),
+                env: env.clone(),
+            };
+            match first(keys).as_str() {
+                "perSystem" if keys.len() == 1 => {
+                    let ctx = ctx.with_env(env.clone());
+                    self.walk_system_function(&bound, Pos::Root, systems.clone(), &ctx);
+                }
+                "flake" => {
+                    let mut current = ctx.at(Pos::Root, None, env.clone());
+                    for (i, key) in keys.iter().enumerate().skip(1) {
+                        let is_last = i + 1 == keys.len();
+                        current = self.descend(&current, key, is_last.then_some(&bound));
+                    }
+                    self.walk(value, &current);
+                }
+                "imports" => self.unresolved(
+                    ctx,
+                    value,
+                    "flake-parts imports are not followed".to_string(),
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    fn record(
+        &mut self,
+        category: Category,
+        name: &str,
+        systems: Option<&Systems>,
+        value: Option<&Bound>,
+    ) {
+        if category == Category::NixosConfigurations {
+            let entry = self
+                .nixos
+                .entry(name.to_string())
+                .or_insert_with(|| NixosConfiguration {
+                    name: name.to_string(),
+                    ..NixosConfiguration::default()
+                });
+            if entry.system.is_none() {
+                entry.system = value.and_then(nixos_system);
+            }
+            if let Some(modules) = value.and_then(nixos_modules) {
+                let facts = inline_module_facts(name, &modules);
+                entry.services.extend(facts.services);
+                entry.container |= facts.container;
+            }
+            if let Some(value) = value.and_then(resolve_expr) {
+                for (category, output) in self_references(&value.expr) {
+                    match category {
+                        Category::Packages => entry.packages.insert(output),
+                        _ => entry.apps.insert(output),
+                    };
+                }
+            }
+        } else {
+            match systems.and_then(Systems::known) {
+                Some(known) => {
+                    for system in known {
+                        if let Some(names) =
+                            self.outputs.outputs_mut(Some(system)).names_mut(category)
+                        {
+                            names.insert(name.to_string());
+                        }
+                    }
+                }
+                None => {
+                    if let Some(names) = self.outputs.outputs_mut(None).names_mut(category) {
+                        names.insert(name.to_string());
+                    }
+                }
+            }
+        }
+        self.definitions.push(Definition {
+            category,
+            name: name.to_string(),
+            systems: systems.and_then(Systems::known).map(<[String]>::to_vec),
+            value: value.cloned(),
+        });
+    }
+
+    fn unresolved(&mut self, ctx: &Ctx, node: &impl Spanned, reason: String) {
+        self.outputs.unresolved.push(UnresolvedOutput {
+            path: ctx.path.join("."),
+            span: node.span(),
+            reason,
+        });
+    }
+}
+
+// ============================================================================
+// Expression Helpers
+// ============================================================================
+
+/// A function application `f a b`, with `let`-bound helpers followed
+pub(crate) struct Call {
+    /// Name of the function (`genAttrs` for `nixpkgs.lib.genAttrs`)
+    pub(crate) head: Option<String>,
+    /// The helper's definition, when `head` is a `let`-bound function
+    pub(crate) wrapper: Option<Bound>,
+    /// Arguments, including those partially applied in `let` bindings
+    pub(crate) args: Vec<Bound>,
+}
+
+impl Call {
+    pub(crate) fn of(expr: &ast::Expr, env: &Env) -> Option<Self> {
+        let ast::Expr::Apply(_) = strip_parens(expr) else {
+            return None;
+        };
+        let mut args = Vec::new();
+        let mut wrapper = None;
+        let mut current = Bound {
+            expr: expr.clone(),
+            env: env.clone(),
+        };
+        let mut depth = 0;
+        let head = loop {
+            depth += 1;
+            if depth > MAX_DEPTH {
+                break None;
+            }
+            match strip_parens(&current.expr) {
+                ast::Expr::Apply(apply) => {
+                    args.push(Bound {
+                        expr: apply.argument()?,
+                        env: current.env.clone(),
+                    });
+                    current.expr = apply.lambda()?;
+                }
+                ast::Expr::Ident(ident) => {
+                    let name = ident_name(&ident);
+                    match current.env.lookup(&name) {
+                        Some(Binding::Expr(bound))
+                            if matches!(strip_parens(&bound.expr), ast::Expr::Lambda(_)) =>
+                        {
+                            wrapper = Some(bound);
+                            break Some(name);
+                        }
+                        Some(Binding::Expr(bound)) => current = bound,
+                        _ => break Some(name),
+                    }
+                }
+                ast::Expr::Select(select) => {
+                    break select
+                        .attrpath()
+                        .and_then(|path| path.attrs().last())
+                        .and_then(|attr| match key_of(&attr) {
+                            Key::Static(name) => Some(name),
+                            Key::Dynamic(_) => None,
+                        });
+                }
+                _ => break None,
+            }
+        };
+        args.reverse();
+        Some(Self {
+            head,
+            wrapper,
+            args,
+        })
+    }
+
+    /// The call a definition evaluates to, looking through variables, `let`
+    /// and `with` (`let x = ...; in pkgs.mkShell { ... }`)
+    pub(crate) fn of_value(bound: &Bound) -> Option<Self> {
+        let mut current = resolve_expr(bound)?;
+        for _ in 0..MAX_DEPTH {
+            match strip_parens(&current.expr) {
+                ast::Expr::LetIn(let_in) => {
+                    current = Bound {
+                        env: current.env.bind_entries(&let_in),
+                        expr: let_in.body()?,
+                    };
+                }
+                ast::Expr::With(with) => current.expr = with.body()?,
+                ast::Expr::Ident(_) => current = resolve_expr(&current)?,
+                _ => return Self::of(&current.expr, &current.env),
+            }
+        }
+        None
+    }
+
+    /// The last argument that is an attribute set (e.g. the arguments of
+    /// `mkDerivation`), looking through `finalAttrs: { ... }`
+    pub(crate) fn attrset_arg(&self) -> Option<(ast::AttrSet, Env)> {
+        self.args.iter().rev().find_map(attrset_of)
+    }
+}
+
+/// Follow variables to the expression they are bound to
+pub(crate) fn resolve_expr(bound: &Bound) -> Option<Bound> {
+    let mut current = bound.clone();
+    for _ in 0..MAX_DEPTH {
+        match strip_parens(&current.expr) {
+            ast::Expr::Ident(ident) => match current.env.lookup(&ident_name(&ident)) {
+                Some(Binding::Expr(next)) => current = next,
+                _ => return None,
+            },
+            _ => return Some(current),
+        }
+    }
+    None
+}
+
+/// The attribute set an expression stands for, looking through variables,
+/// `let`, `with` and function bodies
+pub(crate) fn attrset_of(bound: &Bound) -> Option<(ast::AttrSet, Env)> {
+    let mut current = resolve_expr(bound)?;
+    for _ in 0..MAX_DEPTH {
+        match strip_parens(&current.expr) {
+            ast::Expr::AttrSet(set) => {
+                let env = if set.rec_token().is_some() {
+                    current.env.bind_entries(&set)
+                } else {
+                    current.env.clone()
+                };
+                return Some((set, env));
+            }
+            ast::Expr::Lambda(lambda) => {
+                current = Bound {
+                    env: current.env.bind_params(lambda.param(), None),
+                    expr: lambda.body()?,
+                };
+            }
+            ast::Expr::LetIn(let_in) => {
+                current = Bound {
+                    env: current.env.bind_entries(&let_in),
+                    expr: let_in.body()?,
+                };
+            }
+            ast::Expr::With(with) => current.expr = with.body()?,
+            ast::Expr::Ident(_) => current = resolve_expr(&current)?,
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// The value of `path` in an attribute set, through nested sets and
+/// `inherit`
+pub(crate) fn attr_value(set: &ast::AttrSet, env: &Env, path: &[&str]) -> Option<Bound> {
+    let (first, rest) = path.split_first()?;
+    for entry in set.entries() {
+        match entry {
+            ast::Entry::AttrpathValue(binding) => {
+                let keys: Vec<Key> = binding
+                    .attrpath()
+                    .into_iter()
+                    .flat_map(|p| p.attrs())
+                    .map(|attr| key_of(&attr))
+                    .collect();
+                let matched = keys
+                    .iter()
+                    .zip(path)
+                    .take_while(|(key, name)| matches!(key, Key::Static(k) if k == *name))
+                    .count();
+                if matched != keys.len() || matched == 0 {
+                    continue;
+                }
+                let value = Bound {
+                    expr: binding.value()?,
+                    env: env.clone(),
+                };
+                if matched == path.len() {
+                    return Some(value);
+                }
+                if let Some((inner, inner_env)) = attrset_of(&value) {
+                    if let Some(found) = attr_value(&inner, &inner_env, &path[matched..]) {
+                        return Some(found);
+                    }
+                }
+            }
+            ast::Entry::Inherit(inherit) if rest.is_empty() && inherit.from().is_none() => {
+                let inherits = inherit
+                    .attrs()
+                    .any(|attr| matches!(key_of(&attr), Key::Static(name) if name == *first));
+                if inherits {
+                    if let Some(Binding::Expr(bound)) = env.lookup(first) {
+                        return Some(bound);
+                    }
+                    return None;
+                }
+            }
+            ast::Entry::Inherit(_) => {}
+        }
+    }
+    None
+}
+
+/// A string known without evaluation
+pub(crate) fn static_string(bound: &Bound) -> Option<String> {
+    match strip_parens(&bound.expr) {
+        ast::Expr::Str(string) => {
+            let mut text = String::new();
+            for part in string.normalized_parts() {
+                match part {
+                    InterpolPart::Literal(literal) => text.push_str(&literal),
+                    InterpolPart::Interpolation(interpol) => {
+                        text.push_str(&static_string(&Bound {
+                            expr: interpol.expr()?,
+                            env: bound.env.clone(),
+                        })?);
+                    }
+                }
+            }
+            Some(text)
+        }
+        ast::Expr::Ident(ident) => match bound.env.lookup(&ident_name(&ident))? {
+            Binding::Expr(next) => static_string(&next),
+            Binding::System(Systems::Known(systems)) if systems.len() == 1 => {
+                systems.into_iter().next()
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The elements of a list, through `++`, `with` and list-valued helpers
+/// such as `lib.optionals cond [ ... ]`
+pub(crate) fn list_elements(bound: &Bound) -> Vec<Bound> {
+    let Some(bound) = resolve_expr(bound) else {
+        return Vec::new();
+    };
+    let env = bound.env.clone();
+    let sub = |expr: Option<ast::Expr>| {
+        expr.map_or_else(Vec::new, |expr| {
+            list_elements(&Bound {
+                expr,
+                env: env.clone(),
+            })
+        })
+    };
+    match strip_parens(&bound.expr) {
+        ast::Expr::List(list) => list
+            .items()
+            .map(|expr| Bound {
+                expr,
+                env: env.clone(),
+            })
+            .collect(),
+        ast::Expr::BinOp(op) if op.operator() == Some(BinOpKind::Concat) => {
+            let mut items = sub(op.lhs());
+            items.extend(sub(op.rhs()));
+            items
+        }
+        ast::Expr::With(with) => sub(with.body()),
+        ast::Expr::Apply(_) => Call::of(&bound.expr, &env)
+            .map(|call| call.args.iter().flat_map(list_elements).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Systems listed by an expression (`[ "x86_64-linux" ]`,
+/// `with flake-utils.lib.system; [ x86_64-linux ]`, ...)
+fn systems_of(bound: &Bound) -> Systems {
+    let Some(bound) = resolve_expr(bound) else {
+        return Systems::Unknown;
+    };
+    match strip_parens(&bound.expr) {
+        ast::Expr::List(_) | ast::Expr::With(_) | ast::Expr::BinOp(_) => {
+            let elements = list_elements(&bound);
+            let names: Option<Vec<String>> = elements
+                .iter()
+                .map(|element| {
+                    static_string(element).or_else(|| {
+                        let name = match strip_parens(&element.expr) {
+                            ast::Expr::Ident(ident) => Some(ident_name(&ident)),
+                            ast::Expr::Select(select) => select
+                                .attrpath()
+                                .and_then(|path| path.attrs().last())
+                                .and_then(|attr| match key_of(&attr) {
+                                    Key::Static(name) => Some(name),
+                                    Key::Dynamic(_) => None,
+                                }),
+                            _ => None,
+                        }?;
+                        looks_like_system(&name).then_some(name)
+                    })
+                })
+                .collect();
+            match names {
+                Some(names) if !names.is_empty() => Systems::Known(names),
+                _ => Systems::Unknown,
+            }
+        }
+        _ => Systems::Unknown,
+    }
+}
+
+/// Systems of the `genAttrs` call inside a helper such as
+/// `forAllSystems = f: nixpkgs.lib.genAttrs systems (system: f ...)`
+fn find_gen_attrs_systems(wrapper: &Bound) -> Option<Systems> {
+    wrapper
+        .expr
+        .syntax()
+        .descendants()
+        .filter_map(ast::Apply::cast)
+        .filter_map(|apply| Call::of(&ast::Expr::Apply(apply), &wrapper.env))
+        .find(|call| call.head.as_deref() == Some("genAttrs"))
+        .and_then(|call| call.args.first().map(systems_of))
+}
+
+/// Arguments of a `nixosSystem { ... }` call
+fn nixos_system_args(value: &Bound) -> Option<(ast::AttrSet, Env)> {
+    let value = resolve_expr(value)?;
+    let call = Call::of(&value.expr, &value.env)?;
+    if call.head.as_deref() != Some("nixosSystem") {
+        return None;
+    }
+    call.attrset_arg()
+}
+
+/// Modules of a `nixosSystem { ... }` call
+fn nixos_modules(value: &Bound) -> Option<Vec<Bound>> {
+    let (set, env) = nixos_system_args(value)?;
+    Some(list_elements(&attr_value(&set, &env, &["modules"])?))
+}
+
+/// System of a `nixosSystem { ... }` call
+fn nixos_system(value: &Bound) -> Option<String> {
+    let (set, env) = nixos_system_args(value)?;
+    if let Some(system) = attr_value(&set, &env, &["system"]).and_then(|v| static_string(&v)) {
+        return Some(system);
+    }
+    nixos_modules(value)?.iter().find_map(|module| {
+        let (set, env) = attrset_of(module)?;
+        static_string(&attr_value(&set, &env, &["nixpkgs", "hostPlatform"])?)
+    })
+}
+
+/// This flake's packages and apps an expression selects from `self`
+/// (`self.packages.${system}.name`, `self.apps.x86_64-linux.name`)
+fn self_references(expr: &ast::Expr) -> Vec<(Category, String)> {
+    expr.syntax()
+        .descendants()
+        .filter_map(ast::Select::cast)
+        .filter_map(|select| {
+            let ast::Expr::Ident(base) = strip_parens(&select.expr()?) else {
+                return None;
+            };
+            if ident_name(&base) != "self" {
+                return None;
+            }
+            let keys: Vec<Key> = select
+                .attrpath()?
+                .attrs()
+                .map(|attr| key_of(&attr))
+                .collect();
+            let category = match keys.first()? {
+                Key::Static(output) if output == "packages" => Category::Packages,
+                Key::Static(output) if output == "apps" => Category::Apps,
+                _ => return None,
+            };
+            // The second attribute is the system, static or not
+            match keys.get(2)? {
+                Key::Static(name) => Some((category, name.clone())),
+                Key::Dynamic(_) => None,
+            }
+        })
+        .collect()
+}
+
+/// Whether a bare name is a Nix system double (`x86_64-linux`)
+fn looks_like_system(name: &str) -> bool {
+    const KERNELS: [&str; 8] = [
+        "linux", "darwin", "freebsd", "netbsd", "openbsd", "windows", "cygwin", "none",
+    ];
+    name.split_once('-').is_some_and(|(arch, kernel)| {
+        !arch.is_empty()
+            && arch.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && KERNELS.contains(&kernel)
+    })
+}
+
+pub(crate) fn ident_name(ident: &ast::Ident) -> String {
+    ident.syntax().text().to_string()
+}
+
+pub(crate) fn key_of(attr: &ast::Attr) -> Key {
+    match attr {
+        ast::Attr::Ident(ident) => Key::Static(ident_name(ident)),
+        ast::Attr::Str(string) => {
+            let parts = string.normalized_parts();
+            match parts.as_slice() {
+                [] => Key::Static(String::new()),
+                [InterpolPart::Literal(text)] => Key::Static(text.clone()),
+                [InterpolPart::Interpolation(interpol)] => Key::Dynamic(interpol.expr()),
+                _ => Key::Dynamic(None),
+            }
+        }
+        ast::Attr::Dynamic(dynamic) => Key::Dynamic(dynamic.expr()),
+    }
+}
+
+pub(crate) fn strip_parens(expr: &ast::Expr) -> ast::Expr {
+    let mut current = expr.clone();
+    while let ast::Expr::Paren(paren) = &current {
+        match paren.expr() {
+            Some(inner) => current = inner,
+            None => break,
+        }
+    }
+    current
+}
+
+/// Nodes reported in [`UnresolvedOutput`]s
+trait Spanned {
+    fn span(&self) -> Span;
+}
+
+impl Spanned for ast::Expr {
+    fn span(&self) -> Span {
+        Span::of(self.syntax())
+    }
+}
+
+impl Spanned for rnix::SyntaxNode {
+    fn span(&self) -> Span {
+        Span::of(self)
+    }
+}
+
+/// Short single-line excerpt of an expression for messages
+fn snippet(expr: &ast::Expr) -> String {
+    let text = expr.syntax().text().to_string();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(40) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nix::ast::NixAst;
+
+    fn analyze(outputs: &str) -> FlakeOutputs {
+        let ast = NixAst::parse(outputs).unwrap();
+        analyze_outputs(&ast.expr().unwrap()).0
+    }
+
+    fn names(set: &BTreeSet<String>) -> Vec<&str> {
+        set.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn test_direct_outputs() {
+        let outputs = analyze(
+            r#"{ self, nixpkgs }: {
+              packages.x86_64-linux = {
+                hello = nixpkgs.legacyPackages.x86_64-linux.hello;
+                default = self.packages.x86_64-linux.hello;
+              };
+              packages."aarch64-darwin".hello = nixpkgs.legacyPackages.aarch64-darwin.hello;
+              devShells.x86_64-linux.default = nixpkgs.legacyPackages.x86_64-linux.mkShell { };
+              apps.x86_64-linux.default = { type = "app"; program = "${self}/bin/hello"; };
+              checks.x86_64-linux.fmt = self.packages.x86_64-linux.hello;
+              defaultPackage.x86_64-linux = self.packages.x86_64-linux.hello;
+              overlays.default = final: prev: { };
+            }"#,
+        );
+
+        let linux = outputs.for_system("x86_64-linux").unwrap();
+        assert_eq!(names(&linux.packages), vec!["default", "hello"]);
+        assert_eq!(names(&linux.dev_shells), vec!["default"]);
+        assert_eq!(names(&linux.apps), vec!["default"]);
+        assert_eq!(names(&linux.checks), vec!["fmt"]);
+        let darwin = outputs.for_system("aarch64-darwin").unwrap();
+        assert_eq!(names(&darwin.packages), vec!["hello"]);
+        assert_eq!(
+            outputs.system_names().collect::<Vec<_>>(),
+            vec!["aarch64-darwin", "x86_64-linux"]
+        );
+        assert_eq!(outputs.patterns, BTreeSet::from([OutputPattern::Direct]));
+        assert!(outputs.unresolved.is_empty(), "{:?}", outputs.unresolved);
+    }
+
+    #[test]
+    fn test_flake_utils() {
+        let outputs = analyze(
+            r#"{ self, nixpkgs, flake-utils }:
+              flake-utils.lib.eachDefaultSystem (system:
+                let pkgs = import nixpkgs { inherit system; };
+                in {
+                  packages = {
+                    default = pkgs.hello;
+                    cli = pkgs.callPackage ./cli.nix { };
+                  };
+                  devShells.default = pkgs.mkShell { };
+                  apps.default = flake-utils.lib.mkApp { drv = self.packages.${system}.cli; };
+                })
+              // {
+                nixosConfigurations.box = nixpkgs.lib.nixosSystem {
+                  system = "x86_64-linux";
+                  modules = [ ./box.nix ];
+                };
+              }"#,
+        );
+
+        assert_eq!(outputs.systems.len(), DEFAULT_SYSTEMS.len());
+        for system in DEFAULT_SYSTEMS {
+            let per_system = outputs.for_system(system).unwrap();
+            assert_eq!(names(&per_system.packages), vec!["cli", "default"]);
+            assert_eq!(names(&per_system.apps), vec!["default"]);
+        }
+        assert_eq!(
+            outputs.nixos_configurations,
+            vec![NixosConfiguration {
+                name: "box".to_string(),
+                system: Some("x86_64-linux".to_string()),
+                ..NixosConfiguration::default()
+            }]
+        );
+        assert!(outputs.patterns.contains(&OutputPattern::FlakeUtils));
+        assert!(outputs.unresolved.is_empty(), "{:?}", outputs.unresolved);
+    }
+
+    #[test]
+    fn test_flake_utils_each_system() {
+        let outputs = analyze(
+            r#"{ nixpkgs, utils, ... }:
+              utils.lib.eachSystem (with utils.lib.system; [ x86_64-linux aarch64-linux ]) (system: {
+                checks.build = nixpkgs.legacyPackages.${system}.hello;
+                defaultPackage = nixpkgs.legacyPackages.${system}.hello;
+              })"#,
+        );
+
+        assert_eq!(
+            outputs.system_names().collect::<Vec<_>>(),
+            vec!["aarch64-linux", "x86_64-linux"]
+        );
+        let linux = outputs.for_system("x86_64-linux").unwrap();
+        assert_eq!(names(&linux.checks), vec!["build"]);
+        assert_eq!(names(&linux.packages), vec!["default"]);
+    }
+
+    #[test]
+    fn test_for_all_systems_helpers() {
+        let outputs = analyze(
+            r#"{ self, nixpkgs }:
+              let
+                supportedSystems = [ "x86_64-linux" "aarch64-darwin" ];
+                forAllSystems = f: nixpkgs.lib.genAttrs supportedSystems (system: f {
+                  pkgs = import nixpkgs { inherit system; };
+                });
+                forEachSystem = nixpkgs.lib.genAttrs [ "riscv64-linux" ];
+              in {
+                packages = forAllSystems ({ pkgs }: {
+                  default = pkgs.hello;
+                });
+                devShells = forEachSystem (system: {
+                  default = nixpkgs.legacyPackages.${system}.mkShell { };
+                });
+                checks = nixpkgs.lib.genAttrs supportedSystems (system: {
+                  test = self.packages.${system}.default;
+                });
+                formatter = forAllSystems ({ pkgs }: pkgs.nixfmt);
+              }"#,
+        );
+
+        assert_eq!(
+            names(&outputs.for_system("aarch64-darwin").unwrap().packages),
+            vec!["default"]
+        );
+        assert_eq!(
+            names(&outputs.for_system("riscv64-linux").unwrap().dev_shells),
+            vec!["default"]
+        );
+        assert_eq!(
+            names(&outputs.for_system("x86_64-linux").unwrap().checks),
+            vec!["test"]
+        );
+        assert_eq!(outputs.patterns, BTreeSet::from([OutputPattern::GenAttrs]));
+        assert!(outputs.unresolved.is_empty(), "{:?}", outputs.unresolved);
+    }
+
+    #[test]
+    fn test_flake_parts() {
+        let outputs = analyze(
+            r#"inputs@{ flake-parts, nixpkgs, ... }:
+              flake-parts.lib.mkFlake { inherit inputs; } {
+                systems = [ "x86_64-linux" "aarch64-linux" ];
+                perSystem = { pkgs, system, ... }: {
+                  packages.default = pkgs.hello;
+                  devShells.default = pkgs.mkShell { };
+                };
+                flake = {
+                  nixosConfigurations.server = nixpkgs.lib.nixosSystem {
+                    modules = [ { nixpkgs.hostPlatform = "aarch64-linux"; } ./server.nix ];
+                  };
+                };
+                flake.nixosConfigurations.laptop = nixpkgs.lib.nixosSystem {
+                  modules = [ ./laptop.nix ];
+                };
+              }"#,
+        );
+
+        for system in ["x86_64-linux", "aarch64-linux"] {
+            let per_system = outputs.for_system(system).unwrap();
+            assert_eq!(names(&per_system.packages), vec!["default"]);
+            assert_eq!(names(&per_system.dev_shells), vec!["default"]);
+        }
+        assert_eq!(
+            outputs.nixos_configurations,
+            vec![
+                NixosConfiguration {
+                    name: "laptop".to_string(),
+                    system: None,
+                    ..NixosConfiguration::default()
+                },
+                NixosConfiguration {
+                    name: "server".to_string(),
+                    system: Some("aarch64-linux".to_string()),
+                    ..NixosConfiguration::default()
+                },
+            ]
+        );
+        assert_eq!(
+            outputs.patterns,
+            BTreeSet::from([OutputPattern::FlakeParts])
+        );
+    }
+
+    #[test]
+    fn test_nixos_system_from_let_binding() {
+        let outputs = analyze(
+            r#"{ nixpkgs, ... }:
+              let
+                system = "x86_64-linux";
+                mkHost = name: nixpkgs.lib.nixosSystem { inherit system; modules = [ ]; };
+              in {
+                nixosConfigurations = {
+                  router = nixpkgs.lib.nixosSystem { inherit system; modules = [ ./router ]; };
+                  web = mkHost "web";
+                };
+              }"#,
+        );
+
+        assert_eq!(
+            outputs.nixos_configurations,
+            vec![
+                NixosConfiguration {
+                    name: "router".to_string(),
+                    system: Some("x86_64-linux".to_string()),
+                    ..NixosConfiguration::default()
+                },
+                NixosConfiguration {
+                    name: "web".to_string(),
+                    system: None,
+                    ..NixosConfiguration::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nixos_configuration_references() {
+        let outputs = analyze(
+            r#"{ self, nixpkgs, ... }:
+              let system = "x86_64-linux";
+              in {
+                packages.${system} = { api = null; worker = null; };
+                nixosConfigurations.api = nixpkgs.lib.nixosSystem {
+                  inherit system;
+                  modules = [
+                    ./hardware.nix
+                    ({ pkgs, ... }: {
+                      environment.systemPackages = [ self.packages.${system}.api pkgs.htop ];
+                      systemd.services.worker.serviceConfig.ExecStart =
+                        "${self.packages.x86_64-linux.worker}/bin/worker";
+                      programs.tool.package = self.apps.${system}.tool.program;
+                    })
+                  ];
+                };
+              }"#,
+        );
+
+        let [api] = outputs.nixos_configurations.as_slice() else {
+            panic!("{:?}", outputs.nixos_configurations);
+        };
+        assert_eq!(api.system.as_deref(), Some("x86_64-linux"));
+        assert_eq!(
+            api.packages,
+            BTreeSet::from(["api".to_string(), "worker".to_string()])
+        );
+        assert_eq!(api.apps, BTreeSet::from(["tool".to_string()]));
+    }
+
+    #[test]
+    fn test_unknown_systems() {
+        let outputs = analyze(
+            r#"{ nixpkgs, ... }: {
+              packages = nixpkgs.lib.genAttrs nixpkgs.lib.systems.flakeExposed (system: {
+                default = nixpkgs.legacyPackages.${system}.hello;
+              });
+            }"#,
+        );
+
+        assert!(outputs.systems.is_empty());
+        assert_eq!(names(&outputs.unknown_system.packages), vec!["default"]);
+        assert!(!outputs.is_empty());
+    }
+
+    #[test]
+    fn test_unresolved_outputs() {
+        let source = r#"{ self, nixpkgs }: {
+              packages = import ./packages.nix { inherit nixpkgs; };
+              devShells.x86_64-linux = self.devShells.aarch64-linux;
+              checks.x86_64-linux.${"a" + "b"} = null;
+            } // import ./more.nix"#;
+        let outputs = analyze(source);
+
+        assert!(outputs.systems.is_empty());
+        let paths: Vec<_> = outputs.unresolved.iter().map(|u| u.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "packages",
+                "devShells.x86_64-linux",
+                "checks.x86_64-linux.${\"a\" + \"b\"}",
+                ""
+            ]
+        );
+        let first = &outputs.unresolved[0];
+        assert_eq!(
+            first.span.slice(source),
+            "import ./packages.nix { inherit nixpkgs; }"
+        );
+        assert!(
+            first.reason.contains("needs evaluation"),
+            "{}",
+            first.reason
+        );
+    }
+
+    #[test]
+    fn test_recursive_and_shadowed_bindings() {
+        let outputs = analyze(
+            r#"{ nixpkgs, ... }:
+              let
+                packages = { x86_64-linux.tool = nixpkgs.hello; };
+                outputs = rec {
+                  inherit packages;
+                  apps = nixpkgs.lib.recursiveUpdate base { x86_64-linux.extra = {}; };
+                  base = { x86_64-linux.tool = {}; };
+                };
+              in outputs"#,
+        );
+
+        let linux = outputs.for_system("x86_64-linux").unwrap();
+        assert_eq!(names(&linux.packages), vec!["tool"]);
+        assert_eq!(names(&linux.apps), vec!["extra", "tool"]);
+    }
+}
@@ -0,0 +1,699 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Flake Input Graph
+//!
+//! Builds a [`petgraph`] graph of a flake's inputs from its resolved
+//! [`FlakeLock`] and answers the questions that come up when a lock file
+//! grows:
+//!
+//! - **Duplicates**: the same source locked several times, typically five
+//!   nixpkgs revisions pulled in by different inputs
+//! - **Follows suggestions**: `inputs.x.inputs.nixpkgs.follows = "nixpkgs";`
+//!   lines that would remove those duplicates
+//! - **Cycles** between inputs
+//! - **Depth**: distance from the root and size of each input's subtree
+//!
+//! The graph exports to Graphviz DOT and Mermaid, with locked revisions on
+//! the nodes and `follows` edges drawn dashed.
+//!
+//! ## Usage
+//!
+//! ```rust,no_run
+//! use cim_domain_nix::nix::{FlakeLock, InputGraph};
+//!
+//! # fn main() -> Result<(), cim_domain_nix::nix::LockError> {
+//! let graph = InputGraph::from_lock(&FlakeLock::from_file("flake.lock")?)?;
+//! for suggestion in graph.follows_suggestions() {
+//!     println!("{suggestion}");
+//! }
+//! std::fs::write("inputs.mmd", graph.to_mermaid()).unwrap();
+//! # Ok(())
+//! # }
+//! ```
+
+use super::flake_lock::{FlakeLock, FlakeRef, LockResult, SourceType};
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{self, Write as _};
+
+/// Limit on input paths enumerated per node; lock graphs are DAGs that can
+/// have many paths to a shared input
+const MAX_PATHS_PER_NODE: usize = 64;
+
+// ============================================================================
+// Graph
+// ============================================================================
+
+/// A node of the lock file
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputNode {
+    /// Node name in the lock file
+    pub name: String,
+    /// Locked reference (`None` for the root)
+    pub locked: Option<FlakeRef>,
+    /// Reference as written in `flake.nix`
+    pub original: Option<FlakeRef>,
+    /// Whether the input is a flake
+    pub flake: bool,
+}
+
+impl InputNode {
+    /// Identity of the source, ignoring the revision
+    /// (`github:nixos/nixpkgs`)
+    pub fn source(&self) -> Option<String> {
+        self.locked.as_ref().map(source_key)
+    }
+
+    /// Short label with the locked revision (`nixpkgs @ 94def63`)
+    fn label(&self) -> String {
+        let Some(locked) = &self.locked else {
+            return self.name.clone();
+        };
+        match locked.rev.as_deref().or(locked.git_ref.as_deref()) {
+            Some(rev) => format!(
+                "{} @ {}",
+                self.name,
+                rev.chars().take(7).collect::<String>()
+            ),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// An input of a node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputEdge {
+    /// Input name
+    pub input: String,
+    /// The `follows` path, if the input follows another one
+    pub follows: Option<Vec<String>>,
+}
+
+/// Graph of flake inputs with every `follows` resolved
+#[derive(Debug, Clone)]
+pub struct InputGraph {
+    graph: DiGraph<InputNode, InputEdge>,
+    root: NodeIndex,
+    by_name: BTreeMap<String, NodeIndex>,
+}
+
+impl InputGraph {
+    /// Build the graph of a lock file
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an input or `follows` path does not resolve.
+    pub fn from_lock(lock: &FlakeLock) -> LockResult<Self> {
+        let resolved = lock.graph()?;
+        let mut graph = DiGraph::new();
+        let mut by_name = BTreeMap::new();
+        for (name, node) in &lock.nodes {
+            let index = graph.add_node(InputNode {
+                name: name.clone(),
+                locked: node.locked.clone(),
+                original: node.original.clone(),
+                flake: node.flake,
+            });
+            by_name.insert(name.clone(), index);
+        }
+        for edge in resolved.edges {
+            graph.add_edge(
+                by_name[&edge.from],
+                by_name[&edge.to],
+                InputEdge {
+                    input: edge.input,
+                    follows: edge.follows,
+                },
+            );
+        }
+        Ok(Self {
+            root: by_name[&lock.root],
+            graph,
+            by_name,
+        })
+    }
+
+    /// The underlying graph
+    pub fn graph(&self) -> &DiGraph<InputNode, InputEdge> {
+        &self.graph
+    }
+
+    /// A node by its lock file name
+    pub fn node(&self, name: &str) -> Option<&InputNode> {
+        self.by_name.get(name).map(|index| &self.graph[*index])
+    }
+
+    /// Input paths from the root to every reachable node, shortest first
+    fn input_paths(&self) -> BTreeMap<NodeIndex, Vec<InputPath>> {
+        let mut paths: BTreeMap<NodeIndex, Vec<InputPath>> = BTreeMap::new();
+        let mut queue = VecDeque::from([(self.root, Vec::new(), vec![self.root])]);
+        while let Some((node, path, visited)) = queue.pop_front() {
+            for edge in self.graph.edges(node) {
+                let target = edge.target();
+                let mut input = path.clone();
+                input.push(edge.weight().input.clone());
+                let found = paths.entry(target).or_default();
+                if found.len() >= MAX_PATHS_PER_NODE {
+                    continue;
+                }
+                found.push(InputPath {
+                    path: input.clone(),
+                    follows: edge.weight().follows.is_some(),
+                });
+                // Only direct edges lead further: the inputs of a followed
+                // node are already reached through the path it follows
+                if edge.weight().follows.is_none() && !visited.contains(&target) {
+                    let mut visited = visited.clone();
+                    visited.push(target);
+                    queue.push_back((target, input, visited));
+                }
+            }
+        }
+        paths
+    }
+
+    // ------------------------------------------------------------------------
+    // Duplicates and follows
+    // ------------------------------------------------------------------------
+
+    /// Sources locked by more than one reachable node
+    pub fn duplicates(&self) -> Vec<DuplicateSource> {
+        let paths = self.input_paths();
+        let mut by_source: BTreeMap<String, Vec<NodeIndex>> = BTreeMap::new();
+        for index in paths.keys() {
+            if let Some(source) = self.graph[*index].source() {
+                by_source.entry(source).or_default().push(*index);
+            }
+        }
+
+        by_source
+            .into_iter()
+            .filter(|(_, nodes)| nodes.len() > 1)
+            .map(|(source, nodes)| {
+                let mut nodes: Vec<DuplicateNode> = nodes
+                    .into_iter()
+                    .map(|index| {
+                        let node = &self.graph[index];
+                        let locked = node.locked.as_ref();
+                        DuplicateNode {
+                            node: node.name.clone(),
+                            rev: locked.and_then(|l| l.rev.clone()),
+                            last_modified: locked.and_then(|l| l.last_modified),
+                            used_by: paths[&index].iter().map(|p| p.path.clone()).collect(),
+                        }
+                    })
+                    .collect();
+                nodes.sort_by(|a, b| {
+                    b.last_modified
+                        .cmp(&a.last_modified)
+                        .then_with(|| a.node.cmp(&b.node))
+                });
+                DuplicateSource { source, nodes }
+            })
+            .collect()
+    }
+
+    /// `follows` declarations that would collapse each duplicated source
+    /// onto one node
+    ///
+    /// The node kept is the one the root uses directly, or else the most
+    /// recently modified one; every other node is replaced, at each input
+    /// path that locks it directly.
+    pub fn follows_suggestions(&self) -> Vec<FollowsSuggestion> {
+        let paths = self.input_paths();
+        let mut suggestions = Vec::new();
+        for duplicate in self.duplicates() {
+            let shortest = |name: &str| {
+                paths[&self.by_name[name]]
+                    .iter()
+                    .filter(|p| !p.follows)
+                    .map(|p| p.path.clone())
+                    .min_by_key(Vec::len)
+            };
+            // `nodes` is sorted newest first
+            let keep = duplicate
+                .nodes
+                .iter()
+                .filter_map(|node| Some((node, shortest(&node.node)?)))
+                .min_by_key(|(_, path)| usize::from(path.len() > 1));
+            let Some((keep, target)) = keep else {
+                continue;
+            };
+            for node in duplicate.nodes.iter().filter(|n| n.node != keep.node) {
+                for path in &paths[&self.by_name[&node.node]] {
+                    // A path below another replaced path disappears with it
+                    let covered = suggestions
+                        .iter()
+                        .any(|s: &FollowsSuggestion| path.path.starts_with(&s.input));
+                    if !path.follows && path.path.len() > 1 && !covered {
+                        suggestions.push(FollowsSuggestion {
+                            input: path.path.clone(),
+                            follows: target.clone(),
+                            source: duplicate.source.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        suggestions.sort_by(|a, b| a.input.cmp(&b.input));
+        suggestions
+    }
+
+    // ------------------------------------------------------------------------
+    // Structure
+    // ------------------------------------------------------------------------
+
+    /// Groups of nodes that depend on each other, by node name
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        tarjan_scc(&self.graph)
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1
+                    || self
+                        .graph
+                        .edges(component[0])
+                        .any(|edge| edge.target() == component[0])
+            })
+            .map(|component| {
+                let mut names: Vec<String> = component
+                    .into_iter()
+                    .map(|index| self.graph[index].name.clone())
+                    .collect();
+                names.sort();
+                names
+            })
+            .collect()
+    }
+
+    /// Depth metrics for every node reachable from the root, by node name
+    pub fn metrics(&self) -> BTreeMap<String, InputMetrics> {
+        let mut distances = BTreeMap::from([(self.root, 0usize)]);
+        let mut queue = VecDeque::from([self.root]);
+        while let Some(node) = queue.pop_front() {
+            for next in self.graph.neighbors_directed(node, Direction::Outgoing) {
+                if !distances.contains_key(&next) {
+                    distances.insert(next, distances[&node] + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let mut heights = BTreeMap::new();
+        distances
+            .iter()
+            .map(|(index, distance)| {
+                let mut below = BTreeSet::new();
+                let mut stack = vec![*index];
+                while let Some(node) = stack.pop() {
+                    for next in self.graph.neighbors_directed(node, Direction::Outgoing) {
+                        if next != *index && below.insert(next) {
+                            stack.push(next);
+                        }
+                    }
+                }
+                let metrics = InputMetrics {
+                    distance: *distance,
+                    transitive_depth: self.height(*index, &mut heights, &mut Vec::new()),
+                    transitive_inputs: below.len(),
+                };
+                (self.graph[*index].name.clone(), metrics)
+            })
+            .collect()
+    }
+
+    /// Longest chain of inputs below `node`; edges closing a cycle are
+    /// ignored
+    fn height(
+        &self,
+        node: NodeIndex,
+        memo: &mut BTreeMap<NodeIndex, usize>,
+        stack: &mut Vec<NodeIndex>,
+    ) -> usize {
+        if let Some(height) = memo.get(&node) {
+            return *height;
+        }
+        stack.push(node);
+        let mut height = 0;
+        let next: Vec<NodeIndex> = self
+            .graph
+            .neighbors_directed(node, Direction::Outgoing)
+            .collect();
+        for next in next {
+            if !stack.contains(&next) {
+                height = height.max(1 + self.height(next, memo, stack));
+            }
+        }
+        stack.pop();
+        memo.insert(node, height);
+        height
+    }
+
+    // ------------------------------------------------------------------------
+    // Export
+    // ------------------------------------------------------------------------
+
+    /// Graphviz DOT rendering
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph inputs {\n    rankdir=LR;\n    node [shape=box];\n");
+        for index in self.graph.node_indices() {
+            let node = &self.graph[index];
+            let shape = if index == self.root {
+                ", shape=doubleoctagon"
+            } else if !node.flake {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [label=\"{}\"{shape}];",
+                escape_dot(&node.name),
+                escape_dot(&node.label())
+            );
+        }
+        for edge in self.graph.edge_references() {
+            let style = if edge.weight().follows.is_some() {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [label=\"{}\"{style}];",
+                escape_dot(&self.graph[edge.source()].name),
+                escape_dot(&self.graph[edge.target()].name),
+                escape_dot(&edge.weight().input)
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Mermaid flowchart rendering
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+        let id = |index: NodeIndex| format!("n{}", index.index());
+        for index in self.graph.node_indices() {
+            let label = escape_mermaid(&self.graph[index].label());
+            let _ = if index == self.root {
+                writeln!(mermaid, "    {}((\"{label}\"))", id(index))
+            } else {
+                writeln!(mermaid, "    {}[\"{label}\"]", id(index))
+            };
+        }
+        for edge in self.graph.edge_references() {
+            let arrow = if edge.weight().follows.is_some() {
+                "-.->"
+            } else {
+                "-->"
+            };
+            let _ = writeln!(
+                mermaid,
+                "    {} {arrow}|\"{}\"| {}",
+                id(edge.source()),
+                escape_mermaid(&edge.weight().input),
+                id(edge.target())
+            );
+        }
+        mermaid
+    }
+}
+
+struct InputPath {
+    path: Vec<String>,
+    /// Whether the last edge is a `follows`
+    follows: bool,
+}
+
+/// Identity of a source, ignoring the revision
+fn source_key(flake_ref: &FlakeRef) -> String {
+    match flake_ref.source_type {
+        // Forges treat owner and repository names case-insensitively
+        SourceType::Github | SourceType::Gitlab | SourceType::Sourcehut => {
+            let mut key = format!(
+                "{}:{}/{}",
+                flake_ref.source_type,
+                flake_ref.owner.as_deref().unwrap_or_default(),
+                flake_ref.repo.as_deref().unwrap_or_default()
+            )
+            .to_lowercase();
+            for extra in [&flake_ref.host, &flake_ref.dir].into_iter().flatten() {
+                key.push('?');
+                key.push_str(extra);
+            }
+            key
+        }
+        _ => {
+            let plain = FlakeRef {
+                url: flake_ref.url.clone(),
+                path: flake_ref.path.clone(),
+                id: flake_ref.id.clone(),
+                dir: flake_ref.dir.clone(),
+                ..FlakeRef::new(flake_ref.source_type.clone())
+            };
+            plain.to_string()
+        }
+    }
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_mermaid(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+// ============================================================================
+// Reports
+// ============================================================================
+
+/// A source locked by several nodes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateSource {
+    /// Source identity (`github:nixos/nixpkgs`)
+    pub source: String,
+    /// Nodes locking it, most recently modified first
+    pub nodes: Vec<DuplicateNode>,
+}
+
+impl DuplicateSource {
+    /// Number of distinct revisions locked
+    pub fn revisions(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter_map(|node| node.rev.as_ref())
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+}
+
+/// One node of a [`DuplicateSource`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateNode {
+    /// Node name in the lock file
+    pub node: String,
+    /// Locked revision
+    pub rev: Option<String>,
+    /// Locked commit time
+    pub last_modified: Option<u64>,
+    /// Input paths resolving to this node
+    pub used_by: Vec<Vec<String>>,
+}
+
+/// A `follows` declaration for the root `flake.nix`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FollowsSuggestion {
+    /// Input path to override (`["home-manager", "nixpkgs"]`)
+    pub input: Vec<String>,
+    /// Input path it should follow (`["nixpkgs"]`)
+    pub follows: Vec<String>,
+    /// Duplicated source this removes
+    pub source: String,
+}
+
+/// Formats as the line to add to `flake.nix`
+impl fmt::Display for FollowsSuggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let input: Vec<String> = self
+            .input
+            .iter()
+            .map(|name| format!("inputs.{name}"))
+            .collect();
+        write!(
+            f,
+            "{}.follows = \"{}\";",
+            input.join("."),
+            self.follows.join("/")
+        )
+    }
+}
+
+/// Depth of a node in the input graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputMetrics {
+    /// Fewest inputs between the root and the node
+    pub distance: usize,
+    /// Longest chain of inputs below the node
+    pub transitive_depth: usize,
+    /// Number of distinct nodes below the node
+    pub transitive_inputs: usize,
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three nixpkgs revisions: the root's, one under home-manager and one
+    /// under stylix (itself below home-manager's sibling)
+    const LOCK: &str = r#"{
+      "nodes": {
+        "home-manager": {
+          "inputs": { "nixpkgs": "nixpkgs_2" },
+          "locked": { "lastModified": 20, "owner": "nix-community", "repo": "home-manager", "rev": "hm00000000", "type": "github" }
+        },
+        "nixpkgs": {
+          "locked": { "lastModified": 30, "owner": "NixOS", "repo": "nixpkgs", "rev": "aaaaaaaaaa", "type": "github" }
+        },
+        "nixpkgs_2": {
+          "locked": { "lastModified": 10, "owner": "NixOS", "repo": "nixpkgs", "rev": "bbbbbbbbbb", "type": "github" }
+        },
+        "nixpkgs_3": {
+          "locked": { "lastModified": 40, "owner": "nixos", "repo": "nixpkgs", "rev": "cccccccccc", "type": "github" }
+        },
+        "root": {
+          "inputs": { "home-manager": "home-manager", "nixpkgs": "nixpkgs", "stylix": "stylix", "wallpapers": "wallpapers" }
+        },
+        "stylix": {
+          "inputs": { "home-manager": ["home-manager"], "nixpkgs": "nixpkgs_3" },
+          "locked": { "lastModified": 5, "owner": "danth", "repo": "stylix", "rev": "st00000000", "type": "github" }
+        },
+        "wallpapers": {
+          "flake": false,
+          "locked": { "narHash": "sha256-x", "type": "tarball", "url": "https://example.com/w.tar.gz" }
+        }
+      },
+      "root": "root",
+      "version": 7
+    }"#;
+
+    fn graph() -> InputGraph {
+        InputGraph::from_lock(&FlakeLock::from_json(LOCK).unwrap()).unwrap()
+    }
+
+    fn path(input: &str) -> Vec<String> {
+        input.split('/').map(String::from).collect()
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let duplicates = graph().duplicates();
+
+        assert_eq!(duplicates.len(), 1);
+        let nixpkgs = &duplicates[0];
+        assert_eq!(nixpkgs.source, "github:nixos/nixpkgs");
+        assert_eq!(nixpkgs.revisions(), 3);
+        let nodes: Vec<_> = nixpkgs.nodes.iter().map(|n| n.node.as_str()).collect();
+        assert_eq!(nodes, vec!["nixpkgs_3", "nixpkgs", "nixpkgs_2"]);
+        assert_eq!(nixpkgs.nodes[2].used_by, vec![path("home-manager/nixpkgs")]);
+    }
+
+    #[test]
+    fn test_follows_suggestions() {
+        let suggestions = graph().follows_suggestions();
+
+        let lines: Vec<_> = suggestions.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "inputs.home-manager.inputs.nixpkgs.follows = \"nixpkgs\";",
+                "inputs.stylix.inputs.nixpkgs.follows = \"nixpkgs\";",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_follows_suggestions_without_root_input() {
+        let lock = FlakeLock::from_json(
+            r#"{
+              "nodes": {
+                "a": { "inputs": { "nixpkgs": "nixpkgs" }, "locked": { "owner": "x", "repo": "a", "type": "github" } },
+                "b": { "inputs": { "nixpkgs": "nixpkgs_2" }, "locked": { "owner": "x", "repo": "b", "type": "github" } },
+                "nixpkgs": { "locked": { "lastModified": 1, "owner": "NixOS", "repo": "nixpkgs", "rev": "old", "type": "github" } },
+                "nixpkgs_2": { "locked": { "lastModified": 2, "owner": "NixOS", "repo": "nixpkgs", "rev": "new", "type": "github" } },
+                "root": { "inputs": { "a": "a", "b": "b" } }
+              },
+              "root": "root",
+              "version": 7
+            }"#,
+        )
+        .unwrap();
+        let suggestions = InputGraph::from_lock(&lock).unwrap().follows_suggestions();
+
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].input, path("a/nixpkgs"));
+        assert_eq!(suggestions[0].follows, path("b/nixpkgs"));
+    }
+
+    #[test]
+    fn test_metrics() {
+        let metrics = graph().metrics();
+
+        assert_eq!(metrics["root"].distance, 0);
+        assert_eq!(metrics["root"].transitive_depth, 3);
+        assert_eq!(metrics["root"].transitive_inputs, 6);
+        assert_eq!(metrics["stylix"].distance, 1);
+        assert_eq!(metrics["stylix"].transitive_depth, 2);
+        assert_eq!(metrics["stylix"].transitive_inputs, 3);
+        assert_eq!(metrics["nixpkgs"].transitive_depth, 0);
+    }
+
+    #[test]
+    fn test_cycles() {
+        assert!(graph().cycles().is_empty());
+
+        let lock = FlakeLock::from_json(
+            r#"{
+              "nodes": {
+                "a": { "inputs": { "b": "b" } },
+                "b": { "inputs": { "a": "a" } },
+                "root": { "inputs": { "a": "a" } }
+              },
+              "root": "root",
+              "version": 7
+            }"#,
+        )
+        .unwrap();
+        let graph = InputGraph::from_lock(&lock).unwrap();
+        assert_eq!(graph.cycles(), vec![vec!["a".to_string(), "b".to_string()]]);
+        assert_eq!(graph.metrics()["root"].transitive_depth, 2);
+    }
+
+    #[test]
+    fn test_export() {
+        let graph = graph();
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph inputs {"));
+        assert!(dot.contains("\"nixpkgs\" [label=\"nixpkgs @ aaaaaaa\"];"));
+        assert!(dot.contains("\"wallpapers\" [label=\"wallpapers\", style=dashed];"));
+        assert!(
+            dot.contains("\"stylix\" -> \"home-manager\" [label=\"home-manager\", style=dashed];")
+        );
+
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        let root = graph.by_name["root"].index();
+        assert!(mermaid.contains(&format!("    n{root}((\"root\"))")));
+        assert_eq!(mermaid.matches("-.->").count(), 1);
+        assert_eq!(mermaid.matches("-->").count(), 6);
+    }
+}
@@ -0,0 +1,599 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Flake Lock Audit
+//!
+//! Checks a [`FlakeLock`] against a pinning policy without running `nix`:
+//!
+//! | Rule                          | Flags                                           | Severity |
+//! |-------------------------------|-------------------------------------------------|----------|
+//! | [`AuditRule::Stale`]          | inputs whose `lastModified` is older than the configured age | warning |
+//! | [`AuditRule::BranchTracking`] | inputs following a branch instead of a revision or tag | info (direct inputs: warning) |
+//! | [`AuditRule::NotReproducible`]| absolute `path:` and `git+file:` inputs, registry (`indirect`) inputs | error / warning |
+//! | [`AuditRule::Unlocked`]       | git inputs locked without a revision (e.g. a dirty checkout), others without a revision or hash | error |
+//!
+//! Every reachable node is checked once and reported under the shortest
+//! input path that uses it.
+//!
+//! ## Usage
+//!
+//! ```rust,no_run
+//! use cim_domain_nix::nix::{AuditConfig, FlakeLock, LockAuditor, Severity};
+//!
+//! # fn main() -> Result<(), cim_domain_nix::nix::LockError> {
+//! let lock = FlakeLock::from_file("flake.lock")?;
+//! let report = LockAuditor::new(AuditConfig { max_age_days: 30, ..AuditConfig::default() })
+//!     .audit(&lock)?;
+//! if !report.passes(Severity::Warning) {
+//!     for finding in &report.findings {
+//!         eprintln!("{finding}");
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::flake_lock::{FlakeLock, FlakeRef, LockResult, SourceType};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// ============================================================================
+// Report
+// ============================================================================
+
+/// How serious a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth knowing, no action required
+    Info,
+    /// Violates the pinning policy
+    Warning,
+    /// Breaks reproducibility
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// Policy rule a finding violates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditRule {
+    /// Locked revision older than [`AuditConfig::max_age_days`]
+    Stale,
+    /// `flake.nix` tracks a branch, so updates move the input
+    BranchTracking,
+    /// The source depends on the machine or the flake registry
+    NotReproducible,
+    /// The lock does not pin a revision or content hash
+    Unlocked,
+}
+
+/// One policy violation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditFinding {
+    /// Input path from the root
+    pub input: Vec<String>,
+    /// Node name in the lock file
+    pub node: String,
+    /// Violated rule
+    pub rule: AuditRule,
+    /// Severity
+    pub severity: Severity,
+    /// Human-readable explanation
+    pub message: String,
+}
+
+impl fmt::Display for AuditFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: '{}': {}",
+            self.severity,
+            self.input.join("/"),
+            self.message
+        )
+    }
+}
+
+/// Result of auditing a lock file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditReport {
+    /// Findings, most severe first
+    pub findings: Vec<AuditFinding>,
+    /// Number of inputs checked
+    pub inputs_checked: usize,
+}
+
+impl AuditReport {
+    /// Severity of the worst finding
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|finding| finding.severity).max()
+    }
+
+    /// Whether no finding reaches `threshold`
+    pub fn passes(&self, threshold: Severity) -> bool {
+        self.max_severity().is_none_or(|worst| worst < threshold)
+    }
+
+    /// Findings for one rule
+    pub fn by_rule(&self, rule: AuditRule) -> impl Iterator<Item = &AuditFinding> {
+        self.findings
+            .iter()
+            .filter(move |finding| finding.rule == rule)
+    }
+}
+
+// ============================================================================
+// Auditor
+// ============================================================================
+
+/// Audit policy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditConfig {
+    /// Inputs last modified longer ago than this are stale
+    pub max_age_days: u64,
+    /// Reference time in seconds since the epoch (default: now)
+    pub now: Option<u64>,
+    /// Only check the flake's own inputs, not their dependencies
+    pub direct_only: bool,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            max_age_days: 90,
+            now: None,
+            direct_only: false,
+        }
+    }
+}
+
+/// Audits lock files against an [`AuditConfig`]
+pub struct LockAuditor {
+    config: AuditConfig,
+}
+
+impl LockAuditor {
+    /// Create an auditor with the given policy
+    pub fn new(config: AuditConfig) -> Self {
+        Self { config }
+    }
+
+    /// Audit a lock file
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an input or `follows` path does not resolve.
+    pub fn audit(&self, lock: &FlakeLock) -> LockResult<AuditReport> {
+        let now = self.config.now.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs())
+        });
+        let mut report = AuditReport::default();
+
+        for (node_name, input) in shortest_paths(lock)? {
+            if self.config.direct_only && input.len() > 1 {
+                continue;
+            }
+            let Some(node) = lock.nodes.get(&node_name) else {
+                continue;
+            };
+            report.inputs_checked += 1;
+            let mut check = Check {
+                input: &input,
+                node: &node_name,
+                findings: &mut report.findings,
+            };
+            match &node.locked {
+                Some(locked) => {
+                    check.locked(locked);
+                    check.freshness(locked, now, self.config.max_age_days);
+                }
+                None => check.report(
+                    AuditRule::Unlocked,
+                    Severity::Error,
+                    "input has no locked reference".to_string(),
+                ),
+            }
+            if let Some(original) = &node.original {
+                check.original(original);
+            }
+        }
+
+        report.findings.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then_with(|| a.input.cmp(&b.input))
+                .then_with(|| a.rule.cmp(&b.rule))
+        });
+        Ok(report)
+    }
+}
+
+impl Default for LockAuditor {
+    fn default() -> Self {
+        Self::new(AuditConfig::default())
+    }
+}
+
+/// Shortest input path to every node reachable from the root
+fn shortest_paths(lock: &FlakeLock) -> LockResult<BTreeMap<String, Vec<String>>> {
+    let graph = lock.graph()?;
+    let mut paths: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut queue = VecDeque::from([(graph.root.as_str(), Vec::new())]);
+    while let Some((node, path)) = queue.pop_front() {
+        for edge in graph.inputs_of(node) {
+            if edge.to == graph.root || paths.contains_key(&edge.to) {
+                continue;
+            }
+            let mut input = path.clone();
+            input.push(edge.input.clone());
+            paths.insert(edge.to.clone(), input.clone());
+            queue.push_back((&edge.to, input));
+        }
+    }
+    Ok(paths)
+}
+
+/// Findings for one node
+struct Check<'a> {
+    input: &'a [String],
+    node: &'a str,
+    findings: &'a mut Vec<AuditFinding>,
+}
+
+impl Check<'_> {
+    fn report(&mut self, rule: AuditRule, severity: Severity, message: String) {
+        self.findings.push(AuditFinding {
+            input: self.input.to_vec(),
+            node: self.node.to_string(),
+            rule,
+            severity,
+            message,
+        });
+    }
+
+    /// Whether the lock pins the input
+    fn locked(&mut self, locked: &FlakeRef) {
+        match locked.source_type {
+            SourceType::Path => {
+                let path = locked.path.as_deref().unwrap_or_default();
+                if path.starts_with('/') {
+                    self.report(
+                        AuditRule::NotReproducible,
+                        Severity::Error,
+                        format!("'path:{path}' only exists on the machine that locked it"),
+                    );
+                }
+            }
+            SourceType::Indirect => self.report(
+                AuditRule::Unlocked,
+                Severity::Error,
+                "locked reference still points into the flake registry".to_string(),
+            ),
+            SourceType::Git => {
+                let url = locked.url.as_deref().unwrap_or_default();
+                if url.starts_with("file:") {
+                    self.report(
+                        AuditRule::NotReproducible,
+                        Severity::Error,
+                        format!("'git+{url}' only exists on the machine that locked it"),
+                    );
+                }
+                // A dirty checkout is locked by its narHash alone
+                if locked.rev.is_none() {
+                    self.report(
+                        AuditRule::Unlocked,
+                        Severity::Error,
+                        format!("'git+{url}' is locked without a revision (dirty working tree?)"),
+                    );
+                }
+                return;
+            }
+            _ => {}
+        }
+
+        if locked.rev.is_none() && locked.nar_hash.is_none() {
+            self.report(
+                AuditRule::Unlocked,
+                Severity::Error,
+                format!("'{locked}' is locked without a revision or hash"),
+            );
+        }
+    }
+
+    fn freshness(&mut self, locked: &FlakeRef, now: u64, max_age_days: u64) {
+        let Some(last_modified) = locked.last_modified else {
+            return;
+        };
+        let age_days = now.saturating_sub(last_modified) / SECONDS_PER_DAY;
+        if age_days > max_age_days {
+            self.report(
+                AuditRule::Stale,
+                Severity::Warning,
+                format!("last modified {age_days} days ago (limit {max_age_days})"),
+            );
+        }
+    }
+
+    /// What `nix flake update` would do with the input
+    fn original(&mut self, original: &FlakeRef) {
+        match original.source_type {
+            SourceType::Indirect => self.report(
+                AuditRule::NotReproducible,
+                Severity::Warning,
+                format!(
+                    "'{original}' is resolved through the flake registry, which differs between machines"
+                ),
+            ),
+            SourceType::Github
+            | SourceType::Gitlab
+            | SourceType::Sourcehut
+            | SourceType::Git
+            | SourceType::Mercurial => {
+                if original.rev.is_some() || original.git_ref.as_deref().is_some_and(is_tag) {
+                    return;
+                }
+                let branch = original.git_ref.as_deref().unwrap_or("the default branch");
+                let severity = if self.input.len() == 1 {
+                    Severity::Warning
+                } else {
+                    Severity::Info
+                };
+                self.report(
+                    AuditRule::BranchTracking,
+                    severity,
+                    format!("tracks {branch} without a pinned revision"),
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether a ref names a tag (`v1.2.0`, `refs/tags/x`, `24.05`) rather than
+/// a branch
+fn is_tag(git_ref: &str) -> bool {
+    if git_ref.starts_with("refs/tags/") {
+        return true;
+    }
+    let version = git_ref.strip_prefix('v').unwrap_or(git_ref);
+    version.contains('.')
+        && version
+            .split('.')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+// ============================================================================
+// Convenience Functions
+// ============================================================================
+
+/// Audit a lock file with the default policy
+///
+/// ## Errors
+///
+/// See [`LockAuditor::audit`].
+pub fn audit_lock(lock: &FlakeLock) -> LockResult<AuditReport> {
+    LockAuditor::default().audit(lock)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = SECONDS_PER_DAY;
+    const NOW: u64 = 1_750_000_000;
+
+    fn lock() -> FlakeLock {
+        let fresh = NOW - 10 * DAY;
+        let old = NOW - 400 * DAY;
+        FlakeLock::from_json(&format!(
+            r#"{{
+              "nodes": {{
+                "root": {{
+                  "inputs": {{
+                    "nixpkgs": "nixpkgs", "tool": "tool", "local": "local",
+                    "registry": "registry", "work": "work", "release": "release"
+                  }}
+                }},
+                "nixpkgs": {{
+                  "locked": {{ "lastModified": {fresh}, "narHash": "sha256-a", "owner": "NixOS", "repo": "nixpkgs", "rev": "aaaa", "type": "github" }},
+                  "original": {{ "owner": "NixOS", "ref": "nixos-unstable", "repo": "nixpkgs", "type": "github" }}
+                }},
+                "tool": {{
+                  "inputs": {{ "nixpkgs": "nixpkgs_2" }},
+                  "locked": {{ "lastModified": {fresh}, "narHash": "sha256-b", "owner": "x", "repo": "tool", "rev": "bbbb", "type": "github" }},
+                  "original": {{ "owner": "x", "repo": "tool", "rev": "bbbb", "type": "github" }}
+                }},
+                "nixpkgs_2": {{
+                  "locked": {{ "lastModified": {old}, "narHash": "sha256-c", "owner": "NixOS", "repo": "nixpkgs", "rev": "cccc", "type": "github" }},
+                  "original": {{ "owner": "NixOS", "repo": "nixpkgs", "type": "github" }}
+                }},
+                "local": {{
+                  "locked": {{ "lastModified": {fresh}, "narHash": "sha256-d", "path": "/home/me/src/lib", "type": "path" }},
+                  "original": {{ "path": "/home/me/src/lib", "type": "path" }}
+                }},
+                "registry": {{
+                  "locked": {{ "lastModified": {fresh}, "narHash": "sha256-e", "owner": "numtide", "repo": "flake-utils", "rev": "eeee", "type": "github" }},
+                  "original": {{ "id": "flake-utils", "type": "indirect" }}
+                }},
+                "work": {{
+                  "locked": {{ "lastModified": {fresh}, "narHash": "sha256-g", "type": "git", "url": "file:///home/me/work" }},
+                  "original": {{ "type": "git", "url": "file:///home/me/work" }}
+                }},
+                "release": {{
+                  "locked": {{ "lastModified": {fresh}, "narHash": "sha256-f", "owner": "y", "ref": "v2.1.0", "repo": "release", "rev": "ffff", "type": "github" }},
+                  "original": {{ "owner": "y", "ref": "v2.1.0", "repo": "release", "type": "github" }}
+                }}
+              }},
+              "root": "root",
+              "version": 7
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    fn auditor() -> LockAuditor {
+        LockAuditor::new(AuditConfig {
+            now: Some(NOW),
+            ..AuditConfig::default()
+        })
+    }
+
+    fn summary(report: &AuditReport) -> Vec<(String, AuditRule, Severity)> {
+        report
+            .findings
+            .iter()
+            .map(|f| (f.input.join("/"), f.rule, f.severity))
+            .collect()
+    }
+
+    #[test]
+    fn test_audit() {
+        let report = auditor().audit(&lock()).unwrap();
+
+        assert_eq!(report.inputs_checked, 7);
+        assert_eq!(
+            summary(&report),
+            vec![
+                (
+                    "local".to_string(),
+                    AuditRule::NotReproducible,
+                    Severity::Error
+                ),
+                (
+                    "work".to_string(),
+                    AuditRule::NotReproducible,
+                    Severity::Error
+                ),
+                ("work".to_string(), AuditRule::Unlocked, Severity::Error),
+                (
+                    "nixpkgs".to_string(),
+                    AuditRule::BranchTracking,
+                    Severity::Warning
+                ),
+                (
+                    "registry".to_string(),
+                    AuditRule::NotReproducible,
+                    Severity::Warning
+                ),
+                (
+                    "tool/nixpkgs".to_string(),
+                    AuditRule::Stale,
+                    Severity::Warning
+                ),
+                (
+                    "work".to_string(),
+                    AuditRule::BranchTracking,
+                    Severity::Warning
+                ),
+                (
+                    "tool/nixpkgs".to_string(),
+                    AuditRule::BranchTracking,
+                    Severity::Info
+                ),
+            ]
+        );
+        assert_eq!(report.max_severity(), Some(Severity::Error));
+        assert!(!report.passes(Severity::Error));
+        assert!(report.findings[2].message.contains("dirty"));
+        assert_eq!(
+            report.by_rule(AuditRule::Stale).next().unwrap().message,
+            "last modified 400 days ago (limit 90)"
+        );
+    }
+
+    #[test]
+    fn test_git_locks() {
+        let lock = FlakeLock::from_json(
+            r#"{
+              "nodes": {
+                "root": { "inputs": { "clean": "clean", "remote": "remote" } },
+                "clean": {
+                  "locked": { "narHash": "sha256-a", "rev": "aaaa", "type": "git", "url": "file:///home/me/clean" },
+                  "original": { "rev": "aaaa", "type": "git", "url": "file:///home/me/clean" }
+                },
+                "remote": {
+                  "locked": { "narHash": "sha256-b", "type": "git", "url": "https://example.com/remote.git" },
+                  "original": { "rev": "bbbb", "type": "git", "url": "https://example.com/remote.git" }
+                }
+              },
+              "root": "root",
+              "version": 7
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            summary(&auditor().audit(&lock).unwrap()),
+            vec![
+                (
+                    "clean".to_string(),
+                    AuditRule::NotReproducible,
+                    Severity::Error
+                ),
+                ("remote".to_string(), AuditRule::Unlocked, Severity::Error),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_direct_only_and_age_limit() {
+        let report = LockAuditor::new(AuditConfig {
+            max_age_days: 5,
+            now: Some(NOW),
+            direct_only: true,
+        })
+        .audit(&lock())
+        .unwrap();
+
+        assert_eq!(report.inputs_checked, 6);
+        assert!(report.findings.iter().all(|f| f.input.len() == 1));
+        assert_eq!(report.by_rule(AuditRule::Stale).count(), 6);
+    }
+
+    #[test]
+    fn test_clean_lock_passes() {
+        let source = include_str!("../../flake.lock");
+        let lock = FlakeLock::from_json(source).unwrap();
+        let report = LockAuditor::new(AuditConfig {
+            max_age_days: 100_000,
+            ..AuditConfig::default()
+        })
+        .audit(&lock)
+        .unwrap();
+
+        assert!(report.passes(Severity::Error));
+        assert!(report
+            .findings
+            .iter()
+            .all(|f| f.rule == AuditRule::BranchTracking));
+    }
+
+    #[test]
+    fn test_is_tag() {
+        assert!(is_tag("v1.2.0"));
+        assert!(is_tag("24.05"));
+        assert!(is_tag("refs/tags/release"));
+        assert!(!is_tag("main"));
+        assert!(!is_tag("nixos-24.05"));
+        assert!(!is_tag("release-1.x"));
+    }
+}
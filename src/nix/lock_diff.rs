@@ -0,0 +1,566 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Flake Lock Diff
+//!
+//! Compares two [`FlakeLock`]s and reports what moved, e.g. after
+//! `nix flake update` or a Renovate pull request.
+//!
+//! Inputs are identified by their input path from the root
+//! (`home-manager/nixpkgs`), not by node name: Nix renames nodes
+//! (`nixpkgs_2`) freely between updates, but the input path is what the
+//! flake author wrote.
+//!
+//! Every [`LockChange`] maps to one of the flake events of the NATS subject
+//! mapping (`doc/nats-subject-mapping.md`) through [`LockChange::event`].
+//!
+//! ## Usage
+//!
+//! ```rust,no_run
+//! use cim_domain_nix::nix::{FlakeLock, LockDiff};
+//!
+//! # fn main() -> Result<(), cim_domain_nix::nix::LockError> {
+//! let old = FlakeLock::from_file("old/flake.lock")?;
+//! let new = FlakeLock::from_file("flake.lock")?;
+//! for change in &LockDiff::between(&old, &new) {
+//!     println!("{} {change}", change.event().subject());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::flake_lock::{FlakeLock, FlakeRef, InputRef, SourceType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// ============================================================================
+// Changes
+// ============================================================================
+
+/// One difference between two lock files
+#[allow(clippy::large_enum_variant)] // built once per diff, never stored in bulk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum LockChange {
+    /// An input that was not locked before
+    InputAdded {
+        /// Input path from the root
+        input: Vec<String>,
+        /// Locked reference (of the followed input, for `follows`)
+        locked: Option<FlakeRef>,
+        /// The `follows` path, if the input follows another one
+        follows: Option<Vec<String>>,
+    },
+
+    /// An input that is no longer locked
+    InputRemoved {
+        /// Input path from the root
+        input: Vec<String>,
+        /// Previously locked reference
+        locked: Option<FlakeRef>,
+    },
+
+    /// The same source locked at another revision
+    RevisionChanged {
+        /// Input path from the root
+        input: Vec<String>,
+        /// Previous revision (or NAR hash for sources without revisions)
+        old_rev: Option<String>,
+        /// New revision (or NAR hash for sources without revisions)
+        new_rev: Option<String>,
+        /// Seconds between the old and new `lastModified`, if both are known
+        last_modified_delta: Option<i64>,
+    },
+
+    /// The input now points to another source (type, repository or URL)
+    SourceChanged {
+        /// Input path from the root
+        input: Vec<String>,
+        /// Previous source type
+        old_type: SourceType,
+        /// New source type
+        new_type: SourceType,
+        /// Previous locked reference
+        old: FlakeRef,
+        /// New locked reference
+        new: FlakeRef,
+    },
+
+    /// The input started, stopped or changed following another input
+    FollowsChanged {
+        /// Input path from the root
+        input: Vec<String>,
+        /// Previous `follows` path (`None`: locked directly)
+        old: Option<Vec<String>>,
+        /// New `follows` path (`None`: locked directly)
+        new: Option<Vec<String>>,
+    },
+}
+
+/// Flake event a lock change is published as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LockEventKind {
+    /// `FlakeInputAdded`
+    InputAdded,
+    /// `FlakeInputRemoved`
+    InputRemoved,
+    /// `FlakeUpdated`
+    Updated,
+}
+
+impl LockEventKind {
+    /// Event type name
+    pub fn event_type(self) -> &'static str {
+        match self {
+            Self::InputAdded => "FlakeInputAdded",
+            Self::InputRemoved => "FlakeInputRemoved",
+            Self::Updated => "FlakeUpdated",
+        }
+    }
+
+    /// NATS subject of the event
+    pub fn subject(self) -> &'static str {
+        match self {
+            Self::InputAdded => "nix.event.flake.input_added",
+            Self::InputRemoved => "nix.event.flake.input_removed",
+            Self::Updated => "nix.event.flake.updated",
+        }
+    }
+}
+
+impl LockChange {
+    /// Input path from the root
+    pub fn input(&self) -> &[String] {
+        match self {
+            Self::InputAdded { input, .. }
+            | Self::InputRemoved { input, .. }
+            | Self::RevisionChanged { input, .. }
+            | Self::SourceChanged { input, .. }
+            | Self::FollowsChanged { input, .. } => input,
+        }
+    }
+
+    /// Input path as Nix prints it (`home-manager/nixpkgs`)
+    pub fn input_name(&self) -> String {
+        self.input().join("/")
+    }
+
+    /// Whether the change concerns a top-level input of the flake
+    pub fn is_direct(&self) -> bool {
+        self.input().len() == 1
+    }
+
+    /// The event this change is published as
+    pub fn event(&self) -> LockEventKind {
+        match self {
+            Self::InputAdded { .. } => LockEventKind::InputAdded,
+            Self::InputRemoved { .. } => LockEventKind::InputRemoved,
+            Self::RevisionChanged { .. }
+            | Self::SourceChanged { .. }
+            | Self::FollowsChanged { .. } => LockEventKind::Updated,
+        }
+    }
+}
+
+impl fmt::Display for LockChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let input = self.input_name();
+        match self {
+            Self::InputAdded {
+                follows: Some(path),
+                ..
+            } => write!(f, "added '{input}': follows '{}'", path.join("/")),
+            Self::InputAdded { locked, .. } => match locked {
+                Some(locked) => write!(f, "added '{input}': '{locked}'"),
+                None => write!(f, "added '{input}'"),
+            },
+            Self::InputRemoved { .. } => write!(f, "removed '{input}'"),
+            Self::RevisionChanged {
+                old_rev, new_rev, ..
+            } => write!(
+                f,
+                "updated '{input}': {} -> {}",
+                short(old_rev.as_deref()),
+                short(new_rev.as_deref())
+            ),
+            Self::SourceChanged { old, new, .. } => {
+                write!(f, "updated '{input}': '{old}' -> '{new}'")
+            }
+            Self::FollowsChanged { old, new, .. } => {
+                let describe = |path: &Option<Vec<String>>| match path {
+                    Some(path) => format!("follows '{}'", path.join("/")),
+                    None => "locked directly".to_string(),
+                };
+                write!(
+                    f,
+                    "rewired '{input}': {} -> {}",
+                    describe(old),
+                    describe(new)
+                )
+            }
+        }
+    }
+}
+
+/// Revisions are shown abbreviated like `nix flake update` does
+fn short(rev: Option<&str>) -> &str {
+    match rev {
+        Some(rev) if rev.len() > 7 && rev.is_char_boundary(7) => &rev[..7],
+        Some(rev) => rev,
+        None => "(none)",
+    }
+}
+
+// ============================================================================
+// Diff
+// ============================================================================
+
+/// All differences between two lock files
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LockDiff {
+    /// Changes, ordered by input path
+    pub changes: Vec<LockChange>,
+}
+
+impl LockDiff {
+    /// Compare an old lock file with a new one
+    pub fn between(old: &FlakeLock, new: &FlakeLock) -> Self {
+        let old_inputs = input_states(old);
+        let new_inputs = input_states(new);
+        let mut changes = Vec::new();
+
+        for (path, old_state) in &old_inputs {
+            match new_inputs.get(path) {
+                None => changes.push(LockChange::InputRemoved {
+                    input: path.clone(),
+                    locked: old_state.locked.cloned(),
+                }),
+                Some(new_state) => compare(path, old_state, new_state, &mut changes),
+            }
+        }
+        for (path, new_state) in &new_inputs {
+            if !old_inputs.contains_key(path) {
+                changes.push(LockChange::InputAdded {
+                    input: path.clone(),
+                    locked: new_state.locked.cloned(),
+                    follows: new_state.follows.cloned(),
+                });
+            }
+        }
+
+        changes.sort_by(|a, b| a.input().cmp(b.input()));
+        Self { changes }
+    }
+
+    /// Check if the lock files are equivalent
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Iterate over the changes
+    pub fn iter(&self) -> std::slice::Iter<'_, LockChange> {
+        self.changes.iter()
+    }
+
+    /// Changes to top-level inputs only
+    pub fn direct(&self) -> impl Iterator<Item = &LockChange> {
+        self.changes.iter().filter(|change| change.is_direct())
+    }
+}
+
+impl<'a> IntoIterator for &'a LockDiff {
+    type Item = &'a LockChange;
+    type IntoIter = std::slice::Iter<'a, LockChange>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.iter()
+    }
+}
+
+/// An input as seen from the root
+struct InputState<'a> {
+    follows: Option<&'a Vec<String>>,
+    /// Locked reference of the node the input resolves to
+    locked: Option<&'a FlakeRef>,
+}
+
+/// Every input path reachable from the root without going through
+/// `follows`
+fn input_states(lock: &FlakeLock) -> BTreeMap<Vec<String>, InputState<'_>> {
+    let mut states = BTreeMap::new();
+    let mut stack = vec![(Vec::new(), vec![lock.root.as_str()])];
+    while let Some((path, ancestors)) = stack.pop() {
+        let node_name = ancestors[ancestors.len() - 1];
+        let Some(node) = lock.nodes.get(node_name) else {
+            continue;
+        };
+        for (name, reference) in &node.inputs {
+            let mut input = path.clone();
+            input.push(name.clone());
+            let (follows, target) = match reference {
+                InputRef::Node(target) => (
+                    None,
+                    lock.nodes.get_key_value(target).map(|(k, _)| k.as_str()),
+                ),
+                InputRef::Follows(follows) => (Some(follows), lock.resolve_path(follows).ok()),
+            };
+            let locked = target
+                .and_then(|target| lock.nodes.get(target))
+                .and_then(|node| node.locked.as_ref());
+            if let (None, Some(target)) = (follows, target) {
+                // Lock files are acyclic apart from `follows`, but a
+                // hand-edited one might not be
+                if !ancestors.contains(&target) {
+                    let mut ancestors = ancestors.clone();
+                    ancestors.push(target);
+                    stack.push((input.clone(), ancestors));
+                }
+            }
+            states.insert(input, InputState { follows, locked });
+        }
+    }
+    states
+}
+
+fn compare(
+    path: &[String],
+    old: &InputState<'_>,
+    new: &InputState<'_>,
+    changes: &mut Vec<LockChange>,
+) {
+    if old.follows != new.follows {
+        changes.push(LockChange::FollowsChanged {
+            input: path.to_vec(),
+            old: old.follows.cloned(),
+            new: new.follows.cloned(),
+        });
+        return;
+    }
+    // A followed input's own change is reported at the path it follows
+    if new.follows.is_some() {
+        return;
+    }
+    let (Some(old_ref), Some(new_ref)) = (old.locked, new.locked) else {
+        return;
+    };
+    if !same_source(old_ref, new_ref) {
+        changes.push(LockChange::SourceChanged {
+            input: path.to_vec(),
+            old_type: old_ref.source_type.clone(),
+            new_type: new_ref.source_type.clone(),
+            old: old_ref.clone(),
+            new: new_ref.clone(),
+        });
+        return;
+    }
+    let from = old_ref.rev.as_ref().or(old_ref.nar_hash.as_ref());
+    let to = new_ref.rev.as_ref().or(new_ref.nar_hash.as_ref());
+    if from != to {
+        changes.push(LockChange::RevisionChanged {
+            input: path.to_vec(),
+            old_rev: from.cloned(),
+            new_rev: to.cloned(),
+            last_modified_delta: match (old_ref.last_modified, new_ref.last_modified) {
+                (Some(old), Some(new)) => i64::try_from(new)
+                    .ok()
+                    .zip(i64::try_from(old).ok())
+                    .map(|(new, old)| new - old),
+                _ => None,
+            },
+        });
+    }
+}
+
+/// Whether two references fetch from the same place
+fn same_source(old: &FlakeRef, new: &FlakeRef) -> bool {
+    old.source_type == new.source_type
+        && old.owner == new.owner
+        && old.repo == new.repo
+        && old.host == new.host
+        && old.url == new.url
+        && old.path == new.path
+        && old.dir == new.dir
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock(nodes: &str) -> FlakeLock {
+        FlakeLock::from_json(&format!(
+            r#"{{ "nodes": {{ {nodes} }}, "root": "root", "version": 7 }}"#
+        ))
+        .unwrap()
+    }
+
+    fn github(name: &str, owner: &str, rev: &str, last_modified: u64, inputs: &str) -> String {
+        format!(
+            r#""{name}": {{
+              "inputs": {{ {inputs} }},
+              "locked": {{ "lastModified": {last_modified}, "owner": "{owner}", "repo": "{name}", "rev": "{rev}", "type": "github" }},
+              "original": {{ "owner": "{owner}", "repo": "{name}", "type": "github" }}
+            }}"#
+        )
+    }
+
+    fn path(input: &str) -> Vec<String> {
+        input.split('/').map(String::from).collect()
+    }
+
+    #[test]
+    fn test_identical_locks() {
+        let source = include_str!("../../flake.lock");
+        let lock = FlakeLock::from_json(source).unwrap();
+        assert!(LockDiff::between(&lock, &lock).is_empty());
+    }
+
+    #[test]
+    fn test_flake_update() {
+        let old = lock(
+            &[
+                r#""root": { "inputs": { "nixpkgs": "nixpkgs", "utils": "utils", "old": "old" } }"#
+                    .to_string(),
+                github("nixpkgs", "NixOS", "aaaaaaaaaa", 1000, ""),
+                github("utils", "numtide", "u1", 500, r#""systems": "systems""#),
+                github("systems", "nix-systems", "s1", 100, ""),
+                github("old", "someone", "o1", 100, ""),
+            ]
+            .join(","),
+        );
+        let new = lock(&[
+            r#""root": { "inputs": { "nixpkgs": "nixpkgs", "utils": "utils", "fresh": "fresh" } }"#.to_string(),
+            github("nixpkgs", "NixOS", "bbbbbbbbbb", 4600, ""),
+            github("utils", "numtide", "u1", 500, r#""systems": "systems", "nixpkgs": ["nixpkgs"]"#),
+            github("systems", "nix-systems", "s1", 100, ""),
+            github("fresh", "someone", "f1", 200, ""),
+        ]
+        .join(","));
+
+        let diff = LockDiff::between(&old, &new);
+        let summary: Vec<_> = diff.changes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            summary,
+            vec![
+                "added 'fresh': 'github:someone/fresh/f1'",
+                "updated 'nixpkgs': aaaaaaa -> bbbbbbb",
+                "removed 'old'",
+                "added 'utils/nixpkgs': follows 'nixpkgs'",
+            ]
+        );
+        assert!(matches!(
+            &diff.changes[1],
+            LockChange::RevisionChanged {
+                last_modified_delta: Some(3600),
+                ..
+            }
+        ));
+        assert_eq!(diff.direct().count(), 3);
+
+        let events: Vec<_> = diff.changes.iter().map(|c| c.event().subject()).collect();
+        assert_eq!(
+            events,
+            vec![
+                "nix.event.flake.input_added",
+                "nix.event.flake.updated",
+                "nix.event.flake.input_removed",
+                "nix.event.flake.input_added",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_renamed_nodes_are_not_changes() {
+        let old = lock(
+            &[
+                r#""root": { "inputs": { "overlay": "overlay" } }"#.to_string(),
+                github("overlay", "oxalica", "r1", 1, r#""nixpkgs": "nixpkgs""#),
+                github("nixpkgs", "NixOS", "n1", 1, ""),
+            ]
+            .join(","),
+        );
+        let new = lock(
+            &[
+                r#""root": { "inputs": { "overlay": "overlay" } }"#.to_string(),
+                github("overlay", "oxalica", "r1", 1, r#""nixpkgs": "nixpkgs_2""#),
+                github("nixpkgs", "NixOS", "n1", 1, "").replacen("\"nixpkgs\"", "\"nixpkgs_2\"", 1),
+            ]
+            .join(","),
+        );
+
+        let diff = LockDiff::between(&old, &new);
+        assert!(
+            diff.changes.iter().all(|change| !matches!(
+                change,
+                LockChange::InputAdded { .. } | LockChange::InputRemoved { .. }
+            )),
+            "{diff:?}"
+        );
+    }
+
+    #[test]
+    fn test_follows_rewired_and_source_changed() {
+        let old = lock(
+            &[
+                r#""root": { "inputs": { "nixpkgs": "nixpkgs", "hm": "hm" } }"#.to_string(),
+                github("hm", "nix-community", "h1", 1, r#""nixpkgs": "nixpkgs_2""#),
+                github("nixpkgs", "NixOS", "n1", 1, ""),
+                github("nixpkgs_2", "NixOS", "n2", 1, ""),
+            ]
+            .join(","),
+        );
+        let new = lock(
+            &[
+                r#""root": { "inputs": { "nixpkgs": "nixpkgs", "hm": "hm" } }"#.to_string(),
+                github("hm", "nix-community", "h1", 1, r#""nixpkgs": ["nixpkgs"]"#),
+                r#""nixpkgs": {
+              "locked": { "type": "git", "url": "https://example.com/nixpkgs.git", "rev": "n1" },
+              "original": { "type": "git", "url": "https://example.com/nixpkgs.git" }
+            }"#
+                .to_string(),
+            ]
+            .join(","),
+        );
+
+        let diff = LockDiff::between(&old, &new);
+        assert_eq!(diff.changes.len(), 2, "{diff:?}");
+        assert_eq!(
+            diff.changes[0],
+            LockChange::FollowsChanged {
+                input: path("hm/nixpkgs"),
+                old: None,
+                new: Some(path("nixpkgs")),
+            }
+        );
+        match &diff.changes[1] {
+            LockChange::SourceChanged {
+                input,
+                old_type,
+                new_type,
+                ..
+            } => {
+                assert_eq!(input, &path("nixpkgs"));
+                assert_eq!(old_type, &SourceType::Github);
+                assert_eq!(new_type, &SourceType::Git);
+            }
+            other => panic!("unexpected change {other:?}"),
+        }
+        assert_eq!(diff.changes[1].event(), LockEventKind::Updated);
+    }
+
+    #[test]
+    fn test_change_serialization() {
+        let change = LockChange::InputRemoved {
+            input: path("old"),
+            locked: None,
+        };
+        let json = serde_json::to_value(&change).unwrap();
+        assert_eq!(json["change"], "input_removed");
+        assert_eq!(json["input"][0], "old");
+        assert_eq!(serde_json::from_value::<LockChange>(json).unwrap(), change);
+        assert_eq!(change.event().event_type(), "FlakeInputRemoved");
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Nix Language Representation Module
+//!
+//! This module implements the SOURCE category in our Category Theory functor:
+//!
+//! ```text
+//! Category(Nix) ──Functor F──> Category(Infrastructure)
+//!   (This Module)                (infrastructure module)
+//! ```
+//!
+//! ## Purpose
+//!
+//! Nix is our **data storage format**. This module provides:
+//! - Rust representations of Nix language constructs
+//! - Parsing of Nix files using rnix-parser
+//! - Conversion of static Nix expressions into [`NixValue`]s
+//! - Evaluation of the pure subset of the language ([`eval`])
+//! - Printing values back to Nix source ([`printer`])
+//! - Reading and writing Rust types as Nix through serde ([`serialization`])
+//! - Static analysis of flakes and their outputs ([`flake_analyzer`])
+//! - Reading, resolving and diffing `flake.lock` files ([`flake_lock`],
+//!   [`lock_diff`])
+//! - Analysing and drawing the flake input graph ([`input_graph`])
+//! - Auditing lock freshness and pinning policy ([`lock_audit`])
+//! - Cataloguing NixOS module options ([`module_analyzer`])
+//! - Auditing overlays and overlay stacks ([`overlay_analyzer`])
+//! - Reading and writing `.drv` derivation files ([`derivation`])
+//! - Analysing closure sizes from `nix path-info` output ([`closure`])
+//! - Evaluating flakes with the Nix CLI or recorded output ([`flake_evaluator`],
+//!   [`backend`]), blocking or on tokio ([`async_evaluator`])
+//! - Reading `nix flake metadata` output ([`flake_metadata`])
+//! - Caching parse and evaluation results by content hash ([`cache`])
+//! - Indexing every `.nix` file of a repository ([`workspace`])
+//! - Detecting secrets committed in Nix files ([`secrets`])
+//! - Type-safe access to Nix data structures
+//!
+//! ## Nix Language Overview
+//!
+//! Nix has 9 types divided into primitives and compounds:
+//!
+//! **Primitive Types (7)**:
+//! - String - Text values
+//! - Integer - Whole numbers
+//! - Float - Decimal numbers
+//! - Bool - true/false
+//! - Null - absence of value
+//! - Path - Filesystem paths
+//! - `LookupPath` - Nix search path entries (`<nixpkgs>`)
+//!
+//! **Compound Types (2)**:
+//! - Attribute Set - Key-value mappings (like objects/dicts)
+//! - List - Ordered collections
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use cim_domain_nix::nix::*;
+//!
+//! // Parse a Nix expression
+//! let source = "{ x = 1; y = 2; }";
+//! let parser = NixParser::new();
+//! let ast = parser.parse_str(source).unwrap();
+//!
+//! // Convert it to a value
+//! let value = ast_to_value(&ast).unwrap();
+//! assert_eq!(value.as_attrset().unwrap().len(), 2);
+//!
+//! // Work with Nix values
+//! let mut attrset = NixAttrset::new();
+//! attrset.insert("name".to_string(), NixValue::String(NixString::new("hello")));
+//! ```
+
+pub mod ast;
+pub mod ast_converter;
+pub mod async_evaluator;
+pub mod backend;
+pub mod cache;
+pub mod closure;
+pub mod derivation;
+pub mod eval;
+pub mod flake_analyzer;
+pub mod flake_evaluator;
+pub mod flake_lock;
+pub mod flake_metadata;
+pub mod flake_outputs;
+pub mod input_graph;
+pub mod lock_audit;
+pub mod lock_diff;
+pub mod module_analyzer;
+pub mod objects;
+pub mod overlay_analyzer;
+pub mod parser;
+pub mod printer;
+pub mod secrets;
+pub mod serialization;
+pub mod value_objects;
+pub mod workspace;
+
+// Re-export commonly used types
+pub use ast::{NixAst, NixExpression, NixNode, Span};
+pub use ast_converter::{ast_to_value, parse_value, AstConverter, ConversionError};
+pub use async_evaluator::{AsyncFlakeEvaluator, ProgressEvent, ProgressKind, DEFAULT_TIMEOUT};
+pub use backend::{
+    AsyncNixBackend, CliBackend, NixBackend, NixOutput, Recording, RecordingBackend, ReplayBackend,
+};
+pub use cache::{
+    flake_key, CacheBackend, CacheError, CacheResult, CacheStats, ContentHash, DiskCache,
+    MemoryCache, NixCache, CACHE_VERSION,
+};
+pub use closure::{
+    format_size, Closure, ClosureError, ClosureResult, ClosureSuggestion, PathInfo, PathSizes,
+};
+pub use derivation::{
+    parse_drv_file, Derivation, DerivationError, DerivationGraph, DerivationOutput,
+    DerivationResult,
+};
+pub use eval::{evaluate, EvalConfig, EvalError, Evaluator};
+pub use flake_analyzer::{
+    analyze_flake, FlakeAnalysis, FlakeAnalyzer, FlakeDevShell, FlakeHost, FlakeInfrastructure,
+    FlakeInput, FlakePackage, ManagementMethod, OperatingSystem, SoftwareArtifact, SoftwareKind,
+    SystemDescription,
+};
+pub use flake_evaluator::{
+    evaluate_flake, nix_available, AppInfo, CheckInfo, DevShellInfo, EvaluatedFlake,
+    EvaluationError, EvaluationResult, FlakeEvaluator, OutputInfo, OutputTree, OutputType,
+    PackageInfo,
+};
+pub use flake_metadata::FlakeMetadata;
+pub use flake_lock::{
+    FlakeLock, FlakeRef, InputRef, LockEdge, LockError, LockGraph, LockNode, LockResult,
+    SourceType,
+};
+pub use flake_outputs::{
+    FlakeOutputs, NixosConfiguration, OutputPattern, SystemOutputs, UnresolvedOutput,
+};
+pub use input_graph::{
+    DuplicateNode, DuplicateSource, FollowsSuggestion, InputEdge, InputGraph, InputMetrics,
+    InputNode,
+};
+pub use lock_audit::{
+    audit_lock, AuditConfig, AuditFinding, AuditReport, AuditRule, LockAuditor, Severity,
+};
+pub use lock_diff::{LockChange, LockDiff, LockEventKind};
+pub use module_analyzer::{
+    ModuleAnalysis, ModuleAnalyzer, ModuleOption, OptionCatalogue, OptionDeclaration,
+    UnresolvedOption,
+};
+pub use objects::{
+    NixApplication, NixAttrsetObject, NixDerivation, NixFlake, NixModule, NixObject, NixOverlay,
+    NixPackage,
+};
+pub use overlay_analyzer::{
+    ModificationKind, OverlayAnalysis, OverlayAnalyzer, OverlayAttribute, OverlayStack,
+    ShadowedDefinition,
+};
+pub use parser::{NixParser, ParseError, ParseResult};
+pub use printer::{print_value, KeyOrder, NixPrinter, PrintConfig, PrintError, PrintResult};
+pub use secrets::{
+    SecretAllowList, SecretError, SecretFinding, SecretKind, SecretResult, SecretScanner,
+};
+pub use serialization::{
+    from_nix_str, from_value, to_nix_string, to_value, SerializationError, SerializationResult,
+};
+pub use value_objects::{
+    NixAttrset, NixBool, NixFloat, NixInteger, NixList, NixLookupPath, NixNull, NixPath, NixString,
+    NixValue,
+};
+pub use workspace::{
+    FileKind, Import, IndexedFile, WorkspaceError, WorkspaceIndex, WorkspaceIndexer,
+    WorkspaceResult,
+};
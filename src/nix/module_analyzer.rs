@@ -0,0 +1,697 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! NixOS Module Analyzer
+//!
+//! Extracts option declarations from NixOS modules without evaluating them.
+//!
+//! - **`mkOption { ... }`** records the `type`, `default`, `description`
+//!   and `example` expressions as written in the source
+//! - **`mkEnableOption "..."`** and **`mkPackageOption pkgs "..." { }`**
+//!   are expanded the way nixpkgs' `lib.options` expands them
+//! - Options are found under `options.<path>`, whether written as nested
+//!   attribute sets or dotted paths, in modules that are plain attribute
+//!   sets or functions of `{ config, lib, pkgs, ... }`
+//! - `let`-bound helpers (`mkPort = d: mkOption { ... };`) are expanded
+//!   with their arguments; other values under `options` that are not
+//!   declarations are reported as [`UnresolvedOption`]s
+//!
+//! Declarations from many modules can be collected into an
+//! [`OptionCatalogue`] and searched by path or description.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use cim_domain_nix::nix::{ModuleAnalyzer, OptionCatalogue};
+//!
+//! let analysis = ModuleAnalyzer::new().analyze_source(r#"
+//!   { lib, ... }: {
+//!     options.services.hello = {
+//!       enable = lib.mkEnableOption "the hello service";
+//!       port = lib.mkOption { type = lib.types.port; default = 8080; };
+//!     };
+//!   }
+//! "#).unwrap();
+//!
+//! let mut catalogue = OptionCatalogue::new();
+//! catalogue.add_module(&analysis);
+//! assert_eq!(catalogue.get("services.hello.port").unwrap().default.as_deref(), Some("8080"));
+//! assert_eq!(catalogue.search("hello").len(), 2);
+//! ```
+
+use super::ast::{NixAst, Span};
+use super::flake_outputs::{
+    attr_value, attrset_of, key_of, list_elements, resolve_expr, static_string, strip_parens,
+    Bound, Call, Env, Key, MAX_DEPTH,
+};
+use super::objects::{NixModule, NixModuleOption};
+use super::parser::{ParseError, ParseResult};
+use rnix::ast::{self, HasEntry};
+use rowan::ast::AstNode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// Module Analysis Result
+// ============================================================================
+
+/// How an option was declared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptionDeclaration {
+    /// `mkOption { ... }`
+    MkOption,
+    /// `mkEnableOption "..."`
+    MkEnableOption,
+    /// `mkPackageOption pkgs "..." { ... }`
+    MkPackageOption,
+}
+
+/// An option declared by a module
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleOption {
+    /// Option path (`services.nginx.enable`)
+    pub path: Vec<String>,
+    /// How the option was declared
+    pub declaration: OptionDeclaration,
+    /// Type expression as written (`types.port`, `with types; listOf str`)
+    pub option_type: Option<String>,
+    /// Default value expression, or the `defaultText` when there is none
+    pub default: Option<String>,
+    /// Description text
+    pub description: Option<String>,
+    /// Example expression
+    pub example: Option<String>,
+    /// Location of the declaration
+    pub span: Span,
+    /// File the option was declared in, if known
+    pub source: Option<PathBuf>,
+}
+
+impl ModuleOption {
+    /// Dotted option path
+    pub fn name(&self) -> String {
+        self.path.join(".")
+    }
+}
+
+impl From<&ModuleOption> for NixModuleOption {
+    fn from(option: &ModuleOption) -> Self {
+        Self {
+            option_type: option.option_type.clone().unwrap_or_default(),
+            default: option.default.clone(),
+            description: option.description.clone(),
+            example: option.example.clone(),
+        }
+    }
+}
+
+/// A value under `options` the analyzer could not read as a declaration,
+/// e.g. a call to an imported helper
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnresolvedOption {
+    /// Option path (`services.nginx.port`)
+    pub path: Vec<String>,
+    /// The value as written (`myLib.mkPortOption 80`)
+    pub expression: String,
+    /// Location of the value
+    pub span: Span,
+}
+
+/// Result of analyzing a module
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleAnalysis {
+    /// Declared options, in source order
+    pub options: Vec<ModuleOption>,
+    /// Values under `options` that are not recognisable declarations
+    pub unresolved: Vec<UnresolvedOption>,
+    /// Entries of `imports`, as written
+    pub imports: Vec<String>,
+    /// Source file, if analyzed from a file
+    pub source: Option<PathBuf>,
+}
+
+impl ModuleAnalysis {
+    /// Convert to a [`NixModule`] with the given name
+    pub fn to_module(&self, name: impl Into<String>) -> NixModule {
+        let mut module = NixModule::new(name.into());
+        for import in &self.imports {
+            module.add_import(import.clone());
+        }
+        for option in &self.options {
+            module.add_option(option.name(), option.into());
+        }
+        if let Some(source) = &self.source {
+            module = module.with_source_path(source.clone());
+        }
+        module
+    }
+}
+
+// ============================================================================
+// Module Analyzer
+// ============================================================================
+
+/// Extracts option declarations from NixOS modules
+pub struct ModuleAnalyzer;
+
+impl ModuleAnalyzer {
+    /// Create a new module analyzer
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Analyze a module from source
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the source does not parse.
+    pub fn analyze_source(&self, source: &str) -> ParseResult<ModuleAnalysis> {
+        let ast = NixAst::parse(source)?;
+        Ok(self.analyze_ast(&ast))
+    }
+
+    /// Analyze a module file
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the file cannot be read or does not parse.
+    pub fn analyze_file(&self, path: impl AsRef<Path>) -> ParseResult<ModuleAnalysis> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| ParseError::IoError(format!("Failed to read file: {e}")))?;
+        let mut analysis = self.analyze_source(&source)?;
+        for option in &mut analysis.options {
+            option.source = Some(path.to_path_buf());
+        }
+        analysis.source = Some(path.to_path_buf());
+        Ok(analysis)
+    }
+
+    /// Analyze a parsed module
+    pub fn analyze_ast(&self, ast: &NixAst) -> ModuleAnalysis {
+        let mut analysis = ModuleAnalysis::default();
+        let Some(expr) = ast.expr() else {
+            return analysis;
+        };
+        let Some((set, env)) = attrset_of(&Bound {
+            expr,
+            env: Env::default(),
+        }) else {
+            return analysis;
+        };
+
+        if let Some(imports) = attr_value(&set, &env, &["imports"]) {
+            analysis.imports = list_elements(&imports)
+                .iter()
+                .map(|import| {
+                    static_string(import).unwrap_or_else(|| import.expr.syntax().text().to_string())
+                })
+                .collect();
+        }
+        walk_options(&set, &env, &[], true, &mut analysis);
+        analysis
+    }
+}
+
+impl Default for ModuleAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Record the options declared in `set`; until `options` has been seen
+/// (`at_top`), only entries under it are considered
+fn walk_options(
+    set: &ast::AttrSet,
+    env: &Env,
+    prefix: &[String],
+    at_top: bool,
+    analysis: &mut ModuleAnalysis,
+) {
+    for entry in set.entries() {
+        let ast::Entry::AttrpathValue(binding) = entry else {
+            continue;
+        };
+        let (Some(attrpath), Some(value)) = (binding.attrpath(), binding.value()) else {
+            continue;
+        };
+        let mut path: Vec<String> = prefix.to_vec();
+        path.extend(attrpath.attrs().map(|attr| match key_of(&attr) {
+            Key::Static(name) => name,
+            Key::Dynamic(_) => attr.syntax().text().to_string(),
+        }));
+        let path = if at_top {
+            match path.split_first() {
+                Some((first, rest)) if first == "options" => rest.to_vec(),
+                _ => continue,
+            }
+        } else {
+            path
+        };
+
+        let value = Bound {
+            expr: value,
+            env: env.clone(),
+        };
+        if let Some(option) = declaration(&value, path.clone()) {
+            analysis.options.push(option);
+        } else if let Some((inner, inner_env)) = attrset_of(&value) {
+            walk_options(&inner, &inner_env, &path, false, analysis);
+        } else if !path.is_empty() {
+            analysis.unresolved.push(UnresolvedOption {
+                path,
+                expression: source_text(&value),
+                span: Span::of(value.expr.syntax()),
+            });
+        }
+    }
+}
+
+/// The option declared by `value`, if it is an option declaration
+fn declaration(value: &Bound, path: Vec<String>) -> Option<ModuleOption> {
+    let (call, expanded) = option_call(value)?;
+    let span = Span::of(value.expr.syntax());
+    let mut option = ModuleOption {
+        path,
+        declaration: OptionDeclaration::MkOption,
+        option_type: None,
+        default: None,
+        description: None,
+        example: None,
+        span,
+        source: None,
+    };
+
+    match call.head.as_deref()? {
+        "mkOption" => {
+            if let Some((set, env)) = call.attrset_arg() {
+                let field = |name: &str| attr_value(&set, &env, &[name]);
+                // In a helper, `default = d;` stands for the argument passed
+                let expression = |v: Bound| match resolve_expr(&v) {
+                    Some(resolved) if expanded => source_text(&resolved),
+                    _ => source_text(&v),
+                };
+                option.option_type = field("type").map(expression);
+                option.default = field("default")
+                    .map(expression)
+                    .or_else(|| field("defaultText").map(|v| documentation(&v)));
+                option.description = field("description").map(|v| documentation(&v));
+                option.example = field("example").map(|v| documentation(&v));
+            }
+        }
+        "mkEnableOption" => {
+            let name = call.args.first().map(documentation)?;
+            option.declaration = OptionDeclaration::MkEnableOption;
+            option.option_type = Some("types.bool".to_string());
+            option.default = Some("false".to_string());
+            option.description = Some(format!("Whether to enable {name}."));
+            option.example = Some("true".to_string());
+        }
+        "mkPackageOption" => {
+            let name = call.args.get(1).and_then(package_name)?;
+            option.declaration = OptionDeclaration::MkPackageOption;
+            option.option_type = Some("types.package".to_string());
+            option.default = Some(format!("pkgs.{name}"));
+            option.description = Some(format!("The {name} package to use."));
+            if let Some((set, env)) = call.args.get(2).and_then(attrset_of) {
+                if let Some(default) = attr_value(&set, &env, &["default"]) {
+                    option.default = package_name(&default).map(|name| format!("pkgs.{name}"));
+                }
+                if let Some(example) = attr_value(&set, &env, &["example"]) {
+                    option.example = Some(documentation(&example));
+                }
+            }
+        }
+        _ => return None,
+    }
+    Some(option)
+}
+
+/// The call `value` evaluates to, expanding `let`-bound helpers with their
+/// arguments (`mkPort 80` with `mkPort = d: mkOption { default = d; }`);
+/// the flag tells whether a helper was expanded
+fn option_call(value: &Bound) -> Option<(Call, bool)> {
+    let mut call = Call::of_value(value)?;
+    for depth in 0..MAX_DEPTH {
+        let Some(mut body) = call.wrapper.take() else {
+            return Some((call, depth > 0));
+        };
+        for arg in &call.args {
+            let ast::Expr::Lambda(lambda) = strip_parens(&body.expr) else {
+                return None;
+            };
+            body = Bound {
+                env: body.env.bind_arg(lambda.param(), arg),
+                expr: lambda.body()?,
+            };
+        }
+        call = Call::of_value(&body)?;
+    }
+    None
+}
+
+/// Attribute path of a package (`"hello"`, `[ "python3Packages" "black" ]`)
+fn package_name(bound: &Bound) -> Option<String> {
+    if let Some(name) = static_string(bound) {
+        return Some(name);
+    }
+    let parts: Option<Vec<String>> = list_elements(bound).iter().map(static_string).collect();
+    parts
+        .filter(|parts| !parts.is_empty())
+        .map(|parts| parts.join("."))
+}
+
+/// Text of documentation values, unwrapping `mdDoc`, `literalMD` and
+/// `literalExpression`
+fn documentation(bound: &Bound) -> String {
+    const WRAPPERS: [&str; 4] = ["mdDoc", "literalMD", "literalExpression", "literalExample"];
+    if let Some(text) = static_string(bound) {
+        return text;
+    }
+    if let Some(call) = Call::of(&bound.expr, &bound.env) {
+        if let ([arg], Some(head)) = (call.args.as_slice(), call.head.as_deref()) {
+            if WRAPPERS.contains(&head) {
+                return static_string(arg).unwrap_or_else(|| source_text(arg));
+            }
+        }
+    }
+    source_text(bound)
+}
+
+fn source_text(bound: &Bound) -> String {
+    strip_parens(&bound.expr).syntax().text().to_string()
+}
+
+// ============================================================================
+// Option Catalogue
+// ============================================================================
+
+/// Searchable collection of option declarations, keyed by dotted path
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptionCatalogue {
+    options: BTreeMap<String, ModuleOption>,
+}
+
+impl OptionCatalogue {
+    /// Create an empty catalogue
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add every option of a module; returns the options it replaced
+    pub fn add_module(&mut self, analysis: &ModuleAnalysis) -> Vec<ModuleOption> {
+        analysis
+            .options
+            .iter()
+            .filter_map(|option| self.insert(option.clone()))
+            .collect()
+    }
+
+    /// Add an option; returns the option previously declared at its path
+    pub fn insert(&mut self, option: ModuleOption) -> Option<ModuleOption> {
+        self.options.insert(option.name(), option)
+    }
+
+    /// Analyze module files and collect their options
+    ///
+    /// ## Errors
+    ///
+    /// Returns the first error from [`ModuleAnalyzer::analyze_file`].
+    pub fn from_files<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> ParseResult<Self> {
+        let analyzer = ModuleAnalyzer::new();
+        let mut catalogue = Self::new();
+        for path in paths {
+            catalogue.add_module(&analyzer.analyze_file(path)?);
+        }
+        Ok(catalogue)
+    }
+
+    /// Option at a dotted path
+    pub fn get(&self, path: &str) -> Option<&ModuleOption> {
+        self.options.get(path)
+    }
+
+    /// Options whose path starts with `prefix` (`services.nginx`)
+    pub fn under<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a ModuleOption> {
+        self.options
+            .range::<str, _>((
+                std::ops::Bound::Included(prefix),
+                std::ops::Bound::Unbounded,
+            ))
+            .take_while(move |(path, _)| path.starts_with(prefix))
+            .filter(move |(path, _)| {
+                path.len() == prefix.len() || path[prefix.len()..].starts_with('.')
+            })
+            .map(|(_, option)| option)
+    }
+
+    /// Options whose path or description contains every word of `query`,
+    /// ignoring case
+    pub fn search(&self, query: &str) -> Vec<&ModuleOption> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        self.options
+            .iter()
+            .filter(|(path, option)| {
+                let haystack = format!(
+                    "{} {}",
+                    path.to_lowercase(),
+                    option
+                        .description
+                        .as_deref()
+                        .unwrap_or_default()
+                        .to_lowercase()
+                );
+                words.iter().all(|word| haystack.contains(word.as_str()))
+            })
+            .map(|(_, option)| option)
+            .collect()
+    }
+
+    /// Iterate over all options in path order
+    pub fn iter(&self) -> impl Iterator<Item = &ModuleOption> {
+        self.options.values()
+    }
+
+    /// Number of options
+    pub fn len(&self) -> usize {
+        self.options.len()
+    }
+
+    /// Whether the catalogue is empty
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODULE: &str = r#"
+      { config, lib, pkgs, ... }:
+      with lib;
+      let
+        cfg = config.services.acme-api;
+        portOption = mkOption {
+          type = types.port;
+          default = 8080;
+          description = "Port to listen on.";
+        };
+      in {
+        imports = [ ./database.nix ../common ];
+
+        options.services.acme-api = {
+          enable = mkEnableOption "the ACME API";
+          package = lib.mkPackageOption pkgs "acme-api" { };
+          port = portOption;
+          settings = {
+            logLevel = mkOption {
+              type = types.enum [ "debug" "info" "warn" ];
+              default = "info";
+              description = lib.mdDoc "Minimum level of log messages.";
+              example = literalExpression ''"debug"'';
+            };
+          };
+        };
+
+        options.services.acme-worker.queues = lib.options.mkOption {
+          type = with types; listOf str;
+          defaultText = literalExpression "[ config.networking.hostName ]";
+        };
+
+        config = mkIf cfg.enable {
+          services.acme-api.port = mkDefault 9090;
+          environment.systemPackages = [ cfg.package ];
+        };
+      }
+    "#;
+
+    fn analysis() -> ModuleAnalysis {
+        ModuleAnalyzer::new().analyze_source(MODULE).unwrap()
+    }
+
+    fn option<'a>(analysis: &'a ModuleAnalysis, name: &str) -> &'a ModuleOption {
+        analysis.options.iter().find(|o| o.name() == name).unwrap()
+    }
+
+    #[test]
+    fn test_finds_declarations() {
+        let analysis = analysis();
+        let names: Vec<String> = analysis.options.iter().map(ModuleOption::name).collect();
+        assert_eq!(
+            names,
+            [
+                "services.acme-api.enable",
+                "services.acme-api.package",
+                "services.acme-api.port",
+                "services.acme-api.settings.logLevel",
+                "services.acme-worker.queues",
+            ]
+        );
+        assert_eq!(analysis.imports, ["./database.nix", "../common"]);
+    }
+
+    #[test]
+    fn test_mk_option_fields() {
+        let analysis = analysis();
+
+        let port = option(&analysis, "services.acme-api.port");
+        assert_eq!(port.declaration, OptionDeclaration::MkOption);
+        assert_eq!(port.option_type.as_deref(), Some("types.port"));
+        assert_eq!(port.default.as_deref(), Some("8080"));
+        assert_eq!(port.description.as_deref(), Some("Port to listen on."));
+
+        let level = option(&analysis, "services.acme-api.settings.logLevel");
+        assert_eq!(
+            level.option_type.as_deref(),
+            Some(r#"types.enum [ "debug" "info" "warn" ]"#)
+        );
+        assert_eq!(level.default.as_deref(), Some(r#""info""#));
+        assert_eq!(
+            level.description.as_deref(),
+            Some("Minimum level of log messages.")
+        );
+        assert_eq!(level.example.as_deref(), Some(r#""debug""#));
+
+        let queues = option(&analysis, "services.acme-worker.queues");
+        assert_eq!(
+            queues.option_type.as_deref(),
+            Some("with types; listOf str")
+        );
+        assert_eq!(
+            queues.default.as_deref(),
+            Some("[ config.networking.hostName ]")
+        );
+    }
+
+    #[test]
+    fn test_option_helpers() {
+        let analysis = analysis();
+
+        let enable = option(&analysis, "services.acme-api.enable");
+        assert_eq!(enable.declaration, OptionDeclaration::MkEnableOption);
+        assert_eq!(enable.option_type.as_deref(), Some("types.bool"));
+        assert_eq!(enable.default.as_deref(), Some("false"));
+        assert_eq!(
+            enable.description.as_deref(),
+            Some("Whether to enable the ACME API.")
+        );
+
+        let package = option(&analysis, "services.acme-api.package");
+        assert_eq!(package.declaration, OptionDeclaration::MkPackageOption);
+        assert_eq!(package.default.as_deref(), Some("pkgs.acme-api"));
+    }
+
+    #[test]
+    fn test_nested_options_attrset() {
+        let analysis = ModuleAnalyzer::new()
+            .analyze_source(
+                r#"{ lib, ... }: {
+                  options = {
+                    networking.acme.enable = lib.mkEnableOption "ACME networking";
+                    "my-app".${name} = lib.mkOption { type = lib.types.str; };
+                  };
+                  config.networking.acme.enable = true;
+                }"#,
+            )
+            .unwrap();
+        let names: Vec<String> = analysis.options.iter().map(ModuleOption::name).collect();
+        assert_eq!(names, ["networking.acme.enable", "my-app.${name}"]);
+    }
+
+    #[test]
+    fn test_helper_declarations() {
+        let analysis = ModuleAnalyzer::new()
+            .analyze_source(
+                r#"{ lib, ... }:
+                let
+                  inherit (lib) mkOption types;
+                  mkPort = d: mkOption { type = types.port; default = d; };
+                  mkStr = { description, default ? null }: mkOption {
+                    type = types.str;
+                    inherit description;
+                  };
+                in {
+                  options.x.port = mkPort 80;
+                  options.x.name = mkStr { description = "Name of x."; };
+                  options.x.tls = myLib.mkTlsOption "x";
+                }"#,
+            )
+            .unwrap();
+
+        let port = option(&analysis, "x.port");
+        assert_eq!(port.declaration, OptionDeclaration::MkOption);
+        assert_eq!(port.option_type.as_deref(), Some("types.port"));
+        assert_eq!(port.default.as_deref(), Some("80"));
+
+        let name = option(&analysis, "x.name");
+        assert_eq!(name.description.as_deref(), Some("Name of x."));
+
+        assert_eq!(analysis.options.len(), 2);
+        assert_eq!(analysis.unresolved.len(), 1);
+        assert_eq!(analysis.unresolved[0].path, ["x", "tls"]);
+        assert_eq!(
+            analysis.unresolved[0].expression,
+            r#"myLib.mkTlsOption "x""#
+        );
+    }
+
+    #[test]
+    fn test_to_module() {
+        let module = analysis().to_module("acme-api");
+        assert_eq!(module.imports.len(), 2);
+        assert_eq!(module.options.len(), 5);
+        assert_eq!(
+            module.options["services.acme-api.port"].option_type,
+            "types.port"
+        );
+    }
+
+    #[test]
+    fn test_catalogue() {
+        let mut catalogue = OptionCatalogue::new();
+        assert!(catalogue.add_module(&analysis()).is_empty());
+        assert_eq!(catalogue.len(), 5);
+
+        let api: Vec<String> = catalogue
+            .under("services.acme-api")
+            .map(ModuleOption::name)
+            .collect();
+        assert_eq!(api.len(), 4);
+        assert_eq!(catalogue.under("services.acme").count(), 0);
+
+        let found = catalogue.search("LOG level");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name(), "services.acme-api.settings.logLevel");
+        assert_eq!(catalogue.search("enable").len(), 1);
+
+        let replaced = catalogue.add_module(&analysis());
+        assert_eq!(replaced.len(), 5);
+    }
+}
@@ -0,0 +1,567 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Overlay Analyzer
+//!
+//! Reports which attributes a nixpkgs overlay (`final: prev: { ... }` or
+//! `self: super: { ... }`) defines, without evaluating it, and how each
+//! definition relates to the package set it is applied to:
+//!
+//! | Kind                                    | Example                                        |
+//! |-----------------------------------------|------------------------------------------------|
+//! | [`ModificationKind::OverrideAttrs`]     | `hello = prev.hello.overrideAttrs (old: ...)`  |
+//! | [`ModificationKind::Override`]          | `hello = prev.hello.override { ... }`          |
+//! | [`ModificationKind::Extension`]         | `lib = prev.lib // { ... }`, `xs = prev.xs ++ [ ... ]` |
+//! | [`ModificationKind::Replacement`]       | `nodejs = prev.nodejs_20`, or a known package defined from scratch |
+//! | [`ModificationKind::NewPackage`]        | `my-tool = final.callPackage ./my-tool { }`    |
+//!
+//! Whether an attribute defined from scratch replaces a package can only be
+//! told from the package set; pass its attribute names to
+//! [`OverlayAnalyzer::with_base_packages`] to tell replacements from new
+//! packages.
+//!
+//! An [`OverlayStack`] composes overlays in order and reports definitions
+//! that a later overlay discards because it does not build on `prev`.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use cim_domain_nix::nix::{ModificationKind, OverlayAnalyzer, OverlayStack};
+//!
+//! let analyzer = OverlayAnalyzer::new();
+//! let pins = analyzer
+//!     .analyze_source("pins", "final: prev: { hello = final.callPackage ./hello { }; }")
+//!     .unwrap();
+//! let patches = analyzer
+//!     .analyze_source("patches", "self: super: { hello = super.hello.overrideAttrs (o: { }); }")
+//!     .unwrap();
+//! assert_eq!(patches.get("hello").unwrap().kind, ModificationKind::OverrideAttrs);
+//!
+//! let stack = OverlayStack::new(vec![patches, pins]);
+//! assert_eq!(stack.shadowed().len(), 1);
+//! ```
+
+use super::ast::{NixAst, Span};
+use super::flake_outputs::{ident_name, key_of, strip_parens, Call, Env, Key};
+use super::objects::NixOverlay;
+use super::parser::{ParseError, ParseResult};
+use rnix::ast::{self, HasEntry};
+use rowan::ast::AstNode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// Overlay Analysis Result
+// ============================================================================
+
+/// How an overlay attribute relates to the package set it is applied to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModificationKind {
+    /// A package that does not exist in the package set
+    NewPackage,
+    /// `prev.<name>.overrideAttrs` (or `overrideDerivation`)
+    OverrideAttrs,
+    /// `prev.<name>.override`
+    Override,
+    /// Builds on `prev.<name>` in another way (`//`, `++`, `extend`, ...)
+    Extension,
+    /// Discards `prev.<name>` for another value
+    Replacement,
+}
+
+impl ModificationKind {
+    /// Whether the definition builds on the previous value of the attribute
+    pub fn builds_on_prev(self) -> bool {
+        matches!(self, Self::OverrideAttrs | Self::Override | Self::Extension)
+    }
+}
+
+impl fmt::Display for ModificationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NewPackage => "new package",
+            Self::OverrideAttrs => "overrideAttrs",
+            Self::Override => "override",
+            Self::Extension => "extension",
+            Self::Replacement => "replacement",
+        })
+    }
+}
+
+/// An attribute defined by an overlay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OverlayAttribute {
+    /// Attribute name
+    pub name: String,
+    /// How the definition relates to the package set
+    pub kind: ModificationKind,
+    /// Location of the definition
+    pub span: Span,
+}
+
+/// Result of analyzing one overlay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OverlayAnalysis {
+    /// Overlay name
+    pub name: String,
+    /// Name of the final package set argument (`final`, `self`)
+    pub final_arg: String,
+    /// Name of the previous package set argument (`prev`, `super`)
+    pub prev_arg: String,
+    /// Defined attributes, in source order
+    pub attributes: Vec<OverlayAttribute>,
+    /// Source file, if analyzed from a file
+    pub source: Option<PathBuf>,
+}
+
+impl OverlayAnalysis {
+    /// Attribute by name
+    pub fn get(&self, name: &str) -> Option<&OverlayAttribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+    }
+
+    /// Attributes of one kind
+    pub fn of_kind(&self, kind: ModificationKind) -> impl Iterator<Item = &OverlayAttribute> {
+        self.attributes
+            .iter()
+            .filter(move |attribute| attribute.kind == kind)
+    }
+
+    /// Convert to a [`NixOverlay`] listing every defined attribute
+    pub fn to_overlay(&self) -> NixOverlay {
+        let mut overlay = NixOverlay::new(self.name.clone());
+        for attribute in &self.attributes {
+            overlay.add_modification(attribute.name.clone());
+        }
+        if let Some(source) = &self.source {
+            overlay = overlay.with_source_path(source.clone());
+        }
+        overlay
+    }
+}
+
+// ============================================================================
+// Overlay Analyzer
+// ============================================================================
+
+/// Analyzes overlay functions
+#[derive(Debug, Clone, Default)]
+pub struct OverlayAnalyzer {
+    base_packages: HashSet<String>,
+}
+
+impl OverlayAnalyzer {
+    /// Create an analyzer that knows nothing about the package set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attribute names of the package set overlays are applied to
+    ///
+    /// Attributes defined from scratch under these names are classified as
+    /// [`ModificationKind::Replacement`] instead of
+    /// [`ModificationKind::NewPackage`].
+    #[must_use]
+    pub fn with_base_packages<S: Into<String>>(
+        mut self,
+        packages: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.base_packages
+            .extend(packages.into_iter().map(Into::into));
+        self
+    }
+
+    /// Analyze an overlay from source
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the source does not parse or is not an overlay
+    /// function of two arguments returning an attribute set.
+    pub fn analyze_source(&self, name: &str, source: &str) -> ParseResult<OverlayAnalysis> {
+        let ast = NixAst::parse(source)?;
+        let expr = ast
+            .expr()
+            .ok_or_else(|| ParseError::InvalidFile("empty overlay".to_string()))?;
+        self.analyze_expr(name, &expr)
+    }
+
+    /// Analyze an overlay file, named after its file stem
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the file cannot be read; see
+    /// [`analyze_source`](Self::analyze_source).
+    pub fn analyze_file(&self, path: impl AsRef<Path>) -> ParseResult<OverlayAnalysis> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| ParseError::IoError(format!("Failed to read file: {e}")))?;
+        let name = path
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        let mut analysis = self.analyze_source(&name, &source)?;
+        analysis.source = Some(path.to_path_buf());
+        Ok(analysis)
+    }
+
+    /// Analyze an overlay expression
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the expression is not an overlay function of two
+    /// arguments returning an attribute set.
+    pub fn analyze_expr(&self, name: &str, expr: &ast::Expr) -> ParseResult<OverlayAnalysis> {
+        let not_overlay =
+            |reason: &str| ParseError::UnsupportedExpression(format!("overlay '{name}': {reason}"));
+
+        let (final_arg, body) = lambda_arg(expr).ok_or_else(|| not_overlay("not a function"))?;
+        let (prev_arg, body) =
+            lambda_arg(&body).ok_or_else(|| not_overlay("expected `final: prev: { ... }`"))?;
+        let set =
+            result_attrset(&body).ok_or_else(|| not_overlay("does not return an attribute set"))?;
+
+        let mut analysis = OverlayAnalysis {
+            name: name.to_string(),
+            final_arg,
+            prev_arg,
+            attributes: Vec::new(),
+            source: None,
+        };
+        for entry in set.entries() {
+            match entry {
+                ast::Entry::AttrpathValue(binding) => {
+                    let mut attrs = binding.attrpath().into_iter().flat_map(|p| p.attrs());
+                    let Some(Key::Static(attr_name)) = attrs.next().map(|attr| key_of(&attr))
+                    else {
+                        continue;
+                    };
+                    let Some(value) = binding.value() else {
+                        continue;
+                    };
+                    // `a.b = ...;` defines a fresh attribute set `a`
+                    let kind = if attrs.next().is_some() {
+                        ModificationKind::Replacement
+                    } else {
+                        self.classify(&analysis, &attr_name, &value)
+                    };
+                    analysis.attributes.push(OverlayAttribute {
+                        name: attr_name,
+                        kind,
+                        span: Span::of(binding.syntax()),
+                    });
+                }
+                ast::Entry::Inherit(inherit) => {
+                    let from = inherit.from().and_then(|from| from.expr());
+                    for attr in inherit.attrs() {
+                        let Key::Static(attr_name) = key_of(&attr) else {
+                            continue;
+                        };
+                        let kind = match &from {
+                            Some(from) if is_var(from, &analysis.prev_arg) => {
+                                ModificationKind::Extension
+                            }
+                            _ => self.defined_from_scratch(&attr_name),
+                        };
+                        analysis.attributes.push(OverlayAttribute {
+                            name: attr_name,
+                            kind,
+                            span: Span::of(attr.syntax()),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(analysis)
+    }
+
+    fn classify(
+        &self,
+        analysis: &OverlayAnalysis,
+        name: &str,
+        value: &ast::Expr,
+    ) -> ModificationKind {
+        let prev = analysis.prev_arg.as_str();
+        let value = strip_parens(value);
+        let uses_prev_attr = value
+            .syntax()
+            .descendants()
+            .filter_map(ast::Select::cast)
+            .any(|select| {
+                select.expr().is_some_and(|base| is_var(&base, prev))
+                    && first_attr(&select).as_deref() == Some(name)
+            });
+
+        if uses_prev_attr {
+            if let Some(call) = Call::of(&value, &Env::default()) {
+                match call.head.as_deref() {
+                    Some("overrideAttrs" | "overrideDerivation") => {
+                        return ModificationKind::OverrideAttrs
+                    }
+                    Some("override" | "overridePythonAttrs") => return ModificationKind::Override,
+                    _ => {}
+                }
+            }
+            return ModificationKind::Extension;
+        }
+
+        // `nodejs = prev.nodejs_20;`
+        if let ast::Expr::Select(select) = &value {
+            if select.expr().is_some_and(|base| is_var(&base, prev)) {
+                return ModificationKind::Replacement;
+            }
+        }
+        self.defined_from_scratch(name)
+    }
+
+    fn defined_from_scratch(&self, name: &str) -> ModificationKind {
+        if self.base_packages.contains(name) {
+            ModificationKind::Replacement
+        } else {
+            ModificationKind::NewPackage
+        }
+    }
+}
+
+/// Parameter name and body of a single-identifier lambda
+fn lambda_arg(expr: &ast::Expr) -> Option<(String, ast::Expr)> {
+    let ast::Expr::Lambda(lambda) = strip_parens(expr) else {
+        return None;
+    };
+    let ast::Param::IdentParam(param) = lambda.param()? else {
+        return None;
+    };
+    Some((ident_name(&param.ident()?), lambda.body()?))
+}
+
+/// The attribute set a function body returns, through `let`, `with` and
+/// `rec`
+fn result_attrset(expr: &ast::Expr) -> Option<ast::AttrSet> {
+    let mut current = strip_parens(expr);
+    loop {
+        match current {
+            ast::Expr::AttrSet(set) => return Some(set),
+            ast::Expr::LetIn(let_in) => current = strip_parens(&let_in.body()?),
+            ast::Expr::With(with) => current = strip_parens(&with.body()?),
+            _ => return None,
+        }
+    }
+}
+
+fn is_var(expr: &ast::Expr, name: &str) -> bool {
+    matches!(strip_parens(expr), ast::Expr::Ident(ident) if ident_name(&ident) == name)
+}
+
+fn first_attr(select: &ast::Select) -> Option<String> {
+    match key_of(&select.attrpath()?.attrs().next()?) {
+        Key::Static(name) => Some(name),
+        Key::Dynamic(_) => None,
+    }
+}
+
+// ============================================================================
+// Overlay Stack
+// ============================================================================
+
+/// A definition that a later overlay throws away
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShadowedDefinition {
+    /// Attribute name
+    pub attribute: String,
+    /// Overlay whose definition is lost
+    pub shadowed: String,
+    /// How the lost definition was written
+    pub shadowed_kind: ModificationKind,
+    /// Later overlay that redefines the attribute without using `prev`
+    pub shadowed_by: String,
+    /// How the redefinition is written
+    pub kind: ModificationKind,
+}
+
+impl fmt::Display for ShadowedDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' from overlay '{}' ({}) is discarded by overlay '{}' ({})",
+            self.attribute, self.shadowed, self.shadowed_kind, self.shadowed_by, self.kind
+        )
+    }
+}
+
+/// Overlays composed in order, as in `nixpkgs.overlays`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OverlayStack {
+    /// Overlays, first applied first
+    pub overlays: Vec<OverlayAnalysis>,
+}
+
+impl OverlayStack {
+    /// Compose overlays in order
+    pub fn new(overlays: Vec<OverlayAnalysis>) -> Self {
+        Self { overlays }
+    }
+
+    /// Overlays defining each attribute, in application order
+    pub fn definitions(&self) -> BTreeMap<&str, Vec<(&OverlayAnalysis, &OverlayAttribute)>> {
+        let mut definitions: BTreeMap<&str, Vec<_>> = BTreeMap::new();
+        for overlay in &self.overlays {
+            for attribute in &overlay.attributes {
+                definitions
+                    .entry(attribute.name.as_str())
+                    .or_default()
+                    .push((overlay, attribute));
+            }
+        }
+        definitions
+    }
+
+    /// Definitions discarded by a later overlay that does not build on
+    /// `prev`
+    pub fn shadowed(&self) -> Vec<ShadowedDefinition> {
+        let mut shadowed = Vec::new();
+        for (name, definitions) in self.definitions() {
+            for pair in definitions.windows(2) {
+                let [(earlier, earlier_attr), (later, later_attr)] = pair else {
+                    continue;
+                };
+                if !later_attr.kind.builds_on_prev() {
+                    shadowed.push(ShadowedDefinition {
+                        attribute: name.to_string(),
+                        shadowed: earlier.name.clone(),
+                        shadowed_kind: earlier_attr.kind,
+                        shadowed_by: later.name.clone(),
+                        kind: later_attr.kind,
+                    });
+                }
+            }
+        }
+        shadowed
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OVERLAY: &str = r#"
+      final: prev:
+      let
+        version = "2.0";
+      in {
+        hello = prev.hello.overrideAttrs (old: {
+          patches = (old.patches or [ ]) ++ [ ./hello.patch ];
+        });
+        curl = (prev.curl.override { http3Support = true; });
+        lib = prev.lib // { acme = import ./lib.nix; };
+        nodejs = prev.nodejs_20;
+        openssl = final.callPackage ./openssl { inherit version; };
+        acme-cli = final.callPackage ./acme-cli { };
+        acme.tools = { };
+        inherit (prev) git;
+        inherit (final.acme-cli) docs;
+      }
+    "#;
+
+    fn kinds(analysis: &OverlayAnalysis) -> Vec<(&str, ModificationKind)> {
+        analysis
+            .attributes
+            .iter()
+            .map(|a| (a.name.as_str(), a.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_classifies_attributes() {
+        let analysis = OverlayAnalyzer::new()
+            .with_base_packages(["openssl", "hello"])
+            .analyze_source("acme", OVERLAY)
+            .unwrap();
+
+        assert_eq!(analysis.final_arg, "final");
+        assert_eq!(analysis.prev_arg, "prev");
+        assert_eq!(
+            kinds(&analysis),
+            vec![
+                ("hello", ModificationKind::OverrideAttrs),
+                ("curl", ModificationKind::Override),
+                ("lib", ModificationKind::Extension),
+                ("nodejs", ModificationKind::Replacement),
+                ("openssl", ModificationKind::Replacement),
+                ("acme-cli", ModificationKind::NewPackage),
+                ("acme", ModificationKind::Replacement),
+                ("git", ModificationKind::Extension),
+                ("docs", ModificationKind::NewPackage),
+            ]
+        );
+        assert_eq!(analysis.of_kind(ModificationKind::Replacement).count(), 3);
+    }
+
+    #[test]
+    fn test_self_super_and_to_overlay() {
+        let analysis = OverlayAnalyzer::new()
+            .analyze_source(
+                "legacy",
+                "self: super: with super; { vim = super.vim.override { python = self.python3; }; }",
+            )
+            .unwrap();
+        assert_eq!(analysis.prev_arg, "super");
+        assert_eq!(kinds(&analysis), vec![("vim", ModificationKind::Override)]);
+
+        let overlay = analysis.to_overlay();
+        assert_eq!(overlay.name, "legacy");
+        assert_eq!(overlay.modifications, vec!["vim"]);
+    }
+
+    #[test]
+    fn test_rejects_non_overlays() {
+        let analyzer = OverlayAnalyzer::new();
+        assert!(analyzer.analyze_source("x", "{ a = 1; }").is_err());
+        assert!(analyzer.analyze_source("x", "final: { a = 1; }").is_err());
+        assert!(analyzer
+            .analyze_source("x", "{ final, prev }: { }")
+            .is_err());
+        assert!(analyzer
+            .analyze_source("x", "final: prev: prev.lib")
+            .is_err());
+    }
+
+    #[test]
+    fn test_stack_shadowing() {
+        let analyzer = OverlayAnalyzer::new();
+        let first = analyzer
+            .analyze_source("first", "final: prev: { tool = final.callPackage ./tool { }; hello = prev.hello.overrideAttrs (o: { }); }")
+            .unwrap();
+        let second = analyzer
+            .analyze_source(
+                "second",
+                "final: prev: { tool = prev.tool.overrideAttrs (o: { }); }",
+            )
+            .unwrap();
+        let third = analyzer
+            .analyze_source(
+                "third",
+                "final: prev: { hello = final.callPackage ./hello { }; }",
+            )
+            .unwrap();
+
+        let stack = OverlayStack::new(vec![first, second, third]);
+        assert_eq!(stack.definitions()["tool"].len(), 2);
+
+        let shadowed = stack.shadowed();
+        assert_eq!(shadowed.len(), 1);
+        assert_eq!(shadowed[0].attribute, "hello");
+        assert_eq!(shadowed[0].shadowed, "first");
+        assert_eq!(shadowed[0].shadowed_by, "third");
+        assert_eq!(
+            shadowed[0].to_string(),
+            "'hello' from overlay 'first' (overrideAttrs) is discarded by overlay 'third' (new package)"
+        );
+    }
+}
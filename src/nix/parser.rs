@@ -16,6 +16,9 @@
 //! ```
 
 use super::ast::{AstError, NixAst};
+use super::ast_converter::AstConverter;
+use super::flake_lock::{FlakeLock, LockError};
+use super::module_analyzer::ModuleAnalyzer;
 use super::objects::*;
 use super::value_objects::*;
 use std::fs;
@@ -112,11 +115,26 @@ impl NixParser {
     }
 
     /// Parse a Nix attrset from source
+    ///
+    /// The source must be static data (see [`AstConverter`]) evaluating to
+    /// an attribute set.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the source does not parse, is not static, or is
+    /// not an attribute set.
     pub fn parse_attrset(&self, source: impl AsRef<str>) -> ParseResult<NixAttrsetObject> {
-        let _ast = self.parse_str(source)?;
-        // For now, return a placeholder
-        // Full implementation will convert AST to attrset
-        Ok(NixAttrsetObject::new(NixAttrset::new()))
+        let ast = self.parse_str(source)?;
+        match AstConverter::new()
+            .convert(&ast)
+            .map_err(|e| ParseError::ConversionError(e.to_string()))?
+        {
+            NixValue::Attrset(attrset) => Ok(NixAttrsetObject::new(attrset)),
+            other => Err(ParseError::ConversionError(format!(
+                "expected an attribute set, got {}",
+                other.type_name()
+            ))),
+        }
     }
 
     /// Parse a flake.nix file
@@ -148,11 +166,22 @@ impl NixParser {
     }
 
     /// Convert AST to Module
-    fn ast_to_module(&self, _ast: &NixAst, source_path: PathBuf) -> ParseResult<NixModule> {
-        // Placeholder implementation
-        // Full implementation will parse module structure
-        Ok(NixModule::new("parsed-module".to_string())
-            .with_source_path(source_path))
+    ///
+    /// Options and imports are read by the [`ModuleAnalyzer`]; the module is
+    /// named after its file (or directory, for `default.nix`).
+    fn ast_to_module(&self, ast: &NixAst, source_path: PathBuf) -> ParseResult<NixModule> {
+        let name = match source_path.file_stem().and_then(|stem| stem.to_str()) {
+            Some("default") => source_path
+                .parent()
+                .and_then(|dir| dir.file_name())
+                .and_then(|dir| dir.to_str()),
+            stem => stem,
+        }
+        .unwrap_or("parsed-module")
+        .to_string();
+        let mut analysis = ModuleAnalyzer::new().analyze_ast(ast);
+        analysis.source = Some(source_path);
+        Ok(analysis.to_module(name))
     }
 
     /// Convert AST to Package
@@ -223,13 +252,10 @@ impl FlakeParser {
     /// Parse flake.lock file
     pub fn parse_lock(&self, flake_dir: impl AsRef<Path>) -> ParseResult<FlakeLock> {
         let lock_path = flake_dir.as_ref().join("flake.lock");
-        let content = fs::read_to_string(&lock_path)
-            .map_err(|e| ParseError::IoError(format!("Failed to read flake.lock: {}", e)))?;
-
-        let lock: FlakeLock = serde_json::from_str(&content)
-            .map_err(|e| ParseError::ConversionError(format!("Invalid flake.lock: {}", e)))?;
-
-        Ok(lock)
+        FlakeLock::from_file(lock_path).map_err(|e| match e {
+            LockError::Io(e) => ParseError::IoError(format!("Failed to read flake.lock: {}", e)),
+            e => ParseError::ConversionError(e.to_string()),
+        })
     }
 }
 
@@ -239,60 +265,6 @@ impl Default for FlakeParser {
     }
 }
 
-/// Flake Lock File
-///
-/// Represents the flake.lock file that pins input versions
-#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
-pub struct FlakeLock {
-    /// Lock file version
-    pub version: u32,
-    /// Locked nodes (inputs)
-    pub nodes: std::collections::HashMap<String, LockedNode>,
-}
-
-/// A locked node in the flake lock file
-#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
-pub struct LockedNode {
-    /// Locked reference
-    pub locked: Option<LockedRef>,
-    /// Original reference
-    pub original: Option<OriginalRef>,
-    /// Input nodes this depends on
-    pub inputs: Option<std::collections::HashMap<String, String>>,
-}
-
-/// Locked reference
-#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
-pub struct LockedRef {
-    /// Reference type (github, git, etc.)
-    #[serde(rename = "type")]
-    pub ref_type: String,
-    /// Owner (for github)
-    pub owner: Option<String>,
-    /// Repository (for github)
-    pub repo: Option<String>,
-    /// Revision hash
-    pub rev: Option<String>,
-    /// Last modified timestamp
-    #[serde(rename = "lastModified")]
-    pub last_modified: Option<u64>,
-}
-
-/// Original reference
-#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
-pub struct OriginalRef {
-    /// Reference type
-    #[serde(rename = "type")]
-    pub ref_type: String,
-    /// Owner (for github)
-    pub owner: Option<String>,
-    /// Repository (for github)
-    pub repo: Option<String>,
-    /// Reference (branch/tag)
-    #[serde(rename = "ref")]
-    pub git_ref: Option<String>,
-}
-
 /// Module Parser - Specialized for parsing NixOS modules
 pub struct ModuleParser {
     parser: NixParser,
@@ -352,6 +324,26 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_parse_attrset_object() {
+        let parser = NixParser::new();
+        let object = parser
+            .parse_attrset(r#"let port = 80; in { host = "web01"; ports = [ port 443 ]; }"#)
+            .unwrap();
+        assert_eq!(object.attrset.len(), 2);
+        let host = object.attrset.get("host").unwrap();
+        assert_eq!(host.as_string().unwrap().value, "web01");
+
+        assert!(matches!(
+            parser.parse_attrset("[ 1 2 ]"),
+            Err(ParseError::ConversionError(_))
+        ));
+        assert!(matches!(
+            parser.parse_attrset("{ x = y; }"),
+            Err(ParseError::ConversionError(_))
+        ));
+    }
+
     #[test]
     fn test_parse_invalid_syntax() {
         let parser = NixParser::new();
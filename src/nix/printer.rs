@@ -0,0 +1,615 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Canonical Nix printer
+//!
+//! Turns a [`NixValue`] back into Nix source. The output is deterministic
+//! (the same value and configuration always print the same text) and parses
+//! back to an equal value:
+//!
+//! ```text
+//! parse_value(&NixPrinter::new().print(&v)?)? == v
+//! ```
+//!
+//! String context is not part of the Nix syntax and is dropped when printing.
+//! Values with no source form — non-finite floats, relative paths without a
+//! `/`, lookup paths with characters outside the lookup path syntax — are
+//! reported as [`PrintError`]s rather than printed lossily.
+//!
+//! ## Layout
+//!
+//! - Collections whose one-line form fits within
+//!   [`PrintConfig::max_inline_width`] are printed on one line
+//!   (`[ 1 2 3 ]`, `{ a = 1; b = 2; }`), larger ones one entry per line.
+//! - Strings spanning several lines are printed as indented strings
+//!   (`''...''`) when that form is unambiguous, otherwise as escaped
+//!   double-quoted strings.
+//! - Attribute names are bare identifiers when possible and quoted otherwise.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use cim_domain_nix::nix::*;
+//!
+//! let mut attrs = NixAttrset::new();
+//! attrs.insert("name".to_string(), NixValue::String(NixString::new("web01")));
+//! attrs.insert("port".to_string(), NixValue::Integer(NixInteger::new(8080)));
+//! let value = NixValue::Attrset(attrs);
+//!
+//! let source = NixPrinter::new().print(&value).unwrap();
+//! assert_eq!(source, "{ name = \"web01\"; port = 8080; }");
+//! assert_eq!(parse_value(&source).unwrap(), value);
+//! ```
+
+use super::value_objects::{NixAttrset, NixPath, NixValue};
+use std::fmt::Write as _;
+use thiserror::Error;
+
+/// Errors that can occur while printing
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum PrintError {
+    /// NaN and infinities have no literal syntax
+    #[error("Float {0} has no Nix literal")]
+    NonFiniteFloat(f64),
+
+    /// The path cannot be written as a path literal
+    #[error("Path cannot be written as a Nix path literal: {0}")]
+    UnrepresentablePath(String),
+
+    /// The lookup path cannot be written as `<name>`
+    #[error("Lookup path cannot be written as a Nix lookup path: {0}")]
+    UnrepresentableLookupPath(String),
+}
+
+/// Result type for printing
+pub type PrintResult<T> = std::result::Result<T, PrintError>;
+
+/// Order in which attribute set keys are printed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Lexicographic order of the key bytes
+    #[default]
+    Sorted,
+    /// The listed keys first, in list order, then the remaining keys sorted
+    ///
+    /// Applies at every nesting level, which is how writers put the
+    /// interesting fields of a record (`type`, `hostname`, ...) on top.
+    Priority(Vec<String>),
+}
+
+/// Printer configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintConfig {
+    /// Spaces per nesting level
+    pub indent: usize,
+    /// Order of attribute set keys
+    pub key_order: KeyOrder,
+    /// Longest one-line form of a non-empty list or attribute set that is
+    /// kept on one line; `0` always breaks them over several lines
+    pub max_inline_width: usize,
+    /// Print strings containing newlines as indented strings (`''...''`)
+    pub multiline_strings: bool,
+}
+
+impl Default for PrintConfig {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            key_order: KeyOrder::Sorted,
+            max_inline_width: 80,
+            multiline_strings: true,
+        }
+    }
+}
+
+/// Nix printer
+#[derive(Debug, Clone, Default)]
+pub struct NixPrinter {
+    config: PrintConfig,
+}
+
+impl NixPrinter {
+    /// Create a printer with the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a printer with a custom configuration
+    pub fn with_config(config: PrintConfig) -> Self {
+        Self { config }
+    }
+
+    /// Set the number of spaces per nesting level
+    #[must_use]
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        self.config.indent = indent;
+        self
+    }
+
+    /// Set the order of attribute set keys
+    #[must_use]
+    pub fn with_key_order(mut self, key_order: KeyOrder) -> Self {
+        self.config.key_order = key_order;
+        self
+    }
+
+    /// Set the longest collection kept on one line
+    #[must_use]
+    pub fn with_max_inline_width(mut self, width: usize) -> Self {
+        self.config.max_inline_width = width;
+        self
+    }
+
+    /// The printer's configuration
+    pub fn config(&self) -> &PrintConfig {
+        &self.config
+    }
+
+    /// Print a value as Nix source
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the value, or any value nested in it, has no
+    /// Nix source form (see [`PrintError`]).
+    pub fn print(&self, value: &NixValue) -> PrintResult<String> {
+        let mut out = String::new();
+        self.write_value(value, 0, &mut out)?;
+        Ok(out)
+    }
+
+    // ------------------------------------------------------------------------
+    // Layout
+    // ------------------------------------------------------------------------
+
+    /// Write `value` starting at the current position, `depth` levels deep
+    fn write_value(&self, value: &NixValue, depth: usize, out: &mut String) -> PrintResult<()> {
+        match value {
+            NixValue::List(list) if !list.is_empty() => {
+                if let Some(flat) = self.inline(value)? {
+                    out.push_str(&flat);
+                    return Ok(());
+                }
+                out.push_str("[\n");
+                for element in &list.elements {
+                    self.pad(depth + 1, out);
+                    if needs_parens(element) {
+                        out.push('(');
+                        self.write_value(element, depth + 1, out)?;
+                        out.push(')');
+                    } else {
+                        self.write_value(element, depth + 1, out)?;
+                    }
+                    out.push('\n');
+                }
+                self.pad(depth, out);
+                out.push(']');
+            }
+            NixValue::Attrset(attrs) if !attrs.is_empty() => {
+                if let Some(flat) = self.inline(value)? {
+                    out.push_str(&flat);
+                    return Ok(());
+                }
+                if attrs.recursive {
+                    out.push_str("rec ");
+                }
+                out.push_str("{\n");
+                for key in self.ordered_keys(attrs) {
+                    self.pad(depth + 1, out);
+                    out.push_str(&attr_name(key));
+                    out.push_str(" = ");
+                    self.write_value(&attrs.attributes[key], depth + 1, out)?;
+                    out.push_str(";\n");
+                }
+                self.pad(depth, out);
+                out.push('}');
+            }
+            NixValue::String(s) if self.is_block_string(&s.value) => {
+                self.write_block_string(&s.value, depth, out);
+            }
+            atom => out.push_str(&Self::atom(atom)?),
+        }
+        Ok(())
+    }
+
+    /// One-line form of a value, if it has one that fits
+    fn inline(&self, value: &NixValue) -> PrintResult<Option<String>> {
+        let Some(flat) = self.flat(value)? else {
+            return Ok(None);
+        };
+        let fits = match value {
+            NixValue::List(list) if !list.is_empty() => flat.len() <= self.config.max_inline_width,
+            NixValue::Attrset(attrs) if !attrs.is_empty() => {
+                flat.len() <= self.config.max_inline_width
+            }
+            _ => true,
+        };
+        Ok(fits.then_some(flat))
+    }
+
+    /// One-line form of a value, ignoring the width limit
+    ///
+    /// `None` when the value contains a string printed as an indented string.
+    fn flat(&self, value: &NixValue) -> PrintResult<Option<String>> {
+        match value {
+            NixValue::List(list) if !list.is_empty() => {
+                let mut out = String::from("[");
+                for element in &list.elements {
+                    let Some(item) = self.flat(element)? else {
+                        return Ok(None);
+                    };
+                    if needs_parens(element) {
+                        let _ = write!(out, " ({item})");
+                    } else {
+                        let _ = write!(out, " {item}");
+                    }
+                }
+                out.push_str(" ]");
+                Ok(Some(out))
+            }
+            NixValue::Attrset(attrs) if !attrs.is_empty() => {
+                let mut out = String::from(if attrs.recursive { "rec {" } else { "{" });
+                for key in self.ordered_keys(attrs) {
+                    let Some(item) = self.flat(&attrs.attributes[key])? else {
+                        return Ok(None);
+                    };
+                    let _ = write!(out, " {} = {item};", attr_name(key));
+                }
+                out.push_str(" }");
+                Ok(Some(out))
+            }
+            NixValue::String(s) if self.is_block_string(&s.value) => Ok(None),
+            atom => Self::atom(atom).map(Some),
+        }
+    }
+
+    fn ordered_keys<'a>(&self, attrs: &'a NixAttrset) -> Vec<&'a String> {
+        let mut keys: Vec<&String> = attrs.attributes.keys().collect();
+        match &self.config.key_order {
+            KeyOrder::Sorted => keys.sort(),
+            KeyOrder::Priority(priority) => keys.sort_by_key(|key| {
+                (
+                    priority
+                        .iter()
+                        .position(|p| p == *key)
+                        .unwrap_or(usize::MAX),
+                    *key,
+                )
+            }),
+        }
+        keys
+    }
+
+    fn pad(&self, depth: usize, out: &mut String) {
+        out.push_str(&" ".repeat(depth * self.config.indent));
+    }
+
+    // ------------------------------------------------------------------------
+    // Atoms
+    // ------------------------------------------------------------------------
+
+    /// Values printed the same way in every layout: scalars and empty
+    /// collections
+    fn atom(value: &NixValue) -> PrintResult<String> {
+        match value {
+            NixValue::Null(_) => Ok("null".to_string()),
+            NixValue::Bool(b) => Ok(b.value.to_string()),
+            NixValue::Integer(i) => Ok(i.value.to_string()),
+            NixValue::Float(f) => float_literal(f.value),
+            NixValue::String(s) => Ok(quote(&s.value)),
+            NixValue::Path(p) => path_literal(p),
+            NixValue::LookupPath(l) => lookup_path_literal(&l.name),
+            NixValue::List(_) => Ok("[ ]".to_string()),
+            NixValue::Attrset(attrs) if attrs.recursive => Ok("rec { }".to_string()),
+            NixValue::Attrset(_) => Ok("{ }".to_string()),
+        }
+    }
+
+    /// Whether a string is printed as an indented string
+    ///
+    /// Only strings whose indented form strips back to exactly the same text
+    /// qualify: every line must start with a visible character (so the
+    /// indentation Nix removes is exactly the one added here), and quotes
+    /// must not touch the `''` delimiters or escapes.
+    fn is_block_string(&self, text: &str) -> bool {
+        self.config.multiline_strings
+            && self.config.indent > 0
+            && text.contains('\n')
+            && !text.contains('\r')
+            && !text.contains("''")
+            && !text.contains("'$")
+            && !text.ends_with('\'')
+            && text
+                .split('\n')
+                .all(|line| !line.starts_with(char::is_whitespace))
+    }
+
+    fn write_block_string(&self, text: &str, depth: usize, out: &mut String) {
+        out.push_str("''\n");
+        let (body, trailing_newline) = match text.strip_suffix('\n') {
+            Some(body) => (body, true),
+            None => (text, false),
+        };
+        for (i, line) in body.split('\n').enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            if !line.is_empty() {
+                self.pad(depth + 1, out);
+                out.push_str(&line.replace("${", "''${"));
+            }
+        }
+        if trailing_newline {
+            out.push('\n');
+            self.pad(depth, out);
+        }
+        out.push_str("''");
+    }
+}
+
+// ============================================================================
+// Literals
+// ============================================================================
+
+/// Keywords that cannot be bare attribute names
+const KEYWORDS: &[&str] = &[
+    "assert", "else", "if", "in", "inherit", "let", "or", "rec", "then", "with",
+];
+
+/// Characters allowed in path literals
+fn is_path_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '+')
+}
+
+/// Whether a list element must be parenthesised (`[ (-1) ]`)
+fn needs_parens(value: &NixValue) -> bool {
+    match value {
+        NixValue::Integer(i) => i.value < 0,
+        NixValue::Float(f) => f.value.is_sign_negative(),
+        _ => false,
+    }
+}
+
+/// An attribute name, quoted unless it is a plain identifier
+fn attr_name(key: &str) -> String {
+    let mut chars = key.chars();
+    let is_ident = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '\'' | '-'));
+    if is_ident && !KEYWORDS.contains(&key) {
+        key.to_string()
+    } else {
+        quote(key)
+    }
+}
+
+/// A double-quoted string literal
+fn quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '$' if chars.peek() == Some(&'{') => out.push_str("\\$"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A float literal that reads back as the same float
+///
+/// Nix float literals need a `.` (`1e5` is not a float), so one is added
+/// to Rust's shortest round-trip representation when missing.
+fn float_literal(value: f64) -> PrintResult<String> {
+    if !value.is_finite() {
+        return Err(PrintError::NonFiniteFloat(value));
+    }
+    let repr = format!("{value:?}");
+    if repr.contains('.') {
+        return Ok(repr);
+    }
+    Ok(match repr.split_once('e') {
+        Some((mantissa, exponent)) => format!("{mantissa}.0e{exponent}"),
+        None => format!("{repr}.0"),
+    })
+}
+
+/// A path literal, interpolating the segments path syntax cannot spell
+///
+/// `./a/b c` becomes `./a/${"b c"}`. The leading segment (`.`, `..`, `~`,
+/// empty for absolute paths, or a plain name) must be literal, and at least
+/// one `/` is needed for the text to lex as a path.
+fn path_literal(path: &NixPath) -> PrintResult<String> {
+    let text = path.path.to_str().unwrap_or_default();
+    let invalid = || PrintError::UnrepresentablePath(path.path.display().to_string());
+
+    let mut segments = text.split('/');
+    let head = segments.next().unwrap_or_default();
+    // Empty segments (`a//b`, trailing `/`) do not change the path's
+    // components, and a path literal cannot contain them
+    let rest: Vec<&str> = segments.filter(|s| !s.is_empty()).collect();
+    if rest.is_empty() || !(head == "~" || head.chars().all(is_path_char)) {
+        return Err(invalid());
+    }
+
+    let mut out = head.to_string();
+    for (i, segment) in rest.iter().enumerate() {
+        out.push('/');
+        if segment.chars().all(is_path_char) {
+            out.push_str(segment);
+        } else if head == "~" {
+            // `~/${...}` is not valid path syntax
+            return Err(invalid());
+        } else {
+            let _ = write!(out, "${{{}}}", quote(&rest[i..].join("/")));
+            break;
+        }
+    }
+    Ok(out)
+}
+
+/// A `<name>` lookup path literal
+fn lookup_path_literal(name: &str) -> PrintResult<String> {
+    let valid = name
+        .split('/')
+        .all(|segment| !segment.is_empty() && segment.chars().all(is_path_char));
+    if valid {
+        Ok(format!("<{name}>"))
+    } else {
+        Err(PrintError::UnrepresentableLookupPath(name.to_string()))
+    }
+}
+
+// ============================================================================
+// Convenience Functions
+// ============================================================================
+
+/// Print a value as Nix source with the default configuration
+///
+/// ## Errors
+///
+/// See [`NixPrinter::print`].
+pub fn print_value(value: &NixValue) -> PrintResult<String> {
+    NixPrinter::new().print(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nix::parse_value;
+    use crate::nix::value_objects::*;
+
+    fn string(s: &str) -> NixValue {
+        NixValue::String(NixString::new(s))
+    }
+
+    fn int(i: i64) -> NixValue {
+        NixValue::Integer(NixInteger::new(i))
+    }
+
+    fn attrs(entries: &[(&str, NixValue)]) -> NixValue {
+        let mut set = NixAttrset::new();
+        for (key, value) in entries {
+            set.insert((*key).to_string(), value.clone());
+        }
+        NixValue::Attrset(set)
+    }
+
+    fn round_trip(value: &NixValue) -> String {
+        let source = print_value(value).unwrap();
+        assert_eq!(&parse_value(&source).unwrap(), value, "source: {source}");
+        source
+    }
+
+    #[test]
+    fn test_print_scalars() {
+        assert_eq!(round_trip(&NixValue::Null(NixNull::new())), "null");
+        assert_eq!(round_trip(&NixValue::Bool(NixBool::new(true))), "true");
+        assert_eq!(round_trip(&int(-42)), "-42");
+        assert_eq!(round_trip(&int(i64::MIN)), "-9223372036854775808");
+        assert_eq!(round_trip(&NixValue::Float(NixFloat::new(1.0))), "1.0");
+        assert_eq!(round_trip(&NixValue::Float(NixFloat::new(1e-7))), "1.0e-7");
+        assert_eq!(
+            round_trip(&NixValue::Float(NixFloat::new(2.5e300))),
+            "2.5e300"
+        );
+        for float in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(matches!(
+                print_value(&NixValue::Float(NixFloat::new(float))),
+                Err(PrintError::NonFiniteFloat(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_print_string_escapes() {
+        assert_eq!(
+            round_trip(&string("say \"hi\" \\ ${x} $y\ttab")),
+            r#""say \"hi\" \\ \${x} $y\ttab""#
+        );
+        round_trip(&string("line\r\n"));
+    }
+
+    #[test]
+    fn test_print_multiline_strings() {
+        let value = attrs(&[("script", string("echo ${HOME}\n\nexit 0\n"))]);
+        assert_eq!(
+            round_trip(&value),
+            "{\n  script = ''\n    echo ''${HOME}\n\n    exit 0\n  '';\n}"
+        );
+        assert_eq!(round_trip(&string("a\nb")), "''\n  a\n  b''");
+
+        // Leading whitespace would be eaten by indentation stripping
+        assert_eq!(round_trip(&string("a\n  b")), r#""a\n  b""#);
+        round_trip(&string("it's\nfine"));
+        round_trip(&string("end'\n'"));
+    }
+
+    #[test]
+    fn test_print_attribute_names() {
+        let value = attrs(&[
+            ("plain-name'", int(1)),
+            ("with space", int(2)),
+            ("in", int(3)),
+            ("1st", int(4)),
+            ("", int(5)),
+        ]);
+        assert_eq!(
+            round_trip(&value),
+            r#"{ "" = 5; "1st" = 4; "in" = 3; plain-name' = 1; "with space" = 2; }"#
+        );
+    }
+
+    #[test]
+    fn test_print_layout() {
+        let list = NixValue::List(NixList::from_vec(vec![int(1), int(-2), string("x")]));
+        assert_eq!(round_trip(&list), r#"[ 1 (-2) "x" ]"#);
+
+        let nested = attrs(&[("list", list.clone()), ("empty", attrs(&[]))]);
+        let printer = NixPrinter::new().with_max_inline_width(0).with_indent(4);
+        let source = printer.print(&nested).unwrap();
+        assert_eq!(
+            source,
+            "{\n    empty = { };\n    list = [\n        1\n        (-2)\n        \"x\"\n    ];\n}"
+        );
+        assert_eq!(parse_value(&source).unwrap(), nested);
+
+        let mut rec = NixAttrset::new_recursive();
+        rec.insert("a".to_string(), int(1));
+        assert_eq!(round_trip(&NixValue::Attrset(rec)), "rec { a = 1; }");
+    }
+
+    #[test]
+    fn test_print_key_order() {
+        let value = attrs(&[("b", int(1)), ("a", int(2)), ("type", int(3))]);
+        let printer = NixPrinter::new().with_key_order(KeyOrder::Priority(vec!["type".into()]));
+        assert_eq!(
+            printer.print(&value).unwrap(),
+            "{ type = 3; a = 2; b = 1; }"
+        );
+    }
+
+    #[test]
+    fn test_print_paths() {
+        let path = |p: &str| NixValue::Path(NixPath::new(p).unwrap());
+        assert_eq!(round_trip(&path("./a/b.nix")), "./a/b.nix");
+        assert_eq!(round_trip(&path("/etc/nixos")), "/etc/nixos");
+        assert_eq!(round_trip(&path("~/src")), "~/src");
+        assert_eq!(round_trip(&path("./a/b c/d")), r#"./a/${"b c/d"}"#);
+        assert_eq!(round_trip(&path("/with space")), r#"/${"with space"}"#);
+        assert!(matches!(
+            print_value(&path("relative")),
+            Err(PrintError::UnrepresentablePath(_))
+        ));
+
+        let lookup = NixValue::LookupPath(NixLookupPath::new("nixpkgs/lib").unwrap());
+        assert_eq!(round_trip(&lookup), "<nixpkgs/lib>");
+        assert!(print_value(&NixValue::LookupPath(NixLookupPath::new("a b").unwrap())).is_err());
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Deserializer reading [`NixValue`]s
+
+use super::{SerializationError, SerializationResult};
+use crate::nix::value_objects::NixValue;
+use serde::de::{self, IntoDeserializer, Unexpected, Visitor};
+
+/// Deserializer reading any `Deserialize` type out of a [`NixValue`]
+///
+/// See the [module documentation](super) for the data model.
+#[derive(Debug, Clone)]
+pub struct NixValueDeserializer {
+    value: NixValue,
+}
+
+impl NixValueDeserializer {
+    /// Create a deserializer over `value`
+    pub fn new(value: NixValue) -> Self {
+        Self { value }
+    }
+
+    /// How the value is described in "invalid type" errors
+    fn unexpected(&self) -> Unexpected<'_> {
+        match &self.value {
+            NixValue::String(s) => Unexpected::Str(&s.value),
+            NixValue::Integer(i) => Unexpected::Signed(i.value),
+            NixValue::Float(f) => Unexpected::Float(f.value),
+            NixValue::Bool(b) => Unexpected::Bool(b.value),
+            NixValue::Null(_) => Unexpected::Unit,
+            NixValue::Path(_) => Unexpected::Other("path"),
+            NixValue::LookupPath(_) => Unexpected::Other("lookup path"),
+            NixValue::List(_) => Unexpected::Seq,
+            NixValue::Attrset(_) => Unexpected::Map,
+        }
+    }
+}
+
+impl IntoDeserializer<'_, SerializationError> for NixValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for NixValueDeserializer {
+    type Error = SerializationError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> SerializationResult<V::Value> {
+        match self.value {
+            NixValue::Null(_) => visitor.visit_unit(),
+            NixValue::Bool(b) => visitor.visit_bool(b.value),
+            NixValue::Integer(i) => visitor.visit_i64(i.value),
+            NixValue::Float(f) => visitor.visit_f64(f.value),
+            NixValue::String(s) => visitor.visit_string(s.value),
+            NixValue::Path(p) => visitor.visit_string(p.as_str().to_string()),
+            NixValue::LookupPath(l) => visitor.visit_string(format!("<{}>", l.name)),
+            NixValue::List(list) => {
+                let mut seq = de::value::SeqDeserializer::new(
+                    list.elements.into_iter().map(NixValueDeserializer::new),
+                );
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            NixValue::Attrset(attrs) => {
+                // Sorted, so errors about the first bad entry are stable
+                let mut entries: Vec<_> = attrs.attributes.into_iter().collect();
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                let mut map =
+                    de::value::MapDeserializer::new(entries.into_iter().map(|(key, value)| {
+                        (KeyDeserializer(key), NixValueDeserializer::new(value))
+                    }));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> SerializationResult<V::Value> {
+        match self.value {
+            NixValue::Null(_) => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> SerializationResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> SerializationResult<V::Value> {
+        match self.value {
+            NixValue::String(s) => {
+                let variant: de::value::StringDeserializer<SerializationError> =
+                    s.value.into_deserializer();
+                visitor.visit_enum(variant)
+            }
+            NixValue::Attrset(attrs) if attrs.len() == 1 => {
+                let (variant, value) = attrs
+                    .attributes
+                    .into_iter()
+                    .next()
+                    .expect("attribute set has one entry");
+                visitor.visit_enum(EnumAccess { variant, value })
+            }
+            NixValue::Attrset(_) => Err(de::Error::invalid_value(
+                Unexpected::Map,
+                &"a string or an attribute set with a single attribute",
+            )),
+            _ => Err(de::Error::invalid_type(self.unexpected(), &"an enum")),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+// ============================================================================
+// Map Keys
+// ============================================================================
+
+/// Deserializer for attribute names
+///
+/// Names are always strings; numbers and booleans written as keys by the
+/// serializer are parsed back when the key type asks for them.
+struct KeyDeserializer(String);
+
+impl IntoDeserializer<'_, SerializationError> for KeyDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// `deserialize_*` methods that parse the key as a scalar
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> SerializationResult<V::Value> {
+                match self.0.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(de::Error::invalid_type(Unexpected::Str(&self.0), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer {
+    type Error = SerializationError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> SerializationResult<V::Value> {
+        visitor.visit_string(self.0)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> SerializationResult<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> SerializationResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> SerializationResult<V::Value> {
+        let variant: de::value::StringDeserializer<SerializationError> = self.0.into_deserializer();
+        visitor.visit_enum(variant)
+    }
+
+    serde::forward_to_deserialize_any! {
+        f32 f64 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+// ============================================================================
+// Enums
+// ============================================================================
+
+/// `{ Variant = value; }`
+struct EnumAccess {
+    variant: String,
+    value: NixValue,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess {
+    type Error = SerializationError;
+    type Variant = NixValueDeserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> SerializationResult<(V::Value, NixValueDeserializer)> {
+        let variant: de::value::StringDeserializer<SerializationError> =
+            self.variant.into_deserializer();
+        let variant = seed.deserialize(variant)?;
+        Ok((variant, NixValueDeserializer::new(self.value)))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for NixValueDeserializer {
+    type Error = SerializationError;
+
+    fn unit_variant(self) -> SerializationResult<()> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> SerializationResult<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> SerializationResult<V::Value> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> SerializationResult<V::Value> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
@@ -0,0 +1,351 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Serde data format for Nix
+//!
+//! Reads Rust types out of Nix source and writes them back as Nix, so
+//! schemas can be plain `#[derive(Serialize, Deserialize)]` types instead of
+//! hand-written attrset traversals.
+//!
+//! - [`from_nix_str`] parses static Nix with the
+//!   [`AstConverter`](crate::nix::AstConverter) and deserializes the value
+//! - [`to_nix_string`] serializes to a [`NixValue`] and prints it with the
+//!   [`NixPrinter`]
+//! - [`from_value`] / [`to_value`] work on [`NixValue`]s directly, e.g. on
+//!   the result of the [`Evaluator`](crate::nix::Evaluator)
+//!
+//! ## Data Model
+//!
+//! | Rust                          | Nix                                  |
+//! |-------------------------------|--------------------------------------|
+//! | `bool`, integers, floats      | booleans, integers, floats           |
+//! | `String`, `char`              | strings                              |
+//! | `Option<T>`                   | `null` or the value                  |
+//! | `()`, unit structs            | `null`                               |
+//! | sequences, tuples             | lists                                |
+//! | maps, structs                 | attribute sets                       |
+//! | unit variants                 | the variant name as a string         |
+//! | other variants                | `{ Variant = <contents>; }`          |
+//!
+//! Paths and lookup paths deserialize as strings (`./a`, `<nixpkgs>`), so
+//! they can be read into `String` or `PathBuf` fields. Map keys must
+//! serialize to strings, numbers, booleans or chars. Missing `Option`
+//! fields deserialize as `None`.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use cim_domain_nix::nix::serialization::{from_nix_str, to_nix_string};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! #[serde(rename_all = "camelCase")]
+//! struct Node {
+//!     hostname: String,
+//!     serial_number: Option<String>,
+//!     ports: Vec<u16>,
+//! }
+//!
+//! let node: Node = from_nix_str(r#"{ hostname = "web01"; ports = [ 80 443 ]; }"#).unwrap();
+//! assert_eq!(node.ports, vec![80, 443]);
+//! assert_eq!(node.serial_number, None);
+//!
+//! let source = to_nix_string(&node).unwrap();
+//! assert_eq!(from_nix_str::<Node>(&source).unwrap(), node);
+//! ```
+
+mod de;
+mod ser;
+
+use super::ast_converter::{parse_value, ConversionError};
+use super::printer::{NixPrinter, PrintError};
+use super::value_objects::NixValue;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use thiserror::Error;
+
+pub use de::NixValueDeserializer;
+pub use ser::NixValueSerializer;
+
+/// Errors that can occur while serializing or deserializing
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum SerializationError {
+    /// The source is not static Nix
+    #[error(transparent)]
+    Conversion(#[from] ConversionError),
+
+    /// The serialized value has no Nix source form
+    #[error(transparent)]
+    Print(#[from] PrintError),
+
+    /// An integer outside the range of Nix integers (`i64`)
+    #[error("Integer {0} is out of range for a Nix integer")]
+    IntegerOutOfRange(String),
+
+    /// A map key that cannot be an attribute name
+    #[error("Attribute names must be strings, got {0}")]
+    InvalidKey(&'static str),
+
+    /// Error reported by a `Serialize` or `Deserialize` implementation
+    #[error("{0}")]
+    Message(String),
+}
+
+impl serde::ser::Error for SerializationError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
+impl serde::de::Error for SerializationError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
+/// Result type for serialization
+pub type SerializationResult<T> = std::result::Result<T, SerializationError>;
+
+// ============================================================================
+// Convenience Functions
+// ============================================================================
+
+/// Deserialize a value of type `T` from static Nix source
+///
+/// ## Errors
+///
+/// Returns [`SerializationError::Conversion`] if the source does not parse or
+/// is not static, or another error if the value does not match `T`.
+pub fn from_nix_str<T: DeserializeOwned>(source: &str) -> SerializationResult<T> {
+    from_value(parse_value(source)?)
+}
+
+/// Deserialize a value of type `T` from a [`NixValue`]
+///
+/// ## Errors
+///
+/// Returns an error if the value does not match `T`.
+pub fn from_value<T: DeserializeOwned>(value: NixValue) -> SerializationResult<T> {
+    T::deserialize(NixValueDeserializer::new(value))
+}
+
+/// Serialize `value` into a [`NixValue`]
+///
+/// ## Errors
+///
+/// Returns an error if `value` contains integers beyond `i64`, non-string map
+/// keys, or fails to serialize itself.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> SerializationResult<NixValue> {
+    value.serialize(NixValueSerializer)
+}
+
+/// Serialize `value` as Nix source with the default printer
+///
+/// ## Errors
+///
+/// See [`to_value`]; also fails on non-finite floats, which have no Nix
+/// literal.
+pub fn to_nix_string<T: Serialize + ?Sized>(value: &T) -> SerializationResult<String> {
+    to_nix_string_with(value, &NixPrinter::new())
+}
+
+/// Serialize `value` as Nix source with a custom printer
+///
+/// ## Errors
+///
+/// See [`to_nix_string`].
+pub fn to_nix_string_with<T: Serialize + ?Sized>(
+    value: &T,
+    printer: &NixPrinter,
+) -> SerializationResult<String> {
+    Ok(printer.print(&to_value(value)?)?)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap};
+    use std::path::PathBuf;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    enum NodeType {
+        PhysicalServer,
+        Router,
+        Vm { cores: u8, memory: Option<u32> },
+        Container(String),
+        Pair(i32, i32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Node {
+        #[serde(rename = "type")]
+        kind: NodeType,
+        hostname: String,
+        serial_number: Option<String>,
+        #[serde(default)]
+        tags: Vec<String>,
+        metadata: BTreeMap<String, String>,
+        weight: f64,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Topology {
+        nodes: HashMap<String, Node>,
+        config: Option<PathBuf>,
+    }
+
+    fn sample() -> Topology {
+        let mut nodes = HashMap::new();
+        nodes.insert(
+            "router01".to_string(),
+            Node {
+                kind: NodeType::Router,
+                hostname: "router01".to_string(),
+                serial_number: Some("ABC \"123\"".to_string()),
+                tags: vec!["edge".to_string()],
+                metadata: BTreeMap::from([("rack".to_string(), "A01".to_string())]),
+                weight: 1.5,
+            },
+        );
+        nodes.insert(
+            "vm01".to_string(),
+            Node {
+                kind: NodeType::Vm {
+                    cores: 4,
+                    memory: None,
+                },
+                hostname: "vm01".to_string(),
+                serial_number: None,
+                tags: Vec::new(),
+                metadata: BTreeMap::new(),
+                weight: -2.0,
+            },
+        );
+        Topology {
+            nodes,
+            config: Some(PathBuf::from("./hosts/vm01.nix")),
+        }
+    }
+
+    #[test]
+    fn test_from_nix_str() {
+        let source = r#"
+            let rack = "A01"; in {
+              nodes.router01 = {
+                type = "router";
+                hostname = "router01";
+                metadata = { inherit rack; };
+                weight = 3;
+              };
+              nodes.vm01 = {
+                type.vm = { cores = 2; };
+                hostname = "vm01";
+                serialNumber = null;
+                tags = [ "a" "b" ];
+                metadata = { };
+                weight = 0.5;
+              };
+              config = ./hosts/vm01.nix;
+            }
+        "#;
+        let topology: Topology = from_nix_str(source).unwrap();
+
+        let router = &topology.nodes["router01"];
+        assert_eq!(router.kind, NodeType::Router);
+        assert_eq!(router.metadata["rack"], "A01");
+        assert_eq!(router.serial_number, None);
+        assert!(router.tags.is_empty());
+        assert!((router.weight - 3.0).abs() < f64::EPSILON);
+
+        let vm = &topology.nodes["vm01"];
+        assert_eq!(
+            vm.kind,
+            NodeType::Vm {
+                cores: 2,
+                memory: None
+            }
+        );
+        assert_eq!(vm.tags, vec!["a", "b"]);
+        assert_eq!(topology.config, Some(PathBuf::from("./hosts/vm01.nix")));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let topology = sample();
+        let source = to_nix_string(&topology).unwrap();
+        assert_eq!(from_nix_str::<Topology>(&source).unwrap(), topology);
+
+        for node_type in [
+            NodeType::PhysicalServer,
+            NodeType::Container("nginx".to_string()),
+            NodeType::Pair(-1, 2),
+        ] {
+            let source = to_nix_string(&node_type).unwrap();
+            assert_eq!(from_nix_str::<NodeType>(&source).unwrap(), node_type);
+        }
+    }
+
+    #[test]
+    fn test_to_value_shapes() {
+        fn round_trip<T>(value: &T, expected: &str)
+        where
+            T: Serialize + DeserializeOwned + PartialEq + fmt::Debug,
+        {
+            let source = to_nix_string(value).unwrap();
+            assert_eq!(source, expected);
+            assert_eq!(&from_nix_str::<T>(&source).unwrap(), value, "{source}");
+        }
+
+        round_trip(&NodeType::PhysicalServer, r#""physical-server""#);
+        round_trip(&NodeType::Pair(1, 2), "{ pair = [ 1 2 ]; }");
+        round_trip(
+            &BTreeMap::from([(1, true), (2, false)]),
+            r#"{ "1" = true; "2" = false; }"#,
+        );
+        round_trip(
+            &BTreeMap::from([(-7i64, 'a'), (300, 'b')]),
+            r#"{ "-7" = "a"; "300" = "b"; }"#,
+        );
+        round_trip(
+            &BTreeMap::from([(false, 0u8), (true, 1)]),
+            "{ false = 0; true = 1; }",
+        );
+        round_trip(&(Some(1u8), None::<u8>, ()), "[ 1 null null ]");
+
+        assert!(from_nix_str::<BTreeMap<u8, bool>>(r#"{ "256" = true; }"#).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            to_value(&u64::MAX),
+            Err(SerializationError::IntegerOutOfRange(_))
+        ));
+        assert!(matches!(
+            to_value(&HashMap::from([(vec![1], 1)])),
+            Err(SerializationError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            to_nix_string(&f64::NAN),
+            Err(SerializationError::Print(_))
+        ));
+        assert!(matches!(
+            from_nix_str::<u8>("x: x"),
+            Err(SerializationError::Conversion(_))
+        ));
+
+        let err = from_nix_str::<u8>("300").unwrap_err();
+        assert!(err.to_string().contains("300"), "{err}");
+        let err = from_nix_str::<Node>("{ hostname = 1; }").unwrap_err();
+        assert!(err.to_string().contains("invalid type"), "{err}");
+        let err = from_nix_str::<NodeType>("{ a = 1; b = 2; }").unwrap_err();
+        assert!(err.to_string().contains("single attribute"), "{err}");
+    }
+}
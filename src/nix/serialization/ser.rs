@@ -0,0 +1,563 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Serializer producing [`NixValue`]s
+
+use super::{SerializationError, SerializationResult};
+use crate::nix::value_objects::{
+    NixAttrset, NixBool, NixFloat, NixInteger, NixList, NixNull, NixString, NixValue,
+};
+use serde::ser::{self, Serialize};
+
+/// Serializer turning any `Serialize` type into a [`NixValue`]
+///
+/// See the [module documentation](super) for the data model.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NixValueSerializer;
+
+fn int(value: i64) -> NixValue {
+    NixValue::Integer(NixInteger::new(value))
+}
+
+fn string(value: impl Into<String>) -> NixValue {
+    NixValue::String(NixString::new(value))
+}
+
+fn null() -> NixValue {
+    NixValue::Null(NixNull::new())
+}
+
+/// `{ Variant = value; }`
+fn tagged(variant: &str, value: NixValue) -> NixValue {
+    let mut attrs = NixAttrset::new();
+    attrs.insert(variant.to_string(), value);
+    NixValue::Attrset(attrs)
+}
+
+fn checked_int<T: TryInto<i64> + ToString + Copy>(value: T) -> SerializationResult<NixValue> {
+    value
+        .try_into()
+        .map(int)
+        .map_err(|_| SerializationError::IntegerOutOfRange(value.to_string()))
+}
+
+impl ser::Serializer for NixValueSerializer {
+    type Ok = NixValue;
+    type Error = SerializationError;
+
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeAttrs;
+    type SerializeStruct = SerializeAttrs;
+    type SerializeStructVariant = SerializeAttrs;
+
+    fn serialize_bool(self, v: bool) -> SerializationResult<NixValue> {
+        Ok(NixValue::Bool(NixBool::new(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> SerializationResult<NixValue> {
+        Ok(int(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> SerializationResult<NixValue> {
+        Ok(int(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> SerializationResult<NixValue> {
+        Ok(int(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> SerializationResult<NixValue> {
+        Ok(int(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> SerializationResult<NixValue> {
+        checked_int(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> SerializationResult<NixValue> {
+        Ok(int(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> SerializationResult<NixValue> {
+        Ok(int(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> SerializationResult<NixValue> {
+        Ok(int(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> SerializationResult<NixValue> {
+        checked_int(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> SerializationResult<NixValue> {
+        checked_int(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> SerializationResult<NixValue> {
+        Ok(NixValue::Float(NixFloat::new(v.into())))
+    }
+
+    fn serialize_f64(self, v: f64) -> SerializationResult<NixValue> {
+        Ok(NixValue::Float(NixFloat::new(v)))
+    }
+
+    fn serialize_char(self, v: char) -> SerializationResult<NixValue> {
+        Ok(string(v))
+    }
+
+    fn serialize_str(self, v: &str) -> SerializationResult<NixValue> {
+        Ok(string(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> SerializationResult<NixValue> {
+        Ok(NixValue::List(NixList::from_vec(
+            v.iter().map(|&b| int(b.into())).collect(),
+        )))
+    }
+
+    fn serialize_none(self) -> SerializationResult<NixValue> {
+        Ok(null())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> SerializationResult<NixValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> SerializationResult<NixValue> {
+        Ok(null())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> SerializationResult<NixValue> {
+        Ok(null())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> SerializationResult<NixValue> {
+        Ok(string(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> SerializationResult<NixValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> SerializationResult<NixValue> {
+        Ok(tagged(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> SerializationResult<SerializeList> {
+        Ok(SerializeList {
+            variant: None,
+            elements: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> SerializationResult<SerializeList> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> SerializationResult<SerializeList> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> SerializationResult<SerializeList> {
+        Ok(SerializeList {
+            variant: Some(variant),
+            elements: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> SerializationResult<SerializeAttrs> {
+        Ok(SerializeAttrs {
+            variant: None,
+            attrs: NixAttrset::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> SerializationResult<SerializeAttrs> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> SerializationResult<SerializeAttrs> {
+        Ok(SerializeAttrs {
+            variant: Some(variant),
+            attrs: NixAttrset::new(),
+            key: None,
+        })
+    }
+}
+
+// ============================================================================
+// Compound Values
+// ============================================================================
+
+/// Sequences, tuples and tuple variants
+#[doc(hidden)]
+pub struct SerializeList {
+    variant: Option<&'static str>,
+    elements: Vec<NixValue>,
+}
+
+impl SerializeList {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> SerializationResult<()> {
+        self.elements.push(value.serialize(NixValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> NixValue {
+        let list = NixValue::List(NixList::from_vec(self.elements));
+        match self.variant {
+            Some(variant) => tagged(variant, list),
+            None => list,
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = NixValue;
+    type Error = SerializationError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> SerializationResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> SerializationResult<NixValue> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = NixValue;
+    type Error = SerializationError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> SerializationResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> SerializationResult<NixValue> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = NixValue;
+    type Error = SerializationError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> SerializationResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> SerializationResult<NixValue> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = NixValue;
+    type Error = SerializationError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> SerializationResult<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> SerializationResult<NixValue> {
+        Ok(self.finish())
+    }
+}
+
+/// Maps, structs and struct variants
+#[doc(hidden)]
+pub struct SerializeAttrs {
+    variant: Option<&'static str>,
+    attrs: NixAttrset,
+    /// Key waiting for its value in `serialize_key` / `serialize_value`
+    key: Option<String>,
+}
+
+impl SerializeAttrs {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> SerializationResult<()> {
+        self.attrs.insert(key, value.serialize(NixValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> NixValue {
+        let attrs = NixValue::Attrset(self.attrs);
+        match self.variant {
+            Some(variant) => tagged(variant, attrs),
+            None => attrs,
+        }
+    }
+}
+
+impl ser::SerializeMap for SerializeAttrs {
+    type Ok = NixValue;
+    type Error = SerializationError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> SerializationResult<()> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> SerializationResult<()> {
+        let key = self.key.take().ok_or_else(|| {
+            SerializationError::Message("map value serialized before its key".into())
+        })?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> SerializationResult<NixValue> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for SerializeAttrs {
+    type Ok = NixValue;
+    type Error = SerializationError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> SerializationResult<()> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> SerializationResult<NixValue> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeAttrs {
+    type Ok = NixValue;
+    type Error = SerializationError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> SerializationResult<()> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> SerializationResult<NixValue> {
+        Ok(self.finish())
+    }
+}
+
+// ============================================================================
+// Map Keys
+// ============================================================================
+
+/// Serializer for attribute names: strings and scalars printed as text
+struct KeySerializer;
+
+impl KeySerializer {
+    fn invalid<T>(kind: &'static str) -> SerializationResult<T> {
+        Err(SerializationError::InvalidKey(kind))
+    }
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = SerializationError;
+
+    type SerializeSeq = ser::Impossible<String, SerializationError>;
+    type SerializeTuple = ser::Impossible<String, SerializationError>;
+    type SerializeTupleStruct = ser::Impossible<String, SerializationError>;
+    type SerializeTupleVariant = ser::Impossible<String, SerializationError>;
+    type SerializeMap = ser::Impossible<String, SerializationError>;
+    type SerializeStruct = ser::Impossible<String, SerializationError>;
+    type SerializeStructVariant = ser::Impossible<String, SerializationError>;
+
+    fn serialize_bool(self, v: bool) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> SerializationResult<String> {
+        Self::invalid("float")
+    }
+
+    fn serialize_f64(self, _v: f64) -> SerializationResult<String> {
+        Self::invalid("float")
+    }
+
+    fn serialize_char(self, v: char) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> SerializationResult<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> SerializationResult<String> {
+        Self::invalid("bytes")
+    }
+
+    fn serialize_none(self) -> SerializationResult<String> {
+        Self::invalid("none")
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> SerializationResult<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> SerializationResult<String> {
+        Self::invalid("unit")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> SerializationResult<String> {
+        Self::invalid("unit struct")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> SerializationResult<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> SerializationResult<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> SerializationResult<String> {
+        Self::invalid("enum variant")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> SerializationResult<Self::SerializeSeq> {
+        Self::invalid("sequence")
+    }
+
+    fn serialize_tuple(self, _len: usize) -> SerializationResult<Self::SerializeTuple> {
+        Self::invalid("tuple")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> SerializationResult<Self::SerializeTupleStruct> {
+        Self::invalid("tuple struct")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> SerializationResult<Self::SerializeTupleVariant> {
+        Self::invalid("enum variant")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> SerializationResult<Self::SerializeMap> {
+        Self::invalid("map")
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> SerializationResult<Self::SerializeStruct> {
+        Self::invalid("struct")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> SerializationResult<Self::SerializeStructVariant> {
+        Self::invalid("enum variant")
+    }
+}
@@ -56,7 +56,12 @@ pub enum NixValueError {
 
     /// Type mismatch
     #[error("Type mismatch: expected {expected}, got {got}")]
-    TypeMismatch { expected: String, got: String },
+    TypeMismatch {
+        /// Type that was expected
+        expected: String,
+        /// Type that was found
+        got: String,
+    },
 
     /// Missing attribute
     #[error("Missing attribute: {0}")]
@@ -593,8 +598,8 @@ mod tests {
 
     #[test]
     fn test_nix_float() {
-        let f = NixFloat::new(3.14);
-        assert_eq!(f.value, 3.14);
+        let f = NixFloat::new(2.5);
+        assert_eq!(f.value, 2.5);
     }
 
     #[test]
@@ -736,9 +741,9 @@ mod tests {
 
     #[test]
     fn test_nix_float_display() {
-        let f = NixFloat::new(2.718);
+        let f = NixFloat::new(1.25);
         let display = format!("{}", f);
-        assert!(display.contains("2.718"));
+        assert!(display.contains("1.25"));
     }
 
     #[test]
@@ -858,10 +863,10 @@ mod tests {
 
     #[test]
     fn test_nix_value_as_float() {
-        let f = NixValue::Float(NixFloat::new(3.14));
+        let f = NixValue::Float(NixFloat::new(2.5));
         let result = f.as_float();
         assert!(result.is_ok());
-        assert!((result.unwrap() - 3.14).abs() < 0.001);
+        assert!((result.unwrap() - 2.5).abs() < 0.001);
     }
 
     #[test]
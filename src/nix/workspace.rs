@@ -0,0 +1,743 @@
+// Copyright 2025 Cowboy AI, LLC.
+
+//! Workspace Indexer
+//!
+//! Walks a repository in parallel, parses every `.nix` file with rnix and
+//! builds a searchable index of what each file is and what it defines:
+//!
+//! - **Kind**: flake, NixOS module, overlay, topology, package expression
+//!   or plain data ([`FileKind`])
+//! - **Attributes** defined at the top level of the file (through the
+//!   arguments of modules and overlays)
+//! - **Imports**: `import ./x.nix`, `callPackage ./x { }` and module
+//!   `imports = [ ./x.nix ]`, resolved to files in the workspace
+//!
+//! Files are parsed on a rayon thread pool with one thread per CPU unless
+//! [`WorkspaceIndexer::with_threads`] says otherwise. Hidden directories
+//! and symlinks (such as `result` links into the store) are not followed.
+//! A file that cannot be read or parsed stays in the index as
+//! [`FileKind::Invalid`] with its error.
+//!
+//! ## Usage
+//!
+//! ```rust
+//! use cim_domain_nix::nix::{FileKind, WorkspaceIndex, WorkspaceIndexer};
+//!
+//! let indexer = WorkspaceIndexer::new();
+//! let mut index = WorkspaceIndex::default();
+//! index.insert(indexer.index_source("overlays/hello.nix", r"
+//!   final: prev: { hello = final.callPackage ../pkgs/hello { }; }
+//! "));
+//! index.insert(indexer.index_source("pkgs/hello/default.nix", r#"
+//!   { stdenv }: stdenv.mkDerivation { pname = "hello"; version = "1.0"; }
+//! "#));
+//!
+//! assert_eq!(index.get("overlays/hello.nix").unwrap().kind, FileKind::Overlay);
+//! assert_eq!(index.defining("hello")[0].path.to_str(), Some("overlays/hello.nix"));
+//! assert_eq!(index.importers("pkgs/hello/default.nix").len(), 1);
+//! ```
+
+use super::ast::{NixAst, Span};
+use super::ast_converter::ast_to_value;
+use super::flake_outputs::{
+    attr_value, attrset_of, ident_name, key_of, strip_parens, Bound, Call, Env, Key,
+};
+use super::overlay_analyzer::OverlayAnalyzer;
+use rayon::prelude::*;
+use rnix::ast::{self, HasEntry};
+use rowan::ast::AstNode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+/// Errors from indexing a workspace
+#[derive(Debug, Error)]
+pub enum WorkspaceError {
+    /// A directory could not be listed
+    #[error("Workspace I/O error for {path}: {source}")]
+    Io {
+        /// Directory
+        path: PathBuf,
+        /// Underlying error
+        source: std::io::Error,
+    },
+
+    /// An exclude pattern is not a valid glob
+    #[error("Invalid exclude pattern: {0}")]
+    Pattern(#[from] glob::PatternError),
+
+    /// The thread pool could not be started
+    #[error("Could not start indexing threads: {0}")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+}
+
+/// Result type for workspace indexing
+pub type WorkspaceResult<T> = Result<T, WorkspaceError>;
+
+// ============================================================================
+// Index Entries
+// ============================================================================
+
+/// What a Nix file is, judged from its shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    /// `flake.nix`, or a set with `outputs` and `inputs` or `description`
+    Flake,
+    /// A set with `options`, `config` or `imports`, or a function of
+    /// `{ config, ... }`
+    NixosModule,
+    /// `final: prev: { ... }`
+    Overlay,
+    /// A set with `nodes`, as read by the topology reader
+    Topology,
+    /// A function returning `mkDerivation` or a `build*` / `write*` helper
+    Package,
+    /// A value that needs no evaluation
+    Data,
+    /// Any other expression
+    Expression,
+    /// The file could not be read or parsed
+    Invalid,
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Flake => "flake",
+            Self::NixosModule => "NixOS module",
+            Self::Overlay => "overlay",
+            Self::Topology => "topology",
+            Self::Package => "package",
+            Self::Data => "data",
+            Self::Expression => "expression",
+            Self::Invalid => "invalid",
+        })
+    }
+}
+
+/// A path a file imports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Import {
+    /// The path as written
+    pub source: String,
+    /// Workspace-relative file it refers to, for relative paths inside
+    /// the workspace (`default.nix` for directories)
+    pub path: Option<PathBuf>,
+    /// Location of the path
+    pub span: Span,
+}
+
+/// One indexed file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexedFile {
+    /// Path relative to the workspace root
+    pub path: PathBuf,
+    /// What the file is
+    pub kind: FileKind,
+    /// Top-level attribute names, in source order
+    pub attributes: Vec<String>,
+    /// Imported paths, in source order
+    pub imports: Vec<Import>,
+    /// Why the file could not be indexed
+    pub error: Option<String>,
+}
+
+impl IndexedFile {
+    fn invalid(path: PathBuf, error: String) -> Self {
+        Self {
+            path,
+            kind: FileKind::Invalid,
+            attributes: Vec::new(),
+            imports: Vec::new(),
+            error: Some(error),
+        }
+    }
+
+    /// Whether the file defines a top-level attribute
+    pub fn defines(&self, attribute: &str) -> bool {
+        self.attributes.iter().any(|name| name == attribute)
+    }
+}
+
+/// Index of the `.nix` files of a workspace
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceIndex {
+    /// Workspace root
+    pub root: PathBuf,
+    /// Files by workspace-relative path
+    pub files: BTreeMap<PathBuf, IndexedFile>,
+}
+
+impl WorkspaceIndex {
+    /// Add a file, replacing any previous entry for its path
+    pub fn insert(&mut self, file: IndexedFile) -> Option<IndexedFile> {
+        self.files.insert(file.path.clone(), file)
+    }
+
+    /// File by workspace-relative path
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<&IndexedFile> {
+        self.files.get(path.as_ref())
+    }
+
+    /// Files in path order
+    pub fn iter(&self) -> impl Iterator<Item = &IndexedFile> {
+        self.files.values()
+    }
+
+    /// Files of one kind
+    pub fn of_kind(&self, kind: FileKind) -> impl Iterator<Item = &IndexedFile> {
+        self.iter().filter(move |file| file.kind == kind)
+    }
+
+    /// Number of files of each kind
+    pub fn counts(&self) -> BTreeMap<FileKind, usize> {
+        let mut counts = BTreeMap::new();
+        for file in self.iter() {
+            *counts.entry(file.kind).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Files that define a top-level attribute
+    pub fn defining(&self, attribute: &str) -> Vec<&IndexedFile> {
+        self.iter().filter(|file| file.defines(attribute)).collect()
+    }
+
+    /// Attributes whose name contains `query`, ignoring case, with the
+    /// file defining them
+    pub fn search(&self, query: &str) -> Vec<(&IndexedFile, &str)> {
+        let query = query.to_lowercase();
+        self.iter()
+            .flat_map(|file| {
+                file.attributes
+                    .iter()
+                    .filter(|name| name.to_lowercase().contains(&query))
+                    .map(move |name| (file, name.as_str()))
+            })
+            .collect()
+    }
+
+    /// Files that import a file
+    pub fn importers(&self, path: impl AsRef<Path>) -> Vec<&IndexedFile> {
+        let path = path.as_ref();
+        self.iter()
+            .filter(|file| {
+                file.imports
+                    .iter()
+                    .any(|import| import.path.as_deref() == Some(path))
+            })
+            .collect()
+    }
+
+    /// Files that could not be indexed
+    pub fn errors(&self) -> impl Iterator<Item = &IndexedFile> {
+        self.of_kind(FileKind::Invalid)
+    }
+
+    /// Number of files
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether no files were indexed
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+// ============================================================================
+// Indexer
+// ============================================================================
+
+/// Builds [`WorkspaceIndex`]es
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceIndexer {
+    exclude: Vec<glob::Pattern>,
+    threads: Option<usize>,
+}
+
+impl WorkspaceIndexer {
+    /// Create an indexer for every `.nix` file outside hidden directories
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skip files and directories whose workspace-relative path matches a
+    /// glob (`vendor/**`, `**/generated.nix`)
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`WorkspaceError::Pattern`] if the glob is invalid.
+    pub fn with_exclude(mut self, pattern: &str) -> WorkspaceResult<Self> {
+        self.exclude.push(glob::Pattern::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Number of threads to parse with (default: one per CPU)
+    #[must_use]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads.max(1));
+        self
+    }
+
+    /// Index every `.nix` file under `root`
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a directory cannot be listed or the thread pool
+    /// cannot be started. Files that cannot be read or parsed are indexed
+    /// as [`FileKind::Invalid`].
+    pub fn index(&self, root: impl AsRef<Path>) -> WorkspaceResult<WorkspaceIndex> {
+        let root = root.as_ref();
+        let paths = self.discover(root)?;
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.threads.unwrap_or_else(num_cpus::get))
+            .build()?;
+        let files: Vec<IndexedFile> = pool.install(|| {
+            paths
+                .into_par_iter()
+                .map(|path| match std::fs::read_to_string(root.join(&path)) {
+                    Ok(source) => self.index_source(path, &source),
+                    Err(e) => IndexedFile::invalid(path, e.to_string()),
+                })
+                .collect()
+        });
+        Ok(WorkspaceIndex {
+            root: root.to_path_buf(),
+            files: files
+                .into_iter()
+                .map(|file| (file.path.clone(), file))
+                .collect(),
+        })
+    }
+
+    /// Workspace-relative paths of the `.nix` files under `root`, sorted
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`WorkspaceError::Io`] if a directory cannot be listed.
+    pub fn discover(&self, root: impl AsRef<Path>) -> WorkspaceResult<Vec<PathBuf>> {
+        let root = root.as_ref();
+        let mut paths = Vec::new();
+        self.collect_nix_files(root, root, &mut paths)?;
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn collect_nix_files(
+        &self,
+        root: &Path,
+        dir: &Path,
+        paths: &mut Vec<PathBuf>,
+    ) -> WorkspaceResult<()> {
+        let io = |source| WorkspaceError::Io {
+            path: dir.to_path_buf(),
+            source,
+        };
+        for entry in std::fs::read_dir(dir).map_err(io)? {
+            let entry = entry.map_err(io)?;
+            let file_type = entry.file_type().map_err(io)?;
+            let path = entry.path();
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if self
+                .exclude
+                .iter()
+                .any(|pattern| pattern.matches_path(relative))
+            {
+                continue;
+            }
+            if file_type.is_dir() && !name.starts_with('.') {
+                self.collect_nix_files(root, &path, paths)?;
+            } else if file_type.is_file() && name.ends_with(".nix") {
+                paths.push(relative.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+
+    /// Index one file from source
+    ///
+    /// `path` is the workspace-relative path the file's imports are
+    /// resolved against.
+    pub fn index_source(&self, path: impl Into<PathBuf>, source: &str) -> IndexedFile {
+        let path = path.into();
+        let ast = match NixAst::parse(source) {
+            Ok(ast) => ast,
+            Err(e) => return IndexedFile::invalid(path, e.to_string()),
+        };
+        let Some(expr) = ast.expr() else {
+            return IndexedFile::invalid(path, "empty file".to_string());
+        };
+
+        let bound = Bound {
+            expr: expr.clone(),
+            env: Env::default(),
+        };
+        let attributes = attrset_of(&bound)
+            .map(|(set, _)| top_level_attributes(&set))
+            .unwrap_or_default();
+        let mut kind = classify(&path, &expr, &bound);
+        if kind == FileKind::Expression && ast_to_value(&ast).is_ok() {
+            kind = FileKind::Data;
+        }
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let imports = imports_of(&expr)
+            .into_iter()
+            .map(|(source, span)| Import {
+                path: resolve_import(dir, &source),
+                source,
+                span,
+            })
+            .collect();
+
+        IndexedFile {
+            path,
+            kind,
+            attributes,
+            imports,
+            error: None,
+        }
+    }
+}
+
+// ============================================================================
+// Classification
+// ============================================================================
+
+/// Builders whose result makes a function a package expression
+fn is_package_builder(name: &str) -> bool {
+    name == "mkDerivation" || name.starts_with("build") || name.starts_with("write")
+}
+
+fn classify(path: &Path, expr: &ast::Expr, bound: &Bound) -> FileKind {
+    let params = pattern_params(expr);
+    let set = attrset_of(bound);
+    let has = |name: &str| {
+        set.as_ref()
+            .is_some_and(|(set, env)| attr_value(set, env, &[name]).is_some())
+    };
+
+    if path.file_name().is_some_and(|name| name == "flake.nix")
+        || (params.is_none() && has("outputs") && (has("inputs") || has("description")))
+    {
+        return FileKind::Flake;
+    }
+    if params.is_none() && has("nodes") {
+        return FileKind::Topology;
+    }
+    if OverlayAnalyzer::new().analyze_expr("", expr).is_ok() {
+        return FileKind::Overlay;
+    }
+    let module_params = ["config", "options", "modulesPath"];
+    if params
+        .as_ref()
+        .is_some_and(|params| params.iter().any(|p| module_params.contains(&p.as_str())))
+        || has("options")
+        || has("config")
+        || has("imports")
+    {
+        return FileKind::NixosModule;
+    }
+    if params.is_some() {
+        if let ast::Expr::Lambda(lambda) = strip_parens(expr) {
+            let body = lambda.body().and_then(|body| {
+                Call::of_value(&Bound {
+                    expr: body,
+                    env: Env::default(),
+                })
+            });
+            if body
+                .and_then(|call| call.head)
+                .is_some_and(|head| is_package_builder(&head))
+            {
+                return FileKind::Package;
+            }
+        }
+    }
+    FileKind::Expression
+}
+
+/// Names of the `{ a, b, ... }:` parameters of a function
+fn pattern_params(expr: &ast::Expr) -> Option<Vec<String>> {
+    let ast::Expr::Lambda(lambda) = strip_parens(expr) else {
+        return None;
+    };
+    let ast::Param::Pattern(pattern) = lambda.param()? else {
+        return None;
+    };
+    Some(
+        pattern
+            .pat_entries()
+            .filter_map(|entry| entry.ident())
+            .map(|ident| ident_name(&ident))
+            .collect(),
+    )
+}
+
+fn top_level_attributes(set: &ast::AttrSet) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut add = |attr: Option<ast::Attr>| {
+        if let Some(Key::Static(name)) = attr.map(|attr| key_of(&attr)) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    };
+    for entry in set.entries() {
+        match entry {
+            ast::Entry::AttrpathValue(binding) => {
+                add(binding.attrpath().and_then(|path| path.attrs().next()));
+            }
+            ast::Entry::Inherit(inherit) => inherit.attrs().for_each(|attr| add(Some(attr))),
+        }
+    }
+    names
+}
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+/// Paths passed to `import` or `callPackage`, or listed in `imports`
+fn imports_of(expr: &ast::Expr) -> Vec<(String, Span)> {
+    let mut imports = Vec::new();
+    for node in expr.syntax().descendants() {
+        if let Some(apply) = ast::Apply::cast(node.clone()) {
+            let Some(ast::Expr::Path(path)) = apply.argument().map(|arg| strip_parens(&arg)) else {
+                continue;
+            };
+            let function = apply.lambda().map(|lambda| strip_parens(&lambda));
+            let head = match function {
+                Some(ast::Expr::Ident(ident)) => Some(ident_name(&ident)),
+                Some(ast::Expr::Select(select)) => select
+                    .attrpath()
+                    .and_then(|path| path.attrs().last())
+                    .and_then(|attr| match key_of(&attr) {
+                        Key::Static(name) => Some(name),
+                        Key::Dynamic(_) => None,
+                    }),
+                _ => None,
+            };
+            if matches!(
+                head.as_deref(),
+                Some("import" | "callPackage" | "callPackages")
+            ) {
+                imports.push((path.syntax().text().to_string(), Span::of(path.syntax())));
+            }
+        } else if let Some(binding) = ast::AttrpathValue::cast(node) {
+            let is_imports = binding
+                .attrpath()
+                .and_then(|path| path.attrs().last())
+                .is_some_and(
+                    |attr| matches!(key_of(&attr), Key::Static(name) if name == "imports"),
+                );
+            let Some(ast::Expr::List(list)) = binding.value().map(|value| strip_parens(&value))
+            else {
+                continue;
+            };
+            if is_imports {
+                for item in list.items() {
+                    if let ast::Expr::Path(path) = strip_parens(&item) {
+                        imports.push((path.syntax().text().to_string(), Span::of(path.syntax())));
+                    }
+                }
+            }
+        }
+    }
+    imports.sort_by_key(|(_, span)| span.start);
+    imports
+}
+
+/// The workspace-relative file a relative import refers to
+fn resolve_import(dir: &Path, source: &str) -> Option<PathBuf> {
+    if !(source == "." || source.starts_with("./") || source.starts_with("../")) {
+        return None;
+    }
+    let mut resolved = PathBuf::new();
+    for component in dir.join(source).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return None;
+                }
+            }
+            Component::Normal(part) => resolved.push(part),
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    if resolved.extension().is_none_or(|ext| ext != "nix") {
+        resolved.push("default.nix");
+    }
+    Some(resolved)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind_of(path: &str, source: &str) -> FileKind {
+        WorkspaceIndexer::new().index_source(path, source).kind
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            kind_of("flake.nix", "{ outputs = _: { }; }"),
+            FileKind::Flake
+        );
+        assert_eq!(
+            kind_of("x.nix", r#"{ description = "x"; outputs = _: { }; }"#),
+            FileKind::Flake
+        );
+        assert_eq!(
+            kind_of(
+                "net.nix",
+                r#"{ nodes = { web01 = { type = "server"; }; }; }"#
+            ),
+            FileKind::Topology
+        );
+        assert_eq!(
+            kind_of("o.nix", "self: super: { hello = super.hello; }"),
+            FileKind::Overlay
+        );
+        assert_eq!(
+            kind_of(
+                "m.nix",
+                "{ config, lib, ... }: { services.nginx.enable = true; }"
+            ),
+            FileKind::NixosModule
+        );
+        assert_eq!(
+            kind_of("m.nix", "{ imports = [ ./hardware.nix ]; }"),
+            FileKind::NixosModule
+        );
+        assert_eq!(
+            kind_of(
+                "p.nix",
+                "{ rustPlatform }: let v = \"1\"; in rustPlatform.buildRustPackage { version = v; }"
+            ),
+            FileKind::Package
+        );
+        assert_eq!(kind_of("d.nix", "[ 1 2 { a = \"b\"; } ]"), FileKind::Data);
+        assert_eq!(
+            kind_of("e.nix", "{ pkgs }: pkgs.hello"),
+            FileKind::Expression
+        );
+
+        let invalid = WorkspaceIndexer::new().index_source("bad.nix", "{ a = ; }");
+        assert_eq!(invalid.kind, FileKind::Invalid);
+        assert!(invalid.error.is_some());
+    }
+
+    #[test]
+    fn test_attributes_and_imports() {
+        let file = WorkspaceIndexer::new().index_source(
+            "hosts/web/default.nix",
+            r#"{ config, pkgs, ... }:
+            let tools = import ../../lib/tools.nix { };
+            in {
+              imports = [ ./hardware.nix ../common ];
+              inherit tools;
+              networking.hostName = "web";
+              environment.systemPackages = [ (pkgs.callPackage ../../pkgs/app { }) ];
+              extra = import <nixpkgs> { };
+              outside = import ../../../elsewhere.nix;
+            }"#,
+        );
+
+        assert_eq!(
+            file.attributes,
+            [
+                "imports",
+                "tools",
+                "networking",
+                "environment",
+                "extra",
+                "outside"
+            ]
+        );
+        let resolved: Vec<_> = file
+            .imports
+            .iter()
+            .map(|import| (import.source.as_str(), import.path.as_deref()))
+            .collect();
+        assert_eq!(
+            resolved,
+            [
+                ("../../lib/tools.nix", Some(Path::new("lib/tools.nix"))),
+                ("./hardware.nix", Some(Path::new("hosts/web/hardware.nix"))),
+                ("../common", Some(Path::new("hosts/common/default.nix"))),
+                ("../../pkgs/app", Some(Path::new("pkgs/app/default.nix"))),
+                ("<nixpkgs>", None),
+                ("../../../elsewhere.nix", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_index_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write(
+            "flake.nix",
+            "{ outputs = { self }: { overlays.default = import ./overlay.nix; }; }",
+        );
+        write(
+            "overlay.nix",
+            "final: prev: { tool = final.callPackage ./pkgs/tool { }; }",
+        );
+        write(
+            "pkgs/tool/default.nix",
+            "{ stdenv }: stdenv.mkDerivation { pname = \"tool\"; }",
+        );
+        write("vendor/skip.nix", "{ }");
+        write(".git/hidden.nix", "{ }");
+        write("broken.nix", "{");
+        write("README.md", "not nix");
+
+        let indexer = WorkspaceIndexer::new()
+            .with_exclude("vendor")
+            .unwrap()
+            .with_threads(2);
+        let index = indexer.index(dir.path()).unwrap();
+
+        assert_eq!(
+            index.files.keys().collect::<Vec<_>>(),
+            [
+                Path::new("broken.nix"),
+                Path::new("flake.nix"),
+                Path::new("overlay.nix"),
+                Path::new("pkgs/tool/default.nix"),
+            ]
+        );
+        assert_eq!(index.counts()[&FileKind::Package], 1);
+        assert_eq!(index.errors().count(), 1);
+        assert_eq!(index.defining("tool").len(), 1);
+        assert_eq!(index.search("TOO").len(), 1);
+        assert_eq!(index.importers("overlay.nix")[0].kind, FileKind::Flake);
+        assert_eq!(
+            index.importers("pkgs/tool/default.nix")[0].path,
+            Path::new("overlay.nix")
+        );
+        assert!(matches!(
+            WorkspaceIndexer::new().with_exclude("["),
+            Err(WorkspaceError::Pattern(_))
+        ));
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright (c) 2025 - Cowboy AI, Inc.
+//! Printer Round-Trip Properties
+//!
+//! Every writer in the crate emits Nix through [`NixPrinter`], so the
+//! printer has to be lossless: printing a value and parsing the result
+//! must give the value back, whatever the layout configuration.
+//!
+//! ## Run with
+//! ```bash
+//! cargo test --test printer_roundtrip
+//! ```
+
+use cim_domain_nix::nix::*;
+use proptest::prelude::*;
+
+// ============================================================================
+// STRATEGIES
+// ============================================================================
+
+/// Strings biased towards the characters that need escaping
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z ]{0,12}",
+        "[a-z\"\\\\$'{}\n\t\r ]{0,16}",
+        any::<String>(),
+    ]
+}
+
+/// Attribute names: identifiers, keywords and arbitrary text
+fn key() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-zA-Z_][a-zA-Z0-9_'-]{0,8}",
+        Just("in".to_string()),
+        Just("or".to_string()),
+        text(),
+    ]
+}
+
+/// Paths that have a literal form, with segments that need interpolation
+fn path() -> impl Strategy<Value = NixValue> {
+    (
+        prop_oneof![Just(""), Just("."), Just(".."), Just("~"), Just("nix")],
+        prop::collection::vec("[a-z0-9._+-]{1,6}", 1..4),
+        prop::option::of("[a-z ]{1,6}"),
+    )
+        .prop_map(|(head, segments, tail)| {
+            let mut path = format!("{head}/{}", segments.join("/"));
+            // Interpolated segments are not valid after `~/`
+            if let Some(tail) = tail.filter(|_| head != "~") {
+                path.push('/');
+                path.push_str(&tail);
+            }
+            NixValue::Path(NixPath::new(path).unwrap())
+        })
+}
+
+fn scalar() -> impl Strategy<Value = NixValue> {
+    prop_oneof![
+        Just(NixValue::Null(NixNull::new())),
+        any::<bool>().prop_map(|b| NixValue::Bool(NixBool::new(b))),
+        any::<i64>().prop_map(|i| NixValue::Integer(NixInteger::new(i))),
+        {
+            use prop::num::f64::{NEGATIVE, NORMAL, POSITIVE, SUBNORMAL, ZERO};
+            POSITIVE | NEGATIVE | NORMAL | SUBNORMAL | ZERO
+        }
+        .prop_map(|f| NixValue::Float(NixFloat::new(f))),
+        text().prop_map(|s| NixValue::String(NixString::new(s))),
+        path(),
+        "[a-z]{1,6}(/[a-z.-]{1,6}){0,2}"
+            .prop_map(|name| NixValue::LookupPath(NixLookupPath::new(name).unwrap())),
+    ]
+}
+
+fn value() -> impl Strategy<Value = NixValue> {
+    scalar().prop_recursive(4, 48, 6, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..6)
+                .prop_map(|elements| NixValue::List(NixList::from_vec(elements))),
+            (
+                prop::collection::hash_map(key(), inner, 0..6),
+                any::<bool>()
+            )
+                .prop_map(|(attributes, recursive)| {
+                    NixValue::Attrset(NixAttrset {
+                        attributes,
+                        recursive,
+                    })
+                }),
+        ]
+    })
+}
+
+fn printer() -> impl Strategy<Value = NixPrinter> {
+    (
+        0usize..5,
+        prop_oneof![Just(0usize), Just(40), Just(usize::MAX)],
+        any::<bool>(),
+        prop::collection::vec(key(), 0..3),
+    )
+        .prop_map(|(indent, max_inline_width, multiline_strings, priority)| {
+            NixPrinter::with_config(PrintConfig {
+                indent,
+                key_order: if priority.is_empty() {
+                    KeyOrder::Sorted
+                } else {
+                    KeyOrder::Priority(priority)
+                },
+                max_inline_width,
+                multiline_strings,
+            })
+        })
+}
+
+// ============================================================================
+// PROPERTIES
+// ============================================================================
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    /// `parse(print(v)) == v` for every printable value and configuration
+    #[test]
+    fn print_then_parse_is_identity(value in value(), printer in printer()) {
+        let source = printer.print(&value).unwrap();
+        let parsed = parse_value(&source)
+            .map_err(|e| TestCaseError::fail(format!("{e} in:\n{source}")))?;
+        prop_assert_eq!(parsed, value, "source:\n{}", source);
+    }
+
+    /// The same value always prints the same text, whatever order its
+    /// attributes were inserted in
+    #[test]
+    fn printing_is_deterministic(value in value()) {
+        let reordered = reinserted(value.clone());
+        prop_assert_eq!(print_value(&value).unwrap(), print_value(&reordered).unwrap());
+    }
+
+    /// Printed source is also accepted by the evaluator
+    #[test]
+    fn printed_source_evaluates_to_the_value(value in value()) {
+        let source = print_value(&value).unwrap();
+        let evaluated = evaluate(&source)
+            .map_err(|e| TestCaseError::fail(format!("{e} in:\n{source}")))?;
+        // The evaluator does not track `rec`, so compare the printed forms
+        // of both sides with the flag cleared
+        prop_assert_eq!(
+            print_value(&without_rec(evaluated)).unwrap(),
+            print_value(&without_rec(value)).unwrap()
+        );
+    }
+}
+
+/// The same value with every attribute set rebuilt in a fresh map, inserting
+/// attributes in reverse name order
+///
+/// A clone keeps the map's hasher and layout, so it would iterate in the
+/// same order as the original; a fresh map does not.
+fn reinserted(value: NixValue) -> NixValue {
+    match value {
+        NixValue::Attrset(attrs) => {
+            let mut entries: Vec<_> = attrs.attributes.into_iter().collect();
+            entries.sort_by(|a, b| b.0.cmp(&a.0));
+            let mut rebuilt = if attrs.recursive {
+                NixAttrset::new_recursive()
+            } else {
+                NixAttrset::new()
+            };
+            for (name, value) in entries {
+                rebuilt.insert(name, reinserted(value));
+            }
+            NixValue::Attrset(rebuilt)
+        }
+        NixValue::List(list) => NixValue::List(NixList::from_vec(
+            list.elements.into_iter().map(reinserted).collect(),
+        )),
+        other => other,
+    }
+}
+
+fn without_rec(value: NixValue) -> NixValue {
+    match value {
+        NixValue::Attrset(attrs) => NixValue::Attrset(NixAttrset {
+            attributes: attrs
+                .attributes
+                .into_iter()
+                .map(|(k, v)| (k, without_rec(v)))
+                .collect(),
+            recursive: false,
+        }),
+        NixValue::List(list) => NixValue::List(NixList::from_vec(
+            list.elements.into_iter().map(without_rec).collect(),
+        )),
+        other => other,
+    }
+}